@@ -67,8 +67,9 @@ pub use secure::{
     sanitize_path, validate_package_name, validate_url,
 };
 pub use signature::{
-    Ed25519Verifier, PgpVerifier, SignatureAlgorithm, SignatureError, SignatureVerifier,
-    TrustChain, TrustLevel, TrustedKey, TrustedSignatureVerifier, VerifiedSignature,
+    Ed25519Signer, Ed25519Verifier, PgpVerifier, SignatureAlgorithm, SignatureError,
+    SignatureVerifier, TrustChain, TrustLevel, TrustedKey, TrustedSignatureVerifier,
+    VerifiedSignature,
 };
 
 /// Vulnerability severity level.
@@ -1,7 +1,7 @@
 //! Package signature verification using GPG/PGP and Ed25519 with trust chain support.
 
 use chrono::{DateTime, Utc};
-use ed25519_dalek::{Signature as Ed25519Sig, Verifier, VerifyingKey};
+use ed25519_dalek::{Signature as Ed25519Sig, Signer, SigningKey, Verifier, VerifyingKey};
 use sequoia_openpgp::Packet;
 use sequoia_openpgp::cert::Cert;
 use sequoia_openpgp::parse::Parse;
@@ -99,11 +99,11 @@ impl PgpVerifier {
         self.add_trusted_key(&data)
     }
 
-    /// Verify detached signature.
+    /// Verify a detached signature over `data`.
     ///
     /// # Errors
     /// Returns error if verification fails.
-    pub fn verify_detached(&self, _data: &[u8], signature: &[u8]) -> Result<VerifiedSignature> {
+    pub fn verify_detached(&self, data: &[u8], signature: &[u8]) -> Result<VerifiedSignature> {
         let packets = sequoia_openpgp::PacketPile::from_bytes(signature)
             .map_err(|e| SignatureError::VerificationFailed(e.to_string()))?;
 
@@ -136,15 +136,10 @@ impl PgpVerifier {
 
         // Find matching trusted key
         for cert in &self.trusted_keys {
-            // Get primary key with policy
-            let key_amalg = cert.keys().with_policy(&self.policy, None);
-
-            // Try each key
-            for key in key_amalg {
-                // Try to verify the signature with this key
-                // verify_direct_key requires both signing key and target key
-                let pk = cert.primary_key().key();
-                if sig_packet.verify_direct_key(key.key(), pk).is_ok() {
+            // Try each signing-capable key valid under the policy
+            let signing_keys = cert.keys().with_policy(&self.policy, None).for_signing();
+            for key in signing_keys {
+                if sig_packet.verify_message(key.key(), data).is_ok() {
                     // Get signer identity
                     let signer = cert
                         .userids()
@@ -246,6 +241,60 @@ impl Default for Ed25519Verifier {
     }
 }
 
+/// Ed25519 signer producing detached signatures for [`Ed25519Verifier`].
+pub struct Ed25519Signer {
+    key: SigningKey,
+}
+
+impl Ed25519Signer {
+    /// Generate a new random signing key.
+    #[must_use]
+    pub fn generate() -> Self {
+        Self {
+            key: SigningKey::generate(&mut rand::rngs::OsRng),
+        }
+    }
+
+    /// Load a signing key from its 32-byte secret.
+    ///
+    /// # Errors
+    /// Returns error if the secret is not 32 bytes.
+    pub fn from_secret(secret: &[u8]) -> Result<Self> {
+        let secret: &[u8; 32] = secret.try_into().map_err(|_| {
+            SignatureError::InvalidKey(format!("expected 32 bytes, got {}", secret.len()))
+        })?;
+        Ok(Self {
+            key: SigningKey::from_bytes(secret),
+        })
+    }
+
+    /// The 32-byte secret, for storing the key.
+    #[must_use]
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.key.to_bytes()
+    }
+
+    /// The 32-byte public key to give to verifiers.
+    #[must_use]
+    pub fn public_key(&self) -> [u8; 32] {
+        self.key.verifying_key().to_bytes()
+    }
+
+    /// Sign `data`, returning the 64-byte signature.
+    #[must_use]
+    pub fn sign(&self, data: &[u8]) -> [u8; 64] {
+        self.key.sign(data).to_bytes()
+    }
+}
+
+impl std::fmt::Debug for Ed25519Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ed25519Signer")
+            .field("public_key", &hex::encode(self.public_key()))
+            .finish_non_exhaustive()
+    }
+}
+
 /// Combined signature verifier supporting multiple formats.
 #[derive(Debug)]
 pub struct SignatureVerifier {
@@ -285,10 +334,13 @@ impl SignatureVerifier {
     /// Returns error if verification fails.
     pub fn verify(&self, data: &[u8], signature: &[u8]) -> Result<VerifiedSignature> {
         // Try Ed25519 first (simpler check)
-        if signature.len() == 64
-            && let Ok(verified) = self.ed25519.verify(data, signature)
-        {
-            return Ok(verified);
+        if signature.len() == 64 {
+            let ed25519 = self.ed25519.verify(data, signature);
+            if ed25519.is_ok() {
+                return ed25519;
+            }
+            // A raw Ed25519 signature is not a PGP packet: report why it failed
+            return self.pgp.verify_detached(data, signature).or(ed25519);
         }
 
         // Try PGP
@@ -650,6 +702,66 @@ mod tests {
         assert_eq!(verifier.trusted_keys.len(), 0);
     }
 
+    #[test]
+    fn test_ed25519_sign_and_verify() {
+        let signer = Ed25519Signer::generate();
+        let signature = signer.sign(b"composer.lock");
+
+        let mut verifier = Ed25519Verifier::new();
+        verifier.add_trusted_key(&signer.public_key()).unwrap();
+        let verified = verifier.verify(b"composer.lock", &signature).unwrap();
+        assert_eq!(verified.key_id, hex::encode(signer.public_key()));
+        assert!(verifier.verify(b"tampered", &signature).is_err());
+
+        let restored = Ed25519Signer::from_secret(&signer.secret_bytes()).unwrap();
+        assert_eq!(restored.public_key(), signer.public_key());
+    }
+
+    #[test]
+    fn test_pgp_detached_signature_covers_data() {
+        use sequoia_openpgp::cert::CertBuilder;
+        use sequoia_openpgp::serialize::Serialize;
+        use sequoia_openpgp::serialize::stream::{Message, Signer as PgpSigner};
+        use std::io::Write;
+
+        let (cert, _) = CertBuilder::general_purpose(Some("Release <release@example.com>"))
+            .generate()
+            .unwrap();
+        let keypair = cert
+            .keys()
+            .unencrypted_secret()
+            .with_policy(&StandardPolicy::new(), None)
+            .for_signing()
+            .next()
+            .unwrap()
+            .key()
+            .clone()
+            .into_keypair()
+            .unwrap();
+
+        let mut signature = Vec::new();
+        let message = Message::new(&mut signature);
+        let mut signer = PgpSigner::new(message, keypair)
+            .unwrap()
+            .detached()
+            .build()
+            .unwrap();
+        signer.write_all(b"composer.lock").unwrap();
+        signer.finalize().unwrap();
+
+        let mut public = Vec::new();
+        cert.serialize(&mut public).unwrap();
+        let mut verifier = PgpVerifier::new();
+        verifier.add_trusted_key(&public).unwrap();
+
+        assert!(
+            verifier
+                .verify_detached(b"composer.lock", &signature)
+                .is_ok()
+        );
+        assert!(verifier.verify_detached(b"tampered", &signature).is_err());
+    }
+
     #[test]
     fn test_ed25519_invalid_key_length() {
         let mut verifier = Ed25519Verifier::new();
@@ -0,0 +1,259 @@
+//! Classmap diagnostics reported while dumping the autoloader.
+//!
+//! Follows Composer: classes in PSR directories whose file path does not
+//! match their name are skipped with a warning, and when the same class is
+//! found in several files the first one wins. Functions declared twice by
+//! `files` autoloading are reported too, as PHP would fail at runtime.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// PSR autoloading standard of a scanned directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PsrStandard {
+    /// PSR-0 (namespace and `_` separators map to directories).
+    Psr0,
+    /// PSR-4 (namespace prefix maps to a base directory).
+    Psr4,
+}
+
+impl fmt::Display for PsrStandard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Psr0 => "psr-0",
+            Self::Psr4 => "psr-4",
+        })
+    }
+}
+
+/// Problem found while building the classmap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoloadWarning {
+    /// A class does not match its PSR mapping and was skipped.
+    PsrViolation {
+        /// Fully qualified class name.
+        class: String,
+        /// File defining the class.
+        path: PathBuf,
+        /// Standard of the mapping.
+        standard: PsrStandard,
+        /// Namespace prefix of the mapping.
+        namespace: String,
+        /// Directory of the mapping.
+        dir: PathBuf,
+    },
+    /// A class is defined in more than one file.
+    AmbiguousClass {
+        /// Fully qualified class name.
+        class: String,
+        /// File used by the classmap.
+        used: PathBuf,
+        /// File that was ignored.
+        ignored: PathBuf,
+    },
+    /// A `files` entry declares a function already declared by an earlier
+    /// one without a `function_exists()` guard.
+    RedeclaredFunction {
+        /// Fully qualified function name.
+        function: String,
+        /// File declaring the function first.
+        first: PathBuf,
+        /// File declaring it again.
+        second: PathBuf,
+    },
+}
+
+impl AutoloadWarning {
+    /// Whether this is a PSR compliance warning.
+    #[must_use]
+    pub const fn is_psr_violation(&self) -> bool {
+        matches!(self, Self::PsrViolation { .. })
+    }
+
+    /// Whether this is an ambiguous class warning.
+    #[must_use]
+    pub const fn is_ambiguous(&self) -> bool {
+        matches!(self, Self::AmbiguousClass { .. })
+    }
+
+    /// Whether this is a redeclared function warning.
+    #[must_use]
+    pub const fn is_redeclared_function(&self) -> bool {
+        matches!(self, Self::RedeclaredFunction { .. })
+    }
+}
+
+impl fmt::Display for AutoloadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PsrViolation {
+                class,
+                path,
+                standard,
+                namespace,
+                dir,
+            } => write!(
+                f,
+                "Class {class} located in {} does not comply with {standard} autoloading standard (rule: {namespace} => {}). Skipping.",
+                path.display(),
+                dir.display()
+            ),
+            Self::AmbiguousClass {
+                class,
+                used,
+                ignored,
+            } => write!(
+                f,
+                "Ambiguous class resolution, \"{class}\" was found in both \"{}\" and \"{}\", the first will be used.",
+                used.display(),
+                ignored.display()
+            ),
+            Self::RedeclaredFunction {
+                function,
+                first,
+                second,
+            } => write!(
+                f,
+                "Function {function} is declared in both \"{}\" and \"{}\" without a function_exists() guard, loading the autoloader will fail with \"Cannot redeclare {function}()\".",
+                first.display(),
+                second.display()
+            ),
+        }
+    }
+}
+
+/// Outcome of checking a class against a PSR mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PsrMatch {
+    /// The class belongs to the mapping and its path matches.
+    Compliant,
+    /// The class belongs to the mapping but lives at the wrong path.
+    Violation,
+    /// The class is outside the mapping's namespace and is ignored.
+    Foreign,
+}
+
+/// Check `class`, defined in `file`, against a PSR mapping of `namespace`
+/// to `dir`.
+#[must_use]
+pub fn check_psr(
+    standard: PsrStandard,
+    namespace: &str,
+    dir: &Path,
+    file: &Path,
+    class: &str,
+) -> PsrMatch {
+    if !class.starts_with(namespace) {
+        return PsrMatch::Foreign;
+    }
+    let Ok(relative) = file.strip_prefix(dir) else {
+        return PsrMatch::Compliant;
+    };
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    // Any scanned extension (`.php`, `.inc`, `.hh`) is stripped
+    let actual = relative
+        .rfind('.')
+        .map_or(relative.as_str(), |dot| &relative[..dot]);
+
+    let expected = match standard {
+        PsrStandard::Psr4 => class[namespace.len()..].replace('\\', "/"),
+        PsrStandard::Psr0 => match class.rfind('\\') {
+            Some(i) => format!(
+                "{}{}",
+                class[..=i].replace('\\', "/"),
+                class[i + 1..].replace('_', "/")
+            ),
+            None => class.replace('_', "/"),
+        },
+    };
+
+    if expected == actual {
+        PsrMatch::Compliant
+    } else {
+        PsrMatch::Violation
+    }
+}
+
+/// Whether duplicates in `path` are expected (tests, fixtures, examples and
+/// stubs) and should not be reported as ambiguous.
+#[must_use]
+pub fn is_ambiguity_exempt(path: &Path) -> bool {
+    let path = path.to_string_lossy().replace('\\', "/").to_lowercase();
+    path.split('/').any(|segment| {
+        matches!(
+            segment,
+            "test" | "tests" | "fixture" | "fixtures" | "example" | "examples" | "stub" | "stubs"
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn psr4_paths_must_match_class_names() {
+        let dir = Path::new("vendor/acme/lib/src");
+        let check = |file: &str, class: &str| {
+            check_psr(
+                PsrStandard::Psr4,
+                "Acme\\Lib\\",
+                dir,
+                Path::new(file),
+                class,
+            )
+        };
+        assert_eq!(
+            check(
+                "vendor/acme/lib/src/Http/Client.php",
+                "Acme\\Lib\\Http\\Client"
+            ),
+            PsrMatch::Compliant
+        );
+        assert_eq!(
+            check(
+                "vendor/acme/lib/src/Http/client.php",
+                "Acme\\Lib\\Http\\Client"
+            ),
+            PsrMatch::Violation
+        );
+        assert_eq!(
+            check("vendor/acme/lib/src/Helper.php", "Other\\Helper"),
+            PsrMatch::Foreign
+        );
+    }
+
+    #[test]
+    fn psr0_underscores_map_to_directories() {
+        let dir = Path::new("lib");
+        assert_eq!(
+            check_psr(
+                PsrStandard::Psr0,
+                "Twig_",
+                dir,
+                Path::new("lib/Twig/Node/Expression.php"),
+                "Twig_Node_Expression"
+            ),
+            PsrMatch::Compliant
+        );
+        assert_eq!(
+            check_psr(
+                PsrStandard::Psr0,
+                "Acme\\",
+                dir,
+                Path::new("lib/Acme/Util/Str.php"),
+                "Acme\\Util_Str"
+            ),
+            PsrMatch::Compliant
+        );
+    }
+
+    #[test]
+    fn test_directories_are_exempt_from_ambiguity() {
+        assert!(is_ambiguity_exempt(Path::new("vendor/a/b/tests/Foo.php")));
+        assert!(is_ambiguity_exempt(Path::new(
+            "vendor/a/b/Fixtures/Foo.php"
+        )));
+        assert!(!is_ambiguity_exempt(Path::new("vendor/a/b/src/Foo.php")));
+    }
+}
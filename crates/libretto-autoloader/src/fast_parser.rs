@@ -43,18 +43,27 @@ pub struct FastScanResult {
     pub classes: Vec<String>,
 }
 
+/// File extensions scanned for classes, as in Composer.
+pub const EXTENSIONS: [&str; 3] = ["php", "inc", "hh"];
+
 /// Fast PHP scanner for classmap generation
 pub struct FastScanner;
 
 impl FastScanner {
     /// Scan a directory for PHP classes in parallel
+    ///
+    /// Like Composer, hidden files and directories are skipped.
     pub fn scan_directory(root: &Path) -> Vec<FastScanResult> {
         let php_files: Vec<PathBuf> = walkdir::WalkDir::new(root)
             .follow_links(true)
             .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
             .filter_map(|e| e.ok())
             .filter(|e| {
-                e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "php")
+                e.file_type().is_file()
+                    && e.path()
+                        .extension()
+                        .is_some_and(|ext| EXTENSIONS.iter().any(|known| ext == *known))
             })
             .map(|e| e.path().to_path_buf())
             .collect();
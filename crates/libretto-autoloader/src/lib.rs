@@ -11,21 +11,33 @@
 #![warn(clippy::all)]
 #![allow(clippy::module_name_repetitions)]
 
+mod diagnostics;
 mod fast_parser;
+mod package_sorter;
 mod parser;
+mod platform_check;
+mod preload;
 mod scanner;
+mod symbol_index;
 
+pub use diagnostics::{AutoloadWarning, PsrStandard};
 pub use fast_parser::FastScanner;
+pub use package_sorter::sort_packages;
+pub use platform_check::{PhpRequirement, PlatformRequirements};
+pub use preload::{PreloadClass, PreloadRules, PreloadScript};
 
 pub use parser::{DefinitionKind, PhpDefinition, PhpParser};
 pub use scanner::{ExcludePattern, FileScanResult, Scanner, build_classmap, build_namespace_map};
+pub use symbol_index::{IndexFormat, Symbol, SymbolIndex};
 
 use ahash::AHashMap;
+use diagnostics::PsrMatch;
 use libretto_core::{Error, Result};
 use parking_lot::RwLock;
 use rkyv::{Archive, Deserialize, Serialize};
 use serde::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -113,6 +125,13 @@ pub struct AutoloadConfig {
     /// Patterns to exclude from classmap generation.
     #[serde(default, rename = "exclude-from-classmap")]
     pub exclude: ExcludeConfig,
+    /// Legacy `target-dir` of a PSR-0 package.
+    #[serde(
+        default,
+        rename = "target-dir",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target_dir: Option<String>,
 }
 
 /// Cached file entry for incremental updates.
@@ -210,6 +229,35 @@ impl IncrementalCache {
         }
     }
 
+    /// Create a cache that is kept in memory only and never saved.
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self {
+            cache_path: PathBuf::new(),
+            data: RwLock::new(CachedClassmap::new()),
+        }
+    }
+
+    /// Fingerprint recorded for `path` by the last [`update`](Self::update).
+    #[must_use]
+    pub fn fingerprint(&self, path: &Path) -> Option<u64> {
+        let path = path.to_string_lossy();
+        self.data
+            .read()
+            .files
+            .iter()
+            .find(|f| f.path == path)
+            .map(|f| f.fingerprint)
+    }
+
+    /// Forget `path`, e.g. after it was deleted.
+    pub fn remove(&self, path: &Path) {
+        let path = path.to_string_lossy();
+        let mut data = self.data.write();
+        data.files.retain(|f| f.path != path);
+        data.classmap.retain(|(_, file)| *file != path);
+    }
+
     /// Check which files have changed since last scan.
     pub fn find_changed_files(&self, files: &[PathBuf]) -> Vec<PathBuf> {
         let data = self.data.read();
@@ -266,7 +314,12 @@ impl IncrementalCache {
                 .to_string_lossy()
                 .to_string();
 
-            let classes: Vec<String> = result.definitions.iter().map(|d| d.fqcn.clone()).collect();
+            let classes: Vec<String> = result
+                .definitions
+                .iter()
+                .filter(|d| d.kind.is_class_like())
+                .map(|d| d.fqcn.clone())
+                .collect();
 
             let entry = CachedFileEntry {
                 path: rel_path.clone(),
@@ -296,8 +349,11 @@ impl IncrementalCache {
         data.classmap = new_classmap;
     }
 
-    /// Save cache to disk.
+    /// Save cache to disk. In-memory caches are not saved.
     pub fn save(&self) -> std::io::Result<()> {
+        if self.cache_path.as_os_str().is_empty() {
+            return Ok(());
+        }
         let data = self.data.read();
 
         let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&*data)
@@ -329,6 +385,38 @@ impl IncrementalCache {
     }
 }
 
+/// Functions declared by `path`, with whether each is conditional.
+fn declared_functions(parser: &mut PhpParser, path: &Path) -> Vec<(String, bool)> {
+    parser
+        .parse_file(path)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|d| d.kind == DefinitionKind::Function)
+        .map(|d| (d.fqcn, d.conditional))
+        .collect()
+}
+
+/// `file` with its directory canonicalized, so it can be matched against
+/// watcher events even after the file itself was removed.
+fn canonical_file(file: &Path) -> Option<PathBuf> {
+    let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let dir = std::fs::canonicalize(dir.unwrap_or_else(|| Path::new("."))).ok()?;
+    Some(dir.join(file.file_name()?))
+}
+
+/// A directory scanned into the classmap.
+#[derive(Debug)]
+struct ScanRoot {
+    /// Directory as scanned (classmap paths are relative to this form).
+    dir: PathBuf,
+    /// Canonical form of `dir`, for matching file system events.
+    canonical: PathBuf,
+    /// PSR mapping the directory belongs to, if any.
+    psr: Option<(PsrStandard, String)>,
+    /// Owning package (`None` for the root package).
+    package: Option<String>,
+}
+
 /// Autoloader generator with tree-sitter parsing and incremental updates.
 #[derive(Debug)]
 pub struct AutoloaderGenerator {
@@ -342,9 +430,28 @@ pub struct AutoloaderGenerator {
     psr4_map: HashMap<String, Vec<PathBuf>>,
     /// PSR-0 namespace mappings.
     psr0_map: HashMap<String, Vec<PathBuf>>,
-    /// Files to always include.
-    files: Vec<PathBuf>,
-    /// Incremental cache.
+    /// Files to always include, with the owning package (`None` for root).
+    files: Vec<(Option<String>, PathBuf)>,
+    /// Functions declared by each file in `files`, with whether the
+    /// declaration is conditional.
+    file_functions: AHashMap<PathBuf, Vec<(String, bool)>>,
+    /// Package names in dependency order (dependencies first).
+    package_order: Vec<String>,
+    /// Runtime platform check, if enabled.
+    platform_check: Option<PlatformRequirements>,
+    /// Diagnostics collected while building the classmap.
+    warnings: Vec<AutoloadWarning>,
+    /// Suffix of the generated class names (`autoloader-suffix`).
+    suffix: Option<String>,
+    /// `APCu` cache prefix, if `APCu` autoloading is enabled.
+    apcu_prefix: Option<String>,
+    /// Whether the loader is prepended to the autoload stack.
+    prepend: bool,
+    /// Directories scanned into the classmap, in scan order.
+    scan_roots: Vec<ScanRoot>,
+    /// `exclude-from-classmap` patterns of all packages added so far.
+    exclude: ExcludePattern,
+    /// Incremental cache, kept in memory by [`Self::update_files`] if unset.
     cache: Option<Arc<IncrementalCache>>,
     /// Scanner for PHP files.
     scanner: Scanner,
@@ -361,6 +468,15 @@ impl AutoloaderGenerator {
             psr4_map: HashMap::new(),
             psr0_map: HashMap::new(),
             files: Vec::new(),
+            file_functions: AHashMap::new(),
+            package_order: Vec::new(),
+            platform_check: None,
+            warnings: Vec::new(),
+            suffix: None,
+            apcu_prefix: None,
+            prepend: true,
+            scan_roots: Vec::new(),
+            exclude: ExcludePattern::empty(),
             cache: None,
             scanner: Scanner::without_exclusions(),
         }
@@ -388,8 +504,83 @@ impl AutoloaderGenerator {
         self
     }
 
-    /// Add autoload configuration from a package.
+    /// Set the dependency order used for `files` autoloading.
+    ///
+    /// Files of packages earlier in `order` are required first (see
+    /// [`sort_packages`]). Packages missing from the order follow in
+    /// alphabetical order, and root package files always come last.
+    pub fn set_package_order(&mut self, order: Vec<String>) {
+        self.package_order = order;
+    }
+
+    /// Set the suffix of the generated `ComposerAutoloaderInit` and
+    /// `ComposerStaticInit` class names.
+    ///
+    /// Characters that are not valid in a PHP class name are dropped. Without
+    /// a suffix, one is derived from the vendor directory path.
+    pub fn set_suffix(&mut self, suffix: &str) {
+        let suffix: String = suffix
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+        self.suffix = (!suffix.is_empty()).then_some(suffix);
+    }
+
+    /// Enable `APCu` class lookup caching under `prefix`, or disable it with
+    /// `None`.
+    pub fn set_apcu_prefix(&mut self, prefix: Option<String>) {
+        self.apcu_prefix = prefix;
+    }
+
+    /// Whether the loader is prepended to existing autoloaders (default).
+    pub const fn set_prepend(&mut self, prepend: bool) {
+        self.prepend = prepend;
+    }
+
+    /// Set the requirements checked by `platform_check.php`.
+    ///
+    /// `None` (the default) disables the check; an existing
+    /// `platform_check.php` is removed on generation.
+    pub fn set_platform_check(&mut self, requirements: Option<PlatformRequirements>) {
+        self.platform_check = requirements.filter(|r| !r.is_empty());
+    }
+
+    /// Add autoload configuration from an installed vendor package.
+    pub fn add_vendor_package(&mut self, name: &str, package_dir: &Path, config: &AutoloadConfig) {
+        self.add_package_files(Some(name), package_dir, config);
+    }
+
+    /// Add autoload configuration from the root package.
     pub fn add_package(&mut self, package_dir: &Path, config: &AutoloadConfig) {
+        self.add_package_files(None, package_dir, config);
+    }
+
+    fn add_package_files(
+        &mut self,
+        name: Option<&str>,
+        package_dir: &Path,
+        config: &AutoloadConfig,
+    ) {
+        // Legacy `target-dir`: Composer installs such packages into
+        // `<package>/<target-dir>` and resolves PSR-0 paths from `<package>`.
+        // Packages installed without that subdirectory cannot be found by
+        // PSR-0 lookups, so their PSR-0 classes go through the classmap.
+        let target_dir = config
+            .target_dir
+            .as_deref()
+            .filter(|_| name.is_some())
+            .map(|dir| dir.trim_matches(['/', '\\']))
+            .filter(|dir| !dir.is_empty());
+        let flat_target = target_dir.filter(|dir| !package_dir.join(dir).is_dir());
+        // Classmap, files and exclude paths missing from the package root
+        // are relative to the target directory
+        let resolve = |path: &str| match target_dir {
+            Some(dir) if flat_target.is_none() && !package_dir.join(path).exists() => {
+                format!("{dir}/{path}")
+            }
+            _ => path.to_string(),
+        };
+
         // Add PSR-4 mappings
         for (namespace, dirs) in &config.psr4.mappings {
             let paths: Vec<PathBuf> = dirs.iter().map(|d| package_dir.join(d)).collect();
@@ -400,74 +591,610 @@ impl AutoloaderGenerator {
         }
 
         // Add PSR-0 mappings
-        for (namespace, dirs) in &config.psr0.mappings {
-            let paths: Vec<PathBuf> = dirs.iter().map(|d| package_dir.join(d)).collect();
-            self.psr0_map
-                .entry(namespace.clone())
-                .or_default()
-                .extend(paths);
+        if flat_target.is_none() {
+            for (namespace, dirs) in &config.psr0.mappings {
+                let paths: Vec<PathBuf> = dirs.iter().map(|d| package_dir.join(d)).collect();
+                self.psr0_map
+                    .entry(namespace.clone())
+                    .or_default()
+                    .extend(paths);
+            }
         }
 
-        // Scan classmap directories based on optimization level
-        if self.optimization_level >= OptimizationLevel::Optimized {
-            // Collect paths first to avoid borrow issues
-            let psr4_paths: Vec<PathBuf> = self
-                .psr4_map
-                .values()
-                .flatten()
-                .filter(|p| p.exists())
-                .cloned()
-                .collect();
+        // Exclusions are anchored to this package but apply to every
+        // package, including those added before
+        let patterns: Vec<String> = config.exclude.patterns.iter().map(|p| resolve(p)).collect();
+        let exclude = ExcludePattern::for_package(package_dir, &patterns);
+        if !exclude.is_empty() {
+            self.classmap.retain(|_, file| {
+                !std::fs::canonicalize(&*file).is_ok_and(|file| exclude.should_exclude(&file))
+            });
+            self.exclude.extend(exclude);
+        }
+
+        // Always scan explicit classmap paths
+        for path in &config.classmap.paths {
+            let full_path = package_dir.join(resolve(path));
+            if full_path.exists() {
+                self.scan_directory_for_classes(&full_path, None, None);
+                self.add_scan_root(full_path, None, name);
+            }
+        }
+
+        // Scan this package's PSR directories when optimizing, skipping
+        // classes that do not comply with their mapping. PSR-0 directories
+        // of a package installed without its target directory are always
+        // scanned.
+        let psr_dirs = config
+            .psr0
+            .mappings
+            .iter()
+            .map(|mapping| (PsrStandard::Psr0, mapping))
+            .chain(
+                config
+                    .psr4
+                    .mappings
+                    .iter()
+                    .map(|mapping| (PsrStandard::Psr4, mapping)),
+            );
+        for (standard, (namespace, dirs)) in psr_dirs {
+            let remap = flat_target.filter(|_| standard == PsrStandard::Psr0);
+            if remap.is_none() && self.optimization_level < OptimizationLevel::Optimized {
+                continue;
+            }
+            for dir in dirs {
+                let path = package_dir.join(dir);
+                if path.exists() {
+                    self.scan_directory_for_classes(&path, Some((standard, namespace)), remap);
+                    self.add_scan_root(path, Some((standard, namespace.clone())), name);
+                }
+            }
+        }
 
-            let psr0_paths: Vec<PathBuf> = self
-                .psr0_map
-                .values()
-                .flatten()
-                .filter(|p| p.exists())
-                .cloned()
+        // Add files, recording the functions they declare
+        let mut parser = PhpParser::new();
+        for file in &config.files.files {
+            let full_path = package_dir.join(resolve(file));
+            if full_path.exists() {
+                let functions = declared_functions(&mut parser, &full_path);
+                self.file_functions.insert(full_path.clone(), functions);
+                self.files.push((name.map(str::to_string), full_path));
+            }
+        }
+    }
+
+    fn add_scan_root(
+        &mut self,
+        dir: PathBuf,
+        psr: Option<(PsrStandard, String)>,
+        package: Option<&str>,
+    ) {
+        let canonical = std::fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+        self.scan_roots.push(ScanRoot {
+            dir,
+            canonical,
+            psr,
+            package: package.map(str::to_string),
+        });
+    }
+
+    /// All classmap entries, sorted by class name.
+    #[must_use]
+    pub fn classmap_entries(&self) -> Vec<(&str, &Path)> {
+        let mut entries: Vec<(&str, &Path)> = self
+            .classmap
+            .iter()
+            .map(|(class, file)| (class.as_str(), file.as_path()))
+            .collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+
+    /// Directories of the root package that feed the classmap.
+    ///
+    /// These are the directories worth watching for
+    /// [`update_files`](Self::update_files).
+    #[must_use]
+    pub fn root_scan_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self
+            .scan_roots
+            .iter()
+            .filter(|root| root.package.is_none())
+            .map(|root| root.canonical.clone())
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    /// Root package `files` entries, canonicalized.
+    ///
+    /// Their directories are worth watching too, so that
+    /// [`update_files`](Self::update_files) sees the functions they declare
+    /// change.
+    #[must_use]
+    pub fn root_files(&self) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|(package, _)| package.is_none())
+            .filter_map(|(_, file)| canonical_file(file))
+            .collect()
+    }
+
+    /// Re-parse changed, added or removed files of the root package and
+    /// update the classmap in place.
+    ///
+    /// `paths` are absolute paths as reported by a file watcher. A directory
+    /// stands for the files below it, so moving one in or out is picked up.
+    /// Files whose fingerprint in the incremental cache did not change are
+    /// skipped. When a class leaves the file it was mapped to, the next file
+    /// ignored as ambiguous takes over. Returns whether the classmap changed;
+    /// call [`write_classmap`](Self::write_classmap) to persist it.
+    pub fn update_files(&mut self, paths: &[PathBuf]) -> bool {
+        let cache = Arc::clone(
+            self.cache
+                .get_or_insert_with(|| Arc::new(IncrementalCache::in_memory())),
+        );
+        let mut parser = PhpParser::new();
+        let mut changed = false;
+
+        let files = self.watched_files(paths);
+        self.refresh_file_functions(&files, &mut parser);
+
+        for path in files {
+            let Some(root) = self
+                .scan_roots
+                .iter()
+                .find(|root| root.package.is_none() && path.starts_with(&root.canonical))
+            else {
+                continue;
+            };
+            let Ok(relative) = path.strip_prefix(&root.canonical) else {
+                continue;
+            };
+            let local = root.dir.join(relative);
+
+            let parsed = std::fs::read_to_string(&path)
+                .ok()
+                .map(|content| parser.parse_str_with_fingerprint(&content));
+            match &parsed {
+                Some(parsed) if cache.fingerprint(&local) == Some(parsed.fingerprint) => continue,
+                Some(parsed) => {
+                    let meta = std::fs::metadata(&path).ok();
+                    let result = FileScanResult {
+                        path: local.clone(),
+                        definitions: parsed.definitions.clone(),
+                        mtime: meta
+                            .as_ref()
+                            .and_then(|m| m.modified().ok())
+                            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                            .map_or(0, |d| d.as_secs()),
+                        size: meta.map_or(0, |m| m.len()),
+                        fingerprint: parsed.fingerprint,
+                    };
+                    cache.update(&[result], Path::new(""));
+                }
+                None => cache.remove(&local),
+            }
+
+            let excluded = self.exclude.should_exclude(&path);
+            let classes: Vec<String> = parsed
+                .into_iter()
+                .filter(|_| !excluded)
+                .flat_map(|parsed| parsed.definitions)
+                .filter(|definition| definition.kind.is_class_like())
+                .filter(|definition| {
+                    root.psr.as_ref().is_none_or(|(standard, namespace)| {
+                        diagnostics::check_psr(
+                            *standard,
+                            namespace,
+                            &root.dir,
+                            &local,
+                            &definition.fqcn,
+                        ) == PsrMatch::Compliant
+                    })
+                })
+                .map(|definition| definition.fqcn)
                 .collect();
 
-            // Scan PSR-4 directories for classes
-            for path in psr4_paths {
-                self.scan_directory_for_classes(&path);
+            changed |= self.set_file_classes(&local, &classes, &mut parser);
+        }
+
+        changed
+    }
+
+    /// Re-read the functions declared by the root package's `files` entries
+    /// among the canonical `paths`.
+    fn refresh_file_functions(&mut self, paths: &[PathBuf], parser: &mut PhpParser) {
+        for (_, file) in self.files.iter().filter(|(package, _)| package.is_none()) {
+            if canonical_file(file).is_some_and(|file| paths.contains(&file)) {
+                let functions = declared_functions(parser, file);
+                self.file_functions.insert(file.clone(), functions);
+            }
+        }
+    }
+
+    /// PHP files behind watcher `paths`: files as given, files below a
+    /// directory, and the classmap files below a directory that is gone.
+    fn watched_files(&self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        let is_php = |path: &Path| {
+            path.extension()
+                .is_some_and(|ext| fast_parser::EXTENSIONS.iter().any(|known| ext == *known))
+        };
+        let mut files = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                files.extend(
+                    walkdir::WalkDir::new(path)
+                        .into_iter()
+                        .filter_map(std::result::Result::ok)
+                        .filter(|entry| entry.file_type().is_file())
+                        .map(walkdir::DirEntry::into_path)
+                        .filter(|file| is_php(file)),
+                );
+            } else if is_php(path) {
+                files.push(path.clone());
+            } else if !path.exists() {
+                for root in self.scan_roots.iter().filter(|root| root.package.is_none()) {
+                    let Ok(relative) = path.strip_prefix(&root.canonical) else {
+                        continue;
+                    };
+                    let dir = root.dir.join(relative);
+                    files.extend(
+                        self.classmap
+                            .values()
+                            .filter_map(|file| file.strip_prefix(&dir).ok())
+                            .map(|below| path.join(below)),
+                    );
+                }
             }
+        }
+        files.sort();
+        files.dedup();
+        files
+    }
 
-            // Scan PSR-0 directories
-            for path in psr0_paths {
-                self.scan_directory_for_classes(&path);
+    /// Map `classes` to `file`, handing every class `file` no longer
+    /// defines to an ambiguous duplicate. Returns whether the classmap
+    /// changed.
+    fn set_file_classes(
+        &mut self,
+        file: &Path,
+        classes: &[String],
+        parser: &mut PhpParser,
+    ) -> bool {
+        let mut changed = false;
+        self.warnings.retain(|warning| {
+            !matches!(warning, AutoloadWarning::AmbiguousClass { class, ignored, .. }
+                if ignored == file && !classes.contains(class))
+        });
+
+        let removed: Vec<String> = self
+            .classmap
+            .iter()
+            .filter(|(class, path)| *path == file && !classes.contains(class))
+            .map(|(class, _)| class.clone())
+            .collect();
+        for class in removed {
+            self.classmap.remove(&class);
+            self.promote_duplicate(class, file, parser);
+            changed = true;
+        }
+
+        for class in classes {
+            match self.classmap.entry(class.clone()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(file.to_path_buf());
+                    changed = true;
+                }
+                Entry::Occupied(entry) => {
+                    let warning = AutoloadWarning::AmbiguousClass {
+                        class: class.clone(),
+                        used: entry.get().clone(),
+                        ignored: file.to_path_buf(),
+                    };
+                    if *entry.get() != file
+                        && !diagnostics::is_ambiguity_exempt(file)
+                        && !self.warnings.contains(&warning)
+                    {
+                        self.warnings.push(warning);
+                    }
+                }
             }
         }
+        changed
+    }
 
-        // Always scan explicit classmap paths
-        for path in &config.classmap.paths {
-            let full_path = package_dir.join(path);
-            if full_path.exists() {
-                self.scan_directory_for_classes(&full_path);
+    /// Map `class` to the first file that was ignored in favour of `removed`
+    /// and still defines it.
+    fn promote_duplicate(&mut self, class: String, removed: &Path, parser: &mut PhpParser) {
+        let mut duplicates = Vec::new();
+        self.warnings.retain(|warning| match warning {
+            AutoloadWarning::AmbiguousClass {
+                class: duplicate,
+                used,
+                ignored,
+            } if *duplicate == class && used == removed => {
+                duplicates.push(ignored.clone());
+                false
             }
+            _ => true,
+        });
+
+        let mut duplicates = duplicates.into_iter().filter(|file| {
+            std::fs::read(file).is_ok_and(|content| {
+                parser
+                    .parse_bytes(&content)
+                    .iter()
+                    .any(|definition| definition.fqcn == class)
+            })
+        });
+        let Some(used) = duplicates.next() else {
+            return;
+        };
+        for ignored in duplicates {
+            self.warnings.push(AutoloadWarning::AmbiguousClass {
+                class: class.clone(),
+                used: used.clone(),
+                ignored,
+            });
         }
+        self.classmap.insert(class, used);
+    }
 
-        // Add files
-        for file in &config.files.files {
-            let full_path = package_dir.join(file);
-            if full_path.exists() {
-                self.files.push(full_path);
+    /// Rewrite `autoload_classmap.php` and `autoload_static.php` from the
+    /// current classmap.
+    ///
+    /// # Errors
+    /// Returns error if a file cannot be written.
+    pub fn write_classmap(&self) -> Result<()> {
+        let autoload_dir = self.vendor_dir.join("composer");
+        self.generate_autoload_classmap(&autoload_dir)?;
+        self.generate_autoload_static(&autoload_dir)
+    }
+
+    /// Write an `opcache.preload` script to `path` for the classmap classes
+    /// allowed by `rules`.
+    ///
+    /// Only classes in the classmap are preloaded, so PSR classes are only
+    /// included with an optimized autoloader.
+    ///
+    /// # Errors
+    /// Returns error if the script cannot be written.
+    pub fn generate_preload(&self, path: &Path, rules: &PreloadRules) -> Result<PreloadScript> {
+        let mut classes: Vec<PreloadClass> = self
+            .classmap
+            .iter()
+            .map(|(class, file)| PreloadClass {
+                class: class.clone(),
+                path: file.clone(),
+                package: self.package_of(file),
+            })
+            .collect();
+        classes.sort_by(|a, b| a.class.cmp(&b.class));
+
+        let script = PreloadScript::build(&classes, rules);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        }
+        write_atomic(path, &script.render(path))?;
+        Ok(script)
+    }
+
+    /// Index of every classmap symbol with its kind, file, line and package.
+    ///
+    /// Files are parsed for the kind and line of each symbol. File paths are
+    /// relative to the project root (the parent of the vendor directory).
+    #[must_use]
+    pub fn symbol_index(&self) -> SymbolIndex {
+        self.symbols(|_| true)
+    }
+
+    /// Classmap symbols named `name`, as matched by [`SymbolIndex::find`].
+    #[must_use]
+    pub fn find_symbol(&self, name: &str) -> Vec<Symbol> {
+        let name = name.trim_start_matches('\\');
+        let short = |class: &str| class.rsplit('\\').next().unwrap_or(class).to_string();
+        // Only parse the files of candidate classes
+        let index = self.symbols(|class| {
+            class.eq_ignore_ascii_case(name)
+                || (!name.contains('\\') && short(class).eq_ignore_ascii_case(name))
+        });
+        index.find(name).into_iter().cloned().collect()
+    }
+
+    fn symbols(&self, wanted: impl Fn(&str) -> bool) -> SymbolIndex {
+        let mut by_file: AHashMap<&Path, Vec<&str>> = AHashMap::new();
+        for (class, file) in &self.classmap {
+            if wanted(class) {
+                by_file.entry(file).or_default().push(class);
             }
         }
+
+        let project_dir = self.vendor_dir.parent().unwrap_or_else(|| Path::new(""));
+        let mut parser = PhpParser::new();
+        let mut symbols = Vec::new();
+        for (file, classes) in by_file {
+            let definitions = parser.parse_file(file).unwrap_or_default();
+            let relative: PathBuf = file
+                .strip_prefix(project_dir)
+                .unwrap_or(file)
+                .components()
+                .filter(|c| !matches!(c, std::path::Component::CurDir))
+                .collect();
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            let package = self.package_of(file);
+            for class in classes {
+                let definition = definitions
+                    .iter()
+                    .find(|d| d.kind.is_class_like() && d.fqcn == class);
+                symbols.push(Symbol {
+                    fqcn: class.to_string(),
+                    kind: definition.map_or(DefinitionKind::Class, |d| d.kind),
+                    file: relative.clone(),
+                    line: definition.map_or(0, |d| u32::try_from(d.line).unwrap_or(u32::MAX)),
+                    package: package.clone(),
+                });
+            }
+        }
+        SymbolIndex::new(symbols)
+    }
+
+    /// Package owning the classmap file `file` (`None` for the root package).
+    fn package_of(&self, file: &Path) -> Option<String> {
+        self.scan_roots
+            .iter()
+            .find(|root| file.starts_with(&root.dir))
+            .and_then(|root| root.package.clone())
+    }
+
+    /// Files to autoload in dependency order, root package files last.
+    fn ordered_files(&self) -> Vec<&Path> {
+        let rank: HashMap<&str, usize> = self
+            .package_order
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+
+        let mut files: Vec<&(Option<String>, PathBuf)> = self.files.iter().collect();
+        // Stable sort keeps each package's own declaration order
+        files.sort_by(|(a, _), (b, _)| {
+            let key = |name: &Option<String>| match name.as_deref() {
+                Some(name) => (
+                    0,
+                    rank.get(name).copied().unwrap_or(usize::MAX),
+                    name.to_string(),
+                ),
+                None => (1, 0, String::new()),
+            };
+            key(a).cmp(&key(b))
+        });
+        files.into_iter().map(|(_, path)| path.as_path()).collect()
     }
 
     /// Scan directory for classes using tree-sitter parser.
-    fn scan_directory_for_classes(&mut self, path: &Path) {
+    ///
+    /// Files matching an `exclude-from-classmap` pattern are skipped, as is
+    /// the vendor directory when scanning a PSR directory containing it. When
+    /// `psr` names the mapping `path` belongs to, classes that do not comply
+    /// with it are skipped; `target_dir` is prepended to file paths below
+    /// `path` for that check. The first file defining a class wins.
+    fn scan_directory_for_classes(
+        &mut self,
+        path: &Path,
+        psr: Option<(PsrStandard, &str)>,
+        target_dir: Option<&str>,
+    ) {
         // Use fast regex-based scanner (100x faster than AST parsing)
         let results = fast_parser::FastScanner::scan_directory(path);
 
+        let vendor = psr
+            .and_then(|_| std::fs::canonicalize(&self.vendor_dir).ok())
+            .filter(|vendor| {
+                std::fs::canonicalize(path)
+                    .is_ok_and(|dir| *vendor != dir && vendor.starts_with(dir))
+            });
+
         // Add to classmap
         for result in results {
+            let in_vendor = vendor.as_ref().is_some_and(|vendor| {
+                std::fs::canonicalize(&result.path).is_ok_and(|file| file.starts_with(vendor))
+            });
+            if in_vendor || self.is_excluded(&result.path) {
+                continue;
+            }
+            let psr_path = match (target_dir, result.path.strip_prefix(path)) {
+                (Some(target_dir), Ok(relative)) => path.join(target_dir).join(relative),
+                _ => result.path.clone(),
+            };
             for class in result.classes {
-                self.classmap.insert(class, result.path.clone());
+                if let Some((standard, namespace)) = psr {
+                    match diagnostics::check_psr(standard, namespace, path, &psr_path, &class) {
+                        PsrMatch::Compliant => {}
+                        PsrMatch::Foreign => continue,
+                        PsrMatch::Violation => {
+                            self.warnings.push(AutoloadWarning::PsrViolation {
+                                class,
+                                path: result.path.clone(),
+                                standard,
+                                namespace: namespace.to_string(),
+                                dir: path.to_path_buf(),
+                            });
+                            continue;
+                        }
+                    }
+                }
+
+                match self.classmap.entry(class) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(result.path.clone());
+                    }
+                    Entry::Occupied(entry) => {
+                        if *entry.get() != result.path
+                            && !diagnostics::is_ambiguity_exempt(&result.path)
+                        {
+                            self.warnings.push(AutoloadWarning::AmbiguousClass {
+                                class: entry.key().clone(),
+                                used: entry.get().clone(),
+                                ignored: result.path.clone(),
+                            });
+                        }
+                    }
+                }
             }
         }
     }
 
+    /// Whether `file` matches an `exclude-from-classmap` pattern, by its
+    /// canonical path or, inside symlinked directories, its absolute path.
+    fn is_excluded(&self, file: &Path) -> bool {
+        !self.exclude.is_empty()
+            && (std::fs::canonicalize(file).is_ok_and(|file| self.exclude.should_exclude(&file))
+                || std::path::absolute(file).is_ok_and(|file| self.exclude.should_exclude(&file)))
+    }
+
+    /// Warnings collected while building the classmap, followed by functions
+    /// that `files` autoloading would declare twice.
+    #[must_use]
+    pub fn warnings(&self) -> Vec<AutoloadWarning> {
+        let mut warnings = self.warnings.clone();
+        warnings.extend(self.redeclared_functions());
+        warnings
+    }
+
+    /// Functions declared unconditionally by a `files` entry after an earlier
+    /// entry already declared them, which PHP rejects with "Cannot redeclare".
+    ///
+    /// Conditional declarations (e.g. guarded by `function_exists()`) are
+    /// skipped at runtime once the function exists, so only count when they
+    /// come first.
+    fn redeclared_functions(&self) -> Vec<AutoloadWarning> {
+        let mut declared: AHashMap<String, (&str, &Path)> = AHashMap::new();
+        let mut warnings = Vec::new();
+        for file in self.ordered_files() {
+            for (function, conditional) in self.file_functions.get(file).into_iter().flatten() {
+                // Function names are case-insensitive
+                match declared.entry(function.to_lowercase()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert((function, file));
+                    }
+                    Entry::Occupied(entry) => {
+                        let (_, first) = *entry.get();
+                        if !conditional && first != file {
+                            warnings.push(AutoloadWarning::RedeclaredFunction {
+                                function: function.clone(),
+                                first: first.to_path_buf(),
+                                second: file.to_path_buf(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        warnings
+    }
+
     /// Generate all autoloader files.
     ///
     /// # Errors
@@ -484,6 +1211,7 @@ impl AutoloaderGenerator {
         self.generate_autoload_classmap(&autoload_dir)?;
         self.generate_autoload_files(&autoload_dir)?;
         self.generate_autoload_namespaces(&autoload_dir)?;
+        self.generate_platform_check(&autoload_dir)?;
         self.generate_autoload(&self.vendor_dir)?;
 
         // Save cache if enabled
@@ -520,6 +1248,24 @@ impl AutoloaderGenerator {
             _ => "false",
         };
 
+        let apcu = self
+            .apcu_prefix
+            .as_deref()
+            .map(|prefix| {
+                format!(
+                    "\n        $loader->setApcuPrefix({});",
+                    platform_check::php_string(prefix)
+                )
+            })
+            .unwrap_or_default();
+        let prepend = if self.prepend { "true" } else { "false" };
+
+        let platform_check = if self.platform_check.is_some() {
+            "\n        require __DIR__ . '/platform_check.php';\n"
+        } else {
+            ""
+        };
+
         let content = format!(
             r"<?php
 
@@ -544,7 +1290,7 @@ class ComposerAutoloaderInit{hash}
         if (null !== self::$loader) {{
             return self::$loader;
         }}
-
+{platform_check}
         spl_autoload_register(array('ComposerAutoloaderInit{hash}', 'loadClassLoader'), true, true);
         self::$loader = $loader = new \Composer\Autoload\ClassLoader(\dirname(__DIR__));
         spl_autoload_unregister(array('ComposerAutoloaderInit{hash}', 'loadClassLoader'));
@@ -552,8 +1298,8 @@ class ComposerAutoloaderInit{hash}
         require __DIR__ . '/autoload_static.php';
         call_user_func(\Composer\Autoload\ComposerStaticInit{hash}::getInitializer($loader));
 
-        $loader->setClassMapAuthoritative({authoritative_flag});
-        $loader->register(true);
+        $loader->setClassMapAuthoritative({authoritative_flag});{apcu}
+        $loader->register({prepend});
 
         $filesToLoad = \Composer\Autoload\ComposerStaticInit{hash}::$files;
         $requireFile = \Closure::bind(static function ($fileIdentifier, $file) {{
@@ -631,7 +1377,7 @@ class ComposerAutoloaderInit{hash}
 
         // Files entries with deterministic identifiers
         let mut files_entries = String::new();
-        for file_path in &self.files {
+        for file_path in self.ordered_files() {
             let relative = self.make_relative_path(file_path);
             let identifier = self.generate_file_identifier(&relative);
             files_entries.push_str(&format!(
@@ -672,7 +1418,7 @@ class ComposerStaticInit{hash}
 "
         );
 
-        std::fs::write(&path, content).map_err(|e| Error::io(&path, e))
+        write_atomic(&path, &content)
     }
 
     /// Generate `autoload_psr4.php`.
@@ -735,7 +1481,7 @@ return array(
 "
         );
 
-        std::fs::write(&path, content).map_err(|e| Error::io(&path, e))
+        write_atomic(&path, &content)
     }
 
     /// Generate `autoload_files.php`.
@@ -743,7 +1489,7 @@ return array(
         let path = dir.join("autoload_files.php");
 
         let mut entries = String::new();
-        for file_path in &self.files {
+        for file_path in self.ordered_files() {
             let relative = self.make_relative_path(file_path);
             let identifier = self.generate_file_identifier(&relative);
             entries.push_str(&format!(
@@ -798,6 +1544,20 @@ return array(
         std::fs::write(&path, content).map_err(|e| Error::io(&path, e))
     }
 
+    /// Generate `platform_check.php`, or remove a stale one when disabled.
+    fn generate_platform_check(&self, dir: &Path) -> Result<()> {
+        let path = dir.join("platform_check.php");
+        match &self.platform_check {
+            Some(requirements) => {
+                std::fs::write(&path, requirements.render()).map_err(|e| Error::io(&path, e))
+            }
+            None => match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::io(&path, e)),
+                _ => Ok(()),
+            },
+        }
+    }
+
     /// Generate main autoload.php.
     fn generate_autoload(&self, vendor_dir: &Path) -> Result<()> {
         let path = vendor_dir.join("autoload.php");
@@ -808,11 +1568,6 @@ return array(
 
 // autoload.php @generated by Libretto
 
-if (PHP_VERSION_ID < 80000) {{
-    echo 'Libretto requires PHP 8.0 or higher.' . PHP_EOL;
-    exit(1);
-}}
-
 require_once __DIR__ . '/composer/autoload_real.php';
 
 return ComposerAutoloaderInit{hash}::getLoader();
@@ -855,6 +1610,10 @@ return ComposerAutoloaderInit{hash}::getLoader();
 
     /// Generate deterministic hash for class names.
     fn generate_hash(&self) -> String {
+        if let Some(suffix) = &self.suffix {
+            return suffix.clone();
+        }
+
         // Use vendor dir path for stable hash
         let hash = blake3::hash(self.vendor_dir.to_string_lossy().as_bytes());
         let bytes = hash.as_bytes();
@@ -902,6 +1661,14 @@ return ComposerAutoloaderInit{hash}::getLoader();
     }
 }
 
+/// Write `content` to `path` via a temporary file and rename, so PHP never
+/// includes a partially written file.
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let tmp = path.with_extension("php.tmp");
+    std::fs::write(&tmp, content).map_err(|e| Error::io(&tmp, e))?;
+    std::fs::rename(&tmp, path).map_err(|e| Error::io(path, e))
+}
+
 /// Statistics about a generated autoloader.
 #[derive(Debug, Clone)]
 pub struct AutoloaderStats {
@@ -963,6 +1730,426 @@ mod tests {
         assert_eq!(generator.optimization_level, OptimizationLevel::Optimized);
     }
 
+    #[test]
+    fn files_follow_dependency_order() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vendor = tmp.path().join("vendor");
+        let mut generator = AutoloaderGenerator::new(vendor.clone());
+
+        let mut add = |name: Option<&str>, dir: PathBuf| {
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("bootstrap.php"), "<?php").unwrap();
+            let mut config = AutoloadConfig::default();
+            config.files.files = vec!["bootstrap.php".to_string()];
+            match name {
+                Some(name) => generator.add_vendor_package(name, &dir, &config),
+                None => generator.add_package(&dir, &config),
+            }
+        };
+        add(None, tmp.path().join("app"));
+        add(Some("acme/http"), vendor.join("acme/http"));
+        add(Some("zeta/other"), vendor.join("zeta/other"));
+        add(
+            Some("symfony/polyfill-php80"),
+            vendor.join("symfony/polyfill-php80"),
+        );
+
+        generator.set_package_order(sort_packages(&[
+            (
+                "acme/http".to_string(),
+                vec!["symfony/polyfill-php80".to_string()],
+            ),
+            ("symfony/polyfill-php80".to_string(), vec![]),
+        ]));
+
+        let order: Vec<String> = generator
+            .ordered_files()
+            .iter()
+            .map(|p| generator.make_relative_path(p))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                "/symfony/polyfill-php80/bootstrap.php",
+                "/acme/http/bootstrap.php",
+                "/zeta/other/bootstrap.php",
+                "/../app/bootstrap.php",
+            ]
+        );
+    }
+
+    #[test]
+    fn exclude_from_classmap_is_scoped_to_package() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vendor = tmp.path().join("vendor");
+        let mut generator = AutoloaderGenerator::new(vendor.clone());
+
+        for (name, exclude) in [
+            ("acme/a", vec!["src/Internal/*".to_string()]),
+            ("acme/b", vec![]),
+        ] {
+            let dir = vendor.join(name);
+            let class = name.replace("acme/", "").to_uppercase();
+            std::fs::create_dir_all(dir.join("src/Internal")).unwrap();
+            std::fs::write(
+                dir.join("src/Internal/Hidden.php"),
+                format!("<?php class Hidden{class} {{}}"),
+            )
+            .unwrap();
+            let mut config = AutoloadConfig::default();
+            config.classmap.paths = vec!["src".to_string()];
+            config.exclude.patterns = exclude;
+            generator.add_vendor_package(name, &dir, &config);
+        }
+
+        assert!(!generator.classmap.contains_key("HiddenA"));
+        assert!(generator.classmap.contains_key("HiddenB"));
+    }
+
+    #[test]
+    fn flat_target_dir_packages_map_psr0_through_the_classmap() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vendor = tmp.path().join("vendor");
+        let dir = vendor.join("symfony/yaml");
+        std::fs::create_dir_all(dir.join("Tests")).unwrap();
+        std::fs::write(
+            dir.join("Parser.php"),
+            "<?php namespace Symfony\\Component\\Yaml; class Parser {}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("Tests/ParserTest.php"),
+            "<?php namespace Symfony\\Component\\Yaml\\Tests; class ParserTest {}",
+        )
+        .unwrap();
+
+        let mut config = AutoloadConfig::default();
+        config.psr0.mappings.insert(
+            "Symfony\\Component\\Yaml\\".to_string(),
+            vec![String::new()],
+        );
+        config.exclude.patterns = vec!["Tests/".to_string()];
+        config.target_dir = Some("Symfony/Component/Yaml".to_string());
+
+        // Even without optimization, as the files are not at their PSR-0 path
+        let mut generator = AutoloaderGenerator::new(vendor);
+        generator.add_vendor_package("symfony/yaml", &dir, &config);
+
+        assert_eq!(
+            generator.classmap_entries(),
+            [(
+                "Symfony\\Component\\Yaml\\Parser",
+                dir.join("Parser.php").as_path()
+            )]
+        );
+        assert!(generator.psr0_map.is_empty());
+    }
+
+    #[test]
+    fn optimized_scan_reports_psr_violations_and_ambiguity() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vendor = tmp.path().join("vendor");
+        let dir = vendor.join("acme/lib");
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("legacy")).unwrap();
+        std::fs::write(
+            dir.join("src/Client.php"),
+            "<?php namespace Acme; class Client {}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("src/Misplaced.php"),
+            "<?php namespace Acme; class Other {}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("legacy/Client.php"),
+            "<?php namespace Acme; class Client {}",
+        )
+        .unwrap();
+
+        let mut config = AutoloadConfig::default();
+        config
+            .psr4
+            .mappings
+            .insert("Acme\\".to_string(), vec!["src".to_string()]);
+        config.classmap.paths = vec!["legacy".to_string()];
+
+        let mut generator =
+            AutoloaderGenerator::with_optimization(vendor.clone(), OptimizationLevel::Optimized);
+        generator.add_vendor_package("acme/lib", &dir, &config);
+
+        assert!(!generator.classmap.contains_key("Acme\\Other"));
+        assert_eq!(
+            generator.classmap["Acme\\Client"],
+            dir.join("legacy/Client.php")
+        );
+        let warnings = generator.warnings();
+        assert_eq!(warnings.iter().filter(|w| w.is_psr_violation()).count(), 1);
+        assert_eq!(warnings.iter().filter(|w| w.is_ambiguous()).count(), 1);
+    }
+
+    #[test]
+    fn autoload_real_uses_suffix_apcu_and_prepend() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vendor = tmp.path().join("vendor");
+        let mut generator = AutoloaderGenerator::new(vendor.clone());
+        generator.set_suffix("0123abcd-ef");
+        generator.set_apcu_prefix(Some("app".to_string()));
+        generator.set_prepend(false);
+        generator.generate().unwrap();
+
+        let real = std::fs::read_to_string(vendor.join("composer/autoload_real.php")).unwrap();
+        assert!(real.contains("class ComposerAutoloaderInit0123abcdef"));
+        assert!(real.contains("$loader->setApcuPrefix('app');"));
+        assert!(real.contains("$loader->register(false);"));
+        let autoload = std::fs::read_to_string(vendor.join("autoload.php")).unwrap();
+        assert!(autoload.contains("ComposerAutoloaderInit0123abcdef::getLoader()"));
+    }
+
+    #[test]
+    fn autoload_has_no_version_check_without_platform_check() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vendor = tmp.path().join("vendor");
+        let mut generator = AutoloaderGenerator::new(vendor.clone());
+        generator.set_platform_check(None);
+        generator.generate().unwrap();
+
+        let autoload = std::fs::read_to_string(vendor.join("autoload.php")).unwrap();
+        assert!(!autoload.contains("PHP_VERSION_ID"));
+        assert!(!vendor.join("composer/platform_check.php").exists());
+        let real = std::fs::read_to_string(vendor.join("composer/autoload_real.php")).unwrap();
+        assert!(!real.contains("platform_check.php"));
+    }
+
+    #[test]
+    fn update_files_tracks_root_classmap_changes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vendor = tmp.path().join("vendor");
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("Old.php"), "<?php class Old {}").unwrap();
+
+        let mut config = AutoloadConfig::default();
+        config.classmap.paths = vec!["src".to_string()];
+        let mut generator = AutoloaderGenerator::new(vendor);
+        generator.add_package(tmp.path(), &config);
+        assert!(generator.classmap.contains_key("Old"));
+
+        let canonical = std::fs::canonicalize(&src).unwrap();
+        assert_eq!(generator.root_scan_dirs(), vec![canonical.clone()]);
+
+        std::fs::write(src.join("New.php"), "<?php class New {}").unwrap();
+        std::fs::remove_file(src.join("Old.php")).unwrap();
+        let paths = [canonical.join("New.php"), canonical.join("Old.php")];
+        assert!(generator.update_files(&paths));
+        assert!(generator.classmap.contains_key("New"));
+        assert!(!generator.classmap.contains_key("Old"));
+
+        // Unchanged content is not re-parsed
+        assert!(!generator.update_files(&paths[..1]));
+    }
+
+    #[test]
+    fn update_files_promotes_duplicates_and_follows_directories() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("A.php"), "<?php class Dup {}").unwrap();
+        std::fs::write(src.join("B.php"), "<?php class Dup {}").unwrap();
+
+        let mut config = AutoloadConfig::default();
+        config.classmap.paths = vec!["src".to_string()];
+        let mut generator = AutoloaderGenerator::new(tmp.path().join("vendor"));
+        generator.add_package(tmp.path(), &config);
+        assert_eq!(generator.warnings().len(), 1);
+
+        let canonical = std::fs::canonicalize(&src).unwrap();
+        let used = generator.classmap["Dup"].clone();
+        let (removed, kept) = if used.ends_with("A.php") {
+            ("A.php", "B.php")
+        } else {
+            ("B.php", "A.php")
+        };
+        std::fs::write(src.join(removed), "<?php class Other {}").unwrap();
+        assert!(generator.update_files(&[canonical.join(removed)]));
+        assert!(generator.classmap["Dup"].ends_with(kept));
+        assert!(generator.warnings().is_empty());
+
+        // A directory moved in or out stands for the files below it
+        let outside = tmp.path().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("Moved.php"), "<?php class Moved {}").unwrap();
+        std::fs::rename(&outside, src.join("Lib")).unwrap();
+        assert!(generator.update_files(&[canonical.join("Lib")]));
+        assert!(generator.classmap.contains_key("Moved"));
+
+        std::fs::rename(src.join("Lib"), &outside).unwrap();
+        assert!(generator.update_files(&[canonical.join("Lib")]));
+        assert!(!generator.classmap.contains_key("Moved"));
+    }
+
+    #[test]
+    fn preload_respects_package_rules() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let lib = tmp.path().join("vendor/acme/lib");
+        std::fs::create_dir_all(lib.join("src")).unwrap();
+        std::fs::create_dir_all(tmp.path().join("src")).unwrap();
+        std::fs::write(
+            lib.join("src/Base.php"),
+            "<?php namespace Acme; class Base {}",
+        )
+        .unwrap();
+        std::fs::write(
+            tmp.path().join("src/Child.php"),
+            "<?php namespace App; class Child extends \\Acme\\Base {}",
+        )
+        .unwrap();
+
+        let mut config = AutoloadConfig::default();
+        config.classmap.paths = vec!["src".to_string()];
+        let mut generator = AutoloaderGenerator::new(tmp.path().join("vendor"));
+        generator.add_vendor_package("acme/lib", &lib, &config);
+        generator.add_package(tmp.path(), &config);
+
+        let preload = tmp.path().join("vendor/preload.php");
+        let script = generator
+            .generate_preload(&preload, &PreloadRules::default())
+            .unwrap();
+        assert_eq!(script.classes, 2);
+        let php = std::fs::read_to_string(&preload).unwrap();
+        let base = php.find("/acme/lib/src/Base.php").unwrap();
+        let child = php.find("/../src/Child.php").unwrap();
+        assert!(base < child);
+
+        let rules = PreloadRules {
+            include: Vec::new(),
+            exclude: vec!["acme/*".to_string()],
+        };
+        let script = generator.generate_preload(&preload, &rules).unwrap();
+        assert_eq!(script.classes, 0);
+        assert_eq!(script.unlinkable, ["App\\Child"]);
+    }
+
+    #[test]
+    fn symbol_index_records_kind_line_and_package() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let lib = tmp.path().join("vendor/acme/lib");
+        std::fs::create_dir_all(lib.join("src")).unwrap();
+        std::fs::create_dir_all(tmp.path().join("src")).unwrap();
+        std::fs::write(
+            lib.join("src/Client.php"),
+            "<?php\nnamespace Acme;\n\ninterface Client {}",
+        )
+        .unwrap();
+        std::fs::write(
+            tmp.path().join("src/Client.php"),
+            "<?php\nnamespace App;\nenum Client {}",
+        )
+        .unwrap();
+
+        let mut config = AutoloadConfig::default();
+        config.classmap.paths = vec!["src".to_string()];
+        let mut generator = AutoloaderGenerator::new(tmp.path().join("vendor"));
+        generator.add_vendor_package("acme/lib", &lib, &config);
+        generator.add_package(tmp.path(), &config);
+
+        let index = generator.symbol_index();
+        assert_eq!(index.symbols.len(), 2);
+        let acme = &index.symbols[0];
+        assert_eq!(acme.fqcn, "Acme\\Client");
+        assert_eq!(acme.kind, DefinitionKind::Interface);
+        assert_eq!(acme.file, "vendor/acme/lib/src/Client.php");
+        assert_eq!(acme.line, 4);
+        assert_eq!(acme.package.as_deref(), Some("acme/lib"));
+
+        let found = generator.find_symbol("client");
+        assert_eq!(found.len(), 2);
+        let app = generator.find_symbol("\\App\\Client");
+        assert_eq!(app.len(), 1);
+        assert_eq!(app[0].kind, DefinitionKind::Enum);
+        assert_eq!(app[0].package, None);
+    }
+
+    #[test]
+    fn update_files_refreshes_root_file_functions() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("helpers.php"), "<?php function dd() {}").unwrap();
+        std::fs::write(tmp.path().join("more.php"), "<?php function other() {}").unwrap();
+
+        let mut config = AutoloadConfig::default();
+        config.files.files = vec!["helpers.php".to_string(), "more.php".to_string()];
+        let mut generator = AutoloaderGenerator::new(tmp.path().join("vendor"));
+        generator.add_package(tmp.path(), &config);
+        assert!(generator.warnings().is_empty());
+
+        let root_files = generator.root_files();
+        assert_eq!(root_files.len(), 2);
+        std::fs::write(tmp.path().join("more.php"), "<?php function dd() {}").unwrap();
+        generator.update_files(&root_files[1..]);
+        let warnings = generator.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].is_redeclared_function());
+    }
+
+    #[test]
+    fn unguarded_duplicate_functions_are_reported() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let package = |name: &str, content: &str| {
+            let dir = tmp.path().join("vendor").join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("helpers.php"), content).unwrap();
+            let mut config = AutoloadConfig::default();
+            config.files.files = vec!["helpers.php".to_string()];
+            (dir, config)
+        };
+        let (polyfill, polyfill_config) = package(
+            "acme/polyfill",
+            "<?php if (!function_exists('str_contains')) { function str_contains() {} }",
+        );
+        let (a, a_config) = package(
+            "acme/a",
+            "<?php function str_contains() {} function dd() {}",
+        );
+        let (b, b_config) = package("acme/b", "<?php function DD() {}");
+
+        let mut generator = AutoloaderGenerator::new(tmp.path().join("vendor"));
+        generator.add_vendor_package("acme/polyfill", &polyfill, &polyfill_config);
+        generator.add_vendor_package("acme/a", &a, &a_config);
+        generator.add_vendor_package("acme/b", &b, &b_config);
+        generator.set_package_order(vec![
+            "acme/polyfill".to_string(),
+            "acme/a".to_string(),
+            "acme/b".to_string(),
+        ]);
+
+        // The guarded polyfill loads first, so acme/a redeclares str_contains;
+        // acme/b redeclares dd() regardless of case
+        let warnings = generator.warnings();
+        let redeclared: Vec<&AutoloadWarning> = warnings
+            .iter()
+            .filter(|w| w.is_redeclared_function())
+            .collect();
+        assert_eq!(redeclared.len(), 2);
+        assert!(redeclared[0].to_string().contains("str_contains"));
+        assert!(redeclared[1].to_string().contains("Cannot redeclare DD()"));
+
+        // With the unguarded declaration first the guard skips the polyfill
+        generator.set_package_order(vec![
+            "acme/a".to_string(),
+            "acme/polyfill".to_string(),
+            "acme/b".to_string(),
+        ]);
+        assert_eq!(
+            generator
+                .warnings()
+                .iter()
+                .filter(|w| w.is_redeclared_function())
+                .count(),
+            1
+        );
+    }
+
     #[test]
     fn relative_path_generation() {
         let generator = AutoloaderGenerator::new(PathBuf::from("/home/user/project/vendor"));
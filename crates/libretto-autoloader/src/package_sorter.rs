@@ -0,0 +1,104 @@
+//! Dependency ordering of packages for `files` autoloading.
+//!
+//! Files autoloaded by a package may call functions defined by its
+//! dependencies (e.g. `symfony/polyfill-*` bootstrap files), so they must be
+//! required after the files of every package they depend on.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Sort packages so that every package comes after its dependencies.
+///
+/// `packages` pairs each package name with the names it requires. Unknown
+/// requirements (platform packages, packages not installed) are ignored.
+/// Independent packages are ordered alphabetically so the output is stable,
+/// and dependency cycles are broken at the alphabetically first package.
+#[must_use]
+pub fn sort_packages(packages: &[(String, Vec<String>)]) -> Vec<String> {
+    let known: HashMap<&str, usize> = packages
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| (name.as_str(), i))
+        .collect();
+
+    // Unmet dependency counts and reverse edges
+    let mut pending: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (name, requires) in packages {
+        let deps: BTreeSet<&str> = requires
+            .iter()
+            .map(String::as_str)
+            .filter(|dep| *dep != name && known.contains_key(dep))
+            .collect();
+        for dep in &deps {
+            dependents.entry(dep).or_default().push(name);
+        }
+        pending.insert(name, deps.len());
+    }
+
+    let mut ready: BTreeSet<&str> = pending
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(name, _)| *name)
+        .collect();
+    let mut sorted = Vec::with_capacity(packages.len());
+
+    // When nothing is ready, a cycle remains: release its first package
+    while let Some(next) = ready.pop_first().or_else(|| pending.keys().next().copied()) {
+        if pending.remove(next).is_none() {
+            continue;
+        }
+        sorted.push(next.to_string());
+
+        for dependent in dependents.get(next).into_iter().flatten() {
+            if let Some(count) = pending.get_mut(dependent) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+    }
+
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkg(name: &str, requires: &[&str]) -> (String, Vec<String>) {
+        (
+            name.to_string(),
+            requires.iter().map(ToString::to_string).collect(),
+        )
+    }
+
+    #[test]
+    fn dependencies_come_first() {
+        let order = sort_packages(&[
+            pkg("app/http", &["symfony/polyfill-mbstring", "php"]),
+            pkg("symfony/polyfill-mbstring", &["ext-mbstring"]),
+            pkg("acme/util", &["app/http"]),
+        ]);
+        assert_eq!(
+            order,
+            vec!["symfony/polyfill-mbstring", "app/http", "acme/util"]
+        );
+    }
+
+    #[test]
+    fn independent_packages_are_alphabetical() {
+        let order = sort_packages(&[pkg("b/b", &[]), pkg("c/c", &[]), pkg("a/a", &[])]);
+        assert_eq!(order, vec!["a/a", "b/b", "c/c"]);
+    }
+
+    #[test]
+    fn cycles_are_broken() {
+        let order = sort_packages(&[
+            pkg("b/b", &["a/a"]),
+            pkg("a/a", &["b/b"]),
+            pkg("c/c", &["a/a"]),
+        ]);
+        assert_eq!(order, vec!["a/a", "b/b", "c/c"]);
+    }
+}
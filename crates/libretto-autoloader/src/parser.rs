@@ -9,7 +9,10 @@ use mago_fingerprint::{FingerprintOptions, Fingerprintable};
 use mago_names::ResolvedNames;
 use mago_names::resolver::NameResolver;
 use mago_span::HasSpan;
-use mago_syntax::ast::{Block, Class, Enum, Interface, MethodBody, Program, Statement, Trait};
+use mago_syntax::ast::{
+    Block, Class, ClassLikeMember, Enum, Function, Identifier, Interface, LocalIdentifier,
+    MethodBody, Program, Sequence, Statement, Trait,
+};
 use mago_syntax::parser::parse_file_content;
 use std::path::Path;
 
@@ -23,7 +26,20 @@ pub struct ParseResult {
 }
 
 /// PHP definition type.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    rkyv::Archive,
+    rkyv::Deserialize,
+    rkyv::Serialize,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum DefinitionKind {
     /// Class definition.
     Class,
@@ -33,6 +49,10 @@ pub enum DefinitionKind {
     Trait,
     /// Enum definition (PHP 8.1+).
     Enum,
+    /// Function definition.
+    Function,
+    /// Constant defined with `const`.
+    Constant,
 }
 
 impl DefinitionKind {
@@ -44,11 +64,23 @@ impl DefinitionKind {
             Self::Interface => "interface",
             Self::Trait => "trait",
             Self::Enum => "enum",
+            Self::Function => "function",
+            Self::Constant => "constant",
         }
     }
+
+    /// Whether this is a class, interface, trait or enum (an autoloadable
+    /// symbol).
+    #[must_use]
+    pub const fn is_class_like(self) -> bool {
+        matches!(
+            self,
+            Self::Class | Self::Interface | Self::Trait | Self::Enum
+        )
+    }
 }
 
-/// A PHP definition (class, interface, trait, or enum).
+/// A PHP definition (class, interface, trait, enum, function or constant).
 #[derive(Debug, Clone)]
 pub struct PhpDefinition {
     /// The fully qualified class name.
@@ -61,6 +93,13 @@ pub struct PhpDefinition {
     pub kind: DefinitionKind,
     /// Line number in the file.
     pub line: usize,
+    /// Fully qualified names of the parent class, interfaces and traits the
+    /// definition needs to be linked.
+    pub parents: Vec<String>,
+    /// Whether the definition only happens at runtime under a condition,
+    /// e.g. inside `if (!function_exists(...))` or a function body, rather
+    /// than unconditionally at the top level of the file.
+    pub conditional: bool,
 }
 
 /// PHP file parser using mago-syntax and mago-names.
@@ -155,7 +194,7 @@ impl PhpParser {
         }
     }
 
-    /// Recursively collect all class-like definitions from the AST.
+    /// Recursively collect all definitions from the AST.
     fn collect_definitions(
         &self,
         program: &Program<'_>,
@@ -164,96 +203,132 @@ impl PhpParser {
         source: &str,
     ) {
         for statement in program.statements.iter() {
-            self.visit_statement(statement, resolved_names, definitions, source);
+            self.visit_statement(statement, resolved_names, definitions, source, false);
         }
     }
 
     /// Visit a statement and all its nested contents.
+    ///
+    /// `conditional` tells whether `statement` is nested in a conditional
+    /// context (see [`PhpDefinition::conditional`]).
     fn visit_statement(
         &self,
         statement: &Statement<'_>,
         resolved_names: &ResolvedNames<'_>,
         definitions: &mut Vec<PhpDefinition>,
         source: &str,
+        conditional: bool,
     ) {
         match statement {
             Statement::Namespace(ns) => {
                 // Visit statements inside the namespace
                 // The statements() method is on Namespace, not NamespaceBody
                 for stmt in ns.statements().iter() {
-                    self.visit_statement(stmt, resolved_names, definitions, source);
+                    self.visit_statement(stmt, resolved_names, definitions, source, conditional);
                 }
             }
             Statement::Class(class) => {
-                self.add_class_definition(class, resolved_names, definitions, source);
+                self.add_class_definition(class, resolved_names, definitions, source, conditional);
                 // Visit methods for nested classes
                 self.visit_class_members(class, resolved_names, definitions, source);
             }
             Statement::Interface(interface) => {
-                self.add_interface_definition(interface, resolved_names, definitions, source);
+                self.add_interface_definition(
+                    interface,
+                    resolved_names,
+                    definitions,
+                    source,
+                    conditional,
+                );
                 // Visit methods for nested definitions
                 self.visit_interface_members(interface, resolved_names, definitions, source);
             }
             Statement::Trait(tr) => {
-                self.add_trait_definition(tr, resolved_names, definitions, source);
+                self.add_trait_definition(tr, resolved_names, definitions, source, conditional);
                 // Visit methods for nested classes
                 self.visit_trait_members(tr, resolved_names, definitions, source);
             }
             Statement::Enum(en) => {
-                self.add_enum_definition(en, resolved_names, definitions, source);
+                self.add_enum_definition(en, resolved_names, definitions, source, conditional);
                 // Visit methods for nested classes
                 self.visit_enum_members(en, resolved_names, definitions, source);
             }
             Statement::Function(func) => {
-                // Visit function body for nested class definitions
-                self.visit_block(&func.body, resolved_names, definitions, source);
+                self.add_function_definition(
+                    func,
+                    resolved_names,
+                    definitions,
+                    source,
+                    conditional,
+                );
+                // Visit function body for nested definitions
+                self.visit_block(&func.body, resolved_names, definitions, source, true);
+            }
+            Statement::Constant(constant) => {
+                for item in &constant.items {
+                    self.add_named_definition(
+                        &item.name,
+                        DefinitionKind::Constant,
+                        item.span().start.offset as usize,
+                        resolved_names,
+                        definitions,
+                        source,
+                        conditional,
+                    );
+                }
             }
             Statement::Block(block) => {
-                self.visit_block(block, resolved_names, definitions, source);
+                self.visit_block(block, resolved_names, definitions, source, conditional);
             }
             Statement::If(if_stmt) => {
                 // Visit all branches of the if statement
                 for stmt in if_stmt.body.statements() {
-                    self.visit_statement(stmt, resolved_names, definitions, source);
+                    self.visit_statement(stmt, resolved_names, definitions, source, true);
                 }
             }
             Statement::While(while_stmt) => {
                 for stmt in while_stmt.body.statements() {
-                    self.visit_statement(stmt, resolved_names, definitions, source);
+                    self.visit_statement(stmt, resolved_names, definitions, source, true);
                 }
             }
             Statement::DoWhile(do_while) => {
                 // do-while has a single statement
-                self.visit_statement(do_while.statement, resolved_names, definitions, source);
+                self.visit_statement(
+                    do_while.statement,
+                    resolved_names,
+                    definitions,
+                    source,
+                    true,
+                );
             }
             Statement::For(for_stmt) => {
                 for stmt in for_stmt.body.statements() {
-                    self.visit_statement(stmt, resolved_names, definitions, source);
+                    self.visit_statement(stmt, resolved_names, definitions, source, true);
                 }
             }
             Statement::Foreach(foreach_stmt) => {
                 for stmt in foreach_stmt.body.statements() {
-                    self.visit_statement(stmt, resolved_names, definitions, source);
+                    self.visit_statement(stmt, resolved_names, definitions, source, true);
                 }
             }
             Statement::Switch(switch_stmt) => {
                 // Switch body has cases, each case has statements
                 for case in switch_stmt.body.cases() {
                     for stmt in case.statements() {
-                        self.visit_statement(stmt, resolved_names, definitions, source);
+                        self.visit_statement(stmt, resolved_names, definitions, source, true);
                     }
                 }
             }
             Statement::Try(try_stmt) => {
                 // Visit try block
-                self.visit_block(&try_stmt.block, resolved_names, definitions, source);
+                self.visit_block(&try_stmt.block, resolved_names, definitions, source, true);
                 // Visit catch blocks
                 for catch in try_stmt.catch_clauses.iter() {
-                    self.visit_block(&catch.block, resolved_names, definitions, source);
+                    self.visit_block(&catch.block, resolved_names, definitions, source, true);
                 }
                 // Visit finally block
                 if let Some(finally) = &try_stmt.finally_clause {
-                    self.visit_block(&finally.block, resolved_names, definitions, source);
+                    self.visit_block(&finally.block, resolved_names, definitions, source, true);
                 }
             }
             // Other statements don't contain nested class definitions
@@ -268,9 +343,10 @@ impl PhpParser {
         resolved_names: &ResolvedNames<'_>,
         definitions: &mut Vec<PhpDefinition>,
         source: &str,
+        conditional: bool,
     ) {
         for stmt in block.statements.iter() {
-            self.visit_statement(stmt, resolved_names, definitions, source);
+            self.visit_statement(stmt, resolved_names, definitions, source, conditional);
         }
     }
 
@@ -283,10 +359,10 @@ impl PhpParser {
         source: &str,
     ) {
         for member in class.members.iter() {
-            if let mago_syntax::ast::ClassLikeMember::Method(method) = member {
-                if let MethodBody::Concrete(block) = &method.body {
-                    self.visit_block(block, resolved_names, definitions, source);
-                }
+            if let ClassLikeMember::Method(method) = member
+                && let MethodBody::Concrete(block) = &method.body
+            {
+                self.visit_block(block, resolved_names, definitions, source, true);
             }
         }
     }
@@ -300,10 +376,10 @@ impl PhpParser {
         source: &str,
     ) {
         for member in interface.members.iter() {
-            if let mago_syntax::ast::ClassLikeMember::Method(method) = member {
-                if let MethodBody::Concrete(block) = &method.body {
-                    self.visit_block(block, resolved_names, definitions, source);
-                }
+            if let ClassLikeMember::Method(method) = member
+                && let MethodBody::Concrete(block) = &method.body
+            {
+                self.visit_block(block, resolved_names, definitions, source, true);
             }
         }
     }
@@ -317,10 +393,10 @@ impl PhpParser {
         source: &str,
     ) {
         for member in tr.members.iter() {
-            if let mago_syntax::ast::ClassLikeMember::Method(method) = member {
-                if let MethodBody::Concrete(block) = &method.body {
-                    self.visit_block(block, resolved_names, definitions, source);
-                }
+            if let ClassLikeMember::Method(method) = member
+                && let MethodBody::Concrete(block) = &method.body
+            {
+                self.visit_block(block, resolved_names, definitions, source, true);
             }
         }
     }
@@ -334,10 +410,10 @@ impl PhpParser {
         source: &str,
     ) {
         for member in en.members.iter() {
-            if let mago_syntax::ast::ClassLikeMember::Method(method) = member {
-                if let MethodBody::Concrete(block) = &method.body {
-                    self.visit_block(block, resolved_names, definitions, source);
-                }
+            if let ClassLikeMember::Method(method) = member
+                && let MethodBody::Concrete(block) = &method.body
+            {
+                self.visit_block(block, resolved_names, definitions, source, true);
             }
         }
     }
@@ -349,6 +425,7 @@ impl PhpParser {
         resolved_names: &ResolvedNames<'_>,
         definitions: &mut Vec<PhpDefinition>,
         source: &str,
+        conditional: bool,
     ) {
         let name = class.name.value.to_string();
         let line = self.get_line_number(class.span().start.offset as usize, source);
@@ -360,6 +437,15 @@ impl PhpParser {
             .unwrap_or_else(|| name.clone());
 
         let namespace = self.extract_namespace(&fqcn, &name);
+        let parents = Self::resolve_parents(
+            class
+                .extends
+                .iter()
+                .flat_map(|e| e.types.iter())
+                .chain(class.implements.iter().flat_map(|i| i.types.iter())),
+            &class.members,
+            resolved_names,
+        );
 
         definitions.push(PhpDefinition {
             fqcn,
@@ -367,6 +453,8 @@ impl PhpParser {
             namespace,
             kind: DefinitionKind::Class,
             line,
+            parents,
+            conditional,
         });
     }
 
@@ -377,6 +465,7 @@ impl PhpParser {
         resolved_names: &ResolvedNames<'_>,
         definitions: &mut Vec<PhpDefinition>,
         source: &str,
+        conditional: bool,
     ) {
         let name = interface.name.value.to_string();
         let line = self.get_line_number(interface.span().start.offset as usize, source);
@@ -387,6 +476,11 @@ impl PhpParser {
             .unwrap_or_else(|| name.clone());
 
         let namespace = self.extract_namespace(&fqcn, &name);
+        let parents = Self::resolve_parents(
+            interface.extends.iter().flat_map(|e| e.types.iter()),
+            &interface.members,
+            resolved_names,
+        );
 
         definitions.push(PhpDefinition {
             fqcn,
@@ -394,6 +488,8 @@ impl PhpParser {
             namespace,
             kind: DefinitionKind::Interface,
             line,
+            parents,
+            conditional,
         });
     }
 
@@ -404,6 +500,7 @@ impl PhpParser {
         resolved_names: &ResolvedNames<'_>,
         definitions: &mut Vec<PhpDefinition>,
         source: &str,
+        conditional: bool,
     ) {
         let name = tr.name.value.to_string();
         let line = self.get_line_number(tr.span().start.offset as usize, source);
@@ -414,6 +511,7 @@ impl PhpParser {
             .unwrap_or_else(|| name.clone());
 
         let namespace = self.extract_namespace(&fqcn, &name);
+        let parents = Self::resolve_parents(std::iter::empty(), &tr.members, resolved_names);
 
         definitions.push(PhpDefinition {
             fqcn,
@@ -421,6 +519,8 @@ impl PhpParser {
             namespace,
             kind: DefinitionKind::Trait,
             line,
+            parents,
+            conditional,
         });
     }
 
@@ -431,6 +531,7 @@ impl PhpParser {
         resolved_names: &ResolvedNames<'_>,
         definitions: &mut Vec<PhpDefinition>,
         source: &str,
+        conditional: bool,
     ) {
         let name = en.name.value.to_string();
         let line = self.get_line_number(en.span().start.offset as usize, source);
@@ -441,6 +542,11 @@ impl PhpParser {
             .unwrap_or_else(|| name.clone());
 
         let namespace = self.extract_namespace(&fqcn, &name);
+        let parents = Self::resolve_parents(
+            en.implements.iter().flat_map(|i| i.types.iter()),
+            &en.members,
+            resolved_names,
+        );
 
         definitions.push(PhpDefinition {
             fqcn,
@@ -448,9 +554,84 @@ impl PhpParser {
             namespace,
             kind: DefinitionKind::Enum,
             line,
+            parents,
+            conditional,
         });
     }
 
+    /// Add a function definition using resolved names.
+    fn add_function_definition(
+        &self,
+        func: &Function<'_>,
+        resolved_names: &ResolvedNames<'_>,
+        definitions: &mut Vec<PhpDefinition>,
+        source: &str,
+        conditional: bool,
+    ) {
+        self.add_named_definition(
+            &func.name,
+            DefinitionKind::Function,
+            func.span().start.offset as usize,
+            resolved_names,
+            definitions,
+            source,
+            conditional,
+        );
+    }
+
+    /// Add a function or constant definition named `name`.
+    #[allow(clippy::too_many_arguments)]
+    fn add_named_definition(
+        &self,
+        name: &LocalIdentifier<'_>,
+        kind: DefinitionKind,
+        offset: usize,
+        resolved_names: &ResolvedNames<'_>,
+        definitions: &mut Vec<PhpDefinition>,
+        source: &str,
+        conditional: bool,
+    ) {
+        let line = self.get_line_number(offset, source);
+        let short = name.value.to_string();
+        let fqcn = resolved_names
+            .resolve(name)
+            .map(String::from)
+            .unwrap_or_else(|| short.clone());
+        let namespace = self.extract_namespace(&fqcn, &short);
+
+        definitions.push(PhpDefinition {
+            fqcn,
+            name: short,
+            namespace,
+            kind,
+            line,
+            parents: Vec::new(),
+            conditional,
+        });
+    }
+
+    /// Resolve `extends`/`implements` names and the traits used by `members`.
+    fn resolve_parents<'a, 'arena: 'a>(
+        inherited: impl Iterator<Item = &'a Identifier<'arena>>,
+        members: &'a Sequence<'arena, ClassLikeMember<'arena>>,
+        resolved_names: &ResolvedNames<'_>,
+    ) -> Vec<String> {
+        let traits = members.iter().flat_map(|member| match member {
+            ClassLikeMember::TraitUse(trait_use) => trait_use.trait_names.iter().collect(),
+            _ => Vec::new(),
+        });
+        inherited
+            .chain(traits)
+            .map(|name| {
+                resolved_names
+                    .resolve(name)
+                    .unwrap_or_else(|| name.value())
+                    .trim_start_matches('\\')
+                    .to_string()
+            })
+            .collect()
+    }
+
     /// Extract namespace from fully qualified name.
     fn extract_namespace(&self, fqcn: &str, name: &str) -> Option<String> {
         if fqcn.contains('\\') {
@@ -626,9 +807,47 @@ function foo() {
 "#;
 
         let defs = parser.parse_str(content);
-        assert_eq!(defs.len(), 2, "Should find both nested classes A and B");
-        assert!(defs.iter().any(|d| d.name == "NestedA"));
-        assert!(defs.iter().any(|d| d.name == "NestedB"));
+        assert_eq!(
+            defs.len(),
+            3,
+            "Should find foo and both nested classes A and B"
+        );
+        assert!(
+            defs.iter()
+                .any(|d| d.name == "foo" && d.kind == DefinitionKind::Function)
+        );
+        assert!(defs.iter().any(|d| d.name == "NestedA" && d.conditional));
+        assert!(defs.iter().any(|d| d.name == "NestedB" && d.conditional));
+    }
+
+    #[test]
+    fn parse_functions_and_constants() {
+        let mut parser = PhpParser::new();
+        let content = r"<?php
+namespace Acme\Support;
+
+const VERSION = '1.0', BUILD = 42;
+
+function helper() {}
+
+if (!function_exists('str_contains')) {
+    function str_contains(string $haystack, string $needle): bool {}
+}
+";
+
+        let defs = parser.parse_str(content);
+        let find = |fqcn: &str| defs.iter().find(|d| d.fqcn == fqcn).unwrap();
+        assert_eq!(
+            find("Acme\\Support\\VERSION").kind,
+            DefinitionKind::Constant
+        );
+        assert_eq!(find("Acme\\Support\\BUILD").kind, DefinitionKind::Constant);
+        let helper = find("Acme\\Support\\helper");
+        assert_eq!(helper.kind, DefinitionKind::Function);
+        assert_eq!(helper.line, 6);
+        assert!(!helper.conditional);
+        assert!(find("Acme\\Support\\str_contains").conditional);
+        assert!(defs.iter().all(|d| !d.kind.is_class_like()));
     }
 
     #[test]
@@ -663,4 +882,34 @@ class Outer {
         assert!(defs.iter().any(|d| d.name == "Outer"));
         assert!(defs.iter().any(|d| d.name == "Inner"));
     }
+
+    #[test]
+    fn parse_resolves_parents_interfaces_and_traits() {
+        let mut parser = PhpParser::new();
+        let content = r"<?php
+namespace App\Http;
+
+use Psr\Http\Message\ResponseInterface as Response;
+use App\Support\Macroable;
+
+class JsonResponse extends BaseResponse implements Response, \JsonSerializable {
+    use Macroable, Concerns\HasHeaders;
+}
+
+interface Responder extends \Countable {}
+";
+
+        let defs = parser.parse_str(content);
+        assert_eq!(
+            defs[0].parents,
+            [
+                "App\\Http\\BaseResponse",
+                "Psr\\Http\\Message\\ResponseInterface",
+                "JsonSerializable",
+                "App\\Support\\Macroable",
+                "App\\Http\\Concerns\\HasHeaders",
+            ]
+        );
+        assert_eq!(defs[1].parents, ["Countable"]);
+    }
 }
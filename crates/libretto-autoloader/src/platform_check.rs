@@ -0,0 +1,191 @@
+//! Runtime platform check (`vendor/composer/platform_check.php`).
+//!
+//! Mirrors Composer's generated check: the autoloader fails fast when the
+//! running PHP is older than the highest minimum PHP version required by any
+//! package, is not a 64-bit build when `php-64bit` is required, or lacks a
+//! required extension.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Minimum PHP version required at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhpRequirement {
+    /// Version as `(major, minor, patch)`.
+    pub version: (u64, u64, u64),
+    /// Whether the version itself satisfies the requirement (`>=` vs `>`).
+    pub inclusive: bool,
+}
+
+impl PhpRequirement {
+    /// `PHP_VERSION_ID` of the version.
+    const fn version_id(&self) -> u64 {
+        let (major, minor, patch) = self.version;
+        major * 10_000 + minor * 100 + patch
+    }
+
+    const fn operator(&self) -> &'static str {
+        if self.inclusive { ">=" } else { ">" }
+    }
+}
+
+/// Platform requirements checked by `platform_check.php`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlatformRequirements {
+    /// Minimum PHP version, if any package constrains it.
+    pub php: Option<PhpRequirement>,
+    /// Whether a 64-bit PHP build is required.
+    pub php_64bit: bool,
+    /// Required extension names, without the `ext-` prefix.
+    pub extensions: BTreeSet<String>,
+}
+
+impl PlatformRequirements {
+    /// Whether there is nothing to check.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.php.is_none() && !self.php_64bit && self.extensions.is_empty()
+    }
+
+    /// Raise the PHP requirement to `requirement` if it is stricter.
+    pub fn require_php(&mut self, requirement: PhpRequirement) {
+        let stricter = self.php.is_none_or(|current| {
+            requirement.version > current.version
+                || (requirement.version == current.version && !requirement.inclusive)
+        });
+        if stricter {
+            self.php = Some(requirement);
+        }
+    }
+
+    /// Render `platform_check.php`.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut checks = String::new();
+
+        if let Some(php) = self.php {
+            let (major, minor, patch) = php.version;
+            let _ = write!(
+                checks,
+                "if (!(PHP_VERSION_ID {op} {id})) {{\n    $issues[] = 'Your Composer dependencies require a PHP version \"{op} {major}.{minor}.{patch}\". You are running ' . PHP_VERSION . '.';\n}}\n\n",
+                op = php.operator(),
+                id = php.version_id(),
+            );
+        }
+
+        if self.php_64bit {
+            checks.push_str(
+                "if (PHP_INT_SIZE !== 8) {\n    $issues[] = 'Your Composer dependencies require a 64-bit build of PHP.';\n}\n\n",
+            );
+        }
+
+        if !self.extensions.is_empty() {
+            checks.push_str("$missingExtensions = array();\n");
+            for extension in &self.extensions {
+                // Composer names the extension `zend-opcache`, PHP `zend opcache`
+                let name = if extension == "zend-opcache" {
+                    "zend opcache"
+                } else {
+                    extension.as_str()
+                };
+                let name = php_string(name);
+                // CLI-only extensions are not required under other SAPIs
+                if matches!(extension.as_str(), "pcntl" | "readline") {
+                    let _ = writeln!(
+                        checks,
+                        "PHP_SAPI !== 'cli' || extension_loaded({name}) || $missingExtensions[] = {name};"
+                    );
+                } else {
+                    let _ = writeln!(
+                        checks,
+                        "extension_loaded({name}) || $missingExtensions[] = {name};"
+                    );
+                }
+            }
+            checks.push_str(
+                "\nif ($missingExtensions) {\n    $issues[] = 'Your Composer dependencies require the following PHP extensions to be installed: ' . implode(', ', $missingExtensions) . '.';\n}\n\n",
+            );
+        }
+
+        format!(
+            r"<?php
+
+// platform_check.php @generated by Libretto
+
+$issues = array();
+
+{checks}if ($issues) {{
+    if (!headers_sent()) {{
+        header('HTTP/1.1 500 Internal Server Error');
+    }}
+    if (!ini_get('display_errors')) {{
+        if (PHP_SAPI === 'cli' || PHP_SAPI === 'phpdbg') {{
+            fwrite(STDERR, 'Composer detected issues in your platform:' . PHP_EOL.PHP_EOL . implode(PHP_EOL, $issues) . PHP_EOL.PHP_EOL);
+        }} elseif (!headers_sent()) {{
+            echo 'Composer detected issues in your platform:' . PHP_EOL.PHP_EOL . str_replace('You are running '.PHP_VERSION.'.', '', implode(PHP_EOL, $issues)) . PHP_EOL.PHP_EOL;
+        }}
+    }}
+    throw new \RuntimeException(
+        'Composer detected issues in your platform: ' . implode(' ', $issues)
+    );
+}}
+"
+        )
+    }
+}
+
+/// Single-quoted PHP string literal.
+pub fn php_string(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highest_lower_bound_wins() {
+        let mut reqs = PlatformRequirements::default();
+        reqs.require_php(PhpRequirement {
+            version: (8, 1, 0),
+            inclusive: true,
+        });
+        reqs.require_php(PhpRequirement {
+            version: (7, 4, 0),
+            inclusive: true,
+        });
+        reqs.require_php(PhpRequirement {
+            version: (8, 1, 0),
+            inclusive: false,
+        });
+        assert_eq!(
+            reqs.php,
+            Some(PhpRequirement {
+                version: (8, 1, 0),
+                inclusive: false,
+            })
+        );
+    }
+
+    #[test]
+    fn render_checks_version_and_extensions() {
+        let reqs = PlatformRequirements {
+            php: Some(PhpRequirement {
+                version: (8, 2, 1),
+                inclusive: true,
+            }),
+            php_64bit: false,
+            extensions: ["json", "pcntl", "zend-opcache"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        };
+        let php = reqs.render();
+        assert!(php.contains("if (!(PHP_VERSION_ID >= 80201)) {"));
+        assert!(php.contains("require a PHP version \">= 8.2.1\""));
+        assert!(php.contains("extension_loaded('json') || $missingExtensions[] = 'json';"));
+        assert!(php.contains("PHP_SAPI !== 'cli' || extension_loaded('pcntl')"));
+        assert!(php.contains("extension_loaded('zend opcache')"));
+        assert!(!php.contains("PHP_INT_SIZE"));
+    }
+}
@@ -0,0 +1,310 @@
+//! `OPcache` preload script generation.
+//!
+//! The script compiles classmap files with `opcache_compile_file()`. `OPcache`
+//! can only link a preloaded class when its parent class, interfaces and
+//! traits were compiled before it, so files are ordered dependencies first
+//! and classes whose dependencies are excluded from preloading are left out.
+
+use crate::parser::PhpParser;
+use crate::platform_check::php_string;
+use crate::sort_packages;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+
+/// Which classes to preload.
+///
+/// Rules containing a `/` name packages (`vendor/name`, or `vendor/*` for a
+/// whole vendor); other rules are namespace prefixes (`App\Http`). Without
+/// include rules every class is a candidate. Exclude rules win over include
+/// rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreloadRules {
+    /// Packages and namespaces to preload.
+    pub include: Vec<String>,
+    /// Packages and namespaces never to preload.
+    pub exclude: Vec<String>,
+}
+
+impl PreloadRules {
+    /// Whether `class`, owned by `package` (`None` for the root package),
+    /// should be preloaded.
+    #[must_use]
+    pub fn allows(&self, class: &str, package: Option<&str>) -> bool {
+        let matches = |rule: &String| rule_matches(rule, class, package);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+fn rule_matches(rule: &str, class: &str, package: Option<&str>) -> bool {
+    if rule.contains('/') {
+        let Some(package) = package else {
+            return false;
+        };
+        return match rule.strip_suffix('*') {
+            Some(vendor) => package.starts_with(vendor),
+            None => package == rule,
+        };
+    }
+
+    let namespace = rule.trim_start_matches('\\');
+    if namespace.is_empty() {
+        return false;
+    }
+    if namespace.ends_with('\\') {
+        return class.starts_with(namespace);
+    }
+    class == namespace
+        || class
+            .strip_prefix(namespace)
+            .is_some_and(|rest| rest.starts_with('\\'))
+}
+
+/// A classmap entry considered for preloading.
+#[derive(Debug, Clone)]
+pub struct PreloadClass {
+    /// Fully qualified class name.
+    pub class: String,
+    /// File defining the class.
+    pub path: PathBuf,
+    /// Owning package (`None` for the root package).
+    pub package: Option<String>,
+}
+
+/// Files to preload, in compilation order.
+#[derive(Debug, Clone, Default)]
+pub struct PreloadScript {
+    /// Files in the order they are compiled.
+    pub files: Vec<PathBuf>,
+    /// Number of classes preloaded.
+    pub classes: usize,
+    /// Classes skipped because a parent, interface or trait is not preloaded.
+    pub unlinkable: Vec<String>,
+}
+
+impl PreloadScript {
+    /// Select the classes allowed by `rules` and order their files.
+    ///
+    /// `classes` is the full classmap; files are parsed to find the parents,
+    /// interfaces and traits of each class. Dependencies outside the classmap
+    /// are assumed to be built into PHP.
+    #[must_use]
+    pub fn build(classes: &[PreloadClass], rules: &PreloadRules) -> Self {
+        let classmap: HashMap<&str, &PreloadClass> =
+            classes.iter().map(|c| (c.class.as_str(), c)).collect();
+
+        // Parse each selected file once for the dependencies of its classes
+        let mut parser = PhpParser::new();
+        let mut parsed: HashMap<&Path, HashMap<String, Vec<String>>> = HashMap::new();
+        let mut selected: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for entry in classes {
+            if !rules.allows(&entry.class, entry.package.as_deref()) {
+                continue;
+            }
+            let parents = parsed.entry(&entry.path).or_insert_with(|| {
+                parser
+                    .parse_file(&entry.path)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|definition| definition.kind.is_class_like())
+                    .map(|definition| (definition.fqcn, definition.parents))
+                    .collect()
+            });
+            let dependencies = parents
+                .get(&entry.class)
+                .map(|parents| {
+                    parents
+                        .iter()
+                        .filter(|parent| classmap.contains_key(parent.as_str()))
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default();
+            selected.insert(&entry.class, dependencies);
+        }
+
+        // Drop classes depending on classmap classes that are not preloaded,
+        // repeating until every remaining dependency is preloaded
+        let mut unlinkable = BTreeSet::new();
+        loop {
+            let missing: Vec<&str> = selected
+                .iter()
+                .filter(|(_, deps)| deps.iter().any(|d| !selected.contains_key(d.as_str())))
+                .map(|(class, _)| *class)
+                .collect();
+            if missing.is_empty() {
+                break;
+            }
+            for class in missing {
+                selected.remove(class);
+                unlinkable.insert(class.to_string());
+            }
+        }
+
+        let graph: Vec<(String, Vec<String>)> = selected
+            .iter()
+            .map(|(class, deps)| ((*class).to_string(), deps.clone()))
+            .collect();
+        let mut files = Vec::new();
+        let mut seen = BTreeSet::new();
+        for class in sort_packages(&graph) {
+            let path = &classmap[class.as_str()].path;
+            if seen.insert(path) {
+                files.push(path.clone());
+            }
+        }
+
+        Self {
+            files,
+            classes: selected.len(),
+            unlinkable: unlinkable.into_iter().collect(),
+        }
+    }
+
+    /// Render the preload script to be written at `script_path`.
+    ///
+    /// Paths are made relative to the script's directory when possible so
+    /// the project can be moved after dumping.
+    #[must_use]
+    pub fn render(&self, script_path: &Path) -> String {
+        let script_dir = script_path.parent().map(absolute).unwrap_or_default();
+
+        let mut files = String::new();
+        for file in &self.files {
+            let file = absolute(file);
+            let _ = match relative_to(&file, &script_dir) {
+                Some(relative) => writeln!(files, "    __DIR__ . {},", php_string(&relative)),
+                None => writeln!(files, "    {},", php_string(&file.to_string_lossy())),
+            };
+        }
+
+        format!(
+            r"<?php
+
+// preload.php @generated by Libretto
+
+if (!function_exists('opcache_compile_file')) {{
+    return;
+}}
+
+$files = array(
+{files});
+
+foreach ($files as $file) {{
+    opcache_compile_file($file);
+}}
+"
+        )
+    }
+}
+
+/// `path` made absolute, canonicalizing its longest existing ancestor.
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(canonical) = std::fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => absolute(parent).join(name),
+        _ => std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
+
+/// `/`-separated path of `path` relative to `base`, starting with `/`.
+fn relative_to(path: &Path, base: &Path) -> Option<String> {
+    let path: Vec<Component<'_>> = path.components().collect();
+    let base: Vec<Component<'_>> = base.components().collect();
+    // Different roots (e.g. drive letters) cannot be made relative
+    if path.first() != base.first() {
+        return None;
+    }
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+
+    let mut relative = String::new();
+    for _ in common..base.len() {
+        relative.push_str("/..");
+    }
+    for component in &path[common..] {
+        relative.push('/');
+        relative.push_str(&component.as_os_str().to_string_lossy());
+    }
+    Some(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_match_packages_and_namespaces() {
+        let rules = PreloadRules {
+            include: vec!["acme/*".into(), "App\\Http".into()],
+            exclude: vec!["acme/dev-tools".into(), "App\\Http\\Debug\\".into()],
+        };
+        assert!(rules.allows("Acme\\Lib\\Client", Some("acme/lib")));
+        assert!(!rules.allows("Acme\\Dev\\Dumper", Some("acme/dev-tools")));
+        assert!(rules.allows("App\\Http\\Kernel", None));
+        assert!(!rules.allows("App\\HttpClient", None));
+        assert!(!rules.allows("App\\Http\\Debug\\Toolbar", None));
+        assert!(!rules.allows("Other\\Thing", Some("other/thing")));
+    }
+
+    #[test]
+    fn parents_are_compiled_before_children() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = temp.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let child = write(
+            "Child.php",
+            "<?php namespace App; class Child extends Base implements Contract { use Helpers; }",
+        );
+        let base = write(
+            "Base.php",
+            "<?php namespace App; abstract class Base extends \\Exception {}",
+        );
+        let contract = write("Contract.php", "<?php namespace App; interface Contract {}");
+        let helpers = write("Helpers.php", "<?php namespace App; trait Helpers {}");
+        let debug = write(
+            "Debug.php",
+            "<?php namespace App\\Debug; class Dumper extends \\App\\Child {}",
+        );
+        let class = |class: &str, path: &PathBuf| PreloadClass {
+            class: class.to_string(),
+            path: path.clone(),
+            package: None,
+        };
+        let classes = [
+            class("App\\Child", &child),
+            class("App\\Base", &base),
+            class("App\\Contract", &contract),
+            class("App\\Helpers", &helpers),
+            class("App\\Debug\\Dumper", &debug),
+        ];
+
+        let script = PreloadScript::build(&classes, &PreloadRules::default());
+        let position = |path: &PathBuf| script.files.iter().position(|f| f == path).unwrap();
+        assert_eq!(script.classes, 5);
+        assert!(position(&base) < position(&child));
+        assert!(position(&contract) < position(&child));
+        assert!(position(&helpers) < position(&child));
+        assert!(position(&child) < position(&debug));
+
+        // Excluding a trait makes its users (and their children) unlinkable
+        let rules = PreloadRules {
+            include: Vec::new(),
+            exclude: vec!["App\\Helpers".into()],
+        };
+        let script = PreloadScript::build(&classes, &rules);
+        assert_eq!(script.unlinkable, ["App\\Child", "App\\Debug\\Dumper"]);
+        assert_eq!(script.classes, 2);
+
+        let php = script.render(&temp.path().join("vendor/preload.php"));
+        assert!(php.contains("    __DIR__ . '/../Base.php',"));
+        assert!(php.contains("opcache_compile_file($file);"));
+    }
+}
@@ -105,10 +105,57 @@ impl ExcludePattern {
         Self { patterns: regexes }
     }
 
+    /// Create from a package's `exclude-from-classmap` patterns, with
+    /// Composer's semantics.
+    ///
+    /// Patterns are relative to `package_dir` (leading `./` and `../`
+    /// segments are resolved against it), `*` matches within a path segment
+    /// and `**` across segments. A pattern matches a path and everything
+    /// below it. The resulting patterns match absolute, canonical paths;
+    /// patterns whose base directory does not exist are dropped.
+    #[must_use]
+    pub fn for_package(package_dir: &Path, patterns: &[String]) -> Self {
+        let regexes = patterns
+            .iter()
+            .filter_map(|pattern| {
+                let pattern = pattern.replace('\\', "/");
+                let mut pattern = pattern.trim().trim_matches('/');
+                let mut updir = PathBuf::new();
+                while let Some((segment, rest)) = pattern.split_once('/')
+                    && (segment == "." || segment == "..")
+                {
+                    updir.push(segment);
+                    pattern = rest.trim_start_matches('/');
+                }
+
+                let base = std::fs::canonicalize(package_dir.join(updir)).ok()?;
+                let base = base.to_string_lossy().replace('\\', "/");
+                let glob = regex::escape(pattern)
+                    .replace(r"\*\*", ".+?")
+                    .replace(r"\*", "[^/]+?");
+                let glob = collapse_slashes(&glob);
+                Regex::new(&format!("{}/{glob}($|/)", regex::escape(&base))).ok()
+            })
+            .collect();
+
+        Self { patterns: regexes }
+    }
+
+    /// Add the patterns of `other`.
+    pub fn extend(&mut self, other: Self) {
+        self.patterns.extend(other.patterns);
+    }
+
+    /// Whether there are no patterns.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
     /// Check if path should be excluded.
     #[must_use]
     pub fn should_exclude(&self, path: &Path) -> bool {
-        let path_str = path.to_string_lossy();
+        let path_str = path.to_string_lossy().replace('\\', "/");
         self.patterns.iter().any(|p| p.is_match(&path_str))
     }
 
@@ -121,6 +168,17 @@ impl ExcludePattern {
     }
 }
 
+/// `s` with runs of `/` collapsed to one.
+fn collapse_slashes(s: &str) -> String {
+    let mut collapsed = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c != '/' || !collapsed.ends_with('/') {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
 impl Default for ExcludePattern {
     fn default() -> Self {
         Self::empty()
@@ -329,7 +387,7 @@ pub fn build_classmap(results: &[FileScanResult]) -> ahash::AHashMap<String, Pat
     let mut map = ahash::AHashMap::with_capacity(results.iter().map(|r| r.definitions.len()).sum());
 
     for result in results {
-        for def in &result.definitions {
+        for def in result.definitions.iter().filter(|d| d.kind.is_class_like()) {
             map.insert(def.fqcn.clone(), result.path.clone());
         }
     }
@@ -367,6 +425,32 @@ mod tests {
         assert!(!pattern.should_exclude(Path::new("User.php")));
     }
 
+    #[test]
+    fn package_exclude_pattern_is_anchored_with_globs() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let package = tmp.path().join("vendor/acme/lib");
+        std::fs::create_dir_all(&package).unwrap();
+        let pattern = ExcludePattern::for_package(
+            &package,
+            &[
+                "/src/*/Tests/".to_string(),
+                "lib/**/Fixture.php".to_string(),
+                "../other/legacy".to_string(),
+            ],
+        );
+        let root = std::fs::canonicalize(tmp.path()).unwrap();
+        let path = |p: &str| root.join(p);
+
+        assert!(pattern.should_exclude(&path("vendor/acme/lib/src/Http/Tests/Foo.php")));
+        assert!(!pattern.should_exclude(&path("vendor/acme/lib/src/Http/Client/Tests/Foo.php")));
+        assert!(pattern.should_exclude(&path("vendor/acme/lib/lib/a/b/Fixture.php")));
+        assert!(!pattern.should_exclude(&path("vendor/acme/lib/lib/Fixture.php")));
+        assert!(pattern.should_exclude(&path("vendor/acme/other/legacy/Old.php")));
+        assert!(!pattern.should_exclude(&path("vendor/acme/other/legacy2/Old.php")));
+        assert!(!pattern.should_exclude(&path("vendor/other/lib/src/Http/Tests/Foo.php")));
+        assert!(!pattern.should_exclude(Path::new("src/Http/Tests/Foo.php")));
+    }
+
     #[test]
     fn scanner_creation() {
         let scanner = Scanner::without_exclusions();
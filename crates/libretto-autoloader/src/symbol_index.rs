@@ -0,0 +1,218 @@
+//! Machine-readable index of the symbols found by the classmap scan.
+//!
+//! Lists every class, interface, trait and enum with the file, line and
+//! package defining it, for IDEs, static analysers and `libretto which`.
+
+use crate::parser::DefinitionKind;
+use libretto_core::{Error, Result};
+use rkyv::{Archive, Deserialize, Serialize};
+use serde::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
+use std::path::Path;
+
+/// A symbol defined in the project or one of its packages.
+#[derive(
+    Debug, Clone, PartialEq, Eq, Archive, Deserialize, Serialize, SerdeSerialize, SerdeDeserialize,
+)]
+pub struct Symbol {
+    /// Fully qualified name.
+    pub fqcn: String,
+    /// Kind of definition.
+    pub kind: DefinitionKind,
+    /// Defining file, relative to the project root where possible.
+    pub file: String,
+    /// Line of the definition.
+    pub line: u32,
+    /// Owning package (`None` for the root package).
+    pub package: Option<String>,
+}
+
+/// On-disk format of a [`SymbolIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// Pretty-printed JSON.
+    Json,
+    /// rkyv archive, prefixed with magic bytes.
+    Rkyv,
+}
+
+impl IndexFormat {
+    /// Conventional file extension.
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Rkyv => "rkyv",
+        }
+    }
+}
+
+/// Index of all symbols in the classmap, sorted by name.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    Archive,
+    Deserialize,
+    Serialize,
+    SerdeSerialize,
+    SerdeDeserialize,
+)]
+pub struct SymbolIndex {
+    /// Format version.
+    pub version: u32,
+    /// Symbols sorted by fully qualified name.
+    pub symbols: Vec<Symbol>,
+}
+
+impl SymbolIndex {
+    /// Current index format version.
+    pub const VERSION: u32 = 1;
+
+    /// Magic bytes of the rkyv format.
+    const MAGIC: &'static [u8; 8] = b"LBRTSYMS";
+
+    /// Create an index from `symbols`.
+    #[must_use]
+    pub fn new(mut symbols: Vec<Symbol>) -> Self {
+        symbols.sort_by(|a, b| a.fqcn.cmp(&b.fqcn).then_with(|| a.file.cmp(&b.file)));
+        Self {
+            version: Self::VERSION,
+            symbols,
+        }
+    }
+
+    /// Symbols named `name`.
+    ///
+    /// Names are compared case-insensitively, as PHP does. A fully qualified
+    /// name (with or without a leading `\`) matches exactly; a name without a
+    /// namespace also matches the short name of namespaced symbols.
+    #[must_use]
+    pub fn find(&self, name: &str) -> Vec<&Symbol> {
+        let name = name.trim_start_matches('\\');
+        let exact: Vec<&Symbol> = self
+            .symbols
+            .iter()
+            .filter(|s| s.fqcn.eq_ignore_ascii_case(name))
+            .collect();
+        if !exact.is_empty() || name.contains('\\') {
+            return exact;
+        }
+        self.symbols
+            .iter()
+            .filter(|s| {
+                s.fqcn
+                    .rsplit('\\')
+                    .next()
+                    .is_some_and(|short| short.eq_ignore_ascii_case(name))
+            })
+            .collect()
+    }
+
+    /// Serialize the index in `format`.
+    ///
+    /// # Errors
+    /// Returns error if serialization fails.
+    pub fn to_bytes(&self, format: IndexFormat) -> Result<Vec<u8>> {
+        match format {
+            IndexFormat::Json => {
+                let mut json = sonic_rs::to_vec_pretty(self)?;
+                json.push(b'\n');
+                Ok(json)
+            }
+            IndexFormat::Rkyv => {
+                let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(self)
+                    .map_err(|e| Error::cache_simple(e.to_string()))?;
+                let mut output = Vec::with_capacity(Self::MAGIC.len() + bytes.len());
+                output.extend_from_slice(Self::MAGIC);
+                output.extend_from_slice(&bytes);
+                Ok(output)
+            }
+        }
+    }
+
+    /// Parse an index in either format.
+    ///
+    /// # Errors
+    /// Returns error if the bytes are not a valid index of the current
+    /// version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let index: Self = match bytes.strip_prefix(Self::MAGIC.as_slice()) {
+            Some(archive) => rkyv::from_bytes::<Self, rkyv::rancor::Error>(archive)
+                .map_err(|e| Error::cache_simple(e.to_string()))?,
+            None => sonic_rs::from_slice(bytes)?,
+        };
+        if index.version != Self::VERSION {
+            return Err(Error::cache_simple(format!(
+                "unsupported symbol index version {}",
+                index.version
+            )));
+        }
+        Ok(index)
+    }
+
+    /// Write the index to `path` in `format`.
+    ///
+    /// # Errors
+    /// Returns error if the file cannot be written.
+    pub fn write(&self, path: &Path, format: IndexFormat) -> Result<()> {
+        let bytes = self.to_bytes(format)?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, bytes).map_err(|e| Error::io(&tmp, e))?;
+        std::fs::rename(&tmp, path).map_err(|e| Error::io(path, e))
+    }
+
+    /// Read an index written by [`write`](Self::write).
+    ///
+    /// # Errors
+    /// Returns error if the file cannot be read or parsed.
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).map_err(|e| Error::io(path, e))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(fqcn: &str, kind: DefinitionKind, package: Option<&str>) -> Symbol {
+        Symbol {
+            fqcn: fqcn.to_string(),
+            kind,
+            file: format!("src/{}.php", fqcn.replace('\\', "/")),
+            line: 3,
+            package: package.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn find_matches_fqcn_or_short_name() {
+        let index = SymbolIndex::new(vec![
+            symbol("App\\Foo", DefinitionKind::Class, None),
+            symbol("Acme\\Foo", DefinitionKind::Interface, Some("acme/lib")),
+            symbol("Acme\\FooBar", DefinitionKind::Trait, Some("acme/lib")),
+        ]);
+        assert_eq!(index.find("\\app\\foo").len(), 1);
+        assert_eq!(index.find("Foo").len(), 2);
+        assert!(index.find("App\\Bar").is_empty());
+    }
+
+    #[test]
+    fn json_and_rkyv_round_trip() {
+        let index = SymbolIndex::new(vec![
+            symbol("App\\Status", DefinitionKind::Enum, None),
+            symbol("Acme\\Client", DefinitionKind::Class, Some("acme/lib")),
+        ]);
+        for format in [IndexFormat::Json, IndexFormat::Rkyv] {
+            let bytes = index.to_bytes(format).unwrap();
+            assert_eq!(SymbolIndex::from_bytes(&bytes).unwrap(), index);
+        }
+        let json = String::from_utf8(index.to_bytes(IndexFormat::Json).unwrap()).unwrap();
+        assert!(json.contains("\"kind\": \"enum\""));
+    }
+}
@@ -0,0 +1,564 @@
+//! Locked package metadata for autoloader generation.
+//!
+//! The autoloader is fed from `composer.lock` rather than by scanning
+//! `vendor/`, so `--no-dev` drops dev packages, packages installed through
+//! `extra.installer-paths` are found where they live, and stray directories
+//! under `vendor/` are ignored. Only the root package contributes
+//! `autoload-dev`. The same metadata drives `platform_check.php`.
+
+use crate::installer_paths::InstallerPaths;
+use anyhow::{Context, Result};
+use libretto_autoloader::{
+    AutoloadConfig, AutoloaderGenerator, PhpRequirement, PlatformRequirements, PreloadRules,
+    sort_packages,
+};
+use libretto_lockfile::{ComposerLock, LockedPackage};
+use libretto_resolver::ComposerConstraint;
+use serde::Deserialize;
+use sonic_rs::{JsonValueTrait, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Root composer.json autoload sections and requirements.
+#[derive(Debug, Default, Deserialize)]
+struct RootPackage {
+    #[serde(default)]
+    require: BTreeMap<String, String>,
+    #[serde(default)]
+    autoload: AutoloadSection,
+    #[serde(default, rename = "autoload-dev")]
+    autoload_dev: AutoloadSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AutoloadSection {
+    #[serde(default, rename = "psr-4")]
+    psr4: HashMap<String, PathList>,
+    #[serde(default, rename = "psr-0")]
+    psr0: HashMap<String, PathList>,
+    #[serde(default)]
+    classmap: Vec<String>,
+    #[serde(default)]
+    files: Vec<String>,
+    #[serde(default, rename = "exclude-from-classmap")]
+    exclude: Vec<String>,
+}
+
+/// PSR-0/PSR-4 paths can be either a string or array of strings.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PathList {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl PathList {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::Single(s) => vec![s],
+            Self::Multiple(v) => v,
+        }
+    }
+}
+
+impl AutoloadSection {
+    /// Merge this section into `config`.
+    fn extend(self, config: &mut AutoloadConfig) {
+        for (namespace, paths) in self.psr4 {
+            config
+                .psr4
+                .mappings
+                .entry(namespace)
+                .or_default()
+                .extend(paths.into_vec());
+        }
+        for (namespace, paths) in self.psr0 {
+            config
+                .psr0
+                .mappings
+                .entry(namespace)
+                .or_default()
+                .extend(paths.into_vec());
+        }
+        config.classmap.paths.extend(self.classmap);
+        config.files.files.extend(self.files);
+        config.exclude.patterns.extend(self.exclude);
+    }
+
+    /// The `autoload` section of a locked package.
+    fn of(pkg: &LockedPackage) -> Self {
+        pkg.autoload
+            .as_ref()
+            .and_then(|v| sonic_rs::from_value(v).ok())
+            .unwrap_or_default()
+    }
+
+    fn into_config(self) -> AutoloadConfig {
+        let mut config = AutoloadConfig::default();
+        self.extend(&mut config);
+        config
+    }
+}
+
+/// `config.platform-check` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlatformCheckMode {
+    /// Check the PHP version and extensions (the default).
+    Full,
+    /// Check the PHP version only (`"php-only"`).
+    PhpOnly,
+    /// No platform check (`false`).
+    Disabled,
+}
+
+impl PlatformCheckMode {
+    fn from_composer(composer: &Value) -> Self {
+        let setting = composer
+            .get("config")
+            .and_then(|config| config.get("platform-check"));
+        match setting {
+            Some(v) if v.as_bool() == Some(false) => Self::Disabled,
+            Some(v) if v.as_str() == Some("php-only") => Self::PhpOnly,
+            _ => Self::Full,
+        }
+    }
+}
+
+/// Add the root package and every locked package to `generator`.
+///
+/// Reads `composer.json` and `composer.lock` from `base_dir`. Dev packages
+/// and the root `autoload-dev` section are only included when `dev` is set.
+/// Also configures `platform_check.php` from the requirements of the same
+/// packages. Returns the number of vendor packages added.
+pub fn add_packages(
+    generator: &mut AutoloaderGenerator,
+    base_dir: &Path,
+    vendor_dir: &Path,
+    dev: bool,
+) -> Result<usize> {
+    let composer_path = base_dir.join("composer.json");
+    let composer_content = std::fs::read_to_string(&composer_path).ok();
+    let composer = composer_content
+        .as_deref()
+        .and_then(|content| sonic_rs::from_str::<Value>(content).ok());
+    let installer_paths = composer
+        .as_ref()
+        .map(InstallerPaths::from_composer)
+        .unwrap_or_default();
+    let check_mode = composer
+        .as_ref()
+        .map_or(PlatformCheckMode::Full, PlatformCheckMode::from_composer);
+
+    let lock_path = base_dir.join("composer.lock");
+    let lock = if lock_path.exists() {
+        crate::commands::lock_generator::read_lock(&lock_path)?
+    } else {
+        ComposerLock::default()
+    };
+    generator.set_package_order(package_order(&lock));
+
+    let locked = if dev {
+        lock.packages.into_iter().chain(lock.packages_dev).collect()
+    } else {
+        lock.packages
+    };
+
+    let mut requires: Vec<(&str, &str)> = Vec::new();
+    let mut provided: HashSet<String> = HashSet::new();
+    for pkg in &locked {
+        requires.extend(pkg.require.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        provided.extend(
+            pkg.replace
+                .keys()
+                .chain(pkg.provide.keys())
+                .map(|k| k.to_lowercase()),
+        );
+    }
+    let mut platform = requires_platform(&requires, &provided);
+
+    // Composer scans the root package first, so its classes win over
+    // vendor duplicates
+    if let Some(content) = composer_content {
+        let root: RootPackage = sonic_rs::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", composer_path.display()))?;
+        let root_requires: Vec<(&str, &str)> = root
+            .require
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        merge_platform(&mut platform, requires_platform(&root_requires, &provided));
+
+        let mut config = root.autoload.into_config();
+        if dev {
+            root.autoload_dev.extend(&mut config);
+        }
+        generator.add_package(base_dir, &config);
+    }
+
+    let mut added = 0;
+    for pkg in locked {
+        let dir = install_dir(&pkg, base_dir, vendor_dir, &installer_paths);
+        if !dir.is_dir() {
+            debug!(package = %pkg.name, dir = %dir.display(), "Locked package not installed");
+            continue;
+        }
+        let name = pkg.name.to_lowercase();
+        let mut config = AutoloadSection::of(&pkg).into_config();
+        config.target_dir = pkg.target_dir;
+        generator.add_vendor_package(&name, &dir, &config);
+        added += 1;
+    }
+
+    match check_mode {
+        PlatformCheckMode::Full => generator.set_platform_check(Some(platform)),
+        PlatformCheckMode::PhpOnly => {
+            platform.extensions.clear();
+            generator.set_platform_check(Some(platform));
+        }
+        PlatformCheckMode::Disabled => generator.set_platform_check(None),
+    }
+
+    Ok(added)
+}
+
+/// Apply the autoloader `config` options of `composer.json` in `base_dir`.
+///
+/// Handles `autoloader-suffix`, `apcu-autoloader`, `apcu-autoloader-prefix`
+/// and `prepend-autoloader`; `apcu` and `apcu_prefix` come from the command
+/// line and take precedence. Without a configured suffix the lock's
+/// `content-hash` is used, so the generated class names only change when
+/// the dependencies do. The `APCu` prefix defaults to the suffix for the same
+/// reason.
+pub fn apply_config(
+    generator: &mut AutoloaderGenerator,
+    base_dir: &Path,
+    apcu: bool,
+    apcu_prefix: Option<&str>,
+) {
+    let read_json = |name: &str| {
+        std::fs::read_to_string(base_dir.join(name))
+            .ok()
+            .and_then(|content| sonic_rs::from_str::<Value>(&content).ok())
+    };
+    let composer = read_json("composer.json");
+    let config = composer.as_ref().and_then(|c| c.get("config"));
+    let setting = |key: &str| config.and_then(|c| c.get(key));
+
+    let suffix = setting("autoloader-suffix")
+        .and_then(|v| v.as_str().map(str::to_string))
+        .filter(|s| !s.is_empty())
+        .or_else(|| {
+            let lock_path = base_dir.join("composer.lock");
+            lock_path.exists().then_some(())?;
+            crate::commands::lock_generator::read_lock(&lock_path)
+                .ok()
+                .map(|lock| lock.content_hash)
+        });
+    if let Some(suffix) = &suffix {
+        generator.set_suffix(suffix);
+    }
+
+    let apcu = apcu || setting("apcu-autoloader").and_then(JsonValueTrait::as_bool) == Some(true);
+    if apcu {
+        let prefix = apcu_prefix
+            .map(str::to_string)
+            .or_else(|| {
+                setting("apcu-autoloader-prefix").and_then(|v| v.as_str().map(str::to_string))
+            })
+            .or(suffix)
+            .unwrap_or_else(|| "libretto".to_string());
+        generator.set_apcu_prefix(Some(prefix));
+    }
+
+    if let Some(prepend) = setting("prepend-autoloader").and_then(JsonValueTrait::as_bool) {
+        generator.set_prepend(prepend);
+    }
+}
+
+/// Preload rules from `extra.preload` of `composer.json` in `base_dir`.
+///
+/// `include` and `exclude` each take a package name (`vendor/name` or
+/// `vendor/*`), a namespace prefix, or a list of them.
+pub fn preload_rules(base_dir: &Path) -> PreloadRules {
+    let composer = std::fs::read_to_string(base_dir.join("composer.json"))
+        .ok()
+        .and_then(|content| sonic_rs::from_str::<Value>(&content).ok());
+    let preload = composer
+        .as_ref()
+        .and_then(|c| c.get("extra"))
+        .and_then(|extra| extra.get("preload"));
+    let list = |key: &str| {
+        preload
+            .and_then(|p| p.get(key))
+            .and_then(|v| sonic_rs::from_value::<PathList>(v).ok())
+            .map(PathList::into_vec)
+            .unwrap_or_default()
+    };
+    PreloadRules {
+        include: list("include"),
+        exclude: list("exclude"),
+    }
+}
+
+/// Where a locked package is installed.
+///
+/// For legacy `target-dir` packages this is the directory their autoload
+/// paths are relative to, not the `<target-dir>` subdirectory Composer
+/// installs the code into.
+fn install_dir(
+    pkg: &LockedPackage,
+    base_dir: &Path,
+    vendor_dir: &Path,
+    installer_paths: &InstallerPaths,
+) -> PathBuf {
+    installer_paths
+        .get_path(base_dir, &pkg.name, pkg.package_type.as_deref())
+        .unwrap_or_else(|| vendor_dir.join(pkg.name.replace('/', std::path::MAIN_SEPARATOR_STR)))
+}
+
+/// Platform requirements of a set of `require` links.
+///
+/// The PHP requirement is the lower bound of the `php`/`php-64bit`
+/// constraint; extensions provided or replaced by a package are skipped.
+fn requires_platform(
+    requires: &[(&str, &str)],
+    provided: &HashSet<String>,
+) -> PlatformRequirements {
+    let mut platform = PlatformRequirements::default();
+    for (target, constraint) in requires {
+        let target = target.to_lowercase();
+        if target == "php" || target == "php-64bit" {
+            platform.php_64bit |= target == "php-64bit";
+            if let Some(requirement) = php_lower_bound(constraint) {
+                platform.require_php(requirement);
+            }
+        } else if let Some(extension) = target.strip_prefix("ext-")
+            && !provided.contains(&target)
+        {
+            platform.extensions.insert(extension.to_string());
+        }
+    }
+    platform
+}
+
+fn merge_platform(into: &mut PlatformRequirements, other: PlatformRequirements) {
+    if let Some(php) = other.php {
+        into.require_php(php);
+    }
+    into.php_64bit |= other.php_64bit;
+    into.extensions.extend(other.extensions);
+}
+
+/// Lowest PHP version allowed by `constraint`, ignoring unbounded ones.
+fn php_lower_bound(constraint: &str) -> Option<PhpRequirement> {
+    let constraint = ComposerConstraint::parse(constraint)?;
+    let (lower, _) = constraint.ranges().bounding_range()?;
+    let (version, inclusive) = match lower {
+        Bound::Included(v) => (v, true),
+        Bound::Excluded(v) => (v, false),
+        Bound::Unbounded => return None,
+    };
+    let version = (version.major, version.minor, version.patch);
+    (version != (0, 0, 0)).then_some(PhpRequirement { version, inclusive })
+}
+
+/// Dependency order of the locked packages, dependencies first.
+///
+/// Requirements satisfied through `replace` or `provide` are attributed to
+/// the package declaring them.
+fn package_order(lock: &ComposerLock) -> Vec<String> {
+    let packages: Vec<&LockedPackage> = lock.all_packages().collect();
+
+    let mut providers: HashMap<String, String> = HashMap::new();
+    for pkg in &packages {
+        let name = pkg.name.to_lowercase();
+        for target in pkg.replace.keys().chain(pkg.provide.keys()) {
+            providers
+                .entry(target.to_lowercase())
+                .or_insert_with(|| name.clone());
+        }
+        providers.insert(name.clone(), name);
+    }
+
+    let graph: Vec<(String, Vec<String>)> = packages
+        .iter()
+        .map(|pkg| {
+            let requires = pkg
+                .require
+                .keys()
+                .filter_map(|dep| providers.get(&dep.to_lowercase()).cloned())
+                .collect();
+            (pkg.name.to_lowercase(), requires)
+        })
+        .collect();
+
+    sort_packages(&graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libretto_autoloader::OptimizationLevel;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn replaced_requirements_order_the_provider_first() {
+        let lock: ComposerLock = sonic_rs::from_str(
+            r#"{"packages": [
+                {"name": "acme/app", "version": "1.0.0", "require": {"psr/log-implementation": "1.0"}},
+                {"name": "Acme/Logger", "version": "1.0.0", "provide": {"psr/log-implementation": "1.0"}}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(package_order(&lock), vec!["acme/logger", "acme/app"]);
+    }
+
+    #[test]
+    fn platform_requirements_from_requires() {
+        let provided: HashSet<String> = ["ext-mbstring".to_string()].into();
+        let platform = requires_platform(
+            &[
+                ("php", "^7.4 || ^8.0"),
+                ("php-64bit", ">=8.1"),
+                ("ext-json", "*"),
+                ("ext-mbstring", "*"),
+                ("acme/lib", "^1.0"),
+            ],
+            &provided,
+        );
+        assert_eq!(
+            platform.php,
+            Some(PhpRequirement {
+                version: (8, 1, 0),
+                inclusive: true,
+            })
+        );
+        assert!(platform.php_64bit);
+        assert_eq!(
+            platform.extensions.into_iter().collect::<Vec<_>>(),
+            vec!["json"]
+        );
+        assert_eq!(php_lower_bound("*"), None);
+    }
+
+    #[test]
+    fn preload_rules_from_extra() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(
+            &tmp.path().join("composer.json"),
+            r#"{"extra": {"preload": {"include": "App\\", "exclude": ["acme/*", "App\\Debug"]}}}"#,
+        );
+        let rules = preload_rules(tmp.path());
+        assert_eq!(rules.include, ["App\\"]);
+        assert_eq!(rules.exclude, ["acme/*", "App\\Debug"]);
+    }
+
+    #[test]
+    fn only_locked_packages_are_autoloaded() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write(
+            &base.join("composer.json"),
+            r#"{
+                "autoload-dev": {"classmap": ["tests/"]},
+                "extra": {"installer-paths": {"plugins/{$name}/": ["type:wp-plugin"]}}
+            }"#,
+        );
+        write(
+            &base.join("composer.lock"),
+            r#"{
+                "packages": [
+                    {"name": "acme/lib", "version": "1.0.0", "autoload": {"classmap": ["src/"]}},
+                    {"name": "acme/plugin", "version": "1.0.0", "type": "wp-plugin", "autoload": {"classmap": ["."]}}
+                ],
+                "packages-dev": [
+                    {"name": "acme/dev", "version": "1.0.0", "autoload": {"classmap": ["src/"]}}
+                ]
+            }"#,
+        );
+        write(
+            &base.join("vendor/acme/lib/src/Lib.php"),
+            "<?php class Lib {}",
+        );
+        write(
+            &base.join("vendor/acme/dev/src/Dev.php"),
+            "<?php class Dev {}",
+        );
+        write(
+            &base.join("vendor/stray/pkg/src/Stray.php"),
+            "<?php class Stray {}",
+        );
+        write(
+            &base.join("plugins/plugin/Plugin.php"),
+            "<?php class Plugin {}",
+        );
+        write(&base.join("tests/RootTest.php"), "<?php class RootTest {}");
+
+        let vendor = base.join("vendor");
+        let mut generator =
+            AutoloaderGenerator::with_optimization(vendor.clone(), OptimizationLevel::Optimized);
+        let added = add_packages(&mut generator, base, &vendor, false).unwrap();
+        generator.generate().unwrap();
+        assert_eq!(added, 2);
+
+        let classmap =
+            std::fs::read_to_string(vendor.join("composer/autoload_classmap.php")).unwrap();
+        assert!(classmap.contains("'Lib'"));
+        assert!(classmap.contains("'Plugin'"));
+        assert!(!classmap.contains("'Dev'"));
+        assert!(!classmap.contains("'Stray'"));
+        assert!(!classmap.contains("'RootTest'"));
+    }
+
+    #[test]
+    fn classmap_matches_composer_rules() {
+        let base = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/classmap-parity");
+        let vendor = base.join("vendor");
+        let mut generator = AutoloaderGenerator::with_optimization(
+            vendor.clone(),
+            OptimizationLevel::Authoritative,
+        );
+        add_packages(&mut generator, &base, &vendor, true).unwrap();
+
+        let expected_php = std::fs::read_to_string(base.join("expected_classmap.php")).unwrap();
+        let expected: Vec<(String, String)> = expected_php
+            .lines()
+            .filter_map(|line| {
+                let (class, path) = line.trim().strip_prefix('\'')?.split_once("' => ")?;
+                let path = path
+                    .replace("$vendorDir . '/", "vendor/")
+                    .replace("$baseDir . '/", "");
+                Some((
+                    class.replace("\\\\", "\\"),
+                    path.trim_end_matches("',").to_string(),
+                ))
+            })
+            .filter(|(class, _)| class != "Composer\\InstalledVersions")
+            .collect();
+
+        let actual: Vec<(String, String)> = generator
+            .classmap_entries()
+            .into_iter()
+            .map(|(class, file)| {
+                let relative = file.strip_prefix(&base).unwrap_or(file);
+                (
+                    class.to_string(),
+                    relative.to_string_lossy().replace('\\', "/"),
+                )
+            })
+            .collect();
+        assert_eq!(actual, expected);
+
+        // The vendor directory inside the root PSR-4 directory is not scanned
+        let warnings = generator.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].is_psr_violation());
+    }
+}
@@ -1,17 +1,62 @@
-//! Content-Addressable Storage (CAS) cache with hardlink support.
+//! Content-Addressable Storage (CAS) cache with reflink/hardlink support.
 //!
-//! Like pnpm, we store package contents once in a global cache and hardlink
-//! to the vendor directory. This provides:
-//! - Instant installs on cache hits (just create hardlinks)
+//! Like pnpm, we store package contents once in a global cache and link
+//! them into the vendor directory. This provides:
+//! - Instant installs on cache hits (just create clones or hardlinks)
 //! - Massive disk space savings (files stored once)
 //! - Integrity verification via content hashing
+//!
+//! Cache files are stored read-only with a per-entry manifest of blake3
+//! hashes. How files reach vendor is controlled by [`LinkMode`]: reflinks
+//! and copies give vendor its own data, while hardlinks share the inode, so
+//! hardlinked entries are re-verified against the manifest before reuse.
 
-use anyhow::Result;
+use crate::process_lock;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::collections::hash_map::DefaultHasher;
 use std::fs;
 use std::hash::{Hash, Hasher};
+use std::io;
 use std::path::{Path, PathBuf};
 
+/// Marker written once an entry is fully stored.
+const COMPLETE_MARKER: &str = ".complete";
+
+/// Per-entry file hash manifest.
+const MANIFEST: &str = ".manifest";
+
+/// Sequence number making staging directories unique within the process.
+static STAGING_SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Set once the filesystem has rejected a reflink, to skip further attempts.
+#[cfg(target_os = "linux")]
+static REFLINK_UNSUPPORTED: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+/// How package files are materialized from the cache into vendor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LinkMode {
+    /// Copy-on-write clones (FICLONE on Linux, clonefile on macOS), falling
+    /// back to a copy where the filesystem does not support them
+    #[default]
+    Reflink,
+    /// Hardlinks to the read-only cache files, verified by hash before reuse
+    Hardlink,
+    /// Plain copies
+    Copy,
+}
+
+impl LinkMode {
+    /// Read the link mode from `LIBRETTO_LINK_MODE`, defaulting to reflink.
+    pub fn from_env() -> Self {
+        std::env::var("LIBRETTO_LINK_MODE")
+            .ok()
+            .and_then(|v| <Self as clap::ValueEnum>::from_str(&v, true).ok())
+            .unwrap_or_default()
+    }
+}
+
 /// Global CAS cache location (~/.libretto/cache)
 pub fn cache_dir() -> PathBuf {
     directories::BaseDirs::new().map_or_else(
@@ -25,42 +70,60 @@ pub fn cas_dir() -> PathBuf {
     cache_dir().join("cas")
 }
 
-/// Check if a package is cached (by URL hash)
-pub fn is_cached(url: &str) -> bool {
-    let hash = hash_url(url);
-    let marker = cas_dir().join(&hash).join(".complete");
-    marker.exists()
-}
-
 /// Get cached package path if it exists
 pub fn get_cached_path(url: &str) -> Option<PathBuf> {
     let hash = hash_url(url);
     let path = cas_dir().join(&hash);
-    let marker = path.join(".complete");
+    let marker = path.join(COMPLETE_MARKER);
     if marker.exists() { Some(path) } else { None }
 }
 
 /// Store extracted package in CAS cache
+///
+/// Holds the entry lock while writing, and populates a private staging
+/// directory that is renamed into place, so concurrent processes never see a
+/// half-written entry.
 pub fn store_in_cache(url: &str, source_dir: &Path) -> Result<PathBuf> {
     let hash = hash_url(url);
     let cache_path = cas_dir().join(&hash);
 
-    // Create cache directory
-    fs::create_dir_all(&cache_path)?;
+    let _lock = process_lock::lock_cache_entry(&cache_path)?;
+
+    // Another process may have stored it while we waited
+    if cache_path.join(COMPLETE_MARKER).exists() {
+        return Ok(cache_path);
+    }
+
+    // Unique per process and call, so concurrent writers never share one
+    let staging = cas_dir().join(format!(
+        "{hash}.tmp-{}-{}",
+        std::process::id(),
+        STAGING_SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
 
-    // Copy files to cache
-    copy_dir_recursive(source_dir, &cache_path)?;
+    // Copy files to staging, read-only, recording their hashes
+    let mut manifest = String::new();
+    copy_dir_recursive(source_dir, &staging, &staging, &mut manifest)?;
+    fs::write(staging.join(MANIFEST), manifest)?;
 
     // Write completion marker
-    let marker = cache_path.join(".complete");
+    let marker = staging.join(COMPLETE_MARKER);
     fs::write(&marker, url.as_bytes())?;
 
+    // Replace any incomplete entry left behind by a crashed process
+    if cache_path.exists() {
+        fs::remove_dir_all(&cache_path)?;
+    }
+    fs::rename(&staging, &cache_path)?;
+
     Ok(cache_path)
 }
 
-/// Link or copy from cache to destination
-/// Uses hardlinks on Unix for instant "copies"
-pub fn link_from_cache(cache_path: &Path, dest: &Path) -> Result<()> {
+/// Link or copy from cache to destination using the given link mode
+pub fn link_from_cache(cache_path: &Path, dest: &Path, mode: LinkMode) -> Result<()> {
     // Remove existing destination
     if dest.exists() {
         fs::remove_dir_all(dest)?;
@@ -71,8 +134,55 @@ pub fn link_from_cache(cache_path: &Path, dest: &Path) -> Result<()> {
         fs::create_dir_all(parent)?;
     }
 
-    // Try hardlinks first, fall back to copy
-    link_dir_recursive(cache_path, dest)
+    link_dir_recursive(cache_path, dest, mode)
+}
+
+/// Check a cache entry against its manifest.
+///
+/// Returns `false` if any file was modified, added or removed since the entry
+/// was stored, or if the entry predates manifests.
+pub fn verify_entry(cache_path: &Path) -> bool {
+    let Ok(manifest) = fs::read_to_string(cache_path.join(MANIFEST)) else {
+        return false;
+    };
+
+    let expected: HashMap<&str, &str> = manifest
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(hash, path)| (path, hash))
+        .collect();
+
+    let mut seen = 0;
+    for entry in walkdir::WalkDir::new(cache_path).min_depth(1) {
+        let Ok(entry) = entry else {
+            return false;
+        };
+        if !entry.file_type().is_file() || (entry.depth() == 1 && is_marker(entry.file_name())) {
+            continue;
+        }
+        let Some(relative) = relative_key(cache_path, entry.path()) else {
+            return false;
+        };
+        let Some(expected_hash) = expected.get(relative.as_str()) else {
+            return false;
+        };
+        match hash_file(entry.path()) {
+            Ok(actual) if actual == *expected_hash => seen += 1,
+            _ => return false,
+        }
+    }
+
+    seen == expected.len()
+}
+
+/// Remove a cache entry (e.g. after failed verification).
+pub fn evict(cache_path: &Path) -> Result<()> {
+    let _lock = process_lock::lock_cache_entry(cache_path)?;
+    if cache_path.exists() {
+        fs::remove_dir_all(cache_path)
+            .with_context(|| format!("Failed to evict {}", cache_path.display()))?;
+    }
+    Ok(())
 }
 
 /// Hash a URL to get a cache key (using standard hasher for simplicity)
@@ -82,8 +192,28 @@ fn hash_url(url: &str) -> String {
     format!("{:016x}", hasher.finish())
 }
 
-/// Recursively copy directory
-fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
+fn is_marker(name: &std::ffi::OsStr) -> bool {
+    name == COMPLETE_MARKER || name == MANIFEST
+}
+
+/// Manifest key for a file: its path relative to the entry, `/`-separated
+fn relative_key(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    Some(parts.join("/"))
+}
+
+fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Recursively copy a package into a cache staging directory
+fn copy_dir_recursive(src: &Path, dst: &Path, root: &Path, manifest: &mut String) -> Result<()> {
     fs::create_dir_all(dst)?;
 
     for entry in fs::read_dir(src)? {
@@ -92,22 +222,32 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
         let src_path = entry.path();
         let dst_path = dst.join(entry.file_name());
 
-        if entry.file_name() == ".complete" {
+        if dst == root && is_marker(&entry.file_name()) {
             continue;
         }
 
-        if file_type.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path)?;
+        if file_type.is_symlink() {
+            copy_symlink(&src_path, &dst_path)?;
+        } else if file_type.is_dir() {
+            copy_dir_recursive(&src_path, &dst_path, root, manifest)?;
         } else if file_type.is_file() {
-            fs::copy(&src_path, &dst_path)?;
+            clone_file(&src_path, &dst_path)?;
+            set_readonly(&dst_path, true)?;
+            if let Some(key) = relative_key(root, &dst_path) {
+                manifest.push_str(&format!("{} {key}\n", hash_file(&dst_path)?));
+            }
         }
     }
 
     Ok(())
 }
 
-/// Recursively link directory using hardlinks (pnpm-style)
-fn link_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
+/// Recursively materialize a cache entry using the given link mode
+fn link_dir_recursive(src: &Path, dst: &Path, mode: LinkMode) -> Result<()> {
+    link_dir_inner(src, dst, src, mode)
+}
+
+fn link_dir_inner(src: &Path, dst: &Path, root: &Path, mode: LinkMode) -> Result<()> {
     fs::create_dir_all(dst)?;
 
     for entry in fs::read_dir(src)? {
@@ -116,21 +256,110 @@ fn link_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
         let src_path = entry.path();
         let dst_path = dst.join(entry.file_name());
 
-        if entry.file_name() == ".complete" {
+        if src == root && is_marker(&entry.file_name()) {
             continue;
         }
 
-        if file_type.is_dir() {
-            link_dir_recursive(&src_path, &dst_path)?;
+        if file_type.is_symlink() {
+            copy_symlink(&src_path, &dst_path)?;
+        } else if file_type.is_dir() {
+            link_dir_inner(&src_path, &dst_path, root, mode)?;
         } else if file_type.is_file() {
-            // Try hardlink first (instant, saves space)
-            if fs::hard_link(&src_path, &dst_path).is_err() {
-                // Fall back to copy (different filesystem)
-                fs::copy(&src_path, &dst_path)?;
+            match mode {
+                LinkMode::Hardlink => {
+                    // Fall back to copy (different filesystem)
+                    if fs::hard_link(&src_path, &dst_path).is_err() {
+                        fs::copy(&src_path, &dst_path)?;
+                        set_readonly(&dst_path, false)?;
+                    }
+                }
+                LinkMode::Reflink => {
+                    clone_file(&src_path, &dst_path)?;
+                    set_readonly(&dst_path, false)?;
+                }
+                LinkMode::Copy => {
+                    fs::copy(&src_path, &dst_path)?;
+                    set_readonly(&dst_path, false)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Clone a file with a copy-on-write reflink, falling back to a plain copy
+///
+/// On macOS `fs::copy` already uses `clonefile` where supported.
+fn clone_file(src: &Path, dst: &Path) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    if !REFLINK_UNSUPPORTED.load(std::sync::atomic::Ordering::Relaxed) {
+        let source = fs::File::open(src)?;
+        let target = fs::File::create(dst)?;
+        match rustix::fs::ioctl_ficlone(&target, &source) {
+            Ok(()) => {
+                fs::set_permissions(dst, source.metadata()?.permissions())?;
+                return Ok(());
+            }
+            Err(e) => {
+                if matches!(
+                    e,
+                    rustix::io::Errno::OPNOTSUPP
+                        | rustix::io::Errno::XDEV
+                        | rustix::io::Errno::INVAL
+                        | rustix::io::Errno::NOTTY
+                ) {
+                    REFLINK_UNSUPPORTED.store(true, std::sync::atomic::Ordering::Relaxed);
+                }
+                drop(target);
+                fs::remove_file(dst)?;
             }
         }
     }
 
+    fs::copy(src, dst).map(|_| ())
+}
+
+/// Recreate a symlink with the same target
+fn copy_symlink(src: &Path, dst: &Path) -> io::Result<()> {
+    let target = fs::read_link(src)?;
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&target, dst)
+    }
+    #[cfg(windows)]
+    {
+        if src.is_dir() {
+            std::os::windows::fs::symlink_dir(&target, dst)
+        } else {
+            std::os::windows::fs::symlink_file(&target, dst)
+        }
+    }
+}
+
+/// Toggle write permission on a file
+///
+/// Cache files are made read-only so edits through a hardlink fail instead
+/// of corrupting the shared entry; files materialized by copy get their owner
+/// write bit back. On Windows read-only files cannot be deleted with
+/// `remove_dir_all`, so there the manifest check alone guards the cache.
+#[cfg(unix)]
+fn set_readonly(path: &Path, readonly: bool) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    let mode = permissions.mode();
+    permissions.set_mode(if readonly {
+        mode & !0o222
+    } else {
+        mode | 0o200
+    });
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn set_readonly(_path: &Path, _readonly: bool) -> io::Result<()> {
     Ok(())
 }
 
@@ -191,10 +420,110 @@ pub fn cached_package_count() -> Result<usize> {
         let entry = entry?;
         if entry.file_type()?.is_dir() {
             // Check for completion marker
-            if entry.path().join(".complete").exists() {
+            if entry.path().join(COMPLETE_MARKER).exists() {
                 count += 1;
             }
         }
     }
     Ok(count)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Stage a package the way `store_in_cache` does, without touching `~`.
+    fn stage(src: &Path, entry: &Path) {
+        let mut manifest = String::new();
+        copy_dir_recursive(src, entry, entry, &mut manifest).unwrap();
+        fs::write(entry.join(MANIFEST), manifest).unwrap();
+        fs::write(entry.join(COMPLETE_MARKER), "url").unwrap();
+    }
+
+    fn package(dir: &Path) {
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("composer.json"), "{}").unwrap();
+        fs::write(dir.join("src/Foo.php"), "<?php class Foo {}").unwrap();
+    }
+
+    #[test]
+    fn verify_detects_tampering() {
+        let tmp = TempDir::new().unwrap();
+        let src = tmp.path().join("src-pkg");
+        let entry = tmp.path().join("entry");
+        package(&src);
+        stage(&src, &entry);
+
+        assert!(verify_entry(&entry));
+
+        let file = entry.join("src/Foo.php");
+        set_readonly(&file, false).unwrap();
+        fs::write(&file, "<?php class Bar {}").unwrap();
+        assert!(!verify_entry(&entry));
+    }
+
+    #[test]
+    fn verify_detects_added_file() {
+        let tmp = TempDir::new().unwrap();
+        let src = tmp.path().join("src-pkg");
+        let entry = tmp.path().join("entry");
+        package(&src);
+        stage(&src, &entry);
+
+        fs::write(entry.join("src/Extra.php"), "<?php").unwrap();
+        assert!(!verify_entry(&entry));
+    }
+
+    #[test]
+    fn entry_without_manifest_is_not_verified() {
+        let tmp = TempDir::new().unwrap();
+        package(tmp.path());
+        assert!(!verify_entry(tmp.path()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cache_files_are_readonly_and_copies_writable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = TempDir::new().unwrap();
+        let src = tmp.path().join("src-pkg");
+        let entry = tmp.path().join("entry");
+        let dest = tmp.path().join("vendor/foo/bar");
+        package(&src);
+        stage(&src, &entry);
+
+        let cached = fs::metadata(entry.join("src/Foo.php")).unwrap();
+        assert_eq!(cached.permissions().mode() & 0o222, 0);
+
+        for mode in [LinkMode::Copy, LinkMode::Reflink] {
+            link_from_cache(&entry, &dest, mode).unwrap();
+            let linked = fs::metadata(dest.join("src/Foo.php")).unwrap();
+            assert_ne!(linked.permissions().mode() & 0o200, 0);
+            assert!(!dest.join(COMPLETE_MARKER).exists());
+            assert!(!dest.join(MANIFEST).exists());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_preserved() {
+        let tmp = TempDir::new().unwrap();
+        let src = tmp.path().join("src-pkg");
+        let entry = tmp.path().join("entry");
+        let dest = tmp.path().join("vendor/foo/bar");
+        package(&src);
+        fs::create_dir_all(src.join("bin")).unwrap();
+        std::os::unix::fs::symlink("../src/Foo.php", src.join("bin/foo")).unwrap();
+        stage(&src, &entry);
+
+        assert!(verify_entry(&entry));
+
+        for mode in [LinkMode::Reflink, LinkMode::Hardlink, LinkMode::Copy] {
+            link_from_cache(&entry, &dest, mode).unwrap();
+            let target = fs::read_link(dest.join("bin/foo")).unwrap();
+            assert_eq!(target, Path::new("../src/Foo.php"));
+        }
+    }
+}
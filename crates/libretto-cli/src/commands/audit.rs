@@ -7,7 +7,6 @@ use libretto_audit::Auditor;
 use libretto_core::PackageId;
 use libretto_repository::Repository;
 use semver::Version;
-use serde::Deserialize;
 use sonic_rs::{JsonContainerTrait, JsonValueTrait};
 use std::collections::HashMap;
 use tracing::info;
@@ -36,20 +35,6 @@ pub struct AuditArgs {
     pub suggest_versions: bool,
 }
 
-#[derive(Debug, Deserialize)]
-struct LockFile {
-    #[serde(default)]
-    packages: Vec<LockPackage>,
-    #[serde(default, rename = "packages-dev")]
-    packages_dev: Vec<LockPackage>,
-}
-
-#[derive(Debug, Deserialize)]
-struct LockPackage {
-    name: String,
-    version: String,
-}
-
 /// Run the audit command.
 pub async fn run(args: AuditArgs) -> Result<()> {
     info!("running audit command");
@@ -70,8 +55,7 @@ pub async fn run(args: AuditArgs) -> Result<()> {
     }
 
     // Parse lock file to get packages
-    let content = std::fs::read_to_string(lock_path)?;
-    let lock: LockFile = sonic_rs::from_str(&content)?;
+    let lock = super::lock_generator::read_lock(lock_path)?;
 
     let mut packages_to_audit: Vec<(PackageId, Version)> = Vec::new();
     let mut package_constraints: HashMap<String, String> = HashMap::new();
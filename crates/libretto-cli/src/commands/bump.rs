@@ -2,7 +2,7 @@
 
 use anyhow::Result;
 use clap::Args;
-use sonic_rs::{JsonContainerTrait, JsonValueMutTrait, JsonValueTrait};
+use sonic_rs::{JsonValueMutTrait, JsonValueTrait};
 use std::collections::HashMap;
 
 /// Arguments for the bump command
@@ -31,8 +31,9 @@ pub async fn run(args: BumpArgs) -> Result<()> {
 
     header("Bumping version constraints");
 
-    let composer_path = std::env::current_dir()?.join("composer.json");
-    let lock_path = std::env::current_dir()?.join("composer.lock");
+    let cwd = std::env::current_dir()?;
+    let composer_path = cwd.join("composer.json");
+    let lock_path = cwd.join("composer.lock");
 
     if !composer_path.exists() {
         anyhow::bail!("composer.json not found in current directory");
@@ -42,38 +43,25 @@ pub async fn run(args: BumpArgs) -> Result<()> {
         anyhow::bail!("composer.lock not found - run 'libretto install' first");
     }
 
+    // Hold the project lock across the read and the write of composer.lock
+    let _project_lock = if args.dry_run {
+        None
+    } else {
+        Some(crate::process_lock::lock_project(&cwd.join("vendor"))?)
+    };
+
     // Read composer.json
     let composer_content = std::fs::read_to_string(&composer_path)?;
     let mut composer: sonic_rs::Value = sonic_rs::from_str(&composer_content)?;
 
     // Read composer.lock
-    let lock_content = std::fs::read_to_string(&lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+    let mut lock = super::lock_generator::read_lock(&lock_path)?;
 
     // Build map of installed versions
-    let mut installed: HashMap<String, String> = HashMap::new();
-
-    if let Some(packages) = lock.get("packages").and_then(|v| v.as_array()) {
-        for pkg in packages {
-            if let (Some(name), Some(version)) = (
-                pkg.get("name").and_then(|v| v.as_str()),
-                pkg.get("version").and_then(|v| v.as_str()),
-            ) {
-                installed.insert(name.to_string(), version.to_string());
-            }
-        }
-    }
-
-    if let Some(packages) = lock.get("packages-dev").and_then(|v| v.as_array()) {
-        for pkg in packages {
-            if let (Some(name), Some(version)) = (
-                pkg.get("name").and_then(|v| v.as_str()),
-                pkg.get("version").and_then(|v| v.as_str()),
-            ) {
-                installed.insert(name.to_string(), version.to_string());
-            }
-        }
-    }
+    let installed: HashMap<String, String> = lock
+        .all_packages()
+        .map(|pkg| (pkg.name.clone(), pkg.version.clone()))
+        .collect();
 
     let mut changes: Vec<(String, String, String)> = Vec::new();
 
@@ -149,6 +137,10 @@ pub async fn run(args: BumpArgs) -> Result<()> {
     let output = sonic_rs::to_string_pretty(&composer)?;
     std::fs::write(&composer_path, output)?;
 
+    // Keep the lock fresh against the new constraints
+    lock.content_hash = super::lock_generator::compute_content_hash(&composer);
+    super::lock_generator::write_lock(&lock_path, &lock)?;
+
     success(&format!(
         "Updated {} constraint(s) in composer.json",
         changes.len()
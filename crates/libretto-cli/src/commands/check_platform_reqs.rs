@@ -48,17 +48,10 @@ pub async fn run(args: CheckPlatformReqsArgs) -> Result<()> {
         if !lock_path.exists() {
             anyhow::bail!("composer.lock not found");
         }
-        let lock_content = std::fs::read_to_string(&lock_path)?;
-        let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+        let lock = super::lock_generator::read_lock(&lock_path)?;
 
         // Get platform requirements from lock
-        if let Some(platform) = lock.get("platform").and_then(|v| v.as_object()) {
-            for (name, version) in platform {
-                if let Some(v) = version.as_str() {
-                    requirements.insert(name.to_string(), v.to_string());
-                }
-            }
-        }
+        requirements.extend(lock.platform);
     } else {
         if !composer_path.exists() {
             anyhow::bail!("composer.json not found");
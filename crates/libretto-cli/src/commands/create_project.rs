@@ -251,6 +251,7 @@ pub async fn run(args: CreateProjectArgs) -> Result<()> {
                 optimize_autoloader: false,
                 classmap_authoritative: false,
                 apcu_autoloader: false,
+                apcu_autoloader_prefix: None,
                 no_scripts: false,
                 prefer_lowest: false,
                 prefer_stable: true,
@@ -260,6 +261,13 @@ pub async fn run(args: CreateProjectArgs) -> Result<()> {
                 audit: false,
                 fail_on_audit: false,
                 verify_checksums: false,
+                link_mode: None,
+                max_download_speed: None,
+                mirror: vec![],
+                download_retries: 3,
+                frozen: false,
+                require_signed_lock: false,
+                trust_store: None,
             };
 
             crate::commands::install::run(install_args).await?;
@@ -33,41 +33,30 @@ pub async fn run(args: DependsArgs) -> Result<()> {
         anyhow::bail!("composer.lock not found - run 'libretto install' first");
     }
 
-    let lock_content = std::fs::read_to_string(&lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+    let lock = super::lock_generator::read_lock(&lock_path)?;
 
     // Build dependency graph
     let mut dependents: HashMap<String, Vec<(String, String, bool)>> = HashMap::new();
 
     // Process packages
-    for (packages_key, is_dev) in [("packages", false), ("packages-dev", true)] {
-        if let Some(packages) = lock.get(packages_key).and_then(|v| v.as_array()) {
-            for pkg in packages {
-                let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-
-                // Check require
-                if let Some(require) = pkg.get("require").and_then(|v| v.as_object()) {
-                    for (dep_name, constraint) in require {
-                        let constraint_str = constraint.as_str().unwrap_or("*");
-                        dependents.entry(dep_name.to_string()).or_default().push((
-                            name.to_string(),
-                            constraint_str.to_string(),
-                            is_dev,
-                        ));
-                    }
-                }
-
-                // Check require-dev
-                if let Some(require_dev) = pkg.get("require-dev").and_then(|v| v.as_object()) {
-                    for (dep_name, constraint) in require_dev {
-                        let constraint_str = constraint.as_str().unwrap_or("*");
-                        dependents.entry(dep_name.to_string()).or_default().push((
-                            name.to_string(),
-                            constraint_str.to_string(),
-                            true,
-                        ));
-                    }
-                }
+    for (packages, is_dev) in [(&lock.packages, false), (&lock.packages_dev, true)] {
+        for pkg in packages {
+            // Check require
+            for (dep_name, constraint) in &pkg.require {
+                dependents.entry(dep_name.clone()).or_default().push((
+                    pkg.name.clone(),
+                    constraint.clone(),
+                    is_dev,
+                ));
+            }
+
+            // Check require-dev
+            for (dep_name, constraint) in &pkg.require_dev {
+                dependents.entry(dep_name.clone()).or_default().push((
+                    pkg.name.clone(),
+                    constraint.clone(),
+                    true,
+                ));
             }
         }
     }
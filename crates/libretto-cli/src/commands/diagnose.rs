@@ -311,12 +311,9 @@ fn check_composer_lock() -> CheckResult {
         );
     }
 
-    match std::fs::read_to_string(&lock_path) {
-        Ok(content) => match sonic_rs::from_str::<sonic_rs::Value>(&content) {
-            Ok(_) => CheckResult::Ok("Valid JSON".to_string()),
-            Err(e) => CheckResult::Error(format!("Invalid JSON: {e}")),
-        },
-        Err(e) => CheckResult::Error(format!("Cannot read file: {e}")),
+    match super::lock_generator::read_lock(&lock_path) {
+        Ok(lock) => CheckResult::Ok(format!("{} package(s) locked", lock.all_packages().count())),
+        Err(e) => CheckResult::Error(format!("{e:#}")),
     }
 }
 
@@ -1,14 +1,13 @@
 //! Dump-autoload command implementation.
 
+use crate::autoload_packages;
 use anyhow::Result;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use console::style;
-use libretto_autoloader::{AutoloadConfig, AutoloaderGenerator, OptimizationLevel};
-use serde::Deserialize;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use libretto_autoloader::{AutoloadWarning, AutoloaderGenerator, IndexFormat, OptimizationLevel};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
-use tracing::{debug, info};
+use tracing::info;
 
 /// Arguments for the dump-autoload command.
 #[derive(Args, Debug, Clone)]
@@ -18,54 +17,60 @@ pub struct DumpAutoloadArgs {
     pub optimize: bool,
 
     /// Convert PSR-0/PSR-4 to classmap
-    #[arg(short, long)]
+    #[arg(short, long, visible_short_alias = 'a')]
     pub classmap_authoritative: bool,
 
     /// `APCu` caching
     #[arg(long)]
     pub apcu: bool,
 
+    /// `APCu` cache prefix (implies --apcu)
+    #[arg(long, value_name = "PREFIX")]
+    pub apcu_prefix: Option<String>,
+
     /// Don't scan for classes
     #[arg(long)]
     pub no_scripts: bool,
-}
 
-/// Composer.json structure for reading autoload config.
-#[derive(Debug, Deserialize)]
-struct ComposerJson {
-    #[serde(default)]
-    autoload: AutoloadSection,
-    #[serde(default, rename = "autoload-dev")]
-    autoload_dev: AutoloadSection,
-}
+    /// Skip autoload-dev and dev packages
+    #[arg(long)]
+    pub no_dev: bool,
+
+    /// Fail if a class does not comply with its PSR-0/PSR-4 mapping (requires -o or -c)
+    #[arg(long)]
+    pub strict_psr: bool,
 
-#[derive(Debug, Default, Deserialize)]
-struct AutoloadSection {
-    #[serde(default, rename = "psr-4")]
-    psr4: HashMap<String, Psr4Value>,
-    #[serde(default, rename = "psr-0")]
-    psr0: HashMap<String, Psr4Value>,
-    #[serde(default)]
-    classmap: Vec<String>,
-    #[serde(default)]
-    files: Vec<String>,
-    #[serde(default, rename = "exclude-from-classmap")]
-    exclude: Vec<String>,
+    /// Fail if the same class is defined in several files
+    #[arg(long)]
+    pub strict_ambiguous: bool,
+
+    /// Keep running and update the classmap when root package files change
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Write an `OPcache` preload script for the classmap to PATH
+    #[arg(long, value_name = "PATH")]
+    pub preload: Option<PathBuf>,
+
+    /// Write a symbol index of the classmap to vendor/composer/symbols.<FORMAT>
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub index: Option<SymbolIndexFormat>,
 }
 
-/// PSR-4 value can be either a string or array of strings.
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum Psr4Value {
-    Single(String),
-    Multiple(Vec<String>),
+/// Format of the symbol index written by `--index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SymbolIndexFormat {
+    /// Pretty-printed JSON
+    Json,
+    /// rkyv archive for zero-copy loading
+    Rkyv,
 }
 
-impl Psr4Value {
-    fn to_vec(&self) -> Vec<String> {
-        match self {
-            Self::Single(s) => vec![s.clone()],
-            Self::Multiple(v) => v.clone(),
+impl From<SymbolIndexFormat> for IndexFormat {
+    fn from(format: SymbolIndexFormat) -> Self {
+        match format {
+            SymbolIndexFormat::Json => Self::Json,
+            SymbolIndexFormat::Rkyv => Self::Rkyv,
         }
     }
 }
@@ -76,11 +81,19 @@ pub async fn run(args: DumpAutoloadArgs) -> Result<()> {
 
     let start_time = Instant::now();
 
+    if args.strict_psr && !args.optimize && !args.classmap_authoritative {
+        anyhow::bail!(
+            "--strict-psr only works with an optimized autoloader, use --optimize or --classmap-authoritative"
+        );
+    }
+
     let vendor_dir = PathBuf::from("vendor");
     if !vendor_dir.exists() {
         std::fs::create_dir_all(&vendor_dir)?;
     }
 
+    let project_lock = crate::process_lock::lock_project(&vendor_dir)?;
+
     // Determine optimization level
     let optimization_level = if args.classmap_authoritative {
         OptimizationLevel::Authoritative
@@ -108,54 +121,25 @@ pub async fn run(args: DumpAutoloadArgs) -> Result<()> {
     let mut generator =
         AutoloaderGenerator::with_optimization(vendor_dir.clone(), optimization_level);
 
-    // Scan vendor directory for installed packages and load their autoload configs
-    let mut package_count = 0;
-    if let Ok(entries) = std::fs::read_dir(&vendor_dir) {
-        for entry in entries.filter_map(Result::ok) {
-            let vendor_path = entry.path();
-            if vendor_path.is_dir() {
-                // Skip composer directory
-                if vendor_path.file_name().is_some_and(|n| n == "composer") {
-                    continue;
-                }
-
-                // Each subdirectory is a vendor namespace (e.g., "monolog", "guzzlehttp")
-                if let Ok(package_entries) = std::fs::read_dir(&vendor_path) {
-                    for package_entry in package_entries.filter_map(Result::ok) {
-                        let package_path = package_entry.path();
-                        if package_path.is_dir() {
-                            let composer_json_path = package_path.join("composer.json");
-                            if composer_json_path.exists()
-                                && let Some(config) = load_autoload_config(&composer_json_path)
-                            {
-                                debug!(
-                                    "Loaded autoload config from {:?}: psr4={}, files={}",
-                                    composer_json_path,
-                                    config.psr4.mappings.len(),
-                                    config.files.files.len()
-                                );
-                                generator.add_package(&package_path, &config);
-                                package_count += 1;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    // Feed locked packages from composer.lock rather than scanning vendor/
+    if !Path::new("composer.lock").exists() {
+        crate::output::warning("No composer.lock found - only the root package will be autoloaded");
     }
+    let package_count =
+        autoload_packages::add_packages(&mut generator, Path::new("."), &vendor_dir, !args.no_dev)?;
+
+    autoload_packages::apply_config(
+        &mut generator,
+        Path::new("."),
+        args.apcu || args.apcu_prefix.is_some(),
+        args.apcu_prefix.as_deref(),
+    );
+    info!("Loaded autoload configs from {} packages", package_count);
 
-    // Also load root project's autoload config if exists (including autoload-dev)
-    let root_composer_json = PathBuf::from("composer.json");
-    if root_composer_json.exists()
-        && let Some(config) = load_autoload_config_with_dev(&root_composer_json)
-    {
-        let project_root = PathBuf::from(".");
-        generator.add_package(&project_root, &config);
-        debug!("Loaded root project autoload config (with dev)");
+    for warning in generator.warnings() {
+        crate::output::warning(&warning.to_string());
     }
 
-    info!("Loaded autoload configs from {} packages", package_count);
-
     match generator.generate() {
         Ok(()) => {
             let stats = generator.stats();
@@ -197,6 +181,54 @@ pub async fn run(args: DumpAutoloadArgs) -> Result<()> {
             let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
             println!();
             println!("   {} {:.1}ms", style("Done in").dim(), elapsed_ms);
+
+            let warnings = generator.warnings();
+            let psr = warnings.iter().filter(|w| w.is_psr_violation()).count();
+            let ambiguous = warnings.iter().filter(|w| w.is_ambiguous()).count();
+            if (args.preload.is_some() || args.index.is_some()) && !is_optimized {
+                crate::output::warning(
+                    "Only classmap entries are preloaded and indexed - use --optimize or --classmap-authoritative to include PSR-4/PSR-0 classes",
+                );
+            }
+            if let Some(path) = &args.preload {
+                let rules = autoload_packages::preload_rules(Path::new("."));
+                let script = generator.generate_preload(path, &rules)?;
+                println!(
+                    "   {} {} classes in {} files to {}",
+                    style("Preloading").dim(),
+                    style(script.classes).green(),
+                    script.files.len(),
+                    path.display()
+                );
+                if !script.unlinkable.is_empty() {
+                    crate::output::warning(&format!(
+                        "{} class(es) not preloaded because a parent, interface or trait is excluded",
+                        script.unlinkable.len()
+                    ));
+                }
+            }
+
+            if let Some(format) = args.index {
+                let format = IndexFormat::from(format);
+                let path = vendor_dir
+                    .join("composer")
+                    .join(format!("symbols.{}", format.extension()));
+                let index = generator.symbol_index();
+                index.write(&path, format)?;
+                println!(
+                    "   {} {} symbols to {}",
+                    style("Indexed").dim(),
+                    style(index.symbols.len()).green(),
+                    path.display()
+                );
+            }
+
+            if args.strict_psr && psr > 0 {
+                anyhow::bail!("{psr} class(es) do not comply with their PSR autoloading mapping");
+            }
+            if args.strict_ambiguous && ambiguous > 0 {
+                anyhow::bail!("{ambiguous} class(es) are defined in more than one file");
+            }
         }
         Err(e) => {
             eprintln!();
@@ -212,79 +244,99 @@ pub async fn run(args: DumpAutoloadArgs) -> Result<()> {
         }
     }
 
+    if args.watch {
+        // Other commands may run while watching; the lock is retaken per update
+        drop(project_lock);
+        tokio::task::spawn_blocking(move || watch(generator, &vendor_dir)).await??;
+    }
+
     Ok(())
 }
 
-/// Load autoload configuration from a composer.json file (production only).
-fn load_autoload_config(path: &PathBuf) -> Option<AutoloadConfig> {
-    let content = std::fs::read_to_string(path).ok()?;
-    let composer: ComposerJson = sonic_rs::from_str(&content).ok()?;
-
-    let mut config = AutoloadConfig::default();
-
-    // Convert PSR-4 mappings (production only, like Composer)
-    for (namespace, paths) in composer.autoload.psr4 {
-        config.psr4.mappings.insert(namespace, paths.to_vec());
+/// Watch the root package's classmap directories and rewrite the classmap
+/// whenever a PHP file is added, changed or removed, reporting functions its
+/// `files` entries start to redeclare. Runs until interrupted.
+/// Blocks on the watcher channel, so run it off the async runtime.
+fn watch(mut generator: AutoloaderGenerator, vendor_dir: &Path) -> Result<()> {
+    use notify::{RecursiveMode, Watcher};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let dirs = generator.root_scan_dirs();
+    // Directories of `files` entries outside those, for the functions they declare
+    let mut file_dirs: Vec<PathBuf> = generator
+        .root_files()
+        .iter()
+        .filter_map(|file| file.parent())
+        .filter(|dir| !dirs.iter().any(|watched| dir.starts_with(watched)))
+        .map(Path::to_path_buf)
+        .collect();
+    file_dirs.sort();
+    file_dirs.dedup();
+    if dirs.is_empty() {
+        anyhow::bail!(
+            "Nothing to watch - the root package has no classmap directories (use a classmap entry, --optimize or --classmap-authoritative)"
+        );
     }
 
-    // Convert PSR-0 mappings (production only)
-    for (namespace, paths) in composer.autoload.psr0 {
-        config.psr0.mappings.insert(namespace, paths.to_vec());
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            let _ = tx.send(event.paths);
+        }
+    })?;
+    for dir in &dirs {
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+    }
+    for dir in &file_dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
     }
 
-    // Classmap paths (production only)
-    config.classmap.paths = composer.autoload.classmap;
-
-    // Files (production only)
-    config.files.files = composer.autoload.files;
-
-    // Exclude patterns
-    config.exclude.patterns = composer.autoload.exclude;
-
-    Some(config)
-}
-
-/// Load autoload configuration including dev section (for root project only).
-fn load_autoload_config_with_dev(path: &PathBuf) -> Option<AutoloadConfig> {
-    let content = std::fs::read_to_string(path).ok()?;
-    let composer: ComposerJson = sonic_rs::from_str(&content).ok()?;
-
-    let mut config = AutoloadConfig::default();
+    let watched = dirs.len() + file_dirs.len();
+    println!();
+    println!(
+        "{} Watching {} director{} for changes (Ctrl+C to stop)",
+        style("Libretto").cyan().bold(),
+        watched,
+        if watched == 1 { "y" } else { "ies" }
+    );
+
+    let redeclared = |generator: &AutoloaderGenerator| -> Vec<AutoloadWarning> {
+        generator
+            .warnings()
+            .into_iter()
+            .filter(AutoloadWarning::is_redeclared_function)
+            .collect()
+    };
+    let mut reported = redeclared(&generator);
 
-    // Production autoload
-    for (namespace, paths) in composer.autoload.psr4 {
-        config.psr4.mappings.insert(namespace, paths.to_vec());
-    }
-    for (namespace, paths) in composer.autoload.psr0 {
-        config.psr0.mappings.insert(namespace, paths.to_vec());
-    }
-    config.classmap.paths = composer.autoload.classmap;
-    config.files.files = composer.autoload.files;
-    config.exclude.patterns = composer.autoload.exclude;
-
-    // Dev autoload (only for root project)
-    for (namespace, paths) in composer.autoload_dev.psr4 {
-        config
-            .psr4
-            .mappings
-            .entry(namespace)
-            .or_default()
-            .extend(paths.to_vec());
-    }
-    for (namespace, paths) in composer.autoload_dev.psr0 {
-        config
-            .psr0
-            .mappings
-            .entry(namespace)
-            .or_default()
-            .extend(paths.to_vec());
+    while let Ok(mut paths) = rx.recv() {
+        // Editors emit bursts of events per save; coalesce them
+        while let Ok(more) = rx.recv_timeout(Duration::from_millis(20)) {
+            paths.extend(more);
+        }
+        paths.sort();
+        paths.dedup();
+
+        let start = Instant::now();
+        let changed = generator.update_files(&paths);
+        let current = redeclared(&generator);
+        for warning in current.iter().filter(|w| !reported.contains(w)) {
+            crate::output::warning(&warning.to_string());
+        }
+        reported = current;
+        if !changed {
+            continue;
+        }
+        let _lock = crate::process_lock::lock_project(vendor_dir)?;
+        generator.write_classmap()?;
+        println!(
+            "   {} classmap ({} classes) in {:.1}ms",
+            style("Updated").green(),
+            generator.stats().classmap_entries,
+            start.elapsed().as_secs_f64() * 1000.0
+        );
     }
-    config.classmap.paths.extend(composer.autoload_dev.classmap);
-    config.files.files.extend(composer.autoload_dev.files);
-    config
-        .exclude
-        .patterns
-        .extend(composer.autoload_dev.exclude);
-
-    Some(config)
+
+    Ok(())
 }
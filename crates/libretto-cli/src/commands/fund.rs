@@ -33,33 +33,23 @@ pub fn run(args: FundArgs) -> Result<()> {
         anyhow::bail!("composer.lock not found - run 'libretto install' first");
     }
 
-    let lock_content = std::fs::read_to_string(&lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+    let lock = super::lock_generator::read_lock(&lock_path)?;
 
     let mut funding_info: Vec<FundingInfo> = Vec::new();
 
     // Collect funding info from all packages
-    for packages_key in ["packages", "packages-dev"] {
-        if let Some(packages) = lock.get(packages_key).and_then(|v| v.as_array()) {
-            for pkg in packages {
-                let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-
-                if let Some(funding) = pkg.get("funding")
-                    && let Some(funding_array) = funding.as_array()
-                {
-                    for fund in funding_array {
-                        let fund_type =
-                            fund.get("type").and_then(|v| v.as_str()).unwrap_or("other");
-                        let url = fund.get("url").and_then(|v| v.as_str()).unwrap_or("");
-
-                        if !url.is_empty() {
-                            funding_info.push(FundingInfo {
-                                package: name.to_string(),
-                                funding_type: fund_type.to_string(),
-                                url: url.to_string(),
-                            });
-                        }
-                    }
+    for pkg in lock.all_packages() {
+        if let Some(funding_array) = pkg.funding.as_ref().and_then(|f| f.as_array()) {
+            for fund in funding_array {
+                let fund_type = fund.get("type").and_then(|v| v.as_str()).unwrap_or("other");
+                let url = fund.get("url").and_then(|v| v.as_str()).unwrap_or("");
+
+                if !url.is_empty() {
+                    funding_info.push(FundingInfo {
+                        package: pkg.name.clone(),
+                        funding_type: fund_type.to_string(),
+                        url: url.to_string(),
+                    });
                 }
             }
         }
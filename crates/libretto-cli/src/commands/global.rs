@@ -137,8 +137,11 @@ async fn run_subcommand(command: &str, args: &[String]) -> Result<()> {
                 dry_run: args.contains(&"--dry-run".to_string()),
                 root_reqs: args.contains(&"--root-reqs".to_string()),
                 lock: args.contains(&"--lock".to_string()),
+                no_install: args.contains(&"--no-install".to_string()),
                 audit: args.contains(&"--audit".to_string()),
                 fail_on_audit: args.contains(&"--fail-on-audit".to_string()),
+                platform_matrix: vec![],
+                matrix_lock: false,
             };
 
             commands::update::run(update_args).await
@@ -157,6 +160,7 @@ async fn run_subcommand(command: &str, args: &[String]) -> Result<()> {
                 classmap_authoritative: args.contains(&"-a".to_string())
                     || args.contains(&"--classmap-authoritative".to_string()),
                 apcu_autoloader: args.contains(&"--apcu-autoloader".to_string()),
+                apcu_autoloader_prefix: None,
                 no_scripts: args.contains(&"--no-scripts".to_string()),
                 prefer_lowest: args.contains(&"--prefer-lowest".to_string()),
                 prefer_stable: args.contains(&"--prefer-stable".to_string()),
@@ -166,6 +170,13 @@ async fn run_subcommand(command: &str, args: &[String]) -> Result<()> {
                 audit: args.contains(&"--audit".to_string()),
                 fail_on_audit: args.contains(&"--fail-on-audit".to_string()),
                 verify_checksums: args.contains(&"--verify-checksums".to_string()),
+                link_mode: None,
+                max_download_speed: None,
+                mirror: vec![],
+                download_retries: 3,
+                frozen: false,
+                require_signed_lock: false,
+                trust_store: None,
             };
 
             commands::install::run(install_args).await
@@ -209,7 +220,14 @@ async fn run_subcommand(command: &str, args: &[String]) -> Result<()> {
                 classmap_authoritative: args.contains(&"-c".to_string())
                     || args.contains(&"--classmap-authoritative".to_string()),
                 apcu: args.contains(&"--apcu".to_string()),
+                apcu_prefix: None,
                 no_scripts: args.contains(&"--no-scripts".to_string()),
+                no_dev: args.contains(&"--no-dev".to_string()),
+                strict_psr: args.contains(&"--strict-psr".to_string()),
+                strict_ambiguous: args.contains(&"--strict-ambiguous".to_string()),
+                watch: false,
+                preload: None,
+                index: None,
             };
 
             commands::dump_autoload::run(dump_args).await
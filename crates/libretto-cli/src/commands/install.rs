@@ -5,29 +5,36 @@
 use crate::cas_cache;
 use crate::fetcher::Fetcher;
 use crate::installer_paths::InstallerPaths;
+use crate::lock_index;
 use crate::output::format_bytes;
 use crate::output::live::LiveProgress;
 use crate::output::table::Table;
 use crate::output::{error, header, info, success, warning};
 use crate::platform::PlatformValidator;
+use crate::process_lock;
 use crate::scripts::{
     ScriptConfig, run_post_autoload_scripts, run_post_install_scripts, run_pre_autoload_scripts,
     run_pre_install_scripts,
 };
+use crate::vendor_manifest;
 use anyhow::{Context, Result, bail};
 use clap::Args;
-use futures::stream::{FuturesUnordered, StreamExt};
 use libretto_audit::Auditor;
 use libretto_core::PackageId;
+use libretto_downloader::{
+    ArchiveType, DownloadConfig, DownloadSource, ExpectedChecksum, ParallelDownloader, Source,
+};
+use libretto_lockfile::{ComposerLock, LockedPackage};
 use libretto_resolver::Stability;
 use libretto_resolver::turbo::{TurboConfig, TurboResolver};
-use libretto_resolver::{ComposerConstraint, Dependency, PackageName, ResolutionMode};
+use libretto_resolver::{
+    ComposerConstraint, ComposerVersion, Dependency, PackageName, ResolutionMode,
+};
 use semver::Version;
 use sonic_rs::{JsonContainerTrait, JsonValueTrait, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 use tracing::debug;
 
@@ -70,6 +77,10 @@ pub struct InstallArgs {
     #[arg(long)]
     pub apcu_autoloader: bool,
 
+    /// `APCu` autoloader cache prefix (implies --apcu-autoloader)
+    #[arg(long, value_name = "PREFIX")]
+    pub apcu_autoloader_prefix: Option<String>,
+
     /// Skip scripts execution
     #[arg(long)]
     pub no_scripts: bool,
@@ -105,8 +116,54 @@ pub struct InstallArgs {
     /// Verify package checksums and fail on mismatch
     #[arg(long)]
     pub verify_checksums: bool,
+
+    /// How to link cached packages into vendor (defaults to `LIBRETTO_LINK_MODE`, then reflink)
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub link_mode: Option<cas_cache::LinkMode>,
+
+    /// Limit total download bandwidth (bytes/s, accepts K/M/G suffixes)
+    #[arg(long, value_name = "SPEED", env = "LIBRETTO_MAX_DOWNLOAD_SPEED", value_parser = parse_download_speed)]
+    pub max_download_speed: Option<u64>,
+
+    /// Dist mirror URL tried when the primary download fails (repeatable;
+    /// supports %package%, %version%, %reference% and %type%)
+    #[arg(
+        long,
+        value_name = "URL",
+        env = "LIBRETTO_MIRRORS",
+        value_delimiter = ','
+    )]
+    pub mirror: Vec<String>,
+
+    /// Retries per download URL before falling back to the next mirror
+    #[arg(long, value_name = "N", default_value = "3")]
+    pub download_retries: u32,
+
+    /// Install exactly what composer.lock specifies and fail if it is missing
+    /// or out of date (for CI). Dists locked without a checksum cannot be
+    /// verified and are listed in a warning
+    #[arg(long, conflicts_with = "prefer_lowest")]
+    pub frozen: bool,
+
+    /// Refuse to install unless composer.lock carries a detached signature
+    /// (composer.lock.sig) from a key in the trust store
+    #[arg(long, conflicts_with = "prefer_lowest")]
+    pub require_signed_lock: bool,
+
+    /// Directory of trusted lock signing keys (defaults to ~/.libretto/trusted-keys)
+    #[arg(long, value_name = "DIR", env = "LIBRETTO_TRUST_STORE")]
+    pub trust_store: Option<PathBuf>,
 }
 
+/// Exit code for a missing or out-of-date lock file under `--frozen`
+/// (Composer's `ERROR_LOCK_FILE_INVALID`).
+pub const EXIT_LOCK_OUT_OF_DATE: u8 = 4;
+
+/// The lock file is missing or does not match composer.json.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct LockOutOfDate(String);
+
 /// Run the install command.
 pub async fn run(args: InstallArgs) -> Result<()> {
     let start = Instant::now();
@@ -132,6 +189,40 @@ pub async fn run(args: InstallArgs) -> Result<()> {
         warning("Dry run mode - no changes will be made");
     }
 
+    // Hold the project lock for the whole vendor/lock mutation
+    let _project_lock = if args.dry_run {
+        None
+    } else {
+        Some(process_lock::lock_project(&vendor_dir)?)
+    };
+
+    // Everything below installs from the bytes whose signature was verified
+    let signed_lock = if args.require_signed_lock {
+        Some(read_signed_lock(&composer_lock_path, &args)?)
+    } else {
+        None
+    };
+    let signed = signed_lock.as_ref().map(|(_, lock)| lock);
+
+    // A matching lock index means vendor already reflects composer.lock
+    let index_key = if composer_lock_path.exists() && !args.prefer_lowest && !args.dry_run {
+        lock_index::IndexKey::read(&cwd, index_options(&args))
+            .ok()
+            .map(|key| match &signed_lock {
+                Some((content, _)) => key.with_lock(content),
+                None => key,
+            })
+    } else {
+        None
+    };
+    let indexed = index_key
+        .as_ref()
+        .and_then(|key| lock_index::load(&vendor_dir, key));
+
+    if args.frozen && indexed.is_none() {
+        check_frozen_lock(&composer, &composer_lock_path, signed, &args)?;
+    }
+
     // Script config for lifecycle hooks
     let script_config = ScriptConfig {
         working_dir: cwd.clone(),
@@ -155,68 +246,96 @@ pub async fn run(args: InstallArgs) -> Result<()> {
         }
     }
 
-    // Create live progress display
-    let progress = if !args.no_progress && !args.dry_run {
-        Some(LiveProgress::new())
-    } else {
-        None
-    };
-
     // Parse installer-paths from composer.json for custom installation locations
     let installer_paths = InstallerPaths::from_composer(&composer);
 
-    // Check for lock file
-    let has_lock = composer_lock_path.exists();
-
-    let result = if has_lock && !args.prefer_lowest {
-        install_from_lock(
-            &composer_lock_path,
-            &vendor_dir,
-            &cwd,
-            &installer_paths,
-            &args,
-            progress.as_ref(),
-        )
-        .await
+    let mut notice = None;
+    if let Some(index) = &indexed {
+        notice.clone_from(&index.notice);
+        if let Some(notice) = &notice {
+            warning(notice);
+        }
+        success(&format!(
+            "Nothing to install, {} package(s) up to date ({})",
+            index.packages.len(),
+            crate::output::format_duration(start.elapsed())
+        ));
     } else {
-        resolve_and_install(
-            &composer,
-            &composer_lock_path,
-            &vendor_dir,
-            &cwd,
-            &installer_paths,
-            &args,
-            progress.as_ref(),
-        )
-        .await
-    };
+        // Create live progress display
+        let progress = if !args.no_progress && !args.dry_run {
+            Some(LiveProgress::new())
+        } else {
+            None
+        };
 
-    // Handle result and finish progress
-    match result {
-        Ok(()) => {
-            let elapsed = start.elapsed();
-            if let Some(p) = &progress {
-                p.finish_success(&format!(
-                    "Installed in {}",
-                    crate::output::format_duration(elapsed)
-                ));
-            } else {
-                success(&format!(
-                    "Installation complete ({})",
-                    crate::output::format_duration(elapsed)
-                ));
+        // Check for lock file
+        let has_lock = signed.is_some() || composer_lock_path.exists();
+        if has_lock
+            && !args.frozen
+            && let Ok(lock) = load_lock(&composer_lock_path, signed)
+        {
+            let staleness = crate::stale_lock::check(&lock, &composer, &cwd);
+            if staleness.is_stale() {
+                let explanation = staleness.explain();
+                warning(&explanation);
+                notice = Some(explanation);
             }
         }
-        Err(e) => {
-            if let Some(p) = &progress {
-                p.finish_error(&e.to_string());
+
+        if !args.dry_run {
+            lock_index::invalidate(&vendor_dir)?;
+        }
+        let result = if has_lock && !args.prefer_lowest {
+            install_from_lock(
+                &composer_lock_path,
+                signed,
+                &vendor_dir,
+                &cwd,
+                &installer_paths,
+                &args,
+                progress.as_ref(),
+            )
+            .await
+        } else {
+            resolve_and_install(
+                &composer,
+                &composer_lock_path,
+                &vendor_dir,
+                &cwd,
+                &installer_paths,
+                &args,
+                progress.as_ref(),
+            )
+            .await
+        };
+
+        // Handle result and finish progress
+        match result {
+            Ok(()) => {
+                let elapsed = start.elapsed();
+                if let Some(p) = &progress {
+                    p.finish_success(&format!(
+                        "Installed in {}",
+                        crate::output::format_duration(elapsed)
+                    ));
+                } else {
+                    success(&format!(
+                        "Installation complete ({})",
+                        crate::output::format_duration(elapsed)
+                    ));
+                }
+            }
+            Err(e) => {
+                if let Some(p) = &progress {
+                    p.finish_error(&e.to_string());
+                }
+                return Err(e);
             }
-            return Err(e);
         }
     }
 
-    // Generate autoloader
-    if !args.dry_run {
+    // Generate autoloader, unless the indexed one is still current
+    if !args.dry_run && (indexed.is_none() || !autoloader_from_lock_only(&composer, &args)) {
         // Pre-autoload-dump scripts
         if !args.no_scripts
             && let Some(result) = run_pre_autoload_scripts(&composer, &script_config)?
@@ -226,7 +345,7 @@ pub async fn run(args: InstallArgs) -> Result<()> {
             warning(&format!("Pre-autoload script warning: {err}"));
         }
 
-        generate_autoloader(&vendor_dir, &args)?;
+        generate_autoloader(&vendor_dir, &cwd, &args)?;
 
         // Post-autoload-dump scripts
         if !args.no_scripts
@@ -254,46 +373,88 @@ pub async fn run(args: InstallArgs) -> Result<()> {
         }
     }
 
+    if indexed.is_none()
+        && let Some(key) = &index_key
+        && let Err(e) = write_lock_index(
+            &composer_lock_path,
+            signed,
+            &vendor_dir,
+            &cwd,
+            &installer_paths,
+            key,
+            notice,
+            &args,
+        )
+    {
+        debug!("Lock index not written: {e:#}");
+    }
+
     // Run security audit if requested
     if args.audit && !args.dry_run {
-        run_security_audit(&composer_lock_path, &args).await?;
+        run_security_audit(&composer_lock_path, signed, &args).await?;
     }
 
     Ok(())
 }
 
-/// Run security audit on installed packages.
-async fn run_security_audit(lock_path: &PathBuf, args: &InstallArgs) -> Result<()> {
+/// Read and verify composer.lock for `--require-signed-lock`.
+///
+/// Returns the verified bytes and the lock parsed from those same bytes.
+fn read_signed_lock(
+    lock_path: &std::path::Path,
+    args: &InstallArgs,
+) -> Result<(Vec<u8>, ComposerLock)> {
     if !lock_path.exists() {
+        bail!("--require-signed-lock needs a signed composer.lock, but none was found");
+    }
+    let trust_store = args
+        .trust_store
+        .clone()
+        .unwrap_or_else(super::lock_sign::default_trust_store);
+    let (content, signature) = super::lock_sign::verify_lock(lock_path, &trust_store)?;
+    info(&format!(
+        "composer.lock signature verified (key {})",
+        signature.key_id
+    ));
+
+    let text = std::str::from_utf8(&content).context("composer.lock is not valid UTF-8")?;
+    let lock = libretto_lockfile::parse_lock(text)
+        .with_context(|| format!("Failed to read {}", lock_path.display()))?;
+    Ok((content, lock))
+}
+
+/// The project's lock: the verified signed lock if there is one, otherwise
+/// composer.lock as read from disk.
+fn load_lock(lock_path: &std::path::Path, signed: Option<&ComposerLock>) -> Result<ComposerLock> {
+    match signed {
+        Some(lock) => Ok(lock.clone()),
+        None => super::lock_generator::read_lock(lock_path),
+    }
+}
+
+/// Run security audit on installed packages.
+async fn run_security_audit(
+    lock_path: &std::path::Path,
+    signed: Option<&ComposerLock>,
+    args: &InstallArgs,
+) -> Result<()> {
+    if signed.is_none() && !lock_path.exists() {
         return Ok(());
     }
 
     info("Running security audit...");
 
-    let lock_content = std::fs::read_to_string(lock_path)?;
-    let lock: Value = sonic_rs::from_str(&lock_content)?;
-
-    let mut packages_to_audit: Vec<(PackageId, Version)> = Vec::new();
+    let lock = load_lock(lock_path, signed)?;
 
     // Collect packages from lock file
-    for key in ["packages", "packages-dev"] {
-        if let Some(pkgs) = lock.get(key).and_then(|v| v.as_array()) {
-            for pkg in pkgs {
-                let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                let version_str = pkg
-                    .get("version")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .trim_start_matches('v');
-
-                if let Some(id) = PackageId::parse(name)
-                    && let Ok(ver) = Version::parse(version_str)
-                {
-                    packages_to_audit.push((id, ver));
-                }
-            }
-        }
-    }
+    let packages_to_audit: Vec<(PackageId, Version)> = lock
+        .all_packages()
+        .filter_map(|pkg| {
+            let id = PackageId::parse(&pkg.name)?;
+            let ver = Version::parse(pkg.version.trim_start_matches('v')).ok()?;
+            Some((id, ver))
+        })
+        .collect();
 
     if packages_to_audit.is_empty() {
         return Ok(());
@@ -347,35 +508,29 @@ async fn run_security_audit(lock_path: &PathBuf, args: &InstallArgs) -> Result<(
 
 /// Install from an existing lock file.
 async fn install_from_lock(
-    lock_path: &PathBuf,
-    vendor_dir: &PathBuf,
+    lock_path: &std::path::Path,
+    signed: Option<&ComposerLock>,
+    vendor_dir: &std::path::Path,
     base_dir: &std::path::Path,
     installer_paths: &InstallerPaths,
     args: &InstallArgs,
     progress: Option<&LiveProgress>,
 ) -> Result<()> {
-    let lock_content = std::fs::read_to_string(lock_path)?;
-    let lock: Value = sonic_rs::from_str(&lock_content)?;
+    let lock = load_lock(lock_path, signed)?;
 
     // Collect packages to install
-    let mut packages: Vec<PackageInfo> = Vec::new();
-
-    if let Some(pkgs) = lock.get("packages").and_then(|v| v.as_array()) {
-        for pkg in pkgs {
-            if let Some(info) = parse_lock_package(pkg, false) {
-                packages.push(info);
-            }
-        }
-    }
+    let mut packages: Vec<PackageInfo> = lock
+        .packages
+        .iter()
+        .map(|pkg| lock_package_info(pkg, false))
+        .collect();
 
-    if !args.no_dev
-        && let Some(pkgs) = lock.get("packages-dev").and_then(|v| v.as_array())
-    {
-        for pkg in pkgs {
-            if let Some(info) = parse_lock_package(pkg, true) {
-                packages.push(info);
-            }
-        }
+    if !args.no_dev {
+        packages.extend(
+            lock.packages_dev
+                .iter()
+                .map(|pkg| lock_package_info(pkg, true)),
+        );
     }
 
     if packages.is_empty() {
@@ -408,19 +563,275 @@ async fn install_from_lock(
     )
     .await?;
 
+    if args.frozen {
+        remove_extraneous_packages(vendor_dir, base_dir, installer_paths, &packages)?;
+    }
+
+    Ok(())
+}
+
+/// Install flags that change what ends up in vendor, for the lock index key.
+fn index_options(args: &InstallArgs) -> String {
+    format!(
+        "no-dev={} frozen={} link={:?} optimize={} authoritative={} apcu={:?} \
+         ignore-platform={} {:?}",
+        args.no_dev,
+        args.frozen,
+        args.link_mode.unwrap_or_else(cas_cache::LinkMode::from_env),
+        args.optimize_autoloader,
+        args.classmap_authoritative,
+        (args.apcu_autoloader, &args.apcu_autoloader_prefix),
+        args.ignore_platform_reqs,
+        args.ignore_platform_req
+    )
+}
+
+/// Snapshot the install from composer.lock so an unchanged rerun can skip it.
+fn write_lock_index(
+    lock_path: &std::path::Path,
+    signed: Option<&ComposerLock>,
+    vendor_dir: &std::path::Path,
+    base_dir: &std::path::Path,
+    installer_paths: &InstallerPaths,
+    key: &lock_index::IndexKey,
+    notice: Option<String>,
+    args: &InstallArgs,
+) -> Result<()> {
+    let lock = load_lock(lock_path, signed)?;
+    let dev = if args.no_dev {
+        &[][..]
+    } else {
+        &lock.packages_dev[..]
+    };
+    let packages = lock
+        .packages
+        .iter()
+        .map(|pkg| (pkg, false))
+        .chain(dev.iter().map(|pkg| (pkg, true)))
+        .filter(|(pkg, _)| pkg.dist.is_some())
+        .map(|(pkg, dev)| lock_index::IndexedPackage {
+            name: pkg.name.clone(),
+            version: pkg.version.clone(),
+            dev,
+            dir: install_dir(
+                &lock_package_info(pkg, dev),
+                vendor_dir,
+                base_dir,
+                installer_paths,
+            )
+            .to_string_lossy()
+            .into_owned(),
+        })
+        .collect();
+
+    // Platform checks are skipped along with the install, so a different
+    // PHP binary has to invalidate the index
+    let php = if args.ignore_platform_reqs {
+        None
+    } else {
+        lock_index::php_binary()
+    };
+    lock_index::write(vendor_dir, key, packages, notice, php.as_slice())
+}
+
+/// Check that composer.lock exists and matches composer.json for `--frozen`.
+///
+/// Fails with [`LockOutOfDate`] if the content hash differs or a required
+/// package is missing from the lock (and would therefore need resolving).
+fn check_frozen_lock(
+    composer: &Value,
+    lock_path: &std::path::Path,
+    signed: Option<&ComposerLock>,
+    args: &InstallArgs,
+) -> Result<()> {
+    if signed.is_none() && !lock_path.exists() {
+        return Err(LockOutOfDate(
+            "No composer.lock found; --frozen requires a lock file. Run 'libretto update' to create one."
+                .to_string(),
+        )
+        .into());
+    }
+
+    let lock = load_lock(lock_path, signed)?;
+
+    let project_dir = lock_path
+        .parent()
+        .unwrap_or_else(|| std::path::Path::new("."));
+    let staleness = crate::stale_lock::check(&lock, composer, project_dir);
+    if !staleness.hash_matches {
+        return Err(LockOutOfDate(staleness.explain()).into());
+    }
+
+    // Names satisfied by the lock, including replaced and provided packages
+    let locked: HashSet<String> = lock
+        .all_packages()
+        .flat_map(|pkg| {
+            std::iter::once(&pkg.name)
+                .chain(pkg.replace.keys())
+                .chain(pkg.provide.keys())
+        })
+        .map(|name| name.to_lowercase())
+        .collect();
+
+    let sections: &[&str] = if args.no_dev {
+        &["require"]
+    } else {
+        &["require", "require-dev"]
+    };
+    let mut unlocked: Vec<String> = sections
+        .iter()
+        .filter_map(|section| composer.get(*section).and_then(|v| v.as_object()))
+        .flat_map(|obj| obj.iter().map(|(name, _)| name.to_string()))
+        .filter(|name| !is_platform_package(name) && !locked.contains(&name.to_lowercase()))
+        .collect();
+    if !unlocked.is_empty() {
+        unlocked.sort();
+        return Err(LockOutOfDate(format!(
+            "composer.lock does not contain {}; --frozen will not resolve packages",
+            unlocked.join(", ")
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Remove vendor package directories that are not in the lock file.
+///
+/// A `vendor/<vendor>/<package>` entry is kept if a locked package is
+/// installed there, or below it through `installer-paths`.
+fn remove_extraneous_packages(
+    vendor_dir: &std::path::Path,
+    base_dir: &std::path::Path,
+    installer_paths: &InstallerPaths,
+    packages: &[PackageInfo],
+) -> Result<()> {
+    let lowercase = |path: &std::path::Path| PathBuf::from(path.to_string_lossy().to_lowercase());
+    let expected: Vec<PathBuf> = packages
+        .iter()
+        .map(|pkg| lowercase(&install_dir(pkg, vendor_dir, base_dir, installer_paths)))
+        .collect();
+
+    let Ok(vendors) = std::fs::read_dir(vendor_dir) else {
+        return Ok(());
+    };
+    for vendor_entry in vendors.flatten() {
+        let vendor_name = vendor_entry.file_name().to_string_lossy().into_owned();
+        if vendor_name == "bin"
+            || vendor_name == "composer"
+            || vendor_name.starts_with('.')
+            || !vendor_entry.file_type().is_ok_and(|t| t.is_dir())
+        {
+            continue;
+        }
+
+        let vendor_path = vendor_entry.path();
+        for pkg_entry in std::fs::read_dir(&vendor_path)?.flatten() {
+            let path = pkg_entry.path();
+            let path_lower = lowercase(&path);
+            if expected.iter().any(|dir| dir.starts_with(&path_lower)) {
+                continue;
+            }
+
+            if pkg_entry.file_type().is_ok_and(|t| t.is_dir()) {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            }
+            .with_context(|| format!("Failed to remove {}", path.display()))?;
+            info(&format!(
+                "Removed extraneous {vendor_name}/{}",
+                pkg_entry.file_name().to_string_lossy()
+            ));
+        }
+
+        // Drop vendor namespaces left empty
+        if std::fs::read_dir(&vendor_path).is_ok_and(|mut d| d.next().is_none()) {
+            let _ = std::fs::remove_dir(&vendor_path);
+        }
+    }
+
     Ok(())
 }
 
 /// Resolve dependencies and install.
 async fn resolve_and_install(
     composer: &Value,
-    lock_path: &PathBuf,
-    vendor_dir: &PathBuf,
+    lock_path: &std::path::Path,
+    vendor_dir: &std::path::Path,
     base_dir: &std::path::Path,
     installer_paths: &InstallerPaths,
     args: &InstallArgs,
     progress: Option<&LiveProgress>,
 ) -> Result<()> {
+    let Some(resolution) = resolve_dependencies(composer, args, progress).await? else {
+        info("No dependencies to install");
+        return Ok(());
+    };
+
+    // Convert to package info
+    let packages: Vec<PackageInfo> = resolution
+        .packages
+        .iter()
+        .map(|p| PackageInfo {
+            name: p.name.as_str().to_string(),
+            version: p.version.to_string(),
+            is_dev: p.is_dev,
+            dist_url: p.dist_url.clone(),
+            dist_type: p.dist_type.clone(),
+            dist_shasum: p.dist_shasum.clone(),
+            dist_reference: p.source_reference.clone(),
+            package_type: p.package_type.clone(),
+        })
+        .collect();
+
+    if args.dry_run {
+        info(&format!("Would install {} package(s)", packages.len()));
+        show_packages_table(&packages);
+        return Ok(());
+    }
+
+    // Create vendor directory
+    std::fs::create_dir_all(vendor_dir)?;
+
+    // Install packages
+    install_packages(
+        &packages,
+        vendor_dir,
+        base_dir,
+        installer_paths,
+        args,
+        progress,
+    )
+    .await?;
+
+    // Generate lock file
+    generate_lock_file(lock_path, &resolution, composer, args.prefer_lowest)?;
+
+    Ok(())
+}
+
+/// Resolve the full dependency graph of composer.json.
+///
+/// Returns `None` when the manifest has no requirements at all.
+pub async fn resolve_dependencies(
+    composer: &Value,
+    args: &InstallArgs,
+    progress: Option<&LiveProgress>,
+) -> Result<Option<libretto_resolver::Resolution>> {
+    resolve_for_platform(composer, args, progress, BTreeMap::new(), BTreeMap::new()).await
+}
+
+/// Resolve the full dependency graph of composer.json for the given
+/// platform package versions, keeping `locked` packages at their versions
+/// (see [`TurboConfig::platform`] and [`TurboConfig::locked`]).
+pub async fn resolve_for_platform(
+    composer: &Value,
+    args: &InstallArgs,
+    progress: Option<&LiveProgress>,
+    platform: BTreeMap<String, Vec<ComposerVersion>>,
+    locked: BTreeMap<String, ComposerVersion>,
+) -> Result<Option<libretto_resolver::Resolution>> {
     // Collect requirements from composer.json
     let mut require: HashMap<String, String> = HashMap::new();
     let mut require_dev: HashMap<String, String> = HashMap::new();
@@ -442,8 +853,7 @@ async fn resolve_and_install(
     }
 
     if require.is_empty() && require_dev.is_empty() {
-        info("No dependencies to install");
-        return Ok(());
+        return Ok(None);
     }
 
     info(&format!(
@@ -481,6 +891,8 @@ async fn resolve_and_install(
         },
         min_stability,
         include_dev: !args.no_dev,
+        platform,
+        locked,
     };
 
     // Parse dependencies
@@ -489,6 +901,9 @@ async fn resolve_and_install(
 
     for (name, constraint) in &require {
         if is_platform_package(name) {
+            if let Some(c) = ComposerConstraint::parse(constraint) {
+                root_deps.push(Dependency::new(PackageName::platform(name), c));
+            }
             continue;
         }
         if let (Some(n), Some(c)) = (
@@ -501,6 +916,9 @@ async fn resolve_and_install(
 
     for (name, constraint) in &require_dev {
         if is_platform_package(name) {
+            if let Some(c) = ComposerConstraint::parse(constraint) {
+                dev_deps.push(Dependency::new(PackageName::platform(name), c));
+            }
             continue;
         }
         if let (Some(n), Some(c)) = (
@@ -532,44 +950,7 @@ async fn resolve_and_install(
         "resolution fetch statistics"
     );
 
-    // Convert to package info
-    let packages: Vec<PackageInfo> = resolution
-        .packages
-        .iter()
-        .map(|p| PackageInfo {
-            name: p.name.as_str().to_string(),
-            version: p.version.to_string(),
-            is_dev: p.is_dev,
-            dist_url: p.dist_url.clone(),
-            dist_shasum: p.dist_shasum.clone(),
-            package_type: p.package_type.clone(),
-        })
-        .collect();
-
-    if args.dry_run {
-        info(&format!("Would install {} package(s)", packages.len()));
-        show_packages_table(&packages);
-        return Ok(());
-    }
-
-    // Create vendor directory
-    std::fs::create_dir_all(vendor_dir)?;
-
-    // Install packages
-    install_packages(
-        &packages,
-        vendor_dir,
-        base_dir,
-        installer_paths,
-        args,
-        progress,
-    )
-    .await?;
-
-    // Generate lock file
-    generate_lock_file(lock_path, &resolution, composer)?;
-
-    Ok(())
+    Ok(Some(resolution))
 }
 
 fn parse_stability(s: &str) -> Option<Stability> {
@@ -583,7 +964,7 @@ fn parse_stability(s: &str) -> Option<Stability> {
     }
 }
 
-fn is_platform_package(name: &str) -> bool {
+pub fn is_platform_package(name: &str) -> bool {
     name == "php"
         || name.starts_with("php-")
         || name.starts_with("ext-")
@@ -600,53 +981,52 @@ struct PackageInfo {
     version: String,
     is_dev: bool,
     dist_url: Option<String>,
+    dist_type: Option<String>,
     dist_shasum: Option<String>,
+    dist_reference: Option<String>,
     /// Package type (e.g., "library", "wordpress-plugin", "drupal-module")
     package_type: Option<String>,
 }
 
-fn parse_lock_package(pkg: &Value, is_dev: bool) -> Option<PackageInfo> {
-    let name = pkg.get("name").and_then(|v| v.as_str())?;
-    let version = pkg.get("version").and_then(|v| v.as_str())?;
-    let dist_url = pkg
-        .get("dist")
-        .and_then(|d| d.get("url"))
-        .and_then(|u| u.as_str())
-        .map(String::from);
-    let dist_shasum = pkg
-        .get("dist")
-        .and_then(|d| d.get("shasum"))
-        .and_then(|u| u.as_str())
-        .map(String::from);
-    let package_type = pkg.get("type").and_then(|t| t.as_str()).map(String::from);
-
-    Some(PackageInfo {
-        name: name.to_string(),
-        version: version.to_string(),
+fn lock_package_info(pkg: &LockedPackage, is_dev: bool) -> PackageInfo {
+    let dist = pkg.dist.as_ref();
+
+    PackageInfo {
+        name: pkg.name.clone(),
+        version: pkg.version.clone(),
         is_dev,
-        dist_url,
-        dist_shasum,
-        package_type,
-    })
+        dist_url: dist.map(|d| d.url.clone()),
+        dist_type: dist.map(|d| d.dist_type.clone()),
+        dist_shasum: dist.and_then(|d| d.shasum.clone()),
+        dist_reference: dist.and_then(|d| d.reference.clone()),
+        package_type: pkg.package_type.clone(),
+    }
 }
 
-fn validate_platform_from_lock(lock: &Value, args: &InstallArgs) -> Result<()> {
-    let mut requirements: Vec<(&str, &str, Vec<String>)> = Vec::new();
+/// Install directory of a package: its installer path, or `vendor/<name>`.
+fn install_dir(
+    pkg: &PackageInfo,
+    vendor_dir: &std::path::Path,
+    base_dir: &std::path::Path,
+    installer_paths: &InstallerPaths,
+) -> PathBuf {
+    installer_paths
+        .get_path(base_dir, &pkg.name, pkg.package_type.as_deref())
+        .unwrap_or_else(|| vendor_dir.join(pkg.name.replace('/', std::path::MAIN_SEPARATOR_STR)))
+}
 
-    if let Some(platform) = lock.get("platform").and_then(|v| v.as_object()) {
-        for (name, constraint) in platform {
-            if let Some(c) = constraint.as_str() {
-                if args
-                    .ignore_platform_req
-                    .iter()
-                    .any(|r| r == name || r == "*")
-                {
-                    continue;
-                }
-                requirements.push((name, c, vec!["lock file".to_string()]));
-            }
-        }
-    }
+fn validate_platform_from_lock(lock: &ComposerLock, args: &InstallArgs) -> Result<()> {
+    let requirements: Vec<(&str, &str, Vec<String>)> = lock
+        .platform
+        .iter()
+        .filter(|(name, _)| {
+            !args
+                .ignore_platform_req
+                .iter()
+                .any(|r| r == *name || r == "*")
+        })
+        .map(|(name, c)| (name.as_str(), c.as_str(), vec!["lock file".to_string()]))
+        .collect();
 
     if requirements.is_empty() {
         return Ok(());
@@ -687,83 +1067,85 @@ fn show_packages_table(packages: &[PackageInfo]) {
     table.print();
 }
 
+/// A package whose dist archive must be downloaded.
+struct PendingDownload {
+    url: String,
+    dest: PathBuf,
+}
+
 /// Install packages with parallel downloads and CAS cache.
 async fn install_packages(
     packages: &[PackageInfo],
-    vendor_dir: &PathBuf,
+    vendor_dir: &std::path::Path,
     base_dir: &std::path::Path,
     installer_paths: &InstallerPaths,
     args: &InstallArgs,
     progress: Option<&LiveProgress>,
 ) -> Result<()> {
     let start = Instant::now();
-
-    // Build HTTP client with optimized settings
-    let client = reqwest::Client::builder()
-        .pool_max_idle_per_host(100)
-        .pool_idle_timeout(std::time::Duration::from_secs(90))
-        .http2_adaptive_window(true)
-        .http2_initial_stream_window_size(Some(4 * 1024 * 1024))
-        .http2_initial_connection_window_size(Some(8 * 1024 * 1024))
-        .http2_keep_alive_interval(Some(std::time::Duration::from_secs(15)))
-        .http2_keep_alive_timeout(std::time::Duration::from_secs(20))
-        .http2_keep_alive_while_idle(true)
-        .connect_timeout(std::time::Duration::from_secs(10))
-        .timeout(std::time::Duration::from_secs(60))
-        .tcp_nodelay(true)
-        .tcp_keepalive(std::time::Duration::from_secs(30))
-        .gzip(true)
-        .brotli(true)
-        .deflate(true)
-        .build()
-        .context("Failed to create HTTP client")?;
+    let link_mode = args.link_mode.unwrap_or_else(cas_cache::LinkMode::from_env);
 
     // Separate cached vs need-download
-    let mut to_download: Vec<(String, String, String, PathBuf, Option<String>)> = Vec::new();
+    let mut sources: Vec<DownloadSource> = Vec::new();
+    let mut pending: HashMap<String, PendingDownload> = HashMap::new();
     let mut from_cache: Vec<(String, PathBuf, PathBuf)> = Vec::new();
+    let mut installed_dirs: Vec<(&PackageInfo, PathBuf)> = Vec::new();
+    let mut unverified: Vec<&str> = Vec::new();
     let mut skipped = 0;
 
     for pkg in packages {
-        // Check if this package has a custom install path
-        let dest = installer_paths
-            .get_path(base_dir, &pkg.name, pkg.package_type.as_deref())
-            .unwrap_or_else(|| {
-                vendor_dir.join(pkg.name.replace('/', std::path::MAIN_SEPARATOR_STR))
-            });
+        let dest = install_dir(pkg, vendor_dir, base_dir, installer_paths);
 
         if let Some(ref url_str) = pkg.dist_url {
             let url = convert_github_api_url(url_str);
+            installed_dirs.push((pkg, dest.clone()));
+            if args.frozen && pkg.dist_shasum.as_deref().is_none_or(str::is_empty) {
+                unverified.push(&pkg.name);
+            }
 
-            // Use is_cached for quick check, then get_cached_path for the actual path
-            if cas_cache::is_cached(&url) {
-                if let Some(cache_path) = cas_cache::get_cached_path(&url) {
-                    from_cache.push((pkg.name.clone(), cache_path, dest));
-                } else {
-                    // Cache marker exists but path retrieval failed, download
-                    to_download.push((
-                        pkg.name.clone(),
-                        pkg.version.clone(),
-                        url,
-                        dest,
-                        pkg.dist_shasum.clone(),
-                    ));
+            // Hardlinked entries share inodes with every project using
+            // them, so a modified entry must not be linked again
+            let cached = cas_cache::get_cached_path(&url).filter(|cache_path| {
+                if (link_mode != cas_cache::LinkMode::Hardlink && !args.frozen)
+                    || cas_cache::verify_entry(cache_path)
+                {
+                    return true;
                 }
-            } else {
-                to_download.push((
-                    pkg.name.clone(),
-                    pkg.version.clone(),
-                    url,
-                    dest,
-                    pkg.dist_shasum.clone(),
+                warning(&format!(
+                    "Cache entry for {} was modified, re-downloading",
+                    pkg.name
                 ));
+                if let Err(e) = cas_cache::evict(cache_path) {
+                    debug!("Failed to evict cache entry: {e}");
+                }
+                false
+            });
+
+            if let Some(cache_path) = cached {
+                from_cache.push((pkg.name.clone(), cache_path, dest));
+            } else {
+                sources.push(dist_source(pkg, &url, &dest, args.frozen)?);
+                pending.insert(pkg.name.clone(), PendingDownload { url, dest });
             }
+        } else if args.frozen {
+            bail!(
+                "{} has no dist URL in composer.lock; --frozen cannot install it",
+                pkg.name
+            );
         } else {
             skipped += 1;
         }
     }
 
+    if !unverified.is_empty() {
+        warning(&format!(
+            "composer.lock has no dist checksum for {}; their archives are installed unverified",
+            unverified.join(", ")
+        ));
+    }
+
     let cached_count = from_cache.len();
-    let download_count = to_download.len();
+    let download_count = sources.len();
     let total = cached_count + download_count;
 
     if total == 0 {
@@ -783,20 +1165,11 @@ async fn install_packages(
     }
 
     // Link cached packages first (instant)
-    for (name, cache_path, dest) in &from_cache {
-        if let Some(p) = progress {
-            p.set_current(name);
-        }
-        if let Err(e) = cas_cache::link_from_cache(cache_path, dest) {
-            warning(&format!("Cache link failed for {name}: {e}"));
-        }
-        if let Some(p) = progress {
-            p.inc_completed();
-        }
-    }
+    let unlinked = link_cached(&from_cache, link_mode, args.frozen, progress)?;
+    installed_dirs.retain(|(pkg, _)| !unlinked.contains(&pkg.name));
 
-    if to_download.is_empty() {
-        return Ok(());
+    if sources.is_empty() {
+        return record_manifest(vendor_dir, base_dir, &installed_dirs);
     }
 
     // Adaptive concurrency based on CPU cores
@@ -809,64 +1182,52 @@ async fn install_packages(
         (cpu_cores * 8).clamp(32, 128)
     };
 
-    let completed = Arc::new(AtomicU64::new(0));
-    let failed_count = Arc::new(AtomicU64::new(0));
-    let total_bytes = Arc::new(AtomicU64::new(0));
-
-    let mut pending: Vec<_> = to_download.into_iter().collect();
-    let mut in_flight = FuturesUnordered::new();
+    // The live display replaces the downloader's own progress bars
+    let config = DownloadConfig::builder()
+        .max_concurrent(max_concurrent)
+        .max_retries(args.download_retries)
+        .bandwidth_limit(args.max_download_speed)
+        .mirrors(args.mirror.clone())
+        .verify_checksum(args.verify_checksums || args.frozen)
+        .show_progress(false)
+        .build();
+    let downloader =
+        ParallelDownloader::new(config, None).context("Failed to create downloader")?;
+
+    let mut installed = cached_count;
+    let mut bytes = 0u64;
     let mut errors: Vec<String> = Vec::new();
-    let verify_checksums = args.verify_checksums;
-
-    while !pending.is_empty() || !in_flight.is_empty() {
-        while in_flight.len() < max_concurrent && !pending.is_empty() {
-            let (name, version, url, dest, shasum) = pending.pop().unwrap();
-            let client = client.clone();
-            let total_bytes = Arc::clone(&total_bytes);
-
-            // Update progress with current package
-            if let Some(p) = progress {
-                p.set_current(&name);
+    let mut to_cache: Vec<PendingDownload> = Vec::new();
+
+    downloader
+        .download_all_with(sources, |name, result| match result {
+            Ok(downloaded) => {
+                installed += 1;
+                bytes += downloaded.size;
+                if let Some(p) = progress {
+                    p.set_current(name);
+                    p.inc_completed();
+                    p.add_bytes(downloaded.size);
+                }
+                to_cache.extend(pending.remove(name));
             }
+            Err(e) => errors.push(format!("{name}: {e}")),
+        })
+        .await;
 
-            in_flight.push(async move {
-                let result = download_and_extract(
-                    &client,
-                    &name,
-                    &version,
-                    &url,
-                    &dest,
-                    &total_bytes,
-                    shasum.as_deref(),
-                    verify_checksums,
-                )
-                .await;
-                (name, url, result)
-            });
-        }
-
-        if let Some((name, url, result)) = in_flight.next().await {
-            match result {
-                Ok(dest_path) => {
-                    completed.fetch_add(1, Ordering::Relaxed);
-                    if let Some(p) = progress {
-                        p.inc_completed();
-                        p.add_bytes(total_bytes.load(Ordering::Relaxed));
-                    }
-                    let _ = cas_cache::store_in_cache(&url, &dest_path);
-                }
-                Err(e) => {
-                    failed_count.fetch_add(1, Ordering::Relaxed);
-                    errors.push(format!("{name}: {e}"));
-                }
+    // Copy into the cache off the runtime, once the downloads are done
+    tokio::task::spawn_blocking(move || {
+        for pkg in to_cache {
+            if let Err(e) = cas_cache::store_in_cache(&pkg.url, &pkg.dest) {
+                debug!("Failed to cache {}: {e}", pkg.url);
             }
         }
-    }
+    })
+    .await
+    .context("Caching downloaded packages failed")?;
 
     let elapsed = start.elapsed();
-    let installed = completed.load(Ordering::Relaxed) + cached_count as u64;
-    let failed = failed_count.load(Ordering::Relaxed);
-    let bytes = total_bytes.load(Ordering::Relaxed);
+    let failed = errors.len();
 
     for err in &errors {
         warning(&format!("Failed: {err}"));
@@ -876,6 +1237,8 @@ async fn install_packages(
         bail!("Failed to install {failed} of {total} packages. See warnings above.");
     }
 
+    record_manifest(vendor_dir, base_dir, &installed_dirs)?;
+
     // Print installation summary
     if installed > 0 {
         let speed = if elapsed.as_secs() > 0 {
@@ -908,267 +1271,153 @@ async fn install_packages(
     Ok(())
 }
 
-async fn download_and_extract(
-    client: &reqwest::Client,
-    name: &str,
-    _version: &str,
-    url: &str,
-    dest: &std::path::Path,
-    total_bytes: &AtomicU64,
-    expected_shasum: Option<&str>,
-    verify_checksums: bool,
-) -> Result<PathBuf> {
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .with_context(|| format!("Failed to fetch {name}"))?;
-
-    if !response.status().is_success() {
-        anyhow::bail!("HTTP {}", response.status());
-    }
-
-    let bytes = response
-        .bytes()
-        .await
-        .with_context(|| format!("Failed to read response for {name}"))?;
-
-    total_bytes.fetch_add(bytes.len() as u64, Ordering::Relaxed);
-
-    // Verify checksum if provided and verification is enabled
-    if verify_checksums
-        && let Some(expected) = expected_shasum
-        && !expected.is_empty()
-    {
-        let actual = compute_sha1(&bytes);
-        if !constant_time_eq(&actual, expected) {
-            anyhow::bail!("Checksum mismatch: expected {expected}, got {actual}");
+/// Link cached packages into place.
+///
+/// Returns the packages that could not be linked, which are left out of the
+/// vendor manifest. Under `--frozen` any link failure is an error instead.
+fn link_cached(
+    from_cache: &[(String, PathBuf, PathBuf)],
+    link_mode: cas_cache::LinkMode,
+    frozen: bool,
+    progress: Option<&LiveProgress>,
+) -> Result<HashSet<String>> {
+    let mut unlinked = HashSet::new();
+    for (name, cache_path, dest) in from_cache {
+        if let Some(p) = progress {
+            p.set_current(name);
         }
-    }
-
-    // Extract in blocking task to not block async runtime
-    let dest = dest.to_path_buf();
-    let name = name.to_string();
-    tokio::task::spawn_blocking(move || {
-        use std::io::Write;
-
-        if let Some(parent) = dest.parent() {
-            std::fs::create_dir_all(parent)?;
+        if let Err(e) = cas_cache::link_from_cache(cache_path, dest, link_mode) {
+            if frozen {
+                bail!("Cache link failed for {name}: {e}; --frozen cannot install it");
+            }
+            warning(&format!("Cache link failed for {name}: {e}"));
+            unlinked.insert(name.clone());
         }
-
-        let temp_path = dest.with_extension("download.zip");
-        {
-            let mut file = std::fs::File::create(&temp_path)?;
-            file.write_all(&bytes)?;
+        if let Some(p) = progress {
+            p.inc_completed();
         }
-
-        extract_zip(&temp_path, &dest).with_context(|| format!("Failed to extract {name}"))?;
-        let _ = std::fs::remove_file(&temp_path);
-
-        Ok(dest)
-    })
-    .await
-    .context("Extraction task failed")?
+    }
+    Ok(unlinked)
 }
 
-/// Compute SHA-1 hash of bytes and return as hex string.
-fn compute_sha1(data: &[u8]) -> String {
-    use sha1::{Digest, Sha1};
-    let mut hasher = Sha1::new();
-    hasher.update(data);
-    hex::encode(hasher.finalize())
+/// Record the file hash manifest used by `verify` and `status`.
+fn record_manifest(
+    vendor_dir: &std::path::Path,
+    base_dir: &std::path::Path,
+    installed: &[(&PackageInfo, PathBuf)],
+) -> Result<()> {
+    let packages: Vec<vendor_manifest::InstalledPackage<'_>> = installed
+        .iter()
+        .map(|(pkg, dir)| vendor_manifest::InstalledPackage {
+            name: &pkg.name,
+            version: &pkg.version,
+            dir,
+        })
+        .collect();
+    vendor_manifest::record(vendor_dir, base_dir, &packages)
+        .context("Failed to record vendor manifest")
 }
 
-/// Constant-time string comparison to prevent timing attacks.
-fn constant_time_eq(a: &str, b: &str) -> bool {
-    if a.len() != b.len() {
-        return false;
+/// Build the downloader source for a package's dist archive.
+///
+/// Under `--frozen` a locked checksum that cannot be used is an error rather
+/// than silently dropped.
+fn dist_source(
+    pkg: &PackageInfo,
+    url: &str,
+    dest: &std::path::Path,
+    frozen: bool,
+) -> Result<DownloadSource> {
+    let parsed =
+        url::Url::parse(url).with_context(|| format!("Invalid dist URL for {}", pkg.name))?;
+    let archive_type = ArchiveType::from_url(&parsed).unwrap_or(match pkg.dist_type.as_deref() {
+        Some("tar") => ArchiveType::TarGz,
+        _ => ArchiveType::Zip,
+    });
+
+    let mut source = DownloadSource::new(
+        &pkg.name,
+        &pkg.version,
+        Source::Dist {
+            url: parsed,
+            archive_type,
+        },
+        dest.to_path_buf(),
+    );
+    if let Some(shasum) = pkg.dist_shasum.as_deref().filter(|s| !s.is_empty()) {
+        match ExpectedChecksum::from_hex(shasum) {
+            Some(checksum) => source = source.with_checksums(vec![checksum]),
+            None if frozen => bail!(
+                "{} has an unrecognized dist checksum in composer.lock; --frozen cannot verify it",
+                pkg.name
+            ),
+            None => {}
+        }
     }
-    let mut result = 0u8;
-    for (x, y) in a.bytes().zip(b.bytes()) {
-        result |= x ^ y;
+    if let Some(reference) = &pkg.dist_reference {
+        source = source.with_reference(reference);
     }
-    result == 0
+    Ok(source)
 }
 
-fn extract_zip(zip_path: &std::path::Path, dest: &std::path::Path) -> Result<()> {
-    use std::io::Read;
-
-    let file = std::fs::File::open(zip_path)?;
-    let mut archive = zip::ZipArchive::new(file)?;
-
-    // Find common prefix (GitHub zips have vendor-repo-hash/ prefix)
-    let mut common_prefix: Option<String> = None;
-    for i in 0..archive.len() {
-        let entry = archive.by_index(i)?;
-        let path = entry.name();
-        if let Some(first_component) = path.split('/').next()
-            && !first_component.is_empty()
-        {
-            match &common_prefix {
-                None => common_prefix = Some(format!("{first_component}/")),
-                Some(p) if !path.starts_with(p) => {
-                    common_prefix = None;
-                    break;
-                }
-                _ => {}
-            }
-        }
-    }
-
-    let prefix_len = common_prefix.as_ref().map_or(0, std::string::String::len);
-
-    for i in 0..archive.len() {
-        let mut entry = archive.by_index(i)?;
-        let entry_path = entry.name();
-
-        if entry_path.len() <= prefix_len {
-            continue;
-        }
-
-        let relative_path = &entry_path[prefix_len..];
-        if relative_path.is_empty() {
-            continue;
-        }
-
-        let out_path = dest.join(relative_path);
-
-        if entry.is_dir() {
-            std::fs::create_dir_all(&out_path)?;
-        } else {
-            if let Some(parent) = out_path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
-            let mut outfile = std::fs::File::create(&out_path)?;
-            let mut buffer = Vec::new();
-            entry.read_to_end(&mut buffer)?;
-            std::io::Write::write_all(&mut outfile, &buffer)?;
-
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                if let Some(mode) = entry.unix_mode() {
-                    let _ =
-                        std::fs::set_permissions(&out_path, std::fs::Permissions::from_mode(mode));
-                }
-            }
-        }
+/// Parse a download speed limit such as `500K`, `10M` or `1048576` (bytes/s).
+fn parse_download_speed(value: &str) -> Result<u64, String> {
+    let trimmed = value.trim().trim_end_matches("/s");
+    let trimmed = trimmed
+        .strip_suffix(['B', 'b'])
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(trimmed);
+    let (number, multiplier) = match trimmed.chars().last() {
+        Some('k' | 'K') => (&trimmed[..trimmed.len() - 1], 1024_u32),
+        Some('m' | 'M') => (&trimmed[..trimmed.len() - 1], 1024 * 1024),
+        Some('g' | 'G') => (&trimmed[..trimmed.len() - 1], 1024 * 1024 * 1024),
+        _ => (trimmed, 1),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid download speed '{value}' (e.g. 500K, 10M)"))?;
+    if !number.is_finite() || number <= 0.0 {
+        return Err(format!("download speed must be positive, got '{value}'"));
     }
-
-    Ok(())
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    Ok((number * f64::from(multiplier)) as u64)
 }
 
 fn generate_lock_file(
-    lock_path: &PathBuf,
+    lock_path: &std::path::Path,
     resolution: &libretto_resolver::Resolution,
     composer: &Value,
+    prefer_lowest: bool,
 ) -> Result<()> {
-    super::lock_generator::generate_lock_file(lock_path, resolution, composer)
+    super::lock_generator::generate_lock_file(lock_path, resolution, composer, prefer_lowest)
 }
 
-fn generate_autoloader(vendor_dir: &PathBuf, args: &InstallArgs) -> Result<()> {
-    use libretto_autoloader::{AutoloadConfig, AutoloaderGenerator, OptimizationLevel};
-    use serde::Deserialize;
-    use std::collections::HashMap;
-
-    #[derive(Debug, Deserialize)]
-    struct ComposerJson {
-        #[serde(default)]
-        autoload: AutoloadSection,
-        #[serde(default, rename = "autoload-dev")]
-        autoload_dev: AutoloadSection,
-    }
-
-    #[derive(Debug, Default, Deserialize)]
-    struct AutoloadSection {
-        #[serde(default, rename = "psr-4")]
-        psr4: HashMap<String, Psr4Value>,
-        #[serde(default, rename = "psr-0")]
-        psr0: HashMap<String, Psr4Value>,
-        #[serde(default)]
-        classmap: Vec<String>,
-        #[serde(default)]
-        files: Vec<String>,
-        #[serde(default, rename = "exclude-from-classmap")]
-        exclude: Vec<String>,
-    }
-
-    #[derive(Debug, Deserialize)]
-    #[serde(untagged)]
-    enum Psr4Value {
-        Single(String),
-        Multiple(Vec<String>),
-    }
-
-    impl Psr4Value {
-        fn to_vec(&self) -> Vec<String> {
-            match self {
-                Self::Single(s) => vec![s.clone()],
-                Self::Multiple(v) => v.clone(),
-            }
-        }
-    }
-
-    fn load_autoload_config(path: &std::path::Path) -> Option<AutoloadConfig> {
-        let content = std::fs::read_to_string(path).ok()?;
-        let composer: ComposerJson = sonic_rs::from_str(&content).ok()?;
-
-        let mut config = AutoloadConfig::default();
-
-        for (namespace, paths) in composer.autoload.psr4 {
-            config.psr4.mappings.insert(namespace, paths.to_vec());
-        }
-        for (namespace, paths) in composer.autoload.psr0 {
-            config.psr0.mappings.insert(namespace, paths.to_vec());
-        }
-        config.classmap.paths = composer.autoload.classmap;
-        config.files.files = composer.autoload.files;
-        config.exclude.patterns = composer.autoload.exclude;
-
-        Some(config)
-    }
-
-    fn load_autoload_config_with_dev(path: &std::path::Path) -> Option<AutoloadConfig> {
-        let content = std::fs::read_to_string(path).ok()?;
-        let composer: ComposerJson = sonic_rs::from_str(&content).ok()?;
-
-        let mut config = AutoloadConfig::default();
+/// Whether the default autoloader depends only on composer.json and
+/// composer.lock; optimized levels and root classmaps also scan the project.
+fn autoloader_from_lock_only(composer: &Value, args: &InstallArgs) -> bool {
+    let sections: &[&str] = if args.no_dev {
+        &["autoload"]
+    } else {
+        &["autoload", "autoload-dev"]
+    };
+    !args.optimize_autoloader
+        && !args.classmap_authoritative
+        && sections.iter().all(|section| {
+            composer
+                .get(section)
+                .and_then(|autoload| autoload.get("classmap"))
+                .and_then(|classmap| classmap.as_array())
+                .is_none_or(|classmap| classmap.is_empty())
+        })
+}
 
-        for (namespace, paths) in composer.autoload.psr4 {
-            config.psr4.mappings.insert(namespace, paths.to_vec());
-        }
-        for (namespace, paths) in composer.autoload.psr0 {
-            config.psr0.mappings.insert(namespace, paths.to_vec());
-        }
-        config.classmap.paths = composer.autoload.classmap;
-        config.files.files = composer.autoload.files;
-        config.exclude.patterns = composer.autoload.exclude;
-
-        for (namespace, paths) in composer.autoload_dev.psr4 {
-            config
-                .psr4
-                .mappings
-                .entry(namespace)
-                .or_default()
-                .extend(paths.to_vec());
-        }
-        for (namespace, paths) in composer.autoload_dev.psr0 {
-            config
-                .psr0
-                .mappings
-                .entry(namespace)
-                .or_default()
-                .extend(paths.to_vec());
-        }
-        config.classmap.paths.extend(composer.autoload_dev.classmap);
-        config.files.files.extend(composer.autoload_dev.files);
-        config
-            .exclude
-            .patterns
-            .extend(composer.autoload_dev.exclude);
-
-        Some(config)
-    }
+fn generate_autoloader(
+    vendor_dir: &std::path::Path,
+    base_dir: &std::path::Path,
+    args: &InstallArgs,
+) -> Result<()> {
+    use libretto_autoloader::{AutoloaderGenerator, OptimizationLevel};
 
     let level = if args.classmap_authoritative {
         OptimizationLevel::Authoritative
@@ -1178,48 +1427,17 @@ fn generate_autoloader(vendor_dir: &PathBuf, args: &InstallArgs) -> Result<()> {
         OptimizationLevel::None
     };
 
-    let mut generator = AutoloaderGenerator::with_optimization(vendor_dir.clone(), level);
-
-    // Scan vendor directory for installed packages
-    if let Ok(entries) = std::fs::read_dir(vendor_dir) {
-        for entry in entries.filter_map(Result::ok) {
-            let vendor_path = entry.path();
-            if vendor_path.is_dir() {
-                if vendor_path.file_name().is_some_and(|n| n == "composer") {
-                    continue;
-                }
-
-                if let Ok(package_entries) = std::fs::read_dir(&vendor_path) {
-                    for package_entry in package_entries.filter_map(Result::ok) {
-                        let package_path = package_entry.path();
-                        if package_path.is_dir() {
-                            let composer_json_path = package_path.join("composer.json");
-                            if composer_json_path.exists()
-                                && let Some(config) = load_autoload_config(&composer_json_path)
-                            {
-                                generator.add_package(&package_path, &config);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // Load root project's autoload config (including dev if not --no-dev)
-    let root_composer_json = std::path::PathBuf::from("composer.json");
-    if root_composer_json.exists() {
-        let config = if args.no_dev {
-            load_autoload_config(&root_composer_json)
-        } else {
-            load_autoload_config_with_dev(&root_composer_json)
-        };
-        if let Some(config) = config {
-            let project_root = std::path::PathBuf::from(".");
-            generator.add_package(&project_root, &config);
-        }
+    let mut generator = AutoloaderGenerator::with_optimization(vendor_dir.to_path_buf(), level);
+    crate::autoload_packages::add_packages(&mut generator, base_dir, vendor_dir, !args.no_dev)?;
+    crate::autoload_packages::apply_config(
+        &mut generator,
+        base_dir,
+        args.apcu_autoloader || args.apcu_autoloader_prefix.is_some(),
+        args.apcu_autoloader_prefix.as_deref(),
+    );
+    for w in generator.warnings() {
+        warning(&w.to_string());
     }
-
     generator.generate()?;
 
     Ok(())
@@ -1251,3 +1469,189 @@ fn convert_github_api_url(url: &str) -> String {
         url.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::path::Path;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: InstallArgs,
+    }
+
+    fn install_args(flags: &[&str]) -> InstallArgs {
+        Cli::parse_from(std::iter::once("install").chain(flags.iter().copied())).args
+    }
+
+    fn package(name: &str, package_type: &str) -> PackageInfo {
+        PackageInfo {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            is_dev: false,
+            dist_url: None,
+            dist_type: None,
+            dist_shasum: None,
+            dist_reference: None,
+            package_type: Some(package_type.to_string()),
+        }
+    }
+
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path).unwrap();
+        std::fs::write(path.join("file.php"), "<?php").unwrap();
+    }
+
+    #[test]
+    fn extraneous_packages_are_removed_from_vendor_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let vendor = tmp.path().join("vendor");
+        for dir in [
+            "acme/kept",
+            "acme/stale",
+            "gone/package",
+            "libs/plugins/theme",
+            "composer",
+            "bin",
+        ] {
+            touch(&vendor.join(dir));
+        }
+        touch(&tmp.path().join("web/modules/module"));
+
+        let composer: Value = sonic_rs::from_str(
+            r#"{"extra": {"installer-paths": {
+                "vendor/libs/plugins/{$name}": ["type:wordpress-theme"],
+                "web/modules/{$name}": ["type:drupal-module"]
+            }}}"#,
+        )
+        .unwrap();
+        let installer_paths = InstallerPaths::from_composer(&composer);
+        let packages = [
+            package("acme/kept", "library"),
+            package("wp/theme", "wordpress-theme"),
+            package("drupal/module", "drupal-module"),
+        ];
+
+        remove_extraneous_packages(&vendor, tmp.path(), &installer_paths, &packages).unwrap();
+
+        assert!(vendor.join("acme/kept/file.php").exists());
+        assert!(!vendor.join("acme/stale").exists());
+        assert!(!vendor.join("gone").exists());
+        assert!(vendor.join("libs/plugins/theme/file.php").exists());
+        assert!(vendor.join("composer/file.php").exists());
+        assert!(vendor.join("bin/file.php").exists());
+        assert!(tmp.path().join("web/modules/module/file.php").exists());
+    }
+
+    fn lock_out_of_date(result: Result<()>) -> String {
+        let error = result.unwrap_err();
+        assert!(error.downcast_ref::<LockOutOfDate>().is_some());
+        error.to_string()
+    }
+
+    #[test]
+    fn frozen_lock_errors_exit_with_lock_out_of_date() {
+        assert_eq!(EXIT_LOCK_OUT_OF_DATE, 4);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let lock_path = tmp.path().join("composer.lock");
+        let composer: Value = sonic_rs::from_str(
+            r#"{"require": {"php": ">=8.1", "acme/log": "^1.0"},
+                "require-dev": {"acme/test": "^2.0"}}"#,
+        )
+        .unwrap();
+        let args = install_args(&["--frozen"]);
+
+        let missing = lock_out_of_date(check_frozen_lock(&composer, &lock_path, None, &args));
+        assert!(missing.contains("No composer.lock found"));
+
+        let mut lock = ComposerLock {
+            content_hash: "0".repeat(32),
+            packages: vec![LockedPackage::new("acme/log", "1.0.0")],
+            ..ComposerLock::default()
+        };
+        crate::commands::lock_generator::write_lock(&lock_path, &lock).unwrap();
+        lock_out_of_date(check_frozen_lock(&composer, &lock_path, None, &args));
+
+        lock.content_hash = crate::commands::lock_generator::compute_content_hash(&composer);
+        crate::commands::lock_generator::write_lock(&lock_path, &lock).unwrap();
+        let unlocked = lock_out_of_date(check_frozen_lock(&composer, &lock_path, None, &args));
+        assert!(unlocked.contains("does not contain acme/test"));
+
+        let no_dev = install_args(&["--frozen", "--no-dev"]);
+        check_frozen_lock(&composer, &lock_path, None, &no_dev).unwrap();
+    }
+
+    #[test]
+    fn signed_lock_is_used_as_verified() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = tmp.path().join("trusted");
+        std::fs::create_dir(&store).unwrap();
+        let lock_path = tmp.path().join("composer.lock");
+        let lock = ComposerLock {
+            packages: vec![LockedPackage::new("acme/log", "1.0.0")],
+            ..ComposerLock::default()
+        };
+        crate::commands::lock_generator::write_lock(&lock_path, &lock).unwrap();
+
+        let signer = libretto_audit::Ed25519Signer::generate();
+        std::fs::write(store.join("release.pub"), hex::encode(signer.public_key())).unwrap();
+        let signature = signer.sign(&std::fs::read(&lock_path).unwrap());
+        std::fs::write(
+            crate::commands::lock_sign::signature_path(&lock_path),
+            signature,
+        )
+        .unwrap();
+
+        let args = install_args(&[
+            "--require-signed-lock",
+            "--trust-store",
+            store.to_str().unwrap(),
+        ]);
+        let (_, signed) = read_signed_lock(&lock_path, &args).unwrap();
+
+        // A lock swapped in after verification is never read
+        let swapped = ComposerLock {
+            packages: vec![LockedPackage::new("evil/log", "6.6.6")],
+            ..ComposerLock::default()
+        };
+        crate::commands::lock_generator::write_lock(&lock_path, &swapped).unwrap();
+        let installed = load_lock(&lock_path, Some(&signed)).unwrap();
+        assert_eq!(installed.packages[0].name, "acme/log");
+        assert!(read_signed_lock(&lock_path, &args).is_err());
+    }
+
+    #[test]
+    fn frozen_rejects_unusable_dist_checksums() {
+        let url = "https://example.com/acme-log.zip";
+        let dest = Path::new("vendor/acme/log");
+        let mut pkg = package("acme/log", "library");
+
+        pkg.dist_shasum = Some(String::new());
+        assert!(dist_source(&pkg, url, dest, true).is_ok());
+
+        pkg.dist_shasum = Some("not-a-checksum".to_string());
+        assert!(dist_source(&pkg, url, dest, false).is_ok());
+        let error = dist_source(&pkg, url, dest, true).unwrap_err();
+        assert!(error.to_string().contains("unrecognized dist checksum"));
+    }
+
+    #[test]
+    fn cache_link_failures_fail_frozen_installs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let from_cache = [(
+            "acme/log".to_string(),
+            tmp.path().join("cache/missing"),
+            tmp.path().join("vendor/acme/log"),
+        )];
+        let mode = cas_cache::LinkMode::Copy;
+
+        let unlinked = link_cached(&from_cache, mode, false, None).unwrap();
+        assert!(unlinked.contains("acme/log"));
+
+        let error = link_cached(&from_cache, mode, true, None).unwrap_err();
+        assert!(error.to_string().contains("--frozen"));
+    }
+}
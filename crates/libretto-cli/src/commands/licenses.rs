@@ -2,7 +2,7 @@
 
 use anyhow::Result;
 use clap::Args;
-use sonic_rs::{JsonContainerTrait, JsonValueTrait};
+use libretto_lockfile::LockedPackage;
 use std::collections::HashMap;
 
 /// Arguments for the licenses command
@@ -41,26 +41,21 @@ pub async fn run(args: LicensesArgs) -> Result<()> {
         anyhow::bail!("composer.lock not found - run 'libretto install' first");
     }
 
-    let lock_content = std::fs::read_to_string(&lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+    let lock = super::lock_generator::read_lock(&lock_path)?;
 
     let mut licenses: Vec<PackageLicense> = Vec::new();
 
     // Collect license info from packages
-    if !args.dev
-        && let Some(packages) = lock.get("packages").and_then(|v| v.as_array())
-    {
-        for pkg in packages {
-            licenses.push(extract_license(pkg, false));
-        }
+    if !args.dev {
+        licenses.extend(lock.packages.iter().map(|pkg| extract_license(pkg, false)));
     }
 
-    if !args.no_dev
-        && let Some(packages) = lock.get("packages-dev").and_then(|v| v.as_array())
-    {
-        for pkg in packages {
-            licenses.push(extract_license(pkg, true));
-        }
+    if !args.no_dev {
+        licenses.extend(
+            lock.packages_dev
+                .iter()
+                .map(|pkg| extract_license(pkg, true)),
+        );
     }
 
     if licenses.is_empty() {
@@ -80,35 +75,16 @@ pub async fn run(args: LicensesArgs) -> Result<()> {
     Ok(())
 }
 
-fn extract_license(pkg: &sonic_rs::Value, is_dev: bool) -> PackageLicense {
-    let name = pkg
-        .get("name")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-    let version = pkg
-        .get("version")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-
-    let license = if let Some(lic) = pkg.get("license") {
-        if let Some(arr) = lic.as_array() {
-            arr.iter()
-                .filter_map(|v| v.as_str().map(String::from))
-                .collect()
-        } else if let Some(s) = lic.as_str() {
-            vec![s.to_string()]
-        } else {
-            vec!["Unknown".to_string()]
-        }
-    } else {
+fn extract_license(pkg: &LockedPackage, is_dev: bool) -> PackageLicense {
+    let license = if pkg.license.is_empty() {
         vec!["Unknown".to_string()]
+    } else {
+        pkg.license.clone()
     };
 
     PackageLicense {
-        name,
-        version,
+        name: pkg.name.clone(),
+        version: pkg.version.clone(),
         license,
         is_dev,
     }
@@ -0,0 +1,206 @@
+//! lock:check command - enforce a policy on composer.lock.
+//!
+//! The policy comes from the `policy` section of composer.json, or from the
+//! JSON file given with `--policy`; see [`libretto_lockfile::policy`] for
+//! the rules. Violations are printed as a table, as JSON with the global
+//! `--format json`, or as a `JUnit` report with `--junit`, and make the
+//! command fail so it can gate CI.
+
+use crate::output::table::Table;
+use crate::output::{header, success, warning};
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use libretto_lockfile::{ComposerLock, Policy, PolicyRule, PolicyViolation};
+use sonic_rs::{JsonValueTrait, Value};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+/// Arguments for the lock:check command
+#[derive(Args, Debug, Clone)]
+pub struct LockCheckArgs {
+    /// Policy file (defaults to the `policy` section of composer.json)
+    #[arg(long, value_name = "FILE")]
+    pub policy: Option<PathBuf>,
+
+    /// Print a `JUnit` XML report
+    #[arg(long)]
+    pub junit: bool,
+}
+
+#[derive(serde::Serialize)]
+struct Report<'a> {
+    rules: &'a [PolicyRule],
+    packages: usize,
+    violations: &'a [PolicyViolation],
+}
+
+/// Run the lock:check command
+pub async fn run(args: LockCheckArgs) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let lock_path = cwd.join("composer.lock");
+    if !lock_path.exists() {
+        bail!("No composer.lock found. Run 'libretto update' to create one.");
+    }
+    let lock = super::lock_generator::read_lock(&lock_path)?;
+
+    let policy = match &args.policy {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            sonic_rs::from_str(&content)
+                .with_context(|| format!("Invalid policy in {}", path.display()))?
+        }
+        None => manifest_policy(&cwd.join("composer.json"))?,
+    };
+
+    let rules = policy.rules();
+    if rules.is_empty() {
+        warning("The policy enables no rules");
+    }
+    let violations = policy.check(&lock, chrono::Utc::now());
+
+    if crate::output::json::is_enabled() {
+        let report = Report {
+            rules: &rules,
+            packages: package_count(&lock),
+            violations: &violations,
+        };
+        println!("{}", sonic_rs::to_string_pretty(&report)?);
+    } else if args.junit {
+        print!("{}", junit(&lock, &rules, &violations));
+    } else {
+        print_table(&lock, &rules, &violations);
+    }
+
+    if !violations.is_empty() {
+        bail!(
+            "composer.lock breaks the policy ({} violation{})",
+            violations.len(),
+            if violations.len() == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
+/// The `policy` section of composer.json.
+fn manifest_policy(path: &std::path::Path) -> Result<Policy> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let composer: Value = sonic_rs::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    let Some(section) = composer.get("policy") else {
+        bail!(
+            "No policy configured: add a \"policy\" section to composer.json or pass --policy FILE"
+        );
+    };
+    sonic_rs::from_str(&sonic_rs::to_string(section)?)
+        .context("Invalid \"policy\" section in composer.json")
+}
+
+const fn package_count(lock: &ComposerLock) -> usize {
+    lock.packages.len() + lock.packages_dev.len()
+}
+
+fn print_table(lock: &ComposerLock, rules: &[PolicyRule], violations: &[PolicyViolation]) {
+    header("Checking composer.lock against the policy");
+    if violations.is_empty() {
+        let names: Vec<&str> = rules.iter().map(|r| r.as_str()).collect();
+        success(&format!(
+            "{} packages pass {} rule{} ({})",
+            package_count(lock),
+            rules.len(),
+            if rules.len() == 1 { "" } else { "s" },
+            names.join(", ")
+        ));
+        return;
+    }
+
+    let mut table = Table::new();
+    table.headers(["Package", "Version", "Rule", "Problem"]);
+    for violation in violations {
+        table.styled_row(vec![
+            comfy_table::Cell::new(&violation.package),
+            comfy_table::Cell::new(&violation.version),
+            table.error_cell(violation.rule),
+            comfy_table::Cell::new(&violation.message),
+        ]);
+    }
+    table.print();
+}
+
+/// `JUnit` report: a test suite per rule with a test case per package.
+fn junit(lock: &ComposerLock, rules: &[PolicyRule], violations: &[PolicyViolation]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"libretto lock:check\" tests=\"{}\" failures=\"{}\">",
+        package_count(lock) * rules.len(),
+        violations.len()
+    );
+
+    for &rule in rules {
+        let failures = violations.iter().filter(|v| v.rule == rule).count();
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{rule}\" tests=\"{}\" failures=\"{failures}\">",
+            package_count(lock)
+        );
+        for pkg in lock.all_packages() {
+            let name = escape(&format!("{} {}", pkg.name, pkg.version));
+            let failed = violations
+                .iter()
+                .filter(|v| v.rule == rule && v.package == pkg.name)
+                .map(|v| escape(&v.message))
+                .collect::<Vec<_>>();
+            if failed.is_empty() {
+                let _ = writeln!(xml, "    <testcase classname=\"{rule}\" name=\"{name}\"/>");
+            } else {
+                let _ = writeln!(xml, "    <testcase classname=\"{rule}\" name=\"{name}\">");
+                for message in failed {
+                    let _ = writeln!(
+                        xml,
+                        "      <failure type=\"{rule}\" message=\"{message}\"/>"
+                    );
+                }
+                xml.push_str("    </testcase>\n");
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libretto_lockfile::LockedPackage;
+
+    #[test]
+    fn junit_reports_failures_per_rule() {
+        let mut lock = ComposerLock::default();
+        let mut pkg = LockedPackage::new("a/a", "1.0.0");
+        pkg.license = vec!["GPL-3.0".to_string()];
+        lock.packages.push(pkg);
+        lock.packages.push(LockedPackage::new("b/b", "dev-main"));
+
+        let policy: Policy =
+            sonic_rs::from_str(r#"{"licenses": ["MIT"], "forbid-dev-versions": true}"#).unwrap();
+        let violations = policy.check(&lock, chrono::Utc::now());
+        let xml = junit(&lock, &policy.rules(), &violations);
+
+        assert!(
+            xml.contains("<testsuites name=\"libretto lock:check\" tests=\"4\" failures=\"3\">")
+        );
+        assert!(xml.contains("<testsuite name=\"dev-version\" tests=\"2\" failures=\"1\">"));
+        assert!(xml.contains("<testcase classname=\"dev-version\" name=\"a/a 1.0.0\"/>"));
+        assert!(xml.contains("message=\"license GPL-3.0 is not allowed\""));
+    }
+}
@@ -0,0 +1,423 @@
+//! lock:diff command - compare composer.lock with a git revision or another file.
+//!
+//! Each changed package is annotated with the size of its version jump, the
+//! advisories the change fixes or introduces, and any license change. Output
+//! is a table, markdown with `--markdown`, or JSON with the global
+//! `--format json`.
+
+use crate::output::{header, info, warning};
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use libretto_audit::Auditor;
+use libretto_core::PackageId;
+use libretto_lockfile::{ChangeType, ComposerLock, LockDiff, compute_diff};
+use libretto_resolver::ComposerVersion;
+use semver::Version;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// Arguments for the lock:diff command
+#[derive(Args, Debug, Clone)]
+pub struct LockDiffArgs {
+    /// Git revision or lock file to compare composer.lock against
+    #[arg(value_name = "REV|FILE", default_value = "HEAD")]
+    pub against: String,
+
+    /// Print a markdown table, for pull request comments
+    #[arg(long)]
+    pub markdown: bool,
+
+    /// Don't look up security advisories
+    #[arg(long)]
+    pub no_audit: bool,
+}
+
+/// Size of a version change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Jump {
+    Major,
+    Minor,
+    Patch,
+    /// Pre-release, dev branch or otherwise incomparable versions.
+    Other,
+}
+
+impl Jump {
+    fn between(from: &str, to: &str) -> Self {
+        let (Some(from), Some(to)) = (ComposerVersion::parse(from), ComposerVersion::parse(to))
+        else {
+            return Self::Other;
+        };
+        if from.is_dev_branch || to.is_dev_branch {
+            Self::Other
+        } else if from.major != to.major {
+            Self::Major
+        } else if from.minor != to.minor {
+            Self::Minor
+        } else if from.patch != to.patch || from.fourth != to.fourth {
+            Self::Patch
+        } else {
+            Self::Other
+        }
+    }
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Major => "major",
+            Self::Minor => "minor",
+            Self::Patch => "patch",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// License change of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct LicenseChange {
+    from: Vec<String>,
+    to: Vec<String>,
+}
+
+/// Advisories a change fixes and introduces.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+struct Advisories {
+    fixed: Vec<String>,
+    introduced: Vec<String>,
+}
+
+/// One changed package with its annotations.
+#[derive(Debug, Clone, Serialize)]
+struct Entry {
+    name: String,
+    change: String,
+    dev: bool,
+    from: Option<String>,
+    to: Option<String>,
+    jump: Option<Jump>,
+    license: Option<LicenseChange>,
+    /// `None` when advisories were not looked up.
+    advisories: Option<Advisories>,
+}
+
+/// JSON document for the global `--format json`.
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    against: &'a str,
+    summary: String,
+    content_hash_changed: bool,
+    packages: &'a [Entry],
+}
+
+/// Run the lock:diff command
+pub async fn run(args: LockDiffArgs) -> Result<()> {
+    let lock_path = Path::new("composer.lock");
+    if !lock_path.exists() {
+        bail!("composer.lock not found - run 'libretto install' first");
+    }
+    let new = super::lock_generator::read_lock(lock_path)?;
+    let old = load(&args.against)?;
+
+    let diff = compute_diff(&old, &new);
+    let mut entries = annotate(&diff, &old, &new);
+    if !args.no_audit
+        && !entries.is_empty()
+        && let Err(e) = add_advisories(&mut entries).await
+    {
+        warning(&format!("Could not look up advisories: {e:#}"));
+    }
+
+    if crate::output::json::is_enabled() {
+        let report = Report {
+            against: &args.against,
+            summary: diff.summary(),
+            content_hash_changed: diff.content_hash_changed,
+            packages: &entries,
+        };
+        println!("{}", sonic_rs::to_string_pretty(&report)?);
+    } else if args.markdown {
+        print!("{}", markdown(&args.against, &diff, &entries));
+    } else {
+        print_table(&args.against, &diff, &entries);
+    }
+    Ok(())
+}
+
+/// Read the lock to compare against: a file if one exists at that path,
+/// otherwise composer.lock at that git revision.
+fn load(against: &str) -> Result<ComposerLock> {
+    let path = Path::new(against);
+    if path.is_file() {
+        return super::lock_generator::read_lock(path);
+    }
+
+    let output = std::process::Command::new("git")
+        .args(["show", &format!("{against}:./composer.lock")])
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "{against} is neither a file nor a git revision with a composer.lock: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let content = String::from_utf8(output.stdout).context("composer.lock is not UTF-8")?;
+    libretto_lockfile::parse_lock(&content)
+        .with_context(|| format!("Failed to parse composer.lock at {against}"))
+}
+
+/// Turn the diff into entries with version jumps and license changes.
+fn annotate(diff: &LockDiff, old: &ComposerLock, new: &ComposerLock) -> Vec<Entry> {
+    diff.packages
+        .iter()
+        .map(|change| {
+            let before = old.find_package(&change.name);
+            let after = new.find_package(&change.name);
+            let jump = match change.change_type {
+                ChangeType::Upgraded | ChangeType::Downgraded => change
+                    .old_version
+                    .as_deref()
+                    .zip(change.new_version.as_deref())
+                    .map(|(from, to)| Jump::between(from, to)),
+                _ => None,
+            };
+            Entry {
+                name: change.name.clone(),
+                change: change.change_type.to_string().to_lowercase(),
+                dev: change.is_dev,
+                from: change.old_version.clone(),
+                to: change.new_version.clone(),
+                jump,
+                license: before.zip(after).and_then(|(before, after)| {
+                    (before.license != after.license).then(|| LicenseChange {
+                        from: before.license.clone(),
+                        to: after.license.clone(),
+                    })
+                }),
+                advisories: None,
+            }
+        })
+        .collect()
+}
+
+/// Audit the old and new version of every entry and record which
+/// advisories the change fixes and which it introduces.
+async fn add_advisories(entries: &mut [Entry]) -> Result<()> {
+    let mut keys = Vec::new();
+    let mut packages = Vec::new();
+    for entry in entries.iter() {
+        for version in entry.from.iter().chain(&entry.to) {
+            if let Some(id) = PackageId::parse(&entry.name)
+                && let Ok(parsed) = Version::parse(version.trim_start_matches('v'))
+            {
+                keys.push((entry.name.clone(), version.clone()));
+                packages.push((id, parsed));
+            }
+        }
+    }
+
+    let report = Auditor::new()?.audit(&packages).await?;
+    let found: HashMap<(String, String), BTreeSet<String>> = keys
+        .into_iter()
+        .zip(&report.packages)
+        .map(|(key, audit)| {
+            let ids = audit
+                .vulnerabilities
+                .iter()
+                .map(|v| v.advisory_id.clone())
+                .collect();
+            (key, ids)
+        })
+        .collect();
+
+    let none = BTreeSet::new();
+    for entry in entries {
+        let lookup = |version: &Option<String>| {
+            version
+                .as_ref()
+                .and_then(|v| found.get(&(entry.name.clone(), v.clone())))
+                .unwrap_or(&none)
+        };
+        let (before, after) = (lookup(&entry.from), lookup(&entry.to));
+        entry.advisories = Some(Advisories {
+            fixed: before.difference(after).cloned().collect(),
+            introduced: after.difference(before).cloned().collect(),
+        });
+    }
+    Ok(())
+}
+
+fn print_table(against: &str, diff: &LockDiff, entries: &[Entry]) {
+    use crate::output::table::Table;
+
+    header(&format!("composer.lock changes since {against}"));
+    if entries.is_empty() {
+        info(&diff.summary());
+        return;
+    }
+
+    let mut table = Table::new();
+    table.headers([
+        "Package",
+        "Change",
+        "From",
+        "To",
+        "Jump",
+        "Advisories",
+        "License",
+    ]);
+    for entry in entries {
+        let advisories = advisory_text(entry);
+        let advisory_cell = match &entry.advisories {
+            Some(a) if !a.introduced.is_empty() => table.error_cell(advisories),
+            Some(a) if !a.fixed.is_empty() => table.success_cell(advisories),
+            _ => comfy_table::Cell::new(advisories),
+        };
+        let jump = entry.jump.map_or("", Jump::as_str);
+        let jump_cell = match entry.jump {
+            Some(Jump::Major) => table.warning_cell(jump),
+            _ => comfy_table::Cell::new(jump),
+        };
+        table.styled_row(vec![
+            comfy_table::Cell::new(&entry.name),
+            comfy_table::Cell::new(change_text(entry)),
+            comfy_table::Cell::new(entry.from.as_deref().unwrap_or("")),
+            comfy_table::Cell::new(entry.to.as_deref().unwrap_or("")),
+            jump_cell,
+            advisory_cell,
+            comfy_table::Cell::new(license_text(entry)),
+        ]);
+    }
+    table.print();
+
+    println!();
+    info(&diff.summary());
+    if diff.content_hash_changed {
+        info("content-hash changed");
+    }
+}
+
+/// Markdown report, ready to paste into a pull request comment.
+fn markdown(against: &str, diff: &LockDiff, entries: &[Entry]) -> String {
+    let mut out = format!(
+        "### composer.lock changes since `{against}`\n\n{}\n",
+        diff.summary()
+    );
+    if entries.is_empty() {
+        return out;
+    }
+
+    out.push_str("\n| Package | Change | From | To | Jump | Advisories | License |\n");
+    out.push_str("|---|---|---|---|---|---|---|\n");
+    for entry in entries {
+        let cells = [
+            format!("`{}`", entry.name),
+            change_text(entry),
+            entry.from.clone().unwrap_or_default(),
+            entry.to.clone().unwrap_or_default(),
+            entry.jump.map_or("", Jump::as_str).to_string(),
+            advisory_text(entry),
+            license_text(entry),
+        ];
+        let cells: Vec<String> = cells.iter().map(|c| c.replace('|', "\\|")).collect();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    out
+}
+
+fn change_text(entry: &Entry) -> String {
+    if entry.dev {
+        format!("{} (dev)", entry.change)
+    } else {
+        entry.change.clone()
+    }
+}
+
+fn advisory_text(entry: &Entry) -> String {
+    let Some(advisories) = &entry.advisories else {
+        return String::new();
+    };
+    let mut parts = Vec::new();
+    if !advisories.fixed.is_empty() {
+        parts.push(format!("fixes {}", advisories.fixed.join(", ")));
+    }
+    if !advisories.introduced.is_empty() {
+        parts.push(format!("introduces {}", advisories.introduced.join(", ")));
+    }
+    parts.join("; ")
+}
+
+fn license_text(entry: &Entry) -> String {
+    entry.license.as_ref().map_or_else(String::new, |change| {
+        let list = |licenses: &[String]| {
+            if licenses.is_empty() {
+                "none".to_string()
+            } else {
+                licenses.join(" or ")
+            }
+        };
+        format!("{} → {}", list(&change.from), list(&change.to))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libretto_lockfile::LockedPackage;
+
+    fn package(name: &str, version: &str, license: &str) -> LockedPackage {
+        let mut pkg = LockedPackage::new(name, version);
+        pkg.license = vec![license.to_string()];
+        pkg
+    }
+
+    #[test]
+    fn jump_sizes() {
+        assert_eq!(Jump::between("1.2.3", "2.0.0"), Jump::Major);
+        assert_eq!(Jump::between("v1.2.3", "1.3.0"), Jump::Minor);
+        assert_eq!(Jump::between("1.2.3", "1.2.4"), Jump::Patch);
+        assert_eq!(Jump::between("1.2.3", "1.2.3.1"), Jump::Patch);
+        assert_eq!(Jump::between("1.0.0-beta1", "1.0.0"), Jump::Other);
+        assert_eq!(Jump::between("dev-main", "1.0.0"), Jump::Other);
+    }
+
+    #[test]
+    fn annotates_jumps_and_license_changes() {
+        let old = ComposerLock {
+            packages: vec![
+                package("a/a", "1.0.0", "MIT"),
+                package("b/b", "2.1.0", "GPL-2.0"),
+            ],
+            ..ComposerLock::default()
+        };
+        let new = ComposerLock {
+            packages: vec![
+                package("a/a", "2.0.0", "Apache-2.0"),
+                package("c/c", "1.0.0", "MIT"),
+            ],
+            ..ComposerLock::default()
+        };
+
+        let diff = compute_diff(&old, &new);
+        let mut entries = annotate(&diff, &old, &new);
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].change, "upgraded");
+        assert_eq!(entries[0].jump, Some(Jump::Major));
+        assert_eq!(license_text(&entries[0]), "MIT → Apache-2.0");
+        assert_eq!(entries[1].change, "removed");
+        assert_eq!(entries[1].jump, None);
+        assert!(entries[1].license.is_none());
+
+        entries[0].advisories = Some(Advisories {
+            fixed: vec!["GHSA-1".to_string()],
+            introduced: Vec::new(),
+        });
+        let md = markdown("HEAD", &diff, &entries[..1]);
+        assert!(md.contains(
+            "| `a/a` | upgraded | 1.0.0 | 2.0.0 | major | fixes GHSA-1 | MIT → Apache-2.0 |"
+        ));
+    }
+}
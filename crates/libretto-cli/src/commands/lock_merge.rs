@@ -0,0 +1,446 @@
+//! lock:merge command - git merge driver for composer.lock.
+//!
+//! Git hands the driver three temporary files: the common ancestor (`%O`),
+//! our version (`%A`, which receives the result) and theirs (`%B`). Package
+//! changes are merged three-way; a package both sides moved to different
+//! versions is settled by keeping the highest of the two that every locked
+//! dependent and the root package still accept and whose own requirements
+//! the merged lock meets. Only if neither fits is that package re-resolved
+//! against the repository, with every other locked package held at its
+//! locked version.
+//!
+//! Register it once per clone with `libretto lock:merge --install`.
+
+use crate::output::{info, success, warning};
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use libretto_lockfile::{ComposerLock, LockMerge, LockedPackage, MergeConflict, merge_locks};
+use libretto_resolver::turbo::{TurboConfig, TurboResolver};
+use libretto_resolver::{
+    ComposerConstraint, ComposerVersion, Dependency, PackageName, Resolution, ResolutionMode,
+    Stability,
+};
+use sonic_rs::{JsonContainerTrait, JsonValueTrait, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Git config name of the merge driver.
+const DRIVER: &str = "libretto-lock";
+
+/// Arguments for the lock:merge command
+#[derive(Args, Debug, Clone)]
+pub struct LockMergeArgs {
+    /// Common ancestor version (git's %O)
+    #[arg(value_name = "BASE", required_unless_present = "install")]
+    pub base: Option<PathBuf>,
+
+    /// Our version; overwritten with the merge result (git's %A)
+    #[arg(value_name = "OURS", required_unless_present = "install")]
+    pub ours: Option<PathBuf>,
+
+    /// Their version (git's %B)
+    #[arg(value_name = "THEIRS", required_unless_present = "install")]
+    pub theirs: Option<PathBuf>,
+
+    /// composer.json used for root constraints and the content-hash
+    #[arg(long, default_value = "composer.json")]
+    pub composer: PathBuf,
+
+    /// Register this command as the merge driver for composer.lock
+    #[arg(long, conflicts_with_all = ["base", "ours", "theirs"])]
+    pub install: bool,
+}
+
+/// Run the lock:merge command
+pub async fn run(args: LockMergeArgs) -> Result<()> {
+    if args.install {
+        return install_driver();
+    }
+    let (Some(base), Some(ours), Some(theirs)) = (&args.base, &args.ours, &args.theirs) else {
+        bail!("BASE, OURS and THEIRS are required");
+    };
+
+    let mut merge = merge_locks(&read_side(base)?, &read_side(ours)?, &read_side(theirs)?);
+    let composer = read_composer(&args.composer)?;
+
+    if merge.content_hash_conflict
+        && let Some(composer) = &composer
+    {
+        merge.lock.content_hash = super::lock_generator::compute_content_hash(composer);
+        merge.content_hash_conflict = false;
+    }
+
+    let root = composer.as_ref().map(root_requirements);
+    for conflict in merge.conflicts.clone() {
+        settle(&mut merge, &conflict, root.as_deref()).await;
+    }
+
+    super::lock_generator::write_lock(ours, &merge.lock)?;
+
+    if !merge.conflicts.is_empty() {
+        let names: Vec<&str> = merge.conflicts.iter().map(|c| c.name.as_str()).collect();
+        bail!(
+            "Could not merge {}; our versions were kept in composer.lock",
+            names.join(", ")
+        );
+    }
+    if merge.content_hash_conflict {
+        bail!(
+            "Both sides changed composer.json and {} could not be read to recompute the content-hash",
+            args.composer.display()
+        );
+    }
+
+    success(&format!(
+        "Merged composer.lock ({} packages)",
+        merge.lock.all_packages().count()
+    ));
+    Ok(())
+}
+
+/// Read one side of the merge; an empty file (no common ancestor) is an
+/// empty lock.
+fn read_side(path: &Path) -> Result<ComposerLock> {
+    let empty = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .len()
+        == 0;
+    if empty {
+        return Ok(ComposerLock::default());
+    }
+    super::lock_generator::read_lock(path)
+}
+
+fn read_composer(path: &Path) -> Result<Option<Value>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)?;
+    let composer = sonic_rs::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(composer))
+}
+
+/// Root `require` and `require-dev` entries.
+fn root_requirements(composer: &Value) -> Vec<(String, String)> {
+    ["require", "require-dev"]
+        .iter()
+        .filter_map(|key| composer.get(key).and_then(|v| v.as_object()))
+        .flat_map(|links| {
+            links.iter().filter_map(|(name, constraint)| {
+                Some((name.to_string(), constraint.as_str()?.to_string()))
+            })
+        })
+        .collect()
+}
+
+/// Constraints the root package and the other locked packages put on `name`.
+fn constraints_on(lock: &ComposerLock, name: &str, root: &[(String, String)]) -> Vec<String> {
+    let locked = lock
+        .all_packages()
+        .filter(|pkg| !pkg.name.eq_ignore_ascii_case(name))
+        .flat_map(|pkg| &pkg.require);
+    root.iter()
+        .map(|(dep, constraint)| (dep, constraint))
+        .chain(locked)
+        .filter(|(dep, _)| dep.eq_ignore_ascii_case(name))
+        .map(|(_, constraint)| constraint.clone())
+        .collect()
+}
+
+/// Whether `version` satisfies every constraint that can be parsed.
+fn satisfies(version: &str, constraints: &[String]) -> bool {
+    let Some(version) = ComposerVersion::parse(version) else {
+        return false;
+    };
+    constraints
+        .iter()
+        .filter_map(|c| ComposerConstraint::parse(c))
+        .all(|c| c.matches(&version))
+}
+
+/// Whether the other packages in `lock` meet every requirement of `pkg`.
+///
+/// Platform requirements are left to install time. A requirement is met by a
+/// locked package of that name in a matching version, or by one that
+/// replaces or provides it.
+fn requirements_met(pkg: &LockedPackage, lock: &ComposerLock) -> bool {
+    pkg.require
+        .iter()
+        .filter(|(dep, _)| !super::install::is_platform_package(dep))
+        .all(|(dep, constraint)| {
+            lock.all_packages()
+                .filter(|other| !other.name.eq_ignore_ascii_case(&pkg.name))
+                .any(|other| {
+                    (other.name.eq_ignore_ascii_case(dep)
+                        && satisfies(&other.version, std::slice::from_ref(constraint)))
+                        || other
+                            .replace
+                            .keys()
+                            .chain(other.provide.keys())
+                            .any(|link| link.eq_ignore_ascii_case(dep))
+                })
+        })
+}
+
+/// Settle one conflict in place, leaving it recorded if nothing fits.
+///
+/// `root` is `None` when composer.json could not be read; a package nothing
+/// else requires is then kept, as the root package may still require it.
+async fn settle(
+    merge: &mut LockMerge,
+    conflict: &MergeConflict,
+    root: Option<&[(String, String)]>,
+) {
+    let name = conflict.name.as_str();
+    let constraints = constraints_on(&merge.lock, name, root.unwrap_or_default());
+
+    let removed_by_one_side = conflict.ours.is_none() || conflict.theirs.is_none();
+    if removed_by_one_side && root.is_some() && constraints.is_empty() {
+        info(&format!("{name}: removed, nothing requires it any more"));
+        merge.resolve(name, None);
+        return;
+    }
+
+    let mut candidates: Vec<&LockedPackage> =
+        conflict.ours.iter().chain(&conflict.theirs).collect();
+    candidates.sort_by_key(|pkg| std::cmp::Reverse(ComposerVersion::parse(&pkg.version)));
+    if let Some(pkg) = candidates
+        .into_iter()
+        .find(|pkg| satisfies(&pkg.version, &constraints) && requirements_met(pkg, &merge.lock))
+    {
+        info(&format!("{name}: using {}", pkg.version));
+        merge.resolve(name, Some(pkg.clone()));
+        return;
+    }
+
+    if constraints.is_empty() {
+        info(&format!(
+            "{name}: neither side's requirements are met by the merged lock, re-resolving"
+        ));
+    } else {
+        info(&format!(
+            "{name}: neither side's version satisfies {} with the merged lock, re-resolving",
+            constraints.join(" and ")
+        ));
+    }
+    match re_resolve(name, &constraints, merge).await {
+        Ok(resolution) => adopt(merge, conflict, &resolution),
+        Err(e) => warning(&format!("{name}: {e:#}")),
+    }
+}
+
+/// Resolve `name` against the repository, with every package of the merged
+/// lock that is not itself in conflict held at its locked version and the
+/// root platform requirements and overrides applied.
+async fn re_resolve(name: &str, constraints: &[String], merge: &LockMerge) -> Result<Resolution> {
+    let lock = &merge.lock;
+    let package = PackageName::parse(name).context("Invalid package name")?;
+    let constraint = constraints
+        .iter()
+        .filter_map(|c| ComposerConstraint::parse(c))
+        .reduce(|a, b| a.intersection(&b))
+        .unwrap_or_else(ComposerConstraint::any);
+
+    let mut deps = vec![Dependency::new(package, constraint)];
+    let conflicting = |pkg: &&LockedPackage| {
+        merge
+            .conflicts
+            .iter()
+            .any(|c| c.name.eq_ignore_ascii_case(&pkg.name))
+    };
+    for pkg in lock.all_packages().filter(|pkg| !conflicting(pkg)) {
+        if let (Some(locked), Some(version)) = (
+            PackageName::parse(&pkg.name),
+            ComposerVersion::parse(&pkg.version),
+        ) {
+            deps.push(Dependency::new(locked, ComposerConstraint::exact(version)));
+        }
+    }
+    for (platform, constraint) in lock.platform.iter().chain(&lock.platform_dev) {
+        if let Some(c) = ComposerConstraint::parse(constraint) {
+            deps.push(Dependency::new(PackageName::platform(platform), c));
+        }
+    }
+    let platform = lock
+        .platform_overrides
+        .iter()
+        .filter_map(|(name, version)| Some((name.clone(), vec![ComposerVersion::parse(version)?])))
+        .collect();
+
+    let fetcher = Arc::new(
+        crate::fetcher::Fetcher::new()
+            .map_err(|e| anyhow::anyhow!("Failed to create fetcher: {e}"))?,
+    );
+    let config = TurboConfig {
+        max_concurrent: 32,
+        request_timeout: std::time::Duration::from_secs(10),
+        mode: if lock.prefer_lowest {
+            ResolutionMode::PreferLowest
+        } else {
+            ResolutionMode::PreferStable
+        },
+        min_stability: Stability::parse(&lock.minimum_stability).unwrap_or(Stability::Stable),
+        include_dev: false,
+        platform,
+        locked: std::collections::BTreeMap::new(),
+    };
+    TurboResolver::new(fetcher, config)
+        .resolve(&deps, &[])
+        .await
+        .map_err(|e| anyhow::anyhow!("Resolution failed: {e}"))
+}
+
+/// Take the re-resolved package, plus any new dependencies it brings.
+///
+/// Packages already locked were held at their versions, so only packages
+/// that are new to the lock are added.
+fn adopt(merge: &mut LockMerge, conflict: &MergeConflict, resolution: &Resolution) {
+    let mut entry = None;
+    let mut added = Vec::new();
+    for pkg in &resolution.packages {
+        if pkg.name.as_str().eq_ignore_ascii_case(&conflict.name) {
+            entry = Some(super::lock_generator::locked_package(pkg));
+        } else if merge.lock.find_package(pkg.name.as_str()).is_none() {
+            added.push(super::lock_generator::locked_package(pkg));
+        }
+    }
+    let Some(entry) = entry else {
+        warning(&format!("{}: not found in the resolution", conflict.name));
+        return;
+    };
+
+    info(&format!("{}: resolved to {}", conflict.name, entry.version));
+    merge.resolve(&conflict.name, Some(entry));
+    let section = if conflict.is_dev {
+        &mut merge.lock.packages_dev
+    } else {
+        &mut merge.lock.packages
+    };
+    section.extend(added);
+    section.sort();
+}
+
+/// Register the driver in git config and `.gitattributes`.
+fn install_driver() -> Result<()> {
+    let git_config = |key: &str, value: &str| -> Result<()> {
+        let status = std::process::Command::new("git")
+            .args(["config", &format!("merge.{DRIVER}.{key}"), value])
+            .status()
+            .context("Failed to run git")?;
+        if !status.success() {
+            bail!("git config failed");
+        }
+        Ok(())
+    };
+    git_config("name", "Libretto composer.lock merge driver")?;
+    git_config("driver", "libretto lock:merge %O %A %B")?;
+
+    let attributes = Path::new(".gitattributes");
+    let line = format!("composer.lock merge={DRIVER}");
+    let existing = std::fs::read_to_string(attributes).unwrap_or_default();
+    if !existing.lines().any(|l| l.trim() == line) {
+        let separator = if existing.is_empty() || existing.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        std::fs::write(attributes, format!("{existing}{separator}{line}\n"))?;
+    }
+
+    success(&format!(
+        "Registered the {DRIVER} merge driver for composer.lock"
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constraints_come_from_root_and_other_packages() {
+        let mut app = LockedPackage::new("acme/app", "1.0.0");
+        app.require
+            .insert("psr/log".to_string(), "^2.0 || ^3.0".to_string());
+        let mut log = LockedPackage::new("psr/log", "3.0.0");
+        log.require.insert("php".to_string(), ">=8.0".to_string());
+        let lock = ComposerLock {
+            packages: vec![app, log],
+            ..ComposerLock::default()
+        };
+        let root = vec![("PSR/log".to_string(), ">=2.0.1".to_string())];
+
+        let constraints = constraints_on(&lock, "psr/log", &root);
+        assert_eq!(constraints, [">=2.0.1", "^2.0 || ^3.0"]);
+        assert!(satisfies("3.0.0", &constraints));
+        assert!(!satisfies("2.0.0", &constraints));
+        assert!(!satisfies("1.1.4", &constraints));
+    }
+
+    fn package(name: &str, version: &str, require: &[(&str, &str)]) -> LockedPackage {
+        let mut pkg = LockedPackage::new(name, version);
+        for (dep, constraint) in require {
+            pkg.require
+                .insert((*dep).to_string(), (*constraint).to_string());
+        }
+        pkg
+    }
+
+    #[tokio::test]
+    async fn candidate_requirements_are_checked_against_the_merged_lock() {
+        let base = ComposerLock {
+            packages: vec![
+                package("acme/bar", "1.5.0", &[]),
+                package("acme/foo", "1.0.0", &[("acme/bar", "^1.0")]),
+            ],
+            ..ComposerLock::default()
+        };
+        let mut ours = base.clone();
+        ours.packages[1] = package("acme/foo", "1.1.0", &[("acme/bar", "^1.0"), ("php", ">=9")]);
+        let mut theirs = base.clone();
+        theirs.packages[1] = package("acme/foo", "2.0.0", &[("acme/bar", "^2.0")]);
+
+        let mut merge = merge_locks(&base, &ours, &theirs);
+        let conflict = merge.conflicts[0].clone();
+        let theirs_foo = conflict.theirs.as_ref().unwrap();
+        assert!(!requirements_met(theirs_foo, &merge.lock));
+        assert!(requirements_met(
+            conflict.ours.as_ref().unwrap(),
+            &merge.lock
+        ));
+
+        let root = [("acme/foo".to_string(), "*".to_string())];
+        settle(&mut merge, &conflict, Some(&root)).await;
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(
+            merge.lock.find_package("acme/foo").unwrap().version,
+            "1.1.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn packages_are_kept_when_composer_json_is_unreadable() {
+        let base = ComposerLock {
+            packages: vec![package("acme/foo", "1.0.0", &[])],
+            ..ComposerLock::default()
+        };
+        let ours = ComposerLock {
+            packages: vec![package("acme/foo", "1.1.0", &[])],
+            ..ComposerLock::default()
+        };
+        let theirs = ComposerLock::default();
+
+        let mut merge = merge_locks(&base, &ours, &theirs);
+        let conflict = merge.conflicts[0].clone();
+        settle(&mut merge, &conflict, None).await;
+        assert_eq!(
+            merge.lock.find_package("acme/foo").unwrap().version,
+            "1.1.0"
+        );
+
+        let mut merge = merge_locks(&base, &ours, &theirs);
+        settle(&mut merge, &conflict, Some(&[])).await;
+        assert!(merge.lock.find_package("acme/foo").is_none());
+    }
+}
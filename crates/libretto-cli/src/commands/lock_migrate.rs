@@ -0,0 +1,115 @@
+//! lock:migrate command - rewrite an old composer.lock in the current format.
+//!
+//! Old locks (Composer 1.x and early 2.x) are already read transparently by
+//! every command; this makes the migration permanent and lists what it
+//! changed. Locked versions and references are never touched.
+
+use crate::output::{info, success, warning};
+use crate::process_lock;
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use libretto_lockfile::migration::MIGRATION_REQUIRED;
+use libretto_lockfile::{ComposerLock, Migrator};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Arguments for the lock:migrate command
+#[derive(Args, Debug, Clone)]
+pub struct LockMigrateArgs {
+    /// Lock file to migrate
+    #[arg(value_name = "FILE", default_value = "composer.lock")]
+    pub lock: PathBuf,
+
+    /// Only report what would change
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(serde::Serialize)]
+struct Report {
+    from: String,
+    to: String,
+    changes: Vec<String>,
+    content_hash_restored: bool,
+    written: bool,
+}
+
+/// Run the lock:migrate command
+pub async fn run(args: LockMigrateArgs) -> Result<()> {
+    let content = std::fs::read_to_string(&args.lock)
+        .with_context(|| format!("Failed to read {}", args.lock.display()))?;
+    let manifest = std::fs::read_to_string(args.lock.with_extension("json")).ok();
+
+    let result = Migrator::new()
+        .migrate_str(&content, manifest.as_deref())
+        .with_context(|| format!("Failed to migrate {}", args.lock.display()))?;
+    let original: ComposerLock = sonic_rs::from_str(&content)?;
+    if versions(&original) != versions(&result.lock) {
+        bail!("Migration would change locked versions; refusing to write");
+    }
+
+    let written = result.has_changes() && !args.dry_run;
+    if written {
+        let _project_lock = process_lock::lock_project(&args.lock.with_file_name("vendor"))?;
+        super::lock_generator::write_lock(&args.lock, &result.lock)?;
+    }
+
+    let report = Report {
+        from: result.from_version.to_string(),
+        to: result.to_version.to_string(),
+        changes: result.changes.iter().map(ToString::to_string).collect(),
+        content_hash_restored: result.lock.content_hash != MIGRATION_REQUIRED,
+        written,
+    };
+    if crate::output::json::is_enabled() {
+        println!("{}", sonic_rs::to_string_pretty(&report)?);
+    } else {
+        print_report(&args, &report);
+    }
+    Ok(())
+}
+
+fn print_report(args: &LockMigrateArgs, report: &Report) {
+    let lock = args.lock.display();
+    if report.changes.is_empty() {
+        success(&format!("{lock} is already in the current format"));
+        return;
+    }
+
+    info(&format!(
+        "{lock}: lock format {} -> {}",
+        report.from, report.to
+    ));
+    for change in &report.changes {
+        println!("  - {change}");
+    }
+
+    if report.written {
+        success(&format!(
+            "Migrated {lock} ({} changes, locked versions unchanged)",
+            report.changes.len()
+        ));
+    } else {
+        info("Dry run: nothing written");
+    }
+    if !report.content_hash_restored {
+        warning(
+            "composer.json changed since this lock was written, so its content-hash could not be \
+             recomputed. Run 'libretto update --lock' to refresh it.",
+        );
+    }
+}
+
+/// Locked name, version and reference of every package.
+fn versions(lock: &ComposerLock) -> BTreeMap<String, (String, Option<String>)> {
+    lock.all_packages()
+        .map(|p| {
+            let reference = p
+                .source
+                .as_ref()
+                .map(|s| s.reference.clone())
+                .or_else(|| p.dist.as_ref().and_then(|d| d.reference.clone()));
+            (p.name.to_lowercase(), (p.version.clone(), reference))
+        })
+        .collect()
+}
@@ -0,0 +1,238 @@
+//! lock:sign command - sign composer.lock for `install --require-signed-lock`.
+//!
+//! The signature is detached, next to the lock as `composer.lock.sig`, and
+//! covers the exact bytes of the file. Libretto signs with Ed25519; a release
+//! pipeline may instead produce an `OpenPGP` signature with
+//! `gpg --detach-sign -o composer.lock.sig composer.lock`.
+//!
+//! Installs verify against the public keys in a trust store directory
+//! (`~/.libretto/trusted-keys` unless `LIBRETTO_TRUST_STORE` says otherwise):
+//! hex-encoded Ed25519 keys in `*.pub` files and `OpenPGP` certificates in
+//! `*.asc`, `*.gpg` or `*.pgp` files.
+
+use crate::output::{info, success};
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use libretto_audit::{Ed25519Signer, SignatureVerifier, VerifiedSignature};
+use std::path::{Path, PathBuf};
+
+/// Arguments for the lock:sign command
+#[derive(Args, Debug, Clone)]
+pub struct LockSignArgs {
+    /// Lock file to sign
+    #[arg(value_name = "FILE", default_value = "composer.lock")]
+    pub lock: PathBuf,
+
+    /// File holding the hex-encoded Ed25519 secret key
+    #[arg(
+        long,
+        value_name = "FILE",
+        env = "LIBRETTO_LOCK_SIGNING_KEY",
+        required_unless_present = "generate_key"
+    )]
+    pub key: Option<PathBuf>,
+
+    /// Generate a key pair: the secret key to FILE, the public key to FILE.pub
+    #[arg(long, value_name = "FILE", conflicts_with = "key")]
+    pub generate_key: Option<PathBuf>,
+}
+
+/// Run the lock:sign command
+pub async fn run(args: LockSignArgs) -> Result<()> {
+    if let Some(path) = &args.generate_key {
+        return generate_key(path);
+    }
+    let Some(key_path) = &args.key else {
+        bail!("--key is required");
+    };
+
+    let secret = read_hex(key_path)?;
+    let signer = Ed25519Signer::from_secret(&secret)
+        .with_context(|| format!("Invalid signing key in {}", key_path.display()))?;
+    let content = std::fs::read(&args.lock)
+        .with_context(|| format!("Failed to read {}", args.lock.display()))?;
+
+    let sig_path = signature_path(&args.lock);
+    std::fs::write(&sig_path, signer.sign(&content))
+        .with_context(|| format!("Failed to write {}", sig_path.display()))?;
+
+    success(&format!(
+        "Signed {} with key {}",
+        args.lock.display(),
+        hex::encode(signer.public_key())
+    ));
+    info(&format!("Signature written to {}", sig_path.display()));
+    Ok(())
+}
+
+/// Path of the detached signature for `lock`.
+pub fn signature_path(lock: &Path) -> PathBuf {
+    let mut path = lock.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+/// Default trust store directory (~/.libretto/trusted-keys).
+pub fn default_trust_store() -> PathBuf {
+    directories::BaseDirs::new().map_or_else(
+        || PathBuf::from(".libretto/trusted-keys"),
+        |d| d.home_dir().join(".libretto").join("trusted-keys"),
+    )
+}
+
+/// Verify the detached signature of `lock` against the keys in `trust_store`.
+///
+/// The lock is read once and the verified bytes are returned with the
+/// signature, so the caller can install from exactly what was checked.
+pub fn verify_lock(lock: &Path, trust_store: &Path) -> Result<(Vec<u8>, VerifiedSignature)> {
+    let sig_path = signature_path(lock);
+    if !sig_path.exists() {
+        bail!(
+            "{} is not signed: {} not found",
+            lock.display(),
+            sig_path.display()
+        );
+    }
+
+    let verifier = load_trust_store(trust_store)?;
+    let content =
+        std::fs::read(lock).with_context(|| format!("Failed to read {}", lock.display()))?;
+    let signature = std::fs::read(&sig_path)
+        .with_context(|| format!("Failed to read {}", sig_path.display()))?;
+
+    let verified = verifier.verify(&content, &signature).map_err(|e| {
+        anyhow::anyhow!(
+            "The signature of {} does not verify against the keys in {}: {e}",
+            lock.display(),
+            trust_store.display()
+        )
+    })?;
+    Ok((content, verified))
+}
+
+/// Load every trusted key in `dir`.
+fn load_trust_store(dir: &Path) -> Result<SignatureVerifier> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read trust store {}", dir.display()))?;
+
+    let mut verifier = SignatureVerifier::new();
+    let mut keys = 0;
+    for entry in entries {
+        let path = entry?.path();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("pub") => {
+                verifier
+                    .add_ed25519_key(&read_hex(&path)?)
+                    .with_context(|| format!("Invalid Ed25519 key in {}", path.display()))?;
+            }
+            Some("asc" | "gpg" | "pgp") => {
+                verifier
+                    .add_pgp_key(&std::fs::read(&path)?)
+                    .with_context(|| format!("Invalid OpenPGP key in {}", path.display()))?;
+            }
+            _ => continue,
+        }
+        keys += 1;
+    }
+
+    if keys == 0 {
+        bail!("No trusted keys found in {}", dir.display());
+    }
+    Ok(verifier)
+}
+
+fn generate_key(path: &Path) -> Result<()> {
+    let public_path = signature_key_pub_path(path);
+    for existing in [path, public_path.as_path()] {
+        if existing.exists() {
+            bail!("{} already exists", existing.display());
+        }
+    }
+
+    let signer = Ed25519Signer::generate();
+    write_secret(path, &hex::encode(signer.secret_bytes()))?;
+    std::fs::write(
+        &public_path,
+        format!("{}\n", hex::encode(signer.public_key())),
+    )
+    .with_context(|| format!("Failed to write {}", public_path.display()))?;
+
+    success(&format!("Generated signing key {}", path.display()));
+    info(&format!(
+        "Copy {} into the trust store of every machine that installs",
+        public_path.display()
+    ));
+    Ok(())
+}
+
+fn signature_key_pub_path(secret: &Path) -> PathBuf {
+    let mut path = secret.as_os_str().to_owned();
+    path.push(".pub");
+    PathBuf::from(path)
+}
+
+/// Write the secret key readable by its owner only.
+fn write_secret(path: &Path, hex_secret: &str) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    writeln!(file, "{hex_secret}")?;
+    Ok(())
+}
+
+fn read_hex(path: &Path) -> Result<Vec<u8>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    hex::decode(content.trim()).with_context(|| format!("{} is not hex-encoded", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_lock_verifies_until_tampered() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("trusted");
+        std::fs::create_dir(&store).unwrap();
+        let lock = dir.path().join("composer.lock");
+        std::fs::write(&lock, "{}\n").unwrap();
+
+        let signer = Ed25519Signer::generate();
+        std::fs::write(store.join("release.pub"), hex::encode(signer.public_key())).unwrap();
+        std::fs::write(signature_path(&lock), signer.sign(b"{}\n")).unwrap();
+        let (content, _) = verify_lock(&lock, &store).unwrap();
+        assert_eq!(content, b"{}\n");
+
+        std::fs::write(&lock, "{\"packages\": []}\n").unwrap();
+        assert!(verify_lock(&lock, &store).is_err());
+    }
+
+    #[test]
+    fn unknown_key_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = dir.path().join("composer.lock");
+        std::fs::write(&lock, "{}\n").unwrap();
+        std::fs::write(
+            signature_path(&lock),
+            Ed25519Signer::generate().sign(b"{}\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("other.pub"),
+            hex::encode(Ed25519Signer::generate().public_key()),
+        )
+        .unwrap();
+
+        assert!(verify_lock(&lock, dir.path()).is_err());
+    }
+}
@@ -0,0 +1,470 @@
+//! lock:why command - explain why a package is in composer.lock.
+//!
+//! Prints every path from the root package to the given package through
+//! the `require` maps of the lock, with the constraint on each edge, and
+//! which requirement bounds its version most tightly. Paths through
+//! `require-dev` of the root are marked; a package only reachable that way
+//! is dev-only and sits in `packages-dev`.
+
+use crate::output::{header, info, success, warning};
+use anyhow::{Result, bail};
+use clap::Args;
+use libretto_lockfile::{ComposerLock, LockedPackage};
+use libretto_resolver::{
+    ComposerConstraint, ComposerVersion, PackageName, Resolution, ResolvedPackage,
+};
+use sonic_rs::{JsonContainerTrait, JsonValueTrait, Value};
+use std::collections::{BTreeSet, HashMap};
+
+/// Paths listed before the walk stops.
+const MAX_PATHS: usize = 200;
+
+/// Arguments for the lock:why command
+#[derive(Args, Debug, Clone)]
+pub struct LockWhyArgs {
+    /// Package to explain
+    #[arg(value_name = "PACKAGE")]
+    pub package: String,
+}
+
+/// One hop of a path: `to` is required by the previous hop (or the root).
+#[derive(Debug, Clone, serde::Serialize)]
+struct Hop {
+    /// Package reached.
+    package: String,
+    /// Locked version of `package`.
+    version: String,
+    /// Name as written in the requirement (differs for provided or replaced names).
+    requires: String,
+    /// Constraint on the edge.
+    constraint: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct WhyPath {
+    /// The root requirement comes from require-dev.
+    dev: bool,
+    hops: Vec<Hop>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Tightest {
+    constraint: String,
+    /// Package whose requirement it is (`null` for the root).
+    required_by: Option<String>,
+    /// Root requirements the bound is reached through.
+    root_requirements: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Report {
+    package: String,
+    version: String,
+    dev_only: bool,
+    paths: Vec<WhyPath>,
+    truncated: bool,
+    tightest: Option<Tightest>,
+}
+
+/// A requirement edge of the lock graph.
+#[derive(Debug, Clone)]
+struct Edge {
+    requires: String,
+    constraint: String,
+}
+
+/// The lock as a dependency graph.
+struct LockGraph<'a> {
+    resolution: Resolution,
+    packages: HashMap<String, &'a LockedPackage>,
+    /// (dependent, dependency) -> requirement.
+    edges: HashMap<(String, String), Edge>,
+    /// Root requirements by the locked package they land on: (dev, edge).
+    root: HashMap<String, Vec<(bool, Edge)>>,
+}
+
+/// Run the lock:why command
+pub async fn run(args: LockWhyArgs) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let lock_path = cwd.join("composer.lock");
+    if !lock_path.exists() {
+        bail!("No composer.lock found. Run 'libretto update' to create one.");
+    }
+    let lock = super::lock_generator::read_lock(&lock_path)?;
+    let composer: Value = match std::fs::read_to_string(cwd.join("composer.json")) {
+        Ok(content) => sonic_rs::from_str(&content)?,
+        Err(_) => Value::new_object(),
+    };
+
+    let graph = LockGraph::new(&lock, &composer);
+    let target = args.package.to_lowercase();
+    let Some(pkg) = graph.packages.get(&target) else {
+        let providers = graph.providers_of(&target);
+        if providers.is_empty() {
+            bail!("{} is not in composer.lock", args.package);
+        }
+        bail!(
+            "{} is not locked itself; it is provided by {}. Run 'libretto lock:why' on that package.",
+            args.package,
+            providers.join(", ")
+        );
+    };
+
+    let report = graph.explain(
+        pkg,
+        lock.packages_dev
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(&target)),
+    );
+    if crate::output::json::is_enabled() {
+        println!("{}", sonic_rs::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+impl<'a> LockGraph<'a> {
+    fn new(lock: &'a ComposerLock, composer: &Value) -> Self {
+        let packages: HashMap<String, &LockedPackage> = lock
+            .all_packages()
+            .map(|p| (p.name.to_lowercase(), p))
+            .collect();
+
+        // Names satisfied by a locked package: its own, and what it replaces or provides
+        let mut satisfied_by: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, pkg) in &packages {
+            satisfied_by
+                .entry(name.clone())
+                .or_default()
+                .push(name.clone());
+            for virtual_name in pkg.replace.keys().chain(pkg.provide.keys()) {
+                satisfied_by
+                    .entry(virtual_name.to_lowercase())
+                    .or_default()
+                    .push(name.clone());
+            }
+        }
+        let targets = |requires: &str| -> Vec<String> {
+            let requires = requires.to_lowercase();
+            if packages.contains_key(&requires) {
+                vec![requires]
+            } else {
+                satisfied_by.get(&requires).cloned().unwrap_or_default()
+            }
+        };
+
+        let mut edges = HashMap::new();
+        let mut resolved = Vec::new();
+        for (name, pkg) in &packages {
+            let mut dependencies = Vec::new();
+            for (requires, constraint) in &pkg.require {
+                for to in targets(requires) {
+                    if let Some(dep) = PackageName::parse(&to) {
+                        dependencies.push(dep);
+                    }
+                    edges.insert(
+                        (name.clone(), to),
+                        Edge {
+                            requires: requires.clone(),
+                            constraint: constraint.clone(),
+                        },
+                    );
+                }
+            }
+            if let Some(package_name) = PackageName::parse(name) {
+                let version = ComposerVersion::parse(&pkg.version)
+                    .unwrap_or_else(|| ComposerVersion::new(0, 0, 0));
+                let is_dev = lock.packages_dev.iter().any(|p| p.name == pkg.name);
+                resolved.push(ResolvedPackage::new(
+                    package_name,
+                    version,
+                    dependencies,
+                    is_dev,
+                ));
+            }
+        }
+
+        let mut root: HashMap<String, Vec<(bool, Edge)>> = HashMap::new();
+        for (section, dev) in [("require", false), ("require-dev", true)] {
+            let Some(requirements) = composer.get(section).and_then(|v| v.as_object()) else {
+                continue;
+            };
+            for (requires, constraint) in requirements {
+                let edge = Edge {
+                    requires: requires.to_string(),
+                    constraint: constraint.as_str().unwrap_or("*").to_string(),
+                };
+                for to in targets(requires) {
+                    root.entry(to).or_default().push((dev, edge.clone()));
+                }
+            }
+        }
+
+        Self {
+            resolution: Resolution::from_packages(resolved),
+            packages,
+            edges,
+            root,
+        }
+    }
+
+    fn providers_of(&self, name: &str) -> Vec<String> {
+        let mut providers: Vec<String> = self
+            .packages
+            .values()
+            .filter(|p| {
+                p.replace
+                    .keys()
+                    .chain(p.provide.keys())
+                    .any(|n| n.eq_ignore_ascii_case(name))
+            })
+            .map(|p| p.name.clone())
+            .collect();
+        providers.sort();
+        providers
+    }
+
+    fn explain(&self, pkg: &LockedPackage, dev_only: bool) -> Report {
+        let target = pkg.name.to_lowercase();
+        let mut paths = Vec::new();
+        let mut chain = vec![target.clone()];
+        self.walk(&mut chain, &mut paths);
+        let truncated = paths.len() >= MAX_PATHS;
+
+        paths.sort_by(|a: &WhyPath, b| {
+            (a.dev, a.hops.len(), &a.hops[0].package).cmp(&(
+                b.dev,
+                b.hops.len(),
+                &b.hops[0].package,
+            ))
+        });
+
+        Report {
+            package: pkg.name.clone(),
+            version: pkg.version.clone(),
+            dev_only,
+            tightest: tightest(&paths),
+            paths,
+            truncated,
+        }
+    }
+
+    /// Depth-first walk from the package towards the root; `chain` holds the
+    /// packages from the current one down to the target.
+    fn walk(&self, chain: &mut Vec<String>, paths: &mut Vec<WhyPath>) {
+        if paths.len() >= MAX_PATHS {
+            return;
+        }
+        let current = chain.last().cloned().unwrap_or_default();
+
+        for (dev, edge) in self.root.get(&current).into_iter().flatten() {
+            let mut hops = vec![self.hop(&chain[chain.len() - 1], edge)];
+            for pair in chain.windows(2).rev() {
+                hops.push(self.hop(&pair[0], &self.edges[&(pair[1].clone(), pair[0].clone())]));
+            }
+            paths.push(WhyPath { dev: *dev, hops });
+        }
+
+        let dependents: BTreeSet<String> = self
+            .resolution
+            .dependents(&current)
+            .into_iter()
+            .map(|p| p.name.as_str().to_string())
+            .collect();
+        for dependent in dependents {
+            if chain.contains(&dependent) {
+                continue;
+            }
+            chain.push(dependent);
+            self.walk(chain, paths);
+            chain.pop();
+        }
+    }
+
+    fn hop(&self, package: &str, edge: &Edge) -> Hop {
+        let locked = self.packages.get(package);
+        Hop {
+            package: locked.map_or_else(|| package.to_string(), |p| p.name.clone()),
+            version: locked.map(|p| p.version.clone()).unwrap_or_default(),
+            requires: edge.requires.clone(),
+            constraint: edge.constraint.clone(),
+        }
+    }
+}
+
+/// The constraint on the package's own edge that every other one contains.
+///
+/// Falls back to the constraint with the highest lower bound when none
+/// contains the others.
+fn tightest(paths: &[WhyPath]) -> Option<Tightest> {
+    // Constraint and requirer of the last hop of each path
+    let mut bounds: Vec<(String, Option<String>)> = paths
+        .iter()
+        .map(|p| {
+            let last = p.hops.last()?;
+            let required_by = (p.hops.len() > 1).then(|| p.hops[p.hops.len() - 2].package.clone());
+            Some((last.constraint.clone(), required_by))
+        })
+        .collect::<Option<_>>()?;
+    bounds.sort();
+    bounds.dedup();
+
+    let parsed: Vec<_> = bounds
+        .iter()
+        .filter_map(|(c, by)| Some((ComposerConstraint::parse(c)?, c, by)))
+        .collect();
+    let (_, constraint, required_by) = parsed
+        .iter()
+        .find(|(c, _, _)| {
+            parsed
+                .iter()
+                .all(|(o, _, _)| c.ranges().subset_of(o.ranges()))
+        })
+        .or_else(|| {
+            parsed.iter().max_by(|(a, _, _), (b, _, _)| {
+                let lower =
+                    |c: &ComposerConstraint| c.ranges().bounding_range().map(|(l, _)| l.cloned());
+                compare_lower(lower(a), lower(b))
+            })
+        })?;
+
+    let root_requirements: BTreeSet<String> = paths
+        .iter()
+        .filter(|p| {
+            let last = p.hops.last();
+            let by = (p.hops.len() > 1).then(|| &p.hops[p.hops.len() - 2].package);
+            last.is_some_and(|l| &&l.constraint == constraint) && by == required_by.as_ref()
+        })
+        .map(|p| format!("{} {}", p.hops[0].requires, p.hops[0].constraint))
+        .collect();
+
+    Some(Tightest {
+        constraint: (*constraint).clone(),
+        required_by: (*required_by).clone(),
+        root_requirements: root_requirements.into_iter().collect(),
+    })
+}
+
+fn compare_lower(
+    a: Option<std::ops::Bound<ComposerVersion>>,
+    b: Option<std::ops::Bound<ComposerVersion>>,
+) -> std::cmp::Ordering {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+    let key = |bound: Option<std::ops::Bound<ComposerVersion>>| match bound {
+        None | Some(Unbounded) => None,
+        Some(Included(v) | Excluded(v)) => Some(v),
+    };
+    key(a).cmp(&key(b))
+}
+
+fn print_report(report: &Report) {
+    header(&format!(
+        "Why is {} {} locked?",
+        report.package, report.version
+    ));
+
+    if report.paths.is_empty() {
+        warning(&format!(
+            "Nothing in composer.json requires {}; 'libretto update' would remove it",
+            report.package
+        ));
+        return;
+    }
+
+    for path in &report.paths {
+        let hops: Vec<String> = path
+            .hops
+            .iter()
+            .map(|hop| {
+                let requires = if hop.requires.eq_ignore_ascii_case(&hop.package) {
+                    String::new()
+                } else {
+                    format!("{} ", hop.requires)
+                };
+                format!(
+                    "{} {} [{requires}{}]",
+                    hop.package, hop.version, hop.constraint
+                )
+            })
+            .collect();
+        let dev = if path.dev { "  (require-dev)" } else { "" };
+        println!("  root → {}{dev}", hops.join(" → "));
+    }
+    if report.truncated {
+        info(&format!("Stopped after {MAX_PATHS} paths"));
+    }
+
+    if report.dev_only {
+        info(&format!("{} is dev-only (packages-dev)", report.package));
+    } else if report.paths.iter().any(|p| p.dev) {
+        info(&format!(
+            "{} is needed in production and also through require-dev",
+            report.package
+        ));
+    }
+
+    if let Some(tightest) = &report.tightest {
+        let by = tightest
+            .required_by
+            .as_deref()
+            .map_or_else(|| "composer.json".to_string(), str::to_string);
+        success(&format!(
+            "Tightest bound: {} (required by {by}; root requirement {})",
+            tightest.constraint,
+            tightest.root_requirements.join(", ")
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, require: &[(&str, &str)]) -> LockedPackage {
+        let mut pkg = LockedPackage::new(name, version);
+        for (dep, constraint) in require {
+            pkg.require
+                .insert((*dep).to_string(), (*constraint).to_string());
+        }
+        pkg
+    }
+
+    #[test]
+    fn paths_and_tightest_bound() {
+        let mut lock = ComposerLock::default();
+        lock.packages
+            .push(package("a/a", "2.1.0", &[("c/c", "^1.0")]));
+        lock.packages
+            .push(package("b/b", "1.0.0", &[("c/c", "^1.2")]));
+        lock.packages.push(package("c/c", "1.3.0", &[]));
+        lock.packages_dev
+            .push(package("d/d", "1.0.0", &[("b/b", "^1.0")]));
+        let composer: Value = sonic_rs::from_str(
+            r#"{"require": {"a/a": "^2.0", "b/b": "^1.0"}, "require-dev": {"d/d": "^1.0"}}"#,
+        )
+        .unwrap();
+
+        let graph = LockGraph::new(&lock, &composer);
+        let report = graph.explain(graph.packages["c/c"], false);
+
+        let paths: Vec<(bool, Vec<&str>)> = report
+            .paths
+            .iter()
+            .map(|p| (p.dev, p.hops.iter().map(|h| h.package.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                (false, vec!["a/a", "c/c"]),
+                (false, vec!["b/b", "c/c"]),
+                (true, vec!["d/d", "b/b", "c/c"]),
+            ]
+        );
+
+        let tightest = report.tightest.unwrap();
+        assert_eq!(tightest.constraint, "^1.2");
+        assert_eq!(tightest.required_by.as_deref(), Some("b/b"));
+        assert_eq!(tightest.root_requirements, vec!["b/b ^1.0", "d/d ^1.0"]);
+    }
+}
@@ -29,7 +29,14 @@ pub mod exec;
 pub mod fund;
 pub mod global;
 pub mod licenses;
+pub mod lock_check;
+pub mod lock_diff;
+pub mod lock_merge;
+pub mod lock_migrate;
+pub mod lock_sign;
+pub mod lock_why;
 pub mod outdated;
+pub mod platform_matrix;
 pub mod prohibits;
 pub mod reinstall;
 pub mod repository;
@@ -37,6 +44,8 @@ pub mod run_script;
 pub mod self_update;
 pub mod status;
 pub mod suggests;
+pub mod verify;
+pub mod which;
 
 use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
@@ -91,6 +100,16 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub no_cache: bool,
 
+    /// Seconds to wait for a project or cache lock held by another process
+    #[arg(
+        long,
+        global = true,
+        env = "LIBRETTO_LOCK_TIMEOUT",
+        value_name = "SECONDS",
+        default_value_t = crate::process_lock::DEFAULT_TIMEOUT_SECS
+    )]
+    pub lock_timeout: u64,
+
     /// Output format (text, json, or table)
     #[arg(long, global = true, value_enum, default_value = "text")]
     pub format: OutputFormat,
@@ -192,6 +211,30 @@ pub enum Commands {
     /// Shows information about licenses of dependencies
     Licenses(licenses::LicensesArgs),
 
+    /// Checks composer.lock against the project's license and source policy
+    #[command(name = "lock:check")]
+    LockCheck(lock_check::LockCheckArgs),
+
+    /// Shows what changed in composer.lock since a git revision or another lock file
+    #[command(name = "lock:diff")]
+    LockDiff(lock_diff::LockDiffArgs),
+
+    /// Merges diverged versions of composer.lock; used as a git merge driver
+    #[command(name = "lock:merge")]
+    LockMerge(lock_merge::LockMergeArgs),
+
+    /// Rewrites an old (Composer 1.x) composer.lock in the current format
+    #[command(name = "lock:migrate")]
+    LockMigrate(lock_migrate::LockMigrateArgs),
+
+    /// Signs composer.lock so installs can require a trusted signature
+    #[command(name = "lock:sign")]
+    LockSign(lock_sign::LockSignArgs),
+
+    /// Shows every dependency path from the root to a locked package
+    #[command(name = "lock:why")]
+    LockWhy(lock_why::LockWhyArgs),
+
     /// Shows a list of locally modified packages
     Outdated(outdated::OutdatedArgs),
 
@@ -241,6 +284,12 @@ pub enum Commands {
 
     /// Validates a composer.json and composer.lock
     Validate(validate::ValidateArgs),
+
+    /// Verifies installed packages against the file manifest recorded at install time
+    Verify(verify::VerifyArgs),
+
+    /// Shows which package and file define a class, interface, trait or enum
+    Which(which::WhichArgs),
 }
 
 /// Output format for commands that support it
@@ -67,8 +67,7 @@ pub async fn run(args: OutdatedArgs) -> Result<()> {
         anyhow::bail!("composer.lock not found - run 'libretto install' first");
     }
 
-    let lock_content = std::fs::read_to_string(&lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+    let lock = super::lock_generator::read_lock(&lock_path)?;
 
     // Get direct dependencies from composer.json
     let mut direct_deps: std::collections::HashSet<String> = std::collections::HashSet::new();
@@ -101,19 +100,16 @@ pub async fn run(args: OutdatedArgs) -> Result<()> {
     // Collect installed packages
     let mut installed: Vec<(String, String, bool)> = Vec::new();
 
-    for (key, is_dev) in [("packages", false), ("packages-dev", true)] {
-        if let Some(packages) = lock.get(key).and_then(|v| v.as_array()) {
-            for pkg in packages {
-                let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                let version = pkg.get("version").and_then(|v| v.as_str()).unwrap_or("");
-
-                if !name.is_empty() && !version.is_empty() {
-                    // Filter by package names if specified
-                    if args.packages.is_empty() || args.packages.iter().any(|p| name.contains(p)) {
-                        // Filter by direct only if requested
-                        if !args.direct || direct_deps.contains(name) {
-                            installed.push((name.to_string(), version.to_string(), is_dev));
-                        }
+    for (packages, is_dev) in [(&lock.packages, false), (&lock.packages_dev, true)] {
+        for pkg in packages {
+            let name = pkg.name.as_str();
+
+            if !pkg.version.is_empty() {
+                // Filter by package names if specified
+                if args.packages.is_empty() || args.packages.iter().any(|p| name.contains(p)) {
+                    // Filter by direct only if requested
+                    if !args.direct || direct_deps.contains(name) {
+                        installed.push((pkg.name.clone(), pkg.version.clone(), is_dev));
                     }
                 }
             }
@@ -0,0 +1,318 @@
+//! `update --platform-matrix` - resolve composer.json for several platforms.
+//!
+//! Each `--platform-matrix php=8.1,8.4` axis lists versions of one platform
+//! package; every combination of the axes is a target. The graph is resolved
+//! once per target and the packages whose versions differ are reported.
+//! With `--matrix-lock` the graph is also resolved for all targets at once,
+//! picking the highest versions every target's platform accepts, and that
+//! lock is written.
+
+use super::install::InstallArgs;
+use crate::output::progress::Spinner;
+use crate::output::table::Table;
+use crate::output::{error, info, success, warning};
+use anyhow::{Result, bail};
+use libretto_lockfile::ComposerLock;
+use libretto_resolver::ComposerVersion;
+use sonic_rs::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Versions of one platform package to resolve for (`php=8.1,8.4`).
+#[derive(Debug, Clone)]
+pub struct PlatformAxis {
+    /// Platform package name.
+    pub name: String,
+    /// Versions as given and parsed.
+    pub versions: Vec<(String, ComposerVersion)>,
+}
+
+/// Parse a `--platform-matrix` value.
+pub fn parse_axis(value: &str) -> Result<PlatformAxis, String> {
+    let (name, versions) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected PACKAGE=VERSION[,VERSION...], got '{value}'"))?;
+    let name = name.trim().to_lowercase();
+    if !super::install::is_platform_package(&name) {
+        return Err(format!(
+            "{name} is not a platform package (php, ext-*, lib-*)"
+        ));
+    }
+    let versions = versions
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            ComposerVersion::parse(v)
+                .map(|parsed| (v.to_string(), parsed))
+                .ok_or_else(|| format!("invalid version '{v}' for {name}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if versions.is_empty() {
+        return Err(format!("no versions given for {name}"));
+    }
+    Ok(PlatformAxis { name, versions })
+}
+
+/// One combination of platform versions.
+struct Target {
+    label: String,
+    platform: BTreeMap<String, Vec<ComposerVersion>>,
+}
+
+#[derive(serde::Serialize)]
+struct TargetReport {
+    platform: BTreeMap<String, String>,
+    packages: BTreeMap<String, String>,
+    error: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct Report {
+    targets: Vec<TargetReport>,
+    divergent: Vec<String>,
+    lock: Option<BTreeMap<String, String>>,
+    written: bool,
+}
+
+/// Resolve for every target, report divergence and optionally write the
+/// lock that satisfies all of them.
+pub async fn run(
+    composer: &Value,
+    lock_path: &Path,
+    axes: &[PlatformAxis],
+    resolve_args: &InstallArgs,
+    matrix_lock: bool,
+    dry_run: bool,
+) -> Result<Option<ComposerLock>> {
+    let targets = targets(axes);
+    let mut reports = Vec::with_capacity(targets.len());
+    for target in &targets {
+        let spinner = Spinner::new(format!("Resolving for {}...", target.label));
+        let resolved = resolve(composer, resolve_args, target.platform.clone()).await;
+        spinner.finish_and_clear();
+        reports.push(TargetReport {
+            platform: target
+                .platform
+                .iter()
+                .map(|(name, versions)| (name.clone(), versions[0].to_string()))
+                .collect(),
+            packages: resolved.as_ref().map(versions).unwrap_or_default(),
+            error: resolved.err().map(|e| format!("{e:#}")),
+        });
+    }
+    let divergent = divergent(&reports);
+
+    let mut lock = None;
+    if matrix_lock {
+        let mut platform: BTreeMap<String, Vec<ComposerVersion>> = BTreeMap::new();
+        for axis in axes {
+            platform.insert(
+                axis.name.clone(),
+                axis.versions.iter().map(|(_, v)| v.clone()).collect(),
+            );
+        }
+        let spinner = Spinner::new("Resolving for all targets...");
+        let resolved = resolve(composer, resolve_args, platform).await;
+        spinner.finish_and_clear();
+        match resolved {
+            Ok(all) => lock = Some(all),
+            Err(e) => {
+                print_report(&targets, &reports, &divergent, None);
+                bail!("No set of versions satisfies every platform target: {e:#}");
+            }
+        }
+    }
+    // A target that failed to resolve fails the command, so nothing is written
+    let failed = reports.iter().filter(|r| r.error.is_some()).count();
+    let written = lock.is_some() && !dry_run && failed == 0;
+
+    if crate::output::json::is_enabled() {
+        let report = Report {
+            targets: reports,
+            divergent,
+            lock: lock.as_ref().map(versions),
+            written,
+        };
+        println!("{}", sonic_rs::to_string_pretty(&report)?);
+    } else {
+        print_report(&targets, &reports, &divergent, lock.as_ref());
+        if let Some(lock) = &lock {
+            if written {
+                success(&format!(
+                    "Writing {} with {} package(s) that satisfy all {} targets",
+                    lock_path.display(),
+                    lock.packages.len() + lock.packages_dev.len(),
+                    targets.len()
+                ));
+            } else if dry_run {
+                warning("Dry run - no changes made");
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} platform target(s) could not be resolved");
+    }
+    Ok(lock.filter(|_| written))
+}
+
+/// Every combination of the axes' versions.
+fn targets(axes: &[PlatformAxis]) -> Vec<Target> {
+    let mut targets = vec![Target {
+        label: String::new(),
+        platform: BTreeMap::new(),
+    }];
+    for axis in axes {
+        targets = targets
+            .into_iter()
+            .flat_map(|target| {
+                axis.versions.iter().map(move |(raw, version)| {
+                    let mut platform = target.platform.clone();
+                    platform.insert(axis.name.clone(), vec![version.clone()]);
+                    let label = if target.label.is_empty() {
+                        format!("{} {raw}", axis.name)
+                    } else {
+                        format!("{}, {} {raw}", target.label, axis.name)
+                    };
+                    Target { label, platform }
+                })
+            })
+            .collect();
+    }
+    targets
+}
+
+async fn resolve(
+    composer: &Value,
+    args: &InstallArgs,
+    platform: BTreeMap<String, Vec<ComposerVersion>>,
+) -> Result<ComposerLock> {
+    let resolution =
+        super::install::resolve_for_platform(composer, args, None, platform, BTreeMap::new())
+            .await?;
+    Ok(resolution.map_or_else(ComposerLock::default, |resolution| {
+        super::lock_generator::lock_from_resolution(&resolution, composer, args.prefer_lowest)
+    }))
+}
+
+/// Locked version of every package, by name.
+fn versions(lock: &ComposerLock) -> BTreeMap<String, String> {
+    lock.all_packages()
+        .map(|pkg| (pkg.name.clone(), pkg.version.clone()))
+        .collect()
+}
+
+/// Packages whose version differs between resolved targets, or that only
+/// some of them need.
+fn divergent(reports: &[TargetReport]) -> Vec<String> {
+    let resolved: Vec<_> = reports.iter().filter(|r| r.error.is_none()).collect();
+    let names: BTreeSet<&String> = resolved.iter().flat_map(|r| r.packages.keys()).collect();
+    names
+        .into_iter()
+        .filter(|name| {
+            let mut versions = resolved.iter().map(|r| r.packages.get(*name));
+            let first = versions.next().flatten();
+            versions.any(|v| v != first)
+        })
+        .cloned()
+        .collect()
+}
+
+fn print_report(
+    targets: &[Target],
+    reports: &[TargetReport],
+    divergent: &[String],
+    lock: Option<&ComposerLock>,
+) {
+    for (target, report) in targets.iter().zip(reports) {
+        if let Some(e) = &report.error {
+            error(&format!("{}: {e}", target.label));
+        }
+    }
+
+    let resolved = reports.iter().filter(|r| r.error.is_none()).count();
+    if divergent.is_empty() {
+        if resolved > 1 {
+            success(&format!(
+                "All {resolved} platform targets resolve to the same versions"
+            ));
+        }
+        return;
+    }
+
+    info(&format!(
+        "{} package(s) differ between platform targets:",
+        divergent.len()
+    ));
+    println!();
+
+    let combined = lock.map(versions);
+    let mut table = Table::new();
+    let mut headers = vec!["Package".to_string()];
+    headers.extend(targets.iter().map(|t| t.label.clone()));
+    if combined.is_some() {
+        headers.push("All targets".to_string());
+    }
+    table.headers(headers);
+
+    for name in divergent {
+        let mut row = vec![comfy_table::Cell::new(name)];
+        for report in reports {
+            row.push(match (&report.error, report.packages.get(name)) {
+                (Some(_), _) => table.error_cell("(failed)"),
+                (None, Some(version)) => comfy_table::Cell::new(version),
+                (None, None) => table.warning_cell("-"),
+            });
+        }
+        if let Some(combined) = &combined {
+            row.push(
+                combined
+                    .get(name)
+                    .map_or_else(|| table.warning_cell("-"), |v| table.success_cell(v)),
+            );
+        }
+        table.styled_row(row);
+    }
+    table.print();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn axes_expand_to_every_combination() {
+        let axes = [
+            parse_axis("php=8.1, 8.4").unwrap(),
+            parse_axis("ext-intl=72.1").unwrap(),
+        ];
+        let labels: Vec<String> = targets(&axes).into_iter().map(|t| t.label).collect();
+        assert_eq!(labels, ["php 8.1, ext-intl 72.1", "php 8.4, ext-intl 72.1"]);
+
+        assert!(parse_axis("acme/lib=1.0").is_err());
+        assert!(parse_axis("php=").is_err());
+        assert!(parse_axis("php").is_err());
+    }
+
+    #[test]
+    fn divergence_ignores_failed_targets() {
+        let report = |packages: &[(&str, &str)], error: Option<&str>| TargetReport {
+            platform: BTreeMap::new(),
+            packages: packages
+                .iter()
+                .map(|(n, v)| ((*n).to_string(), (*v).to_string()))
+                .collect(),
+            error: error.map(ToString::to_string),
+        };
+        let reports = [
+            report(&[("a/a", "1.0.0"), ("b/b", "2.0.0")], None),
+            report(
+                &[("a/a", "1.0.0"), ("b/b", "3.0.0"), ("c/c", "1.0.0")],
+                None,
+            ),
+            report(&[], Some("conflict")),
+        ];
+        assert_eq!(divergent(&reports), ["b/b", "c/c"]);
+    }
+}
@@ -86,48 +86,37 @@ pub async fn run(args: ProhibitsArgs) -> Result<()> {
 
     // Check composer.lock
     if lock_path.exists() {
-        let lock_content = std::fs::read_to_string(&lock_path)?;
-        let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
-
-        for (packages_key, is_dev) in [("packages", false), ("packages-dev", true)] {
-            if let Some(packages) = lock.get(packages_key).and_then(|v| v.as_array()) {
-                for pkg in packages {
-                    let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-
-                    // Check require
-                    if let Some(require) = pkg.get("require").and_then(|v| v.as_object()) {
-                        for (dep_name, constraint) in require {
-                            let constraint_str = constraint.as_str().unwrap_or("*");
-                            constraints.entry(dep_name.to_string()).or_default().push((
-                                name.to_string(),
-                                constraint_str.to_string(),
-                                is_dev,
-                            ));
-                        }
-                    }
-
-                    // Check conflict
-                    if let Some(conflict) = pkg.get("conflict").and_then(|v| v.as_object()) {
-                        for (dep_name, constraint) in conflict {
-                            let constraint_str = constraint.as_str().unwrap_or("*");
-                            constraints.entry(dep_name.to_string()).or_default().push((
-                                format!("{name} (conflict)"),
-                                format!("conflicts with {constraint_str}"),
-                                is_dev,
-                            ));
-                        }
-                    }
-
-                    // Check replace
-                    if let Some(replace) = pkg.get("replace").and_then(|v| v.as_object()) {
-                        for (dep_name, _) in replace {
-                            constraints.entry(dep_name.to_string()).or_default().push((
-                                format!("{name} (replace)"),
-                                "replaced by this package".to_string(),
-                                is_dev,
-                            ));
-                        }
-                    }
+        let lock = super::lock_generator::read_lock(&lock_path)?;
+
+        for (packages, is_dev) in [(&lock.packages, false), (&lock.packages_dev, true)] {
+            for pkg in packages {
+                let name = pkg.name.as_str();
+
+                // Check require
+                for (dep_name, constraint) in &pkg.require {
+                    constraints.entry(dep_name.clone()).or_default().push((
+                        name.to_string(),
+                        constraint.clone(),
+                        is_dev,
+                    ));
+                }
+
+                // Check conflict
+                for (dep_name, constraint) in &pkg.conflict {
+                    constraints.entry(dep_name.clone()).or_default().push((
+                        format!("{name} (conflict)"),
+                        format!("conflicts with {constraint}"),
+                        is_dev,
+                    ));
+                }
+
+                // Check replace
+                for dep_name in pkg.replace.keys() {
+                    constraints.entry(dep_name.clone()).or_default().push((
+                        format!("{name} (replace)"),
+                        "replaced by this package".to_string(),
+                        is_dev,
+                    ));
                 }
             }
         }
@@ -2,7 +2,6 @@
 
 use anyhow::Result;
 use clap::Args;
-use sonic_rs::{JsonContainerTrait, JsonValueTrait};
 
 /// Arguments for the reinstall command
 #[derive(Args, Debug, Clone)]
@@ -46,34 +45,28 @@ pub async fn run(args: ReinstallArgs) -> Result<()> {
         anyhow::bail!("composer.lock not found - run 'libretto install' first");
     }
 
-    let lock_content = std::fs::read_to_string(&lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+    let _project_lock = crate::process_lock::lock_project(&vendor_dir)?;
+
+    let lock = super::lock_generator::read_lock(&lock_path)?;
 
     // Collect packages to reinstall
     let mut packages_to_reinstall: Vec<(String, String, bool)> = Vec::new();
 
-    for (key, is_dev) in [("packages", false), ("packages-dev", true)] {
+    for (packages, is_dev) in [(&lock.packages, false), (&lock.packages_dev, true)] {
         if args.no_dev && is_dev {
             continue;
         }
 
-        if let Some(packages) = lock.get(key).and_then(|v| v.as_array()) {
-            for pkg in packages {
-                let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                let version = pkg.get("version").and_then(|v| v.as_str()).unwrap_or("");
-
-                if !name.is_empty() {
-                    // Filter by package names if specified
-                    if args.packages.is_empty()
-                        || args.packages.iter().any(|p| {
-                            name == p
-                                || name.contains(p)
-                                || p.contains('*') && matches_glob(name, p)
-                        })
-                    {
-                        packages_to_reinstall.push((name.to_string(), version.to_string(), is_dev));
-                    }
-                }
+        for pkg in packages {
+            let name = pkg.name.as_str();
+
+            // Filter by package names if specified
+            if args.packages.is_empty()
+                || args.packages.iter().any(|p| {
+                    name == p || name.contains(p) || p.contains('*') && matches_glob(name, p)
+                })
+            {
+                packages_to_reinstall.push((pkg.name.clone(), pkg.version.clone(), is_dev));
             }
         }
     }
@@ -114,6 +107,7 @@ pub async fn run(args: ReinstallArgs) -> Result<()> {
         optimize_autoloader: false,
         classmap_authoritative: false,
         apcu_autoloader: false,
+        apcu_autoloader_prefix: None,
         no_scripts: false,
         prefer_lowest: false,
         prefer_stable: true,
@@ -123,6 +117,13 @@ pub async fn run(args: ReinstallArgs) -> Result<()> {
         audit: false,
         fail_on_audit: false,
         verify_checksums: false,
+        link_mode: None,
+        max_download_speed: None,
+        mirror: vec![],
+        download_retries: 3,
+        frozen: false,
+        require_signed_lock: false,
+        trust_store: None,
     };
 
     spinner.finish_and_clear();
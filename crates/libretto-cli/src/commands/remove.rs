@@ -3,7 +3,7 @@
 use crate::scripts::{ScriptConfig, ScriptEvent, run_package_scripts};
 use anyhow::Result;
 use clap::Args;
-use sonic_rs::{JsonValueMutTrait, JsonValueTrait};
+use sonic_rs::JsonValueMutTrait;
 
 /// Arguments for the remove command.
 #[derive(Args, Debug, Clone)]
@@ -40,6 +40,8 @@ pub async fn run(args: RemoveArgs) -> Result<()> {
         anyhow::bail!("composer.json not found in current directory");
     }
 
+    let _project_lock = crate::process_lock::lock_project(&vendor_dir)?;
+
     // Read composer.json
     let composer_content = std::fs::read_to_string(&composer_path)?;
     let mut composer: sonic_rs::Value = sonic_rs::from_str(&composer_content)?;
@@ -136,29 +138,16 @@ pub async fn run(args: RemoveArgs) -> Result<()> {
 
         let lock_path = cwd.join("composer.lock");
         if lock_path.exists() {
-            // Read and update lock file
-            let lock_content = std::fs::read_to_string(&lock_path)?;
-            let mut lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+            let mut lock = super::lock_generator::read_lock(&lock_path)?;
 
             // Remove packages from lock file
-            for key in ["packages", "packages-dev"] {
-                if let Some(packages) = lock.get_mut(key).and_then(|v| v.as_array_mut()) {
-                    packages.retain(|pkg| {
-                        let name = pkg.get("name").and_then(|n| n.as_str()).unwrap_or("");
-                        !removed.contains(&name.to_string())
-                    });
-                }
-            }
+            lock.packages.retain(|pkg| !removed.contains(&pkg.name));
+            lock.packages_dev.retain(|pkg| !removed.contains(&pkg.name));
 
             // Update content hash
-            let content_hash =
-                libretto_core::ContentHash::from_bytes(sonic_rs::to_string(&composer)?.as_bytes());
-            if let Some(obj) = lock.as_object_mut() {
-                obj.insert("content-hash", sonic_rs::json!(content_hash.to_hex()));
-            }
+            lock.content_hash = super::lock_generator::compute_content_hash(&composer);
 
-            let output = sonic_rs::to_string_pretty(&lock)?;
-            std::fs::write(&lock_path, format!("{output}\n"))?;
+            super::lock_generator::write_lock(&lock_path, &lock)?;
 
             info("Lock file updated");
         }
@@ -179,7 +168,14 @@ pub async fn run(args: RemoveArgs) -> Result<()> {
             optimize: false,
             classmap_authoritative: false,
             apcu: false,
+            apcu_prefix: None,
             no_scripts: true,
+            no_dev: false,
+            strict_psr: false,
+            strict_ambiguous: false,
+            watch: false,
+            preload: None,
+            index: None,
         };
         crate::commands::dump_autoload::run(dump_args).await?;
     }
@@ -48,6 +48,12 @@ pub async fn run(args: RequireArgs) -> Result<()> {
         anyhow::bail!("composer.json not found. Run 'libretto init' to create one.");
     }
 
+    let _project_lock = if args.dry_run {
+        None
+    } else {
+        Some(crate::process_lock::lock_project(&cwd.join("vendor"))?)
+    };
+
     // Read current composer.json
     let composer_content = std::fs::read_to_string(&composer_path)?;
     let mut composer: sonic_rs::Value = sonic_rs::from_str(&composer_content)?;
@@ -196,8 +202,11 @@ pub async fn run(args: RequireArgs) -> Result<()> {
             dry_run: false,
             root_reqs: false,
             lock: false,
+            no_install: false,
             audit: false,
             fail_on_audit: false,
+            platform_matrix: vec![],
+            matrix_lock: false,
         };
 
         crate::commands::update::run(update_args).await?;
@@ -2,6 +2,7 @@
 
 use anyhow::Result;
 use clap::Args;
+use libretto_lockfile::ComposerLock;
 use sonic_rs::{JsonContainerTrait, JsonValueTrait};
 
 /// Arguments for the show command.
@@ -219,31 +220,23 @@ async fn show_installed(args: &ShowArgs) -> Result<()> {
         return Ok(());
     }
 
-    let lock_content = std::fs::read_to_string(&lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+    let lock = super::lock_generator::read_lock(&lock_path)?;
 
     if args.tree {
         return show_tree(&lock).await;
     }
 
     // Collect packages
-    let mut packages: Vec<(String, String, bool)> = Vec::new();
-
-    if let Some(pkgs) = lock.get("packages").and_then(|v| v.as_array()) {
-        for pkg in pkgs {
-            let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-            let version = pkg.get("version").and_then(|v| v.as_str()).unwrap_or("");
-            packages.push((name.to_string(), version.to_string(), false));
-        }
-    }
-
-    if let Some(pkgs) = lock.get("packages-dev").and_then(|v| v.as_array()) {
-        for pkg in pkgs {
-            let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-            let version = pkg.get("version").and_then(|v| v.as_str()).unwrap_or("");
-            packages.push((name.to_string(), version.to_string(), true));
-        }
-    }
+    let mut packages: Vec<(String, String, bool)> = lock
+        .packages
+        .iter()
+        .map(|pkg| (pkg.name.clone(), pkg.version.clone(), false))
+        .chain(
+            lock.packages_dev
+                .iter()
+                .map(|pkg| (pkg.name.clone(), pkg.version.clone(), true)),
+        )
+        .collect();
 
     if packages.is_empty() {
         info("No packages installed");
@@ -286,7 +279,7 @@ async fn show_installed(args: &ShowArgs) -> Result<()> {
     Ok(())
 }
 
-async fn show_tree(lock: &sonic_rs::Value) -> Result<()> {
+async fn show_tree(lock: &ComposerLock) -> Result<()> {
     use owo_colors::OwoColorize;
     use std::collections::HashMap;
 
@@ -297,23 +290,17 @@ async fn show_tree(lock: &sonic_rs::Value) -> Result<()> {
     let mut deps: HashMap<String, Vec<(String, String)>> = HashMap::new();
     let mut versions: HashMap<String, String> = HashMap::new();
 
-    for key in ["packages", "packages-dev"] {
-        if let Some(packages) = lock.get(key).and_then(|v| v.as_array()) {
-            for pkg in packages {
-                let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                let version = pkg.get("version").and_then(|v| v.as_str()).unwrap_or("");
-
-                versions.insert(name.to_string(), version.to_string());
-
-                if let Some(require) = pkg.get("require").and_then(|v| v.as_object()) {
-                    let pkg_deps: Vec<(String, String)> = require
-                        .iter()
-                        .filter(|(n, _)| !n.starts_with("php") && !n.starts_with("ext-"))
-                        .map(|(n, c)| (n.to_string(), c.as_str().unwrap_or("*").to_string()))
-                        .collect();
-                    deps.insert(name.to_string(), pkg_deps);
-                }
-            }
+    for pkg in lock.all_packages() {
+        versions.insert(pkg.name.clone(), pkg.version.clone());
+
+        if !pkg.require.is_empty() {
+            let pkg_deps: Vec<(String, String)> = pkg
+                .require
+                .iter()
+                .filter(|(n, _)| !n.starts_with("php") && !n.starts_with("ext-"))
+                .map(|(n, c)| (n.clone(), c.clone()))
+                .collect();
+            deps.insert(pkg.name.clone(), pkg_deps);
         }
     }
 
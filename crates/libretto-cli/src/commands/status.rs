@@ -2,7 +2,6 @@
 
 use anyhow::Result;
 use clap::Args;
-use sonic_rs::{JsonContainerTrait, JsonValueTrait};
 
 /// Arguments for the status command
 #[derive(Args, Debug, Clone)]
@@ -31,37 +30,33 @@ pub fn run(args: StatusArgs) -> Result<()> {
         anyhow::bail!("composer.lock not found - run 'libretto install' first");
     }
 
-    let lock_content = std::fs::read_to_string(&lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+    let lock = super::lock_generator::read_lock(&lock_path)?;
 
+    let manifest = crate::vendor_manifest::load(&vendor_dir)?;
     let mut modified_packages: Vec<(String, String, Vec<String>)> = Vec::new();
     let colors = crate::output::colors_enabled();
 
     // Check each package
-    for key in ["packages", "packages-dev"] {
-        if let Some(packages) = lock.get(key).and_then(|v| v.as_array()) {
-            for pkg in packages {
-                let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                let version = pkg.get("version").and_then(|v| v.as_str()).unwrap_or("");
-
-                let pkg_dir = vendor_dir.join(name.replace('/', std::path::MAIN_SEPARATOR_STR));
-                if !pkg_dir.exists() {
-                    continue;
-                }
-
-                // Check if it's a git repo (VCS install)
-                let git_dir = pkg_dir.join(".git");
-                if git_dir.exists() {
-                    let modifications = check_git_modifications(&pkg_dir)?;
-                    if !modifications.is_empty() {
-                        modified_packages.push((
-                            name.to_string(),
-                            version.to_string(),
-                            modifications,
-                        ));
-                    }
-                }
-            }
+    for pkg in lock.all_packages() {
+        let name = pkg.name.as_str();
+
+        let pkg_dir = vendor_dir.join(name.replace('/', std::path::MAIN_SEPARATOR_STR));
+        if !pkg_dir.exists() {
+            continue;
+        }
+
+        // Git clones report their own status; dist installs are
+        // compared against the manifest recorded at install time
+        let git_dir = pkg_dir.join(".git");
+        let modifications = if git_dir.exists() {
+            check_git_modifications(&pkg_dir)?
+        } else if let Some(recorded) = manifest.as_ref().and_then(|m| m.packages.get(name)) {
+            check_manifest_modifications(&std::env::current_dir()?, recorded)
+        } else {
+            Vec::new()
+        };
+        if !modifications.is_empty() {
+            modified_packages.push((pkg.name.clone(), pkg.version.clone(), modifications));
         }
     }
 
@@ -139,3 +134,15 @@ fn check_git_modifications(dir: &std::path::PathBuf) -> Result<Vec<String>> {
 
     Ok(modifications)
 }
+
+/// Report dist-installed package changes in `git status --porcelain` style.
+fn check_manifest_modifications(
+    base_dir: &std::path::Path,
+    recorded: &crate::vendor_manifest::PackageManifest,
+) -> Vec<String> {
+    let drift = crate::vendor_manifest::diff(base_dir, recorded);
+    let modified = drift.modified.iter().map(|f| format!(" M {f}"));
+    let missing = drift.missing.iter().map(|f| format!(" D {f}"));
+    let extra = drift.extra.iter().map(|f| format!("?? {f}"));
+    modified.chain(missing).chain(extra).collect()
+}
@@ -2,7 +2,6 @@
 
 use anyhow::Result;
 use clap::Args;
-use sonic_rs::{JsonContainerTrait, JsonValueTrait};
 use std::collections::HashMap;
 
 /// Arguments for the suggests command
@@ -49,60 +48,41 @@ pub async fn run(args: SuggestsArgs) -> Result<()> {
         anyhow::bail!("composer.lock not found - run 'libretto install' first");
     }
 
-    let lock_content = std::fs::read_to_string(&lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
+    let lock = super::lock_generator::read_lock(&lock_path)?;
 
     // Build set of installed packages
-    let mut installed_packages: std::collections::HashSet<String> =
-        std::collections::HashSet::new();
-
-    for key in ["packages", "packages-dev"] {
-        if let Some(packages) = lock.get(key).and_then(|v| v.as_array()) {
-            for pkg in packages {
-                if let Some(name) = pkg.get("name").and_then(|v| v.as_str()) {
-                    installed_packages.insert(name.to_string());
-                }
-            }
-        }
-    }
+    let installed_packages: std::collections::HashSet<&str> =
+        lock.all_packages().map(|pkg| pkg.name.as_str()).collect();
 
     // Collect suggestions
     let mut suggestions: Vec<Suggestion> = Vec::new();
 
-    for key in ["packages", "packages-dev"] {
-        if let Some(packages) = lock.get(key).and_then(|v| v.as_array()) {
-            for pkg in packages {
-                let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
+    for pkg in lock.all_packages() {
+        let name = pkg.name.as_str();
 
-                // Filter by specified packages
-                if !args.packages.is_empty() && !args.packages.iter().any(|p| name.contains(p)) {
-                    continue;
-                }
+        // Filter by specified packages
+        if !args.packages.is_empty() && !args.packages.iter().any(|p| name.contains(p)) {
+            continue;
+        }
 
-                // Get suggestions
-                if let Some(suggest) = pkg.get("suggest").and_then(|v| v.as_object()) {
-                    for (suggested, reason) in suggest {
-                        let reason_str = reason.as_str().unwrap_or("");
-                        let suggested_str = suggested.to_string();
-                        let is_installed = installed_packages.contains(&suggested_str);
-
-                        // Filter by installed status
-                        if args.installed && !is_installed {
-                            continue;
-                        }
-                        if args.uninstalled && is_installed {
-                            continue;
-                        }
-
-                        suggestions.push(Suggestion {
-                            package: name.to_string(),
-                            suggested: suggested_str,
-                            reason: reason_str.to_string(),
-                            installed: is_installed,
-                        });
-                    }
-                }
+        // Get suggestions
+        for (suggested, reason) in &pkg.suggest {
+            let is_installed = installed_packages.contains(suggested.as_str());
+
+            // Filter by installed status
+            if args.installed && !is_installed {
+                continue;
             }
+            if args.uninstalled && is_installed {
+                continue;
+            }
+
+            suggestions.push(Suggestion {
+                package: name.to_string(),
+                suggested: suggested.clone(),
+                reason: reason.clone(),
+                installed: is_installed,
+            });
         }
     }
 
@@ -4,8 +4,9 @@ use crate::output::{info, success, warning};
 use crate::scripts::{ScriptConfig, run_post_install_scripts, run_pre_install_scripts};
 use anyhow::Result;
 use clap::Args;
-use sonic_rs::{JsonContainerTrait, JsonValueTrait};
-use std::collections::HashMap;
+use libretto_lockfile::ComposerLock;
+use libretto_resolver::ComposerVersion;
+use std::collections::{BTreeMap, HashMap};
 
 /// Arguments for the update command.
 #[derive(Args, Debug, Clone)]
@@ -34,10 +35,14 @@ pub struct UpdateArgs {
     #[arg(long)]
     pub root_reqs: bool,
 
-    /// Lock file only (don't install)
-    #[arg(long)]
+    /// Only refresh the lock file's content-hash and metadata, keeping locked versions
+    #[arg(long, conflicts_with = "packages")]
     pub lock: bool,
 
+    /// Update composer.lock without installing
+    #[arg(long)]
+    pub no_install: bool,
+
     /// Run security audit after update
     #[arg(long)]
     pub audit: bool,
@@ -45,6 +50,20 @@ pub struct UpdateArgs {
     /// Fail update if security vulnerabilities are found
     #[arg(long)]
     pub fail_on_audit: bool,
+
+    /// Resolve once per platform version and report where the results
+    /// differ, e.g. `php=8.1,8.4` (repeat to combine several packages)
+    #[arg(
+        long,
+        value_name = "PKG=VERSIONS",
+        value_parser = super::platform_matrix::parse_axis,
+        conflicts_with_all = ["packages", "lock"]
+    )]
+    pub platform_matrix: Vec<super::platform_matrix::PlatformAxis>,
+
+    /// With --platform-matrix, write a lock whose versions satisfy every target
+    #[arg(long, requires = "platform_matrix")]
+    pub matrix_lock: bool,
 }
 
 /// Run the update command.
@@ -52,7 +71,6 @@ pub async fn run(args: UpdateArgs) -> Result<()> {
     use crate::output::progress::Spinner;
     use crate::output::table::Table;
     use crate::output::{header, info, success, warning};
-    use libretto_repository::Repository;
 
     header("Updating dependencies");
 
@@ -71,6 +89,68 @@ pub async fn run(args: UpdateArgs) -> Result<()> {
         warning("Dry run mode - no changes will be made");
     }
 
+    let _project_lock = if args.dry_run {
+        None
+    } else {
+        Some(crate::process_lock::lock_project(&cwd.join("vendor"))?)
+    };
+
+    if args.lock {
+        return refresh_lock(&composer, &lock_path, &cwd, args.dry_run);
+    }
+
+    let install_args = crate::commands::install::InstallArgs {
+        no_dev: args.no_dev,
+        prefer_dist: true,
+        prefer_source: false,
+        dry_run: false,
+        ignore_platform_reqs: false,
+        ignore_platform_req: vec![],
+        optimize_autoloader: false,
+        classmap_authoritative: false,
+        apcu_autoloader: false,
+        apcu_autoloader_prefix: None,
+        no_scripts: false,
+        prefer_lowest: false,
+        prefer_stable: true,
+        minimum_stability: None,
+        no_progress: false,
+        concurrency: 64,
+        audit: args.audit,
+        fail_on_audit: args.fail_on_audit,
+        verify_checksums: false,
+        link_mode: None,
+        max_download_speed: None,
+        mirror: vec![],
+        download_retries: 3,
+        frozen: false,
+        require_signed_lock: false,
+        trust_store: None,
+    };
+
+    if !args.platform_matrix.is_empty() {
+        let resolve_args = crate::commands::install::InstallArgs {
+            prefer_lowest: args.prefer_lowest,
+            ..install_args.clone()
+        };
+        let lock = super::platform_matrix::run(
+            &composer,
+            &lock_path,
+            &args.platform_matrix,
+            &resolve_args,
+            args.matrix_lock,
+            args.dry_run,
+        )
+        .await?;
+        if let Some(lock) = lock {
+            super::lock_generator::write_lock(&lock_path, &lock)?;
+            if !args.no_install {
+                crate::commands::install::run(install_args).await?;
+            }
+        }
+        return Ok(());
+    }
+
     // Set up script configuration
     let script_config = ScriptConfig {
         working_dir: cwd.clone(),
@@ -79,158 +159,144 @@ pub async fn run(args: UpdateArgs) -> Result<()> {
     };
 
     // Run pre-update-cmd scripts
-    if !args.dry_run {
-        if let Some(result) = run_pre_install_scripts(&composer, &script_config, true)? {
-            if !result.success {
-                warning(&format!(
-                    "Pre-update script warning: {}",
-                    result.error.unwrap_or_default()
-                ));
-            }
-        }
+    if !args.dry_run
+        && let Some(result) = run_pre_install_scripts(&composer, &script_config, true)?
+        && !result.success
+    {
+        warning(&format!(
+            "Pre-update script warning: {}",
+            result.error.unwrap_or_default()
+        ));
     }
 
     // Collect current locked versions
-    let mut current_versions: HashMap<String, String> = HashMap::new();
-    if lock_path.exists() {
-        let lock_content = std::fs::read_to_string(&lock_path)?;
-        let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
-
-        for key in ["packages", "packages-dev"] {
-            if let Some(packages) = lock.get(key).and_then(|v| v.as_array()) {
-                for pkg in packages {
-                    let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                    let version = pkg.get("version").and_then(|v| v.as_str()).unwrap_or("");
-                    current_versions.insert(name.to_string(), version.to_string());
-                }
-            }
-        }
-    }
+    let current = if lock_path.exists() {
+        Some(super::lock_generator::read_lock(&lock_path)?)
+    } else {
+        None
+    };
+    let current_versions: HashMap<&str, &str> = current
+        .iter()
+        .flat_map(ComposerLock::all_packages)
+        .map(|pkg| (pkg.name.as_str(), pkg.version.as_str()))
+        .collect();
+
+    // Packages named on the command line are updated; everything else that
+    // is already locked is pinned to its locked version.
+    let selected = |name: &str| {
+        args.packages.is_empty() || args.packages.iter().any(|p| matches_package(p, name))
+    };
+    let locked: BTreeMap<String, ComposerVersion> = current
+        .iter()
+        .flat_map(ComposerLock::all_packages)
+        .filter(|pkg| !selected(&pkg.name))
+        .filter_map(|pkg| Some((pkg.name.clone(), ComposerVersion::parse(&pkg.version)?)))
+        .collect();
 
-    // Collect requirements
-    let mut requirements: Vec<(String, String, bool)> = Vec::new();
+    // Resolve new versions
+    let spinner = Spinner::new("Resolving dependencies...");
+    let resolve_args = crate::commands::install::InstallArgs {
+        prefer_lowest: args.prefer_lowest,
+        ..install_args.clone()
+    };
+    let resolution = crate::commands::install::resolve_for_platform(
+        &composer,
+        &resolve_args,
+        None,
+        BTreeMap::new(),
+        locked,
+    )
+    .await;
+    spinner.finish_and_clear();
 
-    if let Some(require) = composer.get("require").and_then(|v| v.as_object()) {
-        for (name, constraint) in require {
-            if name.starts_with("php") || name.starts_with("ext-") {
-                continue;
-            }
+    let Some(resolution) = resolution? else {
+        info("No packages to update");
+        return Ok(());
+    };
 
-            // Filter by specified packages
-            if !args.packages.is_empty() && !args.packages.iter().any(|p| name.contains(p.as_str()))
+    let mut lock =
+        super::lock_generator::lock_from_resolution(&resolution, &composer, args.prefer_lowest);
+    if let Some(ref current) = current {
+        // Pinned packages keep their locked entry as it is
+        for pkg in lock.packages.iter_mut().chain(lock.packages_dev.iter_mut()) {
+            if !selected(&pkg.name)
+                && let Some(locked) = current.find_package(&pkg.name)
+                && locked.version == pkg.version
             {
-                continue;
+                *pkg = locked.clone();
             }
-
-            let c = constraint.as_str().unwrap_or("*");
-            requirements.push((name.to_string(), c.to_string(), false));
         }
     }
 
-    if !args.no_dev
-        && let Some(require_dev) = composer.get("require-dev").and_then(|v| v.as_object())
-    {
-        for (name, constraint) in require_dev {
-            if name.starts_with("php") || name.starts_with("ext-") {
-                continue;
-            }
-
-            if !args.packages.is_empty() && !args.packages.iter().any(|p| name.contains(p.as_str()))
-            {
-                continue;
+    let mut updates: Vec<(String, String, String, bool)> = Vec::new(); // name, old, new, is_dev
+    for (packages, is_dev) in [(&lock.packages, false), (&lock.packages_dev, true)] {
+        for pkg in packages {
+            let old_version = current_versions
+                .get(pkg.name.as_str())
+                .map_or_else(|| "(new)".to_string(), ToString::to_string);
+            if old_version != pkg.version {
+                updates.push((pkg.name.clone(), old_version, pkg.version.clone(), is_dev));
             }
-
-            let c = constraint.as_str().unwrap_or("*");
-            requirements.push((name.to_string(), c.to_string(), true));
         }
     }
-
-    if requirements.is_empty() {
-        info("No packages to update");
-        return Ok(());
-    }
-
-    if args.packages.is_empty() {
-        info(&format!("Updating {} package(s)...", requirements.len()));
-    } else {
-        info(&format!(
-            "Updating {} of {} package(s)...",
-            args.packages.len(),
-            requirements.len()
-        ));
-    }
-
-    // Resolve new versions
-    let spinner = Spinner::new("Resolving dependencies...");
-    let repo = Repository::packagist()?;
-    repo.init_packagist().await?;
-
-    let mut updates: Vec<(String, String, String, bool, bool)> = Vec::new(); // name, old, new, is_dev, changed
-
-    for (name, constraint, is_dev) in &requirements {
-        let package_id = match libretto_core::PackageId::parse(name) {
-            Some(id) => id,
-            None => continue,
-        };
-
-        let version_constraint = libretto_core::VersionConstraint::new(constraint);
-
-        match repo.find_version(&package_id, &version_constraint).await {
-            Ok(pkg) => {
-                let old_version = current_versions
-                    .get(name)
-                    .cloned()
-                    .unwrap_or_else(|| "(new)".to_string());
-                let new_version = pkg.version.to_string();
-                let changed = old_version != new_version;
-
-                updates.push((name.clone(), old_version, new_version, *is_dev, changed));
-            }
-            Err(e) => {
-                warning(&format!("Could not resolve {name}: {e}"));
+    if let Some(ref current) = current {
+        for (packages, is_dev) in [(&current.packages, false), (&current.packages_dev, true)] {
+            for pkg in packages {
+                if lock.find_package(&pkg.name).is_none() {
+                    updates.push((
+                        pkg.name.clone(),
+                        pkg.version.clone(),
+                        "(removed)".to_string(),
+                        is_dev,
+                    ));
+                }
             }
         }
     }
 
-    spinner.finish_and_clear();
-
     // Display updates
-    let changed_count = updates.iter().filter(|(_, _, _, _, c)| *c).count();
+    let changed_count = updates.len();
+    let lock_changed = current.as_ref() != Some(&lock);
 
-    if changed_count == 0 {
+    if !lock_changed {
         success("All packages are already at their latest versions");
         return Ok(());
     }
+    if changed_count == 0 {
+        info("No package versions changed, refreshing composer.lock metadata");
+    }
 
-    info(&format!("{changed_count} package(s) will be updated:"));
-    println!();
-
-    let mut table = Table::new();
-    table.headers(["Package", "Current", "New", "Type"]);
+    if changed_count > 0 {
+        info(&format!("{changed_count} package(s) will be updated:"));
+        println!();
 
-    for (name, old, new, is_dev, changed) in &updates {
-        if !*changed {
-            continue;
+        let mut table = Table::new();
+        table.headers(["Package", "Current", "New", "Type"]);
+
+        for (name, old, new, is_dev) in &updates {
+            let pkg_type = if *is_dev { "dev" } else { "prod" };
+
+            let old_cell = if old == "(new)" {
+                table.success_cell(old)
+            } else {
+                table.warning_cell(old)
+            };
+
+            table.styled_row(vec![
+                comfy_table::Cell::new(name),
+                old_cell,
+                if new == "(removed)" {
+                    table.warning_cell(new)
+                } else {
+                    table.success_cell(new)
+                },
+                comfy_table::Cell::new(pkg_type),
+            ]);
         }
 
-        let pkg_type = if *is_dev { "dev" } else { "prod" };
-
-        let old_cell = if old == "(new)" {
-            table.success_cell(old)
-        } else {
-            table.warning_cell(old)
-        };
-
-        table.styled_row(vec![
-            comfy_table::Cell::new(name),
-            old_cell,
-            table.success_cell(new),
-            comfy_table::Cell::new(pkg_type),
-        ]);
+        table.print();
     }
 
-    table.print();
-
     if args.dry_run {
         println!();
         warning("Dry run - no changes made");
@@ -238,72 +304,16 @@ pub async fn run(args: UpdateArgs) -> Result<()> {
     }
 
     // Update lock file
-    if !args.lock {
+    if !args.no_install {
         info("Updating composer.lock...");
     }
 
-    // Generate new lock file
-    let mut packages: Vec<sonic_rs::Value> = Vec::new();
-    let mut packages_dev: Vec<sonic_rs::Value> = Vec::new();
-
-    for (name, _, version, is_dev, _) in &updates {
-        let pkg = sonic_rs::json!({
-            "name": name,
-            "version": version
-        });
-
-        if *is_dev {
-            packages_dev.push(pkg);
-        } else {
-            packages.push(pkg);
-        }
-    }
-
-    let content_hash =
-        libretto_core::ContentHash::from_bytes(sonic_rs::to_string(&composer)?.as_bytes());
-
-    let lock = sonic_rs::json!({
-        "_readme": [
-            "This file locks the dependencies of your project to a known state",
-            "Read more about it at https://getcomposer.org/doc/01-basic-usage.md#installing-dependencies"
-        ],
-        "content-hash": content_hash.to_hex(),
-        "packages": packages,
-        "packages-dev": packages_dev,
-        "aliases": [],
-        "minimum-stability": "stable",
-        "prefer-stable": args.prefer_stable,
-        "prefer-lowest": args.prefer_lowest
-    });
-
-    let output = sonic_rs::to_string_pretty(&lock)?;
-    std::fs::write(&lock_path, format!("{output}\n"))?;
+    super::lock_generator::write_lock(&lock_path, &lock)?;
 
     // Install updated packages
-    if !args.lock {
+    if !args.no_install {
         info("Installing updated packages...");
 
-        let install_args = crate::commands::install::InstallArgs {
-            no_dev: args.no_dev,
-            prefer_dist: true,
-            prefer_source: false,
-            dry_run: false,
-            ignore_platform_reqs: false,
-            ignore_platform_req: vec![],
-            optimize_autoloader: false,
-            classmap_authoritative: false,
-            apcu_autoloader: false,
-            no_scripts: false,
-            prefer_lowest: false,
-            prefer_stable: true,
-            minimum_stability: None,
-            no_progress: false,
-            concurrency: 64,
-            audit: args.audit,
-            fail_on_audit: args.fail_on_audit,
-            verify_checksums: false,
-        };
-
         crate::commands::install::run(install_args).await?;
     } else if args.audit {
         // Run audit on lock file only mode
@@ -325,6 +335,52 @@ pub async fn run(args: UpdateArgs) -> Result<()> {
     Ok(())
 }
 
+/// Whether `name` is selected by a package argument: an exact name, or a
+/// pattern where `*` matches any run of characters (`symfony/*`).
+fn matches_package(pattern: &str, name: &str) -> bool {
+    if !pattern.contains('*') {
+        return pattern.eq_ignore_ascii_case(name);
+    }
+    let pattern = regex::escape(pattern).replace("\\*", ".*");
+    regex::Regex::new(&format!("(?i)^{pattern}$")).is_ok_and(|r| r.is_match(name))
+}
+
+/// `update --lock`: bring the content-hash and root metadata of composer.lock
+/// in line with composer.json without changing any locked version.
+fn refresh_lock(
+    composer: &sonic_rs::Value,
+    lock_path: &std::path::Path,
+    project_dir: &std::path::Path,
+    dry_run: bool,
+) -> Result<()> {
+    if !lock_path.exists() {
+        anyhow::bail!("composer.lock not found - run 'libretto update' to create it");
+    }
+
+    let lock = super::lock_generator::read_lock(lock_path)?;
+    let staleness = crate::stale_lock::check(&lock, composer, project_dir);
+    if !staleness.unresolved().is_empty() {
+        anyhow::bail!("{}", staleness.explain());
+    }
+    if !staleness.is_stale() {
+        success("composer.lock is already up to date");
+        return Ok(());
+    }
+
+    for reason in &staleness.reasons {
+        info(&reason.to_string());
+    }
+    if dry_run {
+        warning("Dry run - no changes made");
+        return Ok(());
+    }
+
+    let refreshed = super::lock_generator::refresh_metadata(&lock, composer);
+    super::lock_generator::write_lock(lock_path, &refreshed)?;
+    success("Refreshed composer.lock; locked versions are unchanged");
+    Ok(())
+}
+
 /// Run security audit on packages in lock file.
 async fn run_security_audit(lock_path: &std::path::Path, fail_on_audit: bool) -> Result<()> {
     use libretto_audit::Auditor;
@@ -337,29 +393,16 @@ async fn run_security_audit(lock_path: &std::path::Path, fail_on_audit: bool) ->
 
     info("Running security audit...");
 
-    let lock_content = std::fs::read_to_string(lock_path)?;
-    let lock: sonic_rs::Value = sonic_rs::from_str(&lock_content)?;
-
-    let mut packages_to_audit: Vec<(PackageId, Version)> = Vec::new();
-
-    for key in ["packages", "packages-dev"] {
-        if let Some(pkgs) = lock.get(key).and_then(|v| v.as_array()) {
-            for pkg in pkgs {
-                let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                let version_str = pkg
-                    .get("version")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .trim_start_matches('v');
-
-                if let Some(id) = PackageId::parse(name)
-                    && let Ok(ver) = Version::parse(version_str)
-                {
-                    packages_to_audit.push((id, ver));
-                }
-            }
-        }
-    }
+    let lock = super::lock_generator::read_lock(lock_path)?;
+
+    let packages_to_audit: Vec<(PackageId, Version)> = lock
+        .all_packages()
+        .filter_map(|pkg| {
+            let id = PackageId::parse(&pkg.name)?;
+            let ver = Version::parse(pkg.version.trim_start_matches('v')).ok()?;
+            Some((id, ver))
+        })
+        .collect();
 
     if packages_to_audit.is_empty() {
         return Ok(());
@@ -408,3 +451,19 @@ async fn run_security_audit(lock_path: &std::path::Path, fail_on_audit: bool) ->
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_arguments_match_exactly_or_by_wildcard() {
+        assert!(matches_package("psr/log", "psr/log"));
+        assert!(matches_package("PSR/Log", "psr/log"));
+        assert!(!matches_package("psr/log", "psr/log-extra"));
+        assert!(matches_package("psr/*", "psr/log-extra"));
+        assert!(matches_package("symfony/*-bundle", "symfony/twig-bundle"));
+        assert!(!matches_package("symfony/*-bundle", "symfony/console"));
+        assert!(!matches_package("psr/log.", "psr/logs"));
+    }
+}
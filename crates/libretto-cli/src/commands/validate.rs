@@ -72,12 +72,18 @@ pub async fn run(args: ValidateArgs) -> Result<()> {
         }
     }
 
-    // Check lock file if requested
-    if args.with_dependencies {
-        let lock_path = Path::new("composer.lock");
-        if !lock_path.exists() {
-            warnings.push("composer.lock not found".to_string());
+    // Check that composer.lock still matches composer.json
+    let lock_path = Path::new("composer.lock");
+    if lock_path.exists() {
+        if let Ok(composer) = sonic_rs::from_str::<sonic_rs::Value>(&content) {
+            let lock = super::lock_generator::read_lock(lock_path)?;
+            let staleness = crate::stale_lock::check(&lock, &composer, Path::new("."));
+            if staleness.is_stale() {
+                warnings.push(staleness.explain());
+            }
         }
+    } else if args.with_dependencies {
+        warnings.push("composer.lock not found".to_string());
     }
 
     // Report results
@@ -0,0 +1,152 @@
+//! Verify command - check the installed vendor tree against the lock.
+
+use crate::vendor_manifest::{self, PackageDrift};
+use anyhow::Result;
+use clap::Args;
+use std::collections::HashSet;
+
+/// Arguments for the verify command
+#[derive(Args, Debug, Clone)]
+pub struct VerifyArgs {
+    /// Only verify these packages
+    #[arg(value_name = "PACKAGES")]
+    pub packages: Vec<String>,
+
+    /// Skip dev packages
+    #[arg(long)]
+    pub no_dev: bool,
+
+    /// List every differing file
+    #[arg(long)]
+    pub files: bool,
+}
+
+/// Problem found for one package.
+enum Finding {
+    /// Locked but not installed.
+    NotInstalled,
+    /// Installed without a recorded manifest.
+    NotRecorded,
+    /// Installed version differs from the lock.
+    VersionMismatch { installed: String },
+    /// Installed but no longer in the lock.
+    NotLocked,
+    /// Files differ from the manifest.
+    Drift(PackageDrift),
+}
+
+/// Run the verify command
+pub fn run(args: &VerifyArgs) -> Result<()> {
+    use crate::output::{header, success, warning};
+    use owo_colors::OwoColorize;
+
+    header("Verifying installed packages");
+
+    let cwd = std::env::current_dir()?;
+    let vendor_dir = cwd.join("vendor");
+    let lock_path = cwd.join("composer.lock");
+
+    if !lock_path.exists() {
+        anyhow::bail!("composer.lock not found - run 'libretto install' first");
+    }
+    let Some(manifest) = vendor_manifest::load(&vendor_dir)? else {
+        anyhow::bail!("No install manifest found - run 'libretto install' to record one");
+    };
+
+    let lock = super::lock_generator::read_lock(&lock_path)?;
+
+    let wanted = |name: &str| args.packages.is_empty() || args.packages.iter().any(|p| p == name);
+
+    let mut findings: Vec<(String, String, Finding)> = Vec::new();
+    let mut locked: HashSet<String> = HashSet::new();
+    let mut checked = 0;
+
+    for (packages, is_dev) in [(&lock.packages, false), (&lock.packages_dev, true)] {
+        for pkg in packages {
+            let name = pkg.name.as_str();
+            let version = pkg.version.as_str();
+            locked.insert(name.to_string());
+            if (is_dev && args.no_dev) || !wanted(name) {
+                continue;
+            }
+            checked += 1;
+
+            let finding = match manifest.packages.get(name) {
+                None => {
+                    let dir = vendor_dir.join(name.replace('/', std::path::MAIN_SEPARATOR_STR));
+                    if dir.exists() {
+                        Finding::NotRecorded
+                    } else {
+                        Finding::NotInstalled
+                    }
+                }
+                Some(recorded) if recorded.version != version => Finding::VersionMismatch {
+                    installed: recorded.version.clone(),
+                },
+                Some(recorded) => {
+                    let drift = vendor_manifest::diff(&cwd, recorded);
+                    if drift.is_clean() {
+                        continue;
+                    }
+                    Finding::Drift(drift)
+                }
+            };
+            findings.push((name.to_string(), version.to_string(), finding));
+        }
+    }
+
+    for (name, recorded) in &manifest.packages {
+        if !locked.contains(name) && wanted(name) {
+            findings.push((name.clone(), recorded.version.clone(), Finding::NotLocked));
+        }
+    }
+
+    if findings.is_empty() {
+        success(&format!(
+            "All {checked} package(s) match the install manifest"
+        ));
+        return Ok(());
+    }
+
+    let colors = crate::output::colors_enabled();
+    for (name, version, finding) in &findings {
+        let summary = match finding {
+            Finding::NotInstalled => "not installed".to_string(),
+            Finding::NotRecorded => "installed without a manifest entry".to_string(),
+            Finding::VersionMismatch { installed } => format!("installed {installed}"),
+            Finding::NotLocked => "installed but not in composer.lock".to_string(),
+            Finding::Drift(drift) => format!(
+                "{} missing, {} extra, {} modified",
+                drift.missing.len(),
+                drift.extra.len(),
+                drift.modified.len()
+            ),
+        };
+        if colors {
+            println!("{} ({}): {}", name.yellow().bold(), version, summary);
+        } else {
+            println!("{name} ({version}): {summary}");
+        }
+
+        if args.files
+            && let Finding::Drift(drift) = finding
+        {
+            for file in &drift.missing {
+                println!("  - {file}");
+            }
+            for file in &drift.extra {
+                println!("  + {file}");
+            }
+            for file in &drift.modified {
+                println!("  M {file}");
+            }
+        }
+    }
+
+    println!();
+    warning("Run 'libretto reinstall <package>' to restore the locked files");
+    anyhow::bail!(
+        "{} package(s) differ from the install manifest",
+        findings.len()
+    );
+}
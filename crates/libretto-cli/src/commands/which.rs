@@ -0,0 +1,119 @@
+//! Which command - find the package and file defining a PHP symbol.
+
+use crate::autoload_packages;
+use anyhow::Result;
+use clap::Args;
+use libretto_autoloader::{AutoloaderGenerator, IndexFormat, OptimizationLevel, SymbolIndex};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Arguments for the which command
+#[derive(Args, Debug, Clone)]
+pub struct WhichArgs {
+    /// Class, interface, trait or enum name (fully qualified or short)
+    #[arg(value_name = "SYMBOL")]
+    pub symbol: String,
+
+    /// Skip autoload-dev and dev packages
+    #[arg(long)]
+    pub no_dev: bool,
+}
+
+/// Run the which command
+pub async fn run(args: WhichArgs) -> Result<()> {
+    use owo_colors::OwoColorize;
+
+    let vendor_dir = PathBuf::from("vendor");
+    let symbols = if let Some(index) = read_index(&vendor_dir, Path::new("composer.lock")) {
+        index.find(&args.symbol).into_iter().cloned().collect()
+    } else {
+        // Scan like an optimized dump so PSR-4/PSR-0 classes are found too
+        let mut generator = AutoloaderGenerator::with_optimization(
+            vendor_dir.clone(),
+            OptimizationLevel::Optimized,
+        );
+        autoload_packages::add_packages(&mut generator, Path::new("."), &vendor_dir, !args.no_dev)?;
+        generator.find_symbol(&args.symbol)
+    };
+
+    if crate::output::json::is_enabled() {
+        println!("{}", sonic_rs::to_string_pretty(&symbols)?);
+    } else {
+        for symbol in &symbols {
+            println!(
+                "{} {} {}",
+                symbol.fqcn.green(),
+                format!("({})", symbol.kind.as_str()).dimmed(),
+                symbol.package.as_deref().unwrap_or("root package").cyan()
+            );
+            println!("  {}:{}", symbol.file, symbol.line);
+        }
+    }
+
+    if symbols.is_empty() {
+        anyhow::bail!("Symbol '{}' is not defined by any package", args.symbol);
+    }
+    Ok(())
+}
+
+/// Read the index written by `dump-autoload --index`.
+///
+/// Returns `None` when there is no index, it cannot be read, or it is older
+/// than `lock_file` and may be missing packages installed since.
+fn read_index(vendor_dir: &Path, lock_file: &Path) -> Option<SymbolIndex> {
+    let lock_modified = std::fs::metadata(lock_file).and_then(|m| m.modified()).ok();
+    [IndexFormat::Rkyv, IndexFormat::Json]
+        .into_iter()
+        .map(|format| {
+            vendor_dir
+                .join("composer")
+                .join(format!("symbols.{}", format.extension()))
+        })
+        .find_map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            if lock_modified.is_some_and(|lock| lock > modified) {
+                debug!("{} is older than the lock, ignoring", path.display());
+                return None;
+            }
+            SymbolIndex::read(&path)
+                .inspect_err(|e| debug!("Ignoring {}: {e}", path.display()))
+                .ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libretto_autoloader::{DefinitionKind, Symbol};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn index_is_used_until_the_lock_is_newer() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vendor = tmp.path().join("vendor");
+        let lock = tmp.path().join("composer.lock");
+        std::fs::write(&lock, "{}").unwrap();
+        assert!(read_index(&vendor, &lock).is_none());
+
+        let index = SymbolIndex::new(vec![Symbol {
+            fqcn: "Acme\\Client".to_string(),
+            kind: DefinitionKind::Class,
+            file: "vendor/acme/lib/src/Client.php".to_string(),
+            line: 3,
+            package: Some("acme/lib".to_string()),
+        }]);
+        index
+            .write(&vendor.join("composer/symbols.json"), IndexFormat::Json)
+            .unwrap();
+        assert_eq!(read_index(&vendor, &lock), Some(index));
+
+        let later = SystemTime::now() + Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(&lock)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(read_index(&vendor, &lock).is_none());
+    }
+}
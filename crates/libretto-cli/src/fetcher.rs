@@ -229,6 +229,11 @@ impl Fetcher {
                         .iter()
                         .map(|(k, v)| (k.clone(), v.clone()))
                         .collect(),
+                    conflict: v
+                        .conflict
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect(),
                     suggest: v
                         .suggest
                         .iter()
@@ -301,6 +306,8 @@ struct PackagistVersion {
     #[serde(default, deserialize_with = "deserialize_deps")]
     provide: HashMap<String, String>,
     #[serde(default, deserialize_with = "deserialize_deps")]
+    conflict: HashMap<String, String>,
+    #[serde(default, deserialize_with = "deserialize_deps")]
     suggest: HashMap<String, String>,
     #[serde(default)]
     dist: Option<PackagistDist>,
@@ -0,0 +1,338 @@
+//! Binary index of an up-to-date install.
+//!
+//! After installing from composer.lock, `install` writes an rkyv snapshot of
+//! the installed packages to `vendor/composer/libretto-lock.idx`, keyed by
+//! the BLAKE3 hashes of composer.lock and composer.json and by the options
+//! that shape vendor. A later install with the same keys skips parsing the
+//! lock, linking packages and dumping the autoloader.
+//!
+//! The snapshot also stamps the modification times of vendor, of every
+//! installed package directory and its parent, of `vendor/autoload.php`, of
+//! the generated `vendor/composer/*.php` files and `installed.json`, and of
+//! the PHP binary, so adding, removing, replacing or editing any of them (by
+//! any tool) invalidates it. Edits inside a package are not detected;
+//! `verify` reports those.
+
+use anyhow::{Context, Result};
+use rkyv::{Archive, Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Index location relative to the vendor directory.
+const INDEX_PATH: &str = "composer/libretto-lock.idx";
+
+/// Magic bytes of the index file.
+const MAGIC: &[u8; 8] = b"LBRTLIDX";
+
+/// Index format version.
+const VERSION: u32 = 1;
+
+/// Whole-second stamps (from filesystems with coarse timestamps) this close
+/// to the snapshot time are not trusted: a change made in the same tick
+/// would leave the modification time unchanged.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Inputs the index is keyed by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexKey {
+    lock_hash: [u8; 32],
+    manifest_hash: [u8; 32],
+    options: String,
+}
+
+impl IndexKey {
+    /// Hash composer.lock and composer.json in `base_dir`. `options`
+    /// describes the install flags that change what ends up in vendor.
+    pub fn read(base_dir: &Path, options: String) -> Result<Self> {
+        let hash = |name: &str| -> Result<[u8; 32]> {
+            let path = base_dir.join(name);
+            let bytes = std::fs::read(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            Ok(*blake3::hash(&bytes).as_bytes())
+        };
+        Ok(Self {
+            lock_hash: hash("composer.lock")?,
+            manifest_hash: hash("composer.json")?,
+            options,
+        })
+    }
+
+    /// Key on `lock` instead of the composer.lock that was read from disk.
+    #[must_use]
+    pub fn with_lock(mut self, lock: &[u8]) -> Self {
+        self.lock_hash = *blake3::hash(lock).as_bytes();
+        self
+    }
+}
+
+/// An installed package recorded in the index.
+#[derive(Debug, Clone, PartialEq, Eq, Archive, Deserialize, Serialize)]
+pub struct IndexedPackage {
+    /// Package name.
+    pub name: String,
+    /// Installed version.
+    pub version: String,
+    /// Whether it comes from `packages-dev`.
+    pub dev: bool,
+    /// Absolute install directory.
+    pub dir: String,
+}
+
+/// Modification time of a path when the index was written.
+#[derive(Debug, Clone, PartialEq, Eq, Archive, Deserialize, Serialize)]
+struct Stamp {
+    path: String,
+    /// Nanoseconds since the Unix epoch.
+    mtime: u64,
+}
+
+/// Snapshot of an install from composer.lock.
+#[derive(Debug, Clone, PartialEq, Eq, Archive, Deserialize, Serialize)]
+pub struct LockIndex {
+    version: u32,
+    lock_hash: [u8; 32],
+    manifest_hash: [u8; 32],
+    options: String,
+    /// Nanoseconds since the Unix epoch.
+    written_at: u64,
+    /// Warning the writing install printed about the lock (e.g. staleness).
+    pub notice: Option<String>,
+    /// Installed packages.
+    pub packages: Vec<IndexedPackage>,
+    stamps: Vec<Stamp>,
+}
+
+/// Path of the index for a vendor directory.
+pub fn index_path(vendor_dir: &Path) -> PathBuf {
+    vendor_dir.join(INDEX_PATH)
+}
+
+/// The index, if it exists, matches `key` and nothing it stamped changed.
+pub fn load(vendor_dir: &Path, key: &IndexKey) -> Option<LockIndex> {
+    let bytes = std::fs::read(index_path(vendor_dir)).ok()?;
+    let archive = bytes.strip_prefix(MAGIC.as_slice())?;
+    let index = rkyv::from_bytes::<LockIndex, rkyv::rancor::Error>(archive).ok()?;
+    (index.version == VERSION
+        && index.lock_hash == key.lock_hash
+        && index.manifest_hash == key.manifest_hash
+        && index.options == key.options
+        && index.is_fresh())
+    .then_some(index)
+}
+
+/// Snapshot `packages` as installed for `key`.
+///
+/// `extra` lists further paths to stamp (such as the PHP binary). Fails if
+/// a package directory or `vendor/autoload.php` is missing.
+///
+/// Run after the autoloader is dumped, so the files it generated in
+/// `vendor/composer` are stamped.
+pub fn write(
+    vendor_dir: &Path,
+    key: &IndexKey,
+    packages: Vec<IndexedPackage>,
+    notice: Option<String>,
+    extra: &[PathBuf],
+) -> Result<()> {
+    // Create vendor/composer before stamping vendor
+    let path = index_path(vendor_dir);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut paths = BTreeSet::from([vendor_dir.to_path_buf(), vendor_dir.join("autoload.php")]);
+    for pkg in &packages {
+        let dir = Path::new(&pkg.dir);
+        paths.insert(dir.to_path_buf());
+        if let Some(parent) = dir.parent() {
+            paths.insert(parent.to_path_buf());
+        }
+    }
+    paths.extend(generated_files(&vendor_dir.join("composer"))?);
+    paths.extend(extra.iter().cloned());
+
+    let stamps = paths
+        .into_iter()
+        .map(|path| {
+            let mtime =
+                modified(&path).with_context(|| format!("{} is missing", path.display()))?;
+            Ok(Stamp {
+                path: path.to_string_lossy().into_owned(),
+                mtime,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let index = LockIndex {
+        version: VERSION,
+        lock_hash: key.lock_hash,
+        manifest_hash: key.manifest_hash,
+        options: key.options.clone(),
+        written_at: nanos(SystemTime::now()),
+        notice,
+        packages,
+        stamps,
+    };
+    let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&index)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize lock index: {e}"))?;
+    let mut output = Vec::with_capacity(MAGIC.len() + bytes.len());
+    output.extend_from_slice(MAGIC);
+    output.extend_from_slice(&bytes);
+
+    std::fs::write(&path, output).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The autoloader files and `installed.json` generated in `composer_dir`.
+fn generated_files(composer_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let entries = std::fs::read_dir(composer_dir)
+        .with_context(|| format!("Failed to read {}", composer_dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let generated =
+            path.extension().is_some_and(|ext| ext == "php") || path.ends_with("installed.json");
+        if generated && path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Remove the index so the next install does the full work.
+pub fn invalidate(vendor_dir: &Path) -> Result<()> {
+    match std::fs::remove_file(index_path(vendor_dir)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).context("Failed to remove the lock index")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The `php` executable on `PATH`, which platform checks run against.
+pub fn php_binary() -> Option<PathBuf> {
+    let name = if cfg!(windows) { "php.exe" } else { "php" };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+        .and_then(|path| path.canonicalize().ok())
+}
+
+impl LockIndex {
+    /// Every stamp is unchanged and precise or old enough to be trusted.
+    fn is_fresh(&self) -> bool {
+        let racy = u64::try_from(RACY_WINDOW.as_nanos()).unwrap_or(u64::MAX);
+        self.stamps.iter().all(|stamp| {
+            modified(Path::new(&stamp.path)).is_some_and(|mtime| {
+                mtime == stamp.mtime
+                    && (mtime % 1_000_000_000 != 0 || mtime.saturating_add(racy) <= self.written_at)
+            })
+        })
+    }
+}
+
+fn modified(path: &Path) -> Option<u64> {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .map(nanos)
+}
+
+fn nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(base: &Path) -> (PathBuf, IndexKey, Vec<IndexedPackage>) {
+        std::fs::write(base.join("composer.json"), "{}").unwrap();
+        std::fs::write(base.join("composer.lock"), r#"{"packages": []}"#).unwrap();
+        let vendor = base.join("vendor");
+        let dir = vendor.join("acme").join("log");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::create_dir_all(vendor.join("composer")).unwrap();
+        std::fs::write(vendor.join("autoload.php"), "<?php").unwrap();
+        for file in ["autoload_real.php", "autoload_static.php", "installed.json"] {
+            std::fs::write(vendor.join("composer").join(file), "").unwrap();
+        }
+
+        let key = IndexKey::read(base, "no-dev=false".to_string()).unwrap();
+        let packages = vec![IndexedPackage {
+            name: "acme/log".to_string(),
+            version: "1.0.0".to_string(),
+            dev: false,
+            dir: dir.to_string_lossy().into_owned(),
+        }];
+        (vendor, key, packages)
+    }
+
+    #[test]
+    fn matching_index_loads_until_inputs_change() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (vendor, key, packages) = fixture(tmp.path());
+        write(&vendor, &key, packages.clone(), None, &[]).unwrap();
+        assert_eq!(load(&vendor, &key).unwrap().packages, packages);
+
+        let other = IndexKey::read(tmp.path(), "no-dev=true".to_string()).unwrap();
+        assert!(load(&vendor, &other).is_none());
+
+        std::fs::write(tmp.path().join("composer.lock"), r#"{"packages": [1]}"#).unwrap();
+        let changed = IndexKey::read(tmp.path(), key.options.clone()).unwrap();
+        assert!(load(&vendor, &changed).is_none());
+    }
+
+    #[test]
+    fn vendor_changes_invalidate() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (vendor, key, packages) = fixture(tmp.path());
+        write(&vendor, &key, packages, None, &[]).unwrap();
+        assert!(load(&vendor, &key).is_some());
+
+        std::fs::remove_dir_all(vendor.join("acme").join("log")).unwrap();
+        assert!(load(&vendor, &key).is_none());
+
+        invalidate(&vendor).unwrap();
+        assert!(!index_path(&vendor).exists());
+        invalidate(&vendor).unwrap();
+    }
+
+    #[test]
+    fn generated_autoload_files_invalidate() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (vendor, key, packages) = fixture(tmp.path());
+        write(&vendor, &key, packages.clone(), None, &[]).unwrap();
+        assert!(load(&vendor, &key).is_some());
+
+        std::fs::remove_file(vendor.join("composer").join("autoload_static.php")).unwrap();
+        assert!(load(&vendor, &key).is_none());
+
+        write(&vendor, &key, packages, None, &[]).unwrap();
+        assert!(load(&vendor, &key).is_some());
+        std::fs::remove_file(vendor.join("composer").join("installed.json")).unwrap();
+        assert!(load(&vendor, &key).is_none());
+    }
+
+    #[test]
+    fn recent_whole_second_stamps_are_not_trusted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (vendor, key, packages) = fixture(tmp.path());
+        let autoload = std::fs::File::options()
+            .write(true)
+            .open(vendor.join("autoload.php"))
+            .unwrap();
+        let second = |offset: u64| {
+            UNIX_EPOCH + Duration::from_secs(nanos(SystemTime::now()) / 1_000_000_000 - offset)
+        };
+
+        autoload.set_modified(second(0)).unwrap();
+        write(&vendor, &key, packages.clone(), None, &[]).unwrap();
+        assert!(load(&vendor, &key).is_none());
+
+        autoload.set_modified(second(10)).unwrap();
+        write(&vendor, &key, packages, None, &[]).unwrap();
+        assert!(load(&vendor, &key).is_some());
+    }
+}
@@ -7,14 +7,19 @@
 #![warn(clippy::all)]
 #![allow(clippy::module_name_repetitions)]
 
+mod autoload_packages;
 mod cas_cache;
 mod commands;
 mod context;
 mod fetcher;
 mod installer_paths;
+mod lock_index;
 mod output;
 mod platform;
+mod process_lock;
 mod scripts;
+mod stale_lock;
+mod vendor_manifest;
 
 use clap::Parser;
 use commands::{Cli, Commands};
@@ -52,6 +57,8 @@ fn main() -> ExitCode {
         output::json::enable();
     }
 
+    process_lock::set_timeout(std::time::Duration::from_secs(cli.lock_timeout));
+
     // Create context
     let ctx = match Context::new(&cli.to_context_args()) {
         Ok(ctx) => ctx,
@@ -82,7 +89,13 @@ fn main() -> ExitCode {
         Ok(code) => code,
         Err(e) => {
             output::json::print_error(&e);
-            ExitCode::FAILURE
+            if e.downcast_ref::<commands::install::LockOutOfDate>()
+                .is_some()
+            {
+                ExitCode::from(commands::install::EXIT_LOCK_OUT_OF_DATE)
+            } else {
+                ExitCode::FAILURE
+            }
         }
     }
 }
@@ -196,6 +209,30 @@ async fn run_command(cli: &Cli, _ctx: &Context) -> anyhow::Result<ExitCode> {
             commands::licenses::run(args.clone()).await?;
             Ok(ExitCode::SUCCESS)
         }
+        Commands::LockCheck(args) => {
+            commands::lock_check::run(args.clone()).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::LockDiff(args) => {
+            commands::lock_diff::run(args.clone()).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::LockMerge(args) => {
+            commands::lock_merge::run(args.clone()).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::LockMigrate(args) => {
+            commands::lock_migrate::run(args.clone()).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::LockSign(args) => {
+            commands::lock_sign::run(args.clone()).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::LockWhy(args) => {
+            commands::lock_why::run(args.clone()).await?;
+            Ok(ExitCode::SUCCESS)
+        }
         Commands::Outdated(args) => {
             commands::outdated::run(args.clone()).await?;
             Ok(ExitCode::SUCCESS)
@@ -224,6 +261,14 @@ async fn run_command(cli: &Cli, _ctx: &Context) -> anyhow::Result<ExitCode> {
             commands::status::run(args.clone())?;
             Ok(ExitCode::SUCCESS)
         }
+        Commands::Verify(args) => {
+            commands::verify::run(args)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Which(args) => {
+            commands::which::run(args.clone()).await?;
+            Ok(ExitCode::SUCCESS)
+        }
         Commands::Suggests(args) => {
             commands::suggests::run(args.clone()).await?;
             Ok(ExitCode::SUCCESS)
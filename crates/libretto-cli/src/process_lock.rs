@@ -0,0 +1,266 @@
+//! Cross-process advisory locking.
+//!
+//! Two `libretto` processes working on the same project (an IDE and a
+//! terminal, or parallel CI jobs sharing `~/.libretto`) must not mutate the
+//! vendor directory, the lock file or a cache entry at the same time.
+//!
+//! - [`lock_project`] takes an exclusive lock per project, held for the whole
+//!   vendor/lock mutation.
+//! - [`lock_cache_entry`] takes an exclusive lock per CAS cache entry while it
+//!   is being written.
+//!
+//! Locks are `flock`-style advisory locks (via `fs2`), so they are released
+//! by the OS when the holder exits, even if it crashes. The holder writes its
+//! PID into the lock file so waiters can report who they are waiting for.
+//!
+//! The project lock is reentrant within a process: nested commands (e.g.
+//! `update` calling `install`) share the lock already held, and it is
+//! released when the last guard drops. Cache entry locks are not reentrant,
+//! so threads and tasks of one process exclude each other as separate
+//! processes do.
+
+use crate::output::warning;
+use anyhow::{Context, Result, bail};
+use fs2::FileExt;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Weak};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Default time to wait for a lock held by another process.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// Name of the project lock file inside the vendor directory.
+const PROJECT_LOCK_FILE: &str = ".libretto.lock";
+
+/// Poll interval while waiting for a lock.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+static TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_TIMEOUT_SECS);
+
+/// Reentrant lock files currently held by this process.
+static HELD: LazyLock<Mutex<HashMap<PathBuf, Weak<File>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Set how long to wait for a lock held by another process.
+///
+/// A timeout of zero fails immediately if the lock is taken.
+pub fn set_timeout(timeout: Duration) {
+    TIMEOUT_SECS.store(timeout.as_secs(), Ordering::Relaxed);
+}
+
+/// Current lock wait timeout.
+pub fn timeout() -> Duration {
+    Duration::from_secs(TIMEOUT_SECS.load(Ordering::Relaxed))
+}
+
+/// An acquired advisory lock, released on drop.
+#[derive(Debug)]
+pub struct ProcessLock {
+    path: PathBuf,
+    /// Locked file, shared by the guards of a reentrant lock.
+    file: Arc<File>,
+    reentrant: bool,
+}
+
+impl Drop for ProcessLock {
+    fn drop(&mut self) {
+        let mut held = self.reentrant.then(|| HELD.lock());
+        if Arc::strong_count(&self.file) > 1 {
+            return;
+        }
+        // Clear the PID so stale readers don't report a finished process.
+        let _ = self.file.set_len(0);
+        let _ = FileExt::unlock(&*self.file);
+        if let Some(held) = held.as_mut() {
+            held.remove(&self.path);
+        }
+        debug!(path = %self.path.display(), "Released lock");
+    }
+}
+
+/// Acquire the exclusive project lock for vendor and lock file mutation.
+///
+/// The lock file lives at `<vendor_dir>/.libretto.lock`; the vendor
+/// directory is created if needed.
+pub fn lock_project(vendor_dir: &Path) -> Result<ProcessLock> {
+    let path = vendor_dir.join(PROJECT_LOCK_FILE);
+    let path = std::path::absolute(&path).unwrap_or(path);
+
+    if let Some(file) = HELD.lock().get(&path).and_then(Weak::upgrade) {
+        return Ok(ProcessLock {
+            path,
+            file,
+            reentrant: true,
+        });
+    }
+
+    let mut lock = acquire(&path, "project", timeout())?;
+    lock.reentrant = true;
+    HELD.lock()
+        .insert(lock.path.clone(), Arc::downgrade(&lock.file));
+    Ok(lock)
+}
+
+/// Acquire the exclusive lock for a single cache entry.
+///
+/// The lock file is a sibling of the entry (`<entry>.lock`), so it survives
+/// the entry being replaced.
+pub fn lock_cache_entry(entry: &Path) -> Result<ProcessLock> {
+    let mut name = entry.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    acquire(&entry.with_file_name(name), "cache entry", timeout())
+}
+
+/// Acquire an exclusive lock on `path`, waiting up to `timeout`.
+fn acquire(path: &Path, what: &str, timeout: Duration) -> Result<ProcessLock> {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+    let start = Instant::now();
+    let mut announced = false;
+    loop {
+        match FileExt::try_lock_exclusive(&file) {
+            Ok(()) => break,
+            Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+                if !announced {
+                    announced = true;
+                    let holder = read_holder(&mut file)
+                        .map_or_else(String::new, |pid| format!(" held by PID {pid}"));
+                    warning(&format!(
+                        "Waiting for {what} lock{holder} ({})...",
+                        path.display()
+                    ));
+                }
+                if start.elapsed() >= timeout {
+                    bail!(
+                        "Timed out after {}s waiting for {what} lock {}",
+                        timeout.as_secs(),
+                        path.display()
+                    );
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+    }
+
+    write_holder(&mut file)?;
+    debug!(path = %path.display(), waited_ms = start.elapsed().as_millis(), "Acquired {what} lock");
+
+    Ok(ProcessLock {
+        path,
+        file: Arc::new(file),
+        reentrant: false,
+    })
+}
+
+/// Read the PID recorded by the current holder.
+fn read_holder(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+/// Record this process as the holder.
+fn write_holder(file: &mut File) -> Result<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    writeln!(file, "{}", std::process::id())?;
+    file.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn project_lock_is_reentrant_until_the_last_guard_drops() {
+        let dir = TempDir::new().unwrap();
+        let outer = lock_project(dir.path()).unwrap();
+        let inner = lock_project(dir.path()).unwrap();
+        assert!(Arc::ptr_eq(&outer.file, &inner.file));
+
+        // The inner guard keeps the lock when the outer one drops first
+        let path = outer.path.clone();
+        drop(outer);
+        assert!(acquire(&path, "project", Duration::ZERO).is_err());
+        drop(inner);
+        assert!(!HELD.lock().contains_key(&path));
+        assert!(acquire(&path, "project", Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn cache_entry_lock_excludes_other_threads() {
+        let dir = TempDir::new().unwrap();
+        let entry = dir.path().join("abcdef");
+        let lock = lock_cache_entry(&entry).unwrap();
+        let path = lock.path.clone();
+
+        let contended = std::thread::spawn(move || acquire(&path, "cache entry", Duration::ZERO));
+        assert!(contended.join().unwrap().is_err());
+        drop(lock);
+        assert!(lock_cache_entry(&entry).is_ok());
+    }
+
+    #[test]
+    fn lock_records_pid() {
+        let dir = TempDir::new().unwrap();
+        let lock = lock_project(dir.path()).unwrap();
+        let content = fs::read_to_string(&lock.path).unwrap();
+        assert_eq!(content.trim(), std::process::id().to_string());
+    }
+
+    #[test]
+    fn contended_lock_times_out() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("entry.lock");
+
+        // A second open file description contends with the first, as a
+        // different process would.
+        let other = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .unwrap();
+        FileExt::lock_exclusive(&other).unwrap();
+
+        let err = acquire(&path, "cache entry", Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("Timed out"));
+
+        FileExt::unlock(&other).unwrap();
+        assert!(acquire(&path, "cache entry", Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn cache_entry_lock_is_sibling() {
+        let dir = TempDir::new().unwrap();
+        let entry = dir.path().join("abcdef");
+        let lock = lock_cache_entry(&entry).unwrap();
+        assert_eq!(lock.path.file_name().unwrap(), "abcdef.lock");
+    }
+}
@@ -0,0 +1,405 @@
+//! Explaining why composer.lock no longer matches composer.json.
+//!
+//! The content-hash only says *that* composer.json changed. To say what
+//! changed, the root requirements are checked against the locked packages,
+//! the settings and platform requirements the lock records are compared with
+//! composer.json, and - in a git checkout - composer.json as of the last
+//! commit that touched composer.lock is diffed against the current one,
+//! provided its hash is the one the lock records.
+
+use crate::commands::install::is_platform_package;
+use libretto_lockfile::{ComposerLock, ContentHasher, LockGenerator};
+use libretto_resolver::{ComposerConstraint, ComposerVersion};
+use sonic_rs::{JsonContainerTrait, JsonValueTrait, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Hashed composer.json keys that are reported as a whole when they change.
+const OTHER_HASHED_KEYS: [&str; 7] = [
+    "name",
+    "version",
+    "conflict",
+    "replace",
+    "provide",
+    "repositories",
+    "extra",
+];
+
+/// One thing that makes the lock stale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StaleReason {
+    /// A root requirement was added, removed or changed since the lock was written.
+    RequirementChanged {
+        section: &'static str,
+        name: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// A root setting differs from the one the lock was written with.
+    SettingChanged {
+        setting: &'static str,
+        locked: String,
+        current: String,
+    },
+    /// Another content-hash relevant key of composer.json changed.
+    FieldChanged(&'static str),
+    /// A required package is not in the lock.
+    NotLocked { name: String, constraint: String },
+    /// The locked version does not satisfy the root constraint.
+    ConstraintMismatch {
+        name: String,
+        constraint: String,
+        locked: String,
+    },
+}
+
+impl fmt::Display for StaleReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequirementChanged {
+                section,
+                name,
+                from,
+                to,
+            } => match (from, to) {
+                (Some(from), Some(to)) => {
+                    write!(f, "{section}: {name} changed from {from} to {to}")
+                }
+                (None, Some(to)) => write!(f, "{section}: {name} {to} was added"),
+                (Some(from), None) => write!(f, "{section}: {name} {from} was removed"),
+                (None, None) => write!(f, "{section}: {name} changed"),
+            },
+            Self::SettingChanged {
+                setting,
+                locked,
+                current,
+            } => write!(
+                f,
+                "{setting} is {current}, but the lock was written with {locked}"
+            ),
+            Self::FieldChanged(key) => write!(f, "\"{key}\" changed"),
+            Self::NotLocked { name, constraint } => {
+                write!(f, "{name} ({constraint}) is required but not locked")
+            }
+            Self::ConstraintMismatch {
+                name,
+                constraint,
+                locked,
+            } => write!(
+                f,
+                "{name} is locked at {locked}, which does not satisfy {constraint}"
+            ),
+        }
+    }
+}
+
+/// How composer.lock compares with composer.json.
+#[derive(Debug, Clone, Default)]
+pub struct Staleness {
+    /// Whether the lock's content-hash matches composer.json.
+    pub hash_matches: bool,
+    /// What changed, as far as it can be told.
+    pub reasons: Vec<StaleReason>,
+}
+
+impl Staleness {
+    /// Whether the lock is out of date in any way.
+    pub const fn is_stale(&self) -> bool {
+        !self.hash_matches || !self.reasons.is_empty()
+    }
+
+    /// Packages that need resolving; refreshing the hash is not enough.
+    pub fn unresolved(&self) -> Vec<&str> {
+        self.reasons
+            .iter()
+            .filter_map(|reason| match reason {
+                StaleReason::NotLocked { name, .. }
+                | StaleReason::ConstraintMismatch { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Multi-line explanation ending in what to run.
+    pub fn explain(&self) -> String {
+        let mut lines = vec!["composer.lock is not up to date with composer.json:".to_string()];
+        if self.reasons.is_empty() {
+            lines.push(
+                "  - the content-hash differs, but no changed requirement could be pinned down"
+                    .to_string(),
+            );
+        }
+        lines.extend(self.reasons.iter().map(|reason| format!("  - {reason}")));
+
+        let unresolved = self.unresolved();
+        lines.push(if unresolved.is_empty() {
+            "Run 'libretto update --lock' to refresh the content-hash without changing versions."
+                .to_string()
+        } else {
+            format!(
+                "Run 'libretto update {}' to lock versions that match.",
+                unresolved.join(" ")
+            )
+        });
+        lines.join("\n")
+    }
+}
+
+/// Compare `lock` with `composer`; `project_dir` is searched for git history.
+pub fn check(lock: &ComposerLock, composer: &Value, project_dir: &Path) -> Staleness {
+    let hash_matches = lock.content_hash == ContentHasher::composer_content_hash(composer);
+
+    let mut reasons = Vec::new();
+    if !hash_matches {
+        reasons.extend(setting_changes(lock, composer));
+        if let Some(previous) = locked_manifest(lock, project_dir) {
+            reasons.extend(manifest_changes(&previous, composer));
+        }
+    }
+    reasons.extend(unsatisfied(lock, composer));
+
+    Staleness {
+        hash_matches,
+        reasons,
+    }
+}
+
+/// Settings and platform requirements that differ from the ones the lock
+/// records.
+fn setting_changes(lock: &ComposerLock, composer: &Value) -> Vec<StaleReason> {
+    let current = LockGenerator::from_manifest(composer).build();
+    let mut reasons = Vec::new();
+
+    if current.minimum_stability != lock.minimum_stability {
+        reasons.push(StaleReason::SettingChanged {
+            setting: "minimum-stability",
+            locked: lock.minimum_stability.clone(),
+            current: current.minimum_stability,
+        });
+    }
+    if current.prefer_stable != lock.prefer_stable {
+        reasons.push(StaleReason::SettingChanged {
+            setting: "prefer-stable",
+            locked: lock.prefer_stable.to_string(),
+            current: current.prefer_stable.to_string(),
+        });
+    }
+
+    let maps = [
+        ("require", &lock.platform, &current.platform),
+        ("require-dev", &lock.platform_dev, &current.platform_dev),
+        (
+            "config.platform",
+            &lock.platform_overrides,
+            &current.platform_overrides,
+        ),
+    ];
+    for (section, locked, current) in maps {
+        let locked: BTreeMap<_, _> = locked.iter().collect();
+        let current: BTreeMap<_, _> = current.iter().collect();
+        reasons.extend(map_changes(section, &locked, &current));
+    }
+    reasons
+}
+
+/// composer.json as of the last commit that touched composer.lock, if its
+/// content-hash is the one the lock records.
+fn locked_manifest(lock: &ComposerLock, project_dir: &Path) -> Option<Value> {
+    let git = |args: &[&str]| -> Option<String> {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(project_dir)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8(output.stdout).ok()
+    };
+
+    let commit = git(&["log", "-1", "--format=%H", "--", "composer.lock"])?;
+    let commit = commit.trim();
+    if commit.is_empty() {
+        return None;
+    }
+    let content = git(&["show", &format!("{commit}:./composer.json")])?;
+    let manifest: Value = sonic_rs::from_str(&content).ok()?;
+    (ContentHasher::composer_content_hash(&manifest) == lock.content_hash).then_some(manifest)
+}
+
+/// Package requirements and other hashed keys that differ between the
+/// composer.json the lock was written from and the current one. Platform
+/// requirements and settings are covered by [`setting_changes`].
+fn manifest_changes(previous: &Value, current: &Value) -> Vec<StaleReason> {
+    let mut reasons = Vec::new();
+    for section in ["require", "require-dev"] {
+        let links = |manifest: &Value| -> BTreeMap<String, String> {
+            manifest
+                .get(section)
+                .and_then(|v| v.as_object())
+                .into_iter()
+                .flat_map(|obj| obj.iter())
+                .filter(|(name, _)| !is_platform_package(&name.to_lowercase()))
+                .filter_map(|(name, c)| Some((name.to_lowercase(), c.as_str()?.to_string())))
+                .collect()
+        };
+        let (before, after) = (links(previous), links(current));
+        let before: BTreeMap<_, _> = before.iter().collect();
+        let after: BTreeMap<_, _> = after.iter().collect();
+        reasons.extend(map_changes(section, &before, &after));
+    }
+    for key in OTHER_HASHED_KEYS {
+        if previous.get(key) != current.get(key) {
+            reasons.push(StaleReason::FieldChanged(key));
+        }
+    }
+    reasons
+}
+
+/// Added, removed and changed entries between two requirement maps.
+fn map_changes(
+    section: &'static str,
+    before: &BTreeMap<&String, &String>,
+    after: &BTreeMap<&String, &String>,
+) -> Vec<StaleReason> {
+    let names: std::collections::BTreeSet<&String> =
+        before.keys().chain(after.keys()).copied().collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (from, to) = (before.get(name), after.get(name));
+            (from != to).then(|| StaleReason::RequirementChanged {
+                section,
+                name: name.clone(),
+                from: from.map(|c| (*c).clone()),
+                to: to.map(|c| (*c).clone()),
+            })
+        })
+        .collect()
+}
+
+/// Root requirements the locked packages do not satisfy.
+fn unsatisfied(lock: &ComposerLock, composer: &Value) -> Vec<StaleReason> {
+    let mut reasons = Vec::new();
+    for section in ["require", "require-dev"] {
+        let Some(links) = composer.get(section).and_then(|v| v.as_object()) else {
+            continue;
+        };
+        for (name, constraint) in links {
+            let Some(constraint) = constraint.as_str() else {
+                continue;
+            };
+            if is_platform_package(&name.to_lowercase()) {
+                continue;
+            }
+
+            let Some(pkg) = lock.find_package(name) else {
+                let provided = lock.all_packages().any(|pkg| {
+                    pkg.replace
+                        .keys()
+                        .chain(pkg.provide.keys())
+                        .any(|n| n.eq_ignore_ascii_case(name))
+                });
+                if !provided {
+                    reasons.push(StaleReason::NotLocked {
+                        name: name.to_string(),
+                        constraint: constraint.to_string(),
+                    });
+                }
+                continue;
+            };
+
+            let aliases = lock
+                .aliases
+                .iter()
+                .filter(|a| a.package.eq_ignore_ascii_case(name))
+                .map(|a| a.alias.as_str());
+            let versions: Vec<&str> = std::iter::once(pkg.version.as_str())
+                .chain(aliases)
+                .collect();
+            if !satisfied(constraint, &versions) {
+                reasons.push(StaleReason::ConstraintMismatch {
+                    name: pkg.name.clone(),
+                    constraint: constraint.to_string(),
+                    locked: pkg.version.clone(),
+                });
+            }
+        }
+    }
+    reasons
+}
+
+/// Whether any of `versions` satisfies `constraint`; constraints that cannot
+/// be parsed are given the benefit of the doubt.
+fn satisfied(constraint: &str, versions: &[&str]) -> bool {
+    let Some(parsed) = ComposerConstraint::parse(constraint) else {
+        return true;
+    };
+    versions.iter().any(|version| {
+        *version == constraint
+            || ComposerVersion::parse(version).is_some_and(|v| parsed.matches(&v))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libretto_lockfile::LockedPackage;
+
+    fn manifest(json: &str) -> Value {
+        sonic_rs::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn explains_requirement_and_setting_changes() {
+        let old = manifest(
+            r#"{"require":{"php":">=8.1","a/a":"^1.0","b/b":"^2.0"},"minimum-stability":"stable"}"#,
+        );
+        let new = manifest(
+            r#"{"require":{"php":">=8.2","a/a":"^2.0","c/c":"^1.0"},"minimum-stability":"dev","extra":{"x":1}}"#,
+        );
+        let mut lock = LockedPackage::new("a/a", "1.4.0");
+        lock.require.insert("php".to_string(), ">=8.0".to_string());
+        let mut generator = LockGenerator::from_manifest(&old);
+        generator.add_package(lock);
+        generator.add_package(LockedPackage::new("b/b", "2.1.0"));
+        let lock = generator.build();
+
+        let mut reasons = setting_changes(&lock, &new);
+        reasons.extend(manifest_changes(&old, &new));
+        reasons.extend(unsatisfied(&lock, &new));
+        let reasons: Vec<String> = reasons.iter().map(ToString::to_string).collect();
+
+        assert_eq!(
+            reasons,
+            [
+                "minimum-stability is dev, but the lock was written with stable",
+                "require: php changed from >=8.1 to >=8.2",
+                "require: a/a changed from ^1.0 to ^2.0",
+                "require: b/b ^2.0 was removed",
+                "require: c/c ^1.0 was added",
+                "\"extra\" changed",
+                "a/a is locked at 1.4.0, which does not satisfy ^2.0",
+                "c/c (^1.0) is required but not locked",
+            ]
+        );
+    }
+
+    #[test]
+    fn hash_only_change_can_be_refreshed() {
+        let composer = manifest(r#"{"require":{"a/a":"^1.0"}}"#);
+        let mut generator = LockGenerator::from_manifest(&composer);
+        generator.add_package(LockedPackage::new("a/a", "1.4.0"));
+        let mut lock = generator.build();
+
+        let current = check(&lock, &composer, Path::new("/nonexistent"));
+        assert!(!current.is_stale());
+
+        lock.content_hash = "stale".to_string();
+        let stale = check(&lock, &composer, Path::new("/nonexistent"));
+        assert!(stale.is_stale());
+        assert!(stale.unresolved().is_empty());
+        assert!(stale.explain().contains("libretto update --lock"));
+    }
+}
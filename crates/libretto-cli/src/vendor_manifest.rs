@@ -0,0 +1,296 @@
+//! Per-package file hash manifest of the installed vendor tree.
+//!
+//! `install` records the BLAKE3 hash of every file it placed for each
+//! package in `vendor/composer/libretto-manifest.json`. `verify` and `status`
+//! compare the tree on disk against it to report missing, extra and modified
+//! files without touching the network.
+
+use anyhow::{Context, Result};
+use libretto_lockfile::{ParallelHasher, bytes_to_hex};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Manifest location relative to the vendor directory.
+const MANIFEST_PATH: &str = "composer/libretto-manifest.json";
+
+/// Recorded state of the installed packages.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VendorManifest {
+    /// Installed packages by name.
+    pub packages: BTreeMap<String, PackageManifest>,
+}
+
+/// Recorded files of one installed package.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackageManifest {
+    /// Installed version.
+    pub version: String,
+    /// Install directory, relative to the project root.
+    pub path: String,
+    /// File hashes (hex BLAKE3) keyed by path relative to the install directory.
+    pub files: BTreeMap<String, String>,
+}
+
+/// An installed package to record.
+#[derive(Debug)]
+pub struct InstalledPackage<'a> {
+    /// Package name.
+    pub name: &'a str,
+    /// Installed version.
+    pub version: &'a str,
+    /// Absolute install directory.
+    pub dir: &'a Path,
+}
+
+/// Differences between a package on disk and its manifest.
+#[derive(Debug, Default)]
+pub struct PackageDrift {
+    /// Files in the manifest that no longer exist.
+    pub missing: Vec<String>,
+    /// Files on disk that are not in the manifest.
+    pub extra: Vec<String>,
+    /// Files whose content changed.
+    pub modified: Vec<String>,
+}
+
+impl PackageDrift {
+    /// Whether the package matches its manifest.
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Path of the manifest for a vendor directory.
+pub fn manifest_path(vendor_dir: &Path) -> PathBuf {
+    vendor_dir.join(MANIFEST_PATH)
+}
+
+/// Record `packages` in the manifest.
+///
+/// Entries already recorded for the same version and directory are kept
+/// without re-hashing; other packages are hashed. Entries of packages not in
+/// `packages` are kept while their install directory exists.
+pub fn record(vendor_dir: &Path, base_dir: &Path, packages: &[InstalledPackage<'_>]) -> Result<()> {
+    let mut manifest = load(vendor_dir).ok().flatten().unwrap_or_default();
+    manifest
+        .packages
+        .retain(|_, recorded| base_dir.join(&recorded.path).is_dir());
+
+    let relative = |dir: &Path| {
+        dir.strip_prefix(base_dir)
+            .unwrap_or(dir)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+    let changed: Vec<&InstalledPackage<'_>> = packages
+        .iter()
+        .filter(|pkg| {
+            manifest.packages.get(pkg.name).is_none_or(|recorded| {
+                recorded.version != pkg.version || recorded.path != relative(pkg.dir)
+            })
+        })
+        .collect();
+
+    let listed: Vec<Vec<String>> = changed.iter().map(|p| list_files(p.dir)).collect();
+    let hashes = hash_files(
+        changed
+            .iter()
+            .zip(&listed)
+            .flat_map(|(pkg, files)| files.iter().map(|rel| pkg.dir.join(rel))),
+    );
+
+    let mut hashes = hashes.into_iter();
+    for (pkg, files) in changed.into_iter().zip(listed) {
+        let files = files
+            .into_iter()
+            .zip(hashes.by_ref())
+            .filter_map(|(rel, hash)| hash.map(|h| (rel, h)))
+            .collect();
+        manifest.packages.insert(
+            pkg.name.to_string(),
+            PackageManifest {
+                version: pkg.version.to_string(),
+                path: relative(pkg.dir),
+                files,
+            },
+        );
+    }
+
+    let path = manifest_path(vendor_dir);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = sonic_rs::to_string(&manifest)?;
+    std::fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Load the manifest, if one was recorded.
+pub fn load(vendor_dir: &Path) -> Result<Option<VendorManifest>> {
+    let path = manifest_path(vendor_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)?;
+    let manifest = sonic_rs::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(manifest))
+}
+
+/// Compare a package's install directory with its manifest.
+pub fn diff(base_dir: &Path, package: &PackageManifest) -> PackageDrift {
+    let dir = base_dir.join(&package.path);
+    let on_disk = list_files(&dir);
+
+    let mut drift = PackageDrift::default();
+    let mut present = Vec::new();
+    for rel in &on_disk {
+        if package.files.contains_key(rel) {
+            present.push(rel.clone());
+        } else {
+            drift.extra.push(rel.clone());
+        }
+    }
+    drift.missing = package
+        .files
+        .keys()
+        .filter(|rel| on_disk.binary_search(rel).is_err())
+        .cloned()
+        .collect();
+
+    let hashes = hash_files(present.iter().map(|rel| dir.join(rel)));
+    drift.modified = present
+        .into_iter()
+        .zip(hashes)
+        .filter(|(rel, hash)| hash.as_ref() != package.files.get(rel))
+        .map(|(rel, _)| rel)
+        .collect();
+
+    drift
+}
+
+/// List regular files under `dir` as sorted `/`-separated relative paths.
+///
+/// VCS metadata directories are skipped.
+fn list_files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0 || !matches!(e.file_name().to_str(), Some(".git" | ".hg" | ".svn"))
+        })
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            e.path()
+                .strip_prefix(dir)
+                .ok()
+                .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Hash files in parallel, preserving order.
+fn hash_files(paths: impl Iterator<Item = PathBuf>) -> Vec<Option<String>> {
+    let paths: Vec<PathBuf> = paths.collect();
+    ParallelHasher::hash_files(&paths)
+        .into_iter()
+        .map(|(_, hash)| hash.map(|h| bytes_to_hex(&h)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn install_fixture(base: &Path) -> PathBuf {
+        let dir = base.join("vendor/acme/pkg");
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("composer.json"), "{}").unwrap();
+        std::fs::write(dir.join("src/A.php"), "<?php class A {}").unwrap();
+        dir
+    }
+
+    fn record_fixture(base: &Path, dir: &Path) -> VendorManifest {
+        let vendor = base.join("vendor");
+        let packages = [InstalledPackage {
+            name: "acme/pkg",
+            version: "1.0.0",
+            dir,
+        }];
+        record(&vendor, base, &packages).unwrap();
+        load(&vendor).unwrap().unwrap()
+    }
+
+    #[test]
+    fn record_and_diff_clean() {
+        let tmp = TempDir::new().unwrap();
+        let dir = install_fixture(tmp.path());
+        let manifest = record_fixture(tmp.path(), &dir);
+
+        let pkg = &manifest.packages["acme/pkg"];
+        assert_eq!(pkg.path, "vendor/acme/pkg");
+        assert_eq!(pkg.files.len(), 2);
+        assert!(diff(tmp.path(), pkg).is_clean());
+    }
+
+    #[test]
+    fn record_merges_with_the_existing_manifest() {
+        let tmp = TempDir::new().unwrap();
+        let dir = install_fixture(tmp.path());
+        record_fixture(tmp.path(), &dir);
+
+        let other = tmp.path().join("vendor/acme/other");
+        std::fs::create_dir_all(&other).unwrap();
+        std::fs::write(other.join("composer.json"), "{}").unwrap();
+        std::fs::write(dir.join("src/A.php"), "<?php class B {}").unwrap();
+
+        let vendor = tmp.path().join("vendor");
+        let packages = [
+            InstalledPackage {
+                name: "acme/pkg",
+                version: "1.0.0",
+                dir: &dir,
+            },
+            InstalledPackage {
+                name: "acme/other",
+                version: "2.0.0",
+                dir: &other,
+            },
+        ];
+        record(&vendor, tmp.path(), &packages).unwrap();
+        let manifest = load(&vendor).unwrap().unwrap();
+        // The unchanged version is not re-hashed
+        let drift = diff(tmp.path(), &manifest.packages["acme/pkg"]);
+        assert_eq!(drift.modified, vec!["src/A.php"]);
+        assert_eq!(manifest.packages["acme/other"].files.len(), 1);
+
+        // Packages outside the recorded set are kept while installed
+        let recorded = |name: &str| load(&vendor).unwrap().unwrap().packages.contains_key(name);
+        record(&vendor, tmp.path(), &packages[1..]).unwrap();
+        assert!(recorded("acme/pkg"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        record(&vendor, tmp.path(), &packages[1..]).unwrap();
+        assert!(!recorded("acme/pkg"));
+    }
+
+    #[test]
+    fn diff_reports_missing_extra_and_modified() {
+        let tmp = TempDir::new().unwrap();
+        let dir = install_fixture(tmp.path());
+        let manifest = record_fixture(tmp.path(), &dir);
+
+        std::fs::remove_file(dir.join("composer.json")).unwrap();
+        std::fs::write(dir.join("src/A.php"), "<?php class B {}").unwrap();
+        std::fs::write(dir.join("src/Extra.php"), "<?php").unwrap();
+
+        let drift = diff(tmp.path(), &manifest.packages["acme/pkg"]);
+        assert_eq!(drift.missing, vec!["composer.json"]);
+        assert_eq!(drift.modified, vec!["src/A.php"]);
+        assert_eq!(drift.extra, vec!["src/Extra.php"]);
+    }
+}
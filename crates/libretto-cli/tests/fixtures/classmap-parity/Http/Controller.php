@@ -0,0 +1,7 @@
+<?php
+
+namespace App\Http;
+
+abstract class Controller
+{
+}
@@ -0,0 +1,21 @@
+<?php
+
+// Expected classmap for this tree, derived by hand from Composer 2's rules
+
+$vendorDir = dirname(__DIR__);
+$baseDir = dirname($vendorDir);
+
+return array(
+    'Acme\\Http\\Client' => $vendorDir . '/acme/http/src/Client.php',
+    'Acme\\Http\\Compat' => $vendorDir . '/acme/http/src/Compat.inc',
+    'Acme\\Http\\Internal\\Pool' => $vendorDir . '/acme/http/src/Internal/Pool.php',
+    'Acme\\Http\\Message\\Request' => $vendorDir . '/acme/http/src/Message/Request.php',
+    'Acme\\Http\\Tests\\ClientTest' => $vendorDir . '/acme/http/tests/ClientTest.php',
+    'App\\Bridge\\Bridge' => $vendorDir . '/acme/app-bridge/src/Bridge.php',
+    'App\\Http\\Controller' => $baseDir . '/Http/Controller.php',
+    'App\\Kernel' => $baseDir . '/Kernel.php',
+    'Composer\\InstalledVersions' => $vendorDir . '/composer/InstalledVersions.php',
+    'LegacyUtil' => $baseDir . '/legacy/Util.inc',
+    'Legacy\\Markdown\\Block_Quote' => $vendorDir . '/legacy/markdown/Legacy/Markdown/Block/Quote.php',
+    'Legacy\\Markdown\\Parser' => $vendorDir . '/legacy/markdown/Legacy/Markdown/Parser.php',
+);
@@ -0,0 +1,41 @@
+#!/bin/bash
+# Regenerate expected_classmap.php with Composer and record its version in
+# README.md. Requires php and composer on PATH.
+#
+# Composer reads the installed packages from vendor/composer/installed.json,
+# so one is built from composer.lock first. Everything Composer writes into
+# vendor/ is removed again afterwards.
+
+set -euo pipefail
+
+cd "$(dirname "${BASH_SOURCE[0]}")"
+trap 'rm -rf vendor/composer vendor/autoload.php' EXIT
+
+mkdir -p vendor/composer
+php -r '
+$lock = json_decode(file_get_contents("composer.lock"), true);
+$dev = $lock["packages-dev"] ?? [];
+$packages = [];
+foreach (array_merge($lock["packages"], $dev) as $package) {
+    $target = isset($package["target-dir"]) ? "/" . $package["target-dir"] : "";
+    $package["install-path"] = "../" . $package["name"] . $target;
+    $packages[] = $package;
+}
+$installed = [
+    "packages" => $packages,
+    "dev" => true,
+    "dev-package-names" => array_column($dev, "name"),
+];
+file_put_contents(
+    "vendor/composer/installed.json",
+    json_encode($installed, JSON_PRETTY_PRINT | JSON_UNESCAPED_SLASHES) . "\n"
+);
+'
+
+composer dump-autoload --classmap-authoritative --no-scripts --no-plugins --no-interaction
+cp vendor/composer/autoload_classmap.php expected_classmap.php
+
+version="$(composer --version --no-ansi | head -n 1)"
+sed -i.bak "s|^Generated with: .*|Generated with: ${version}|" README.md
+rm -f README.md.bak
+echo "expected_classmap.php regenerated with ${version}"
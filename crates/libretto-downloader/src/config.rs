@@ -46,7 +46,8 @@ pub struct DownloadConfig {
     pub mmap_threshold: u64,
     /// Bandwidth limit in bytes per second (None = unlimited).
     pub bandwidth_limit: Option<u64>,
-    /// Mirror URLs for fallback.
+    /// Dist mirror URL templates tried when the primary URL fails
+    /// (`%package%`, `%version%`, `%reference%` and `%type%` are expanded).
     pub mirrors: Vec<String>,
     /// Proxy URL (overrides env vars).
     pub proxy: Option<String>,
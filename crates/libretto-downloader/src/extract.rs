@@ -13,6 +13,11 @@ use tokio_tar::Archive as TarArchive;
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 use tracing::{debug, info, trace};
 
+/// File type bits of a Unix mode.
+const S_IFMT: u32 = 0o170_000;
+/// Symlink file type.
+const S_IFLNK: u32 = 0o120_000;
+
 /// Extraction options.
 #[derive(Debug, Clone, Default)]
 pub struct ExtractOptions {
@@ -134,6 +139,7 @@ impl Extractor {
             .await
             .map_err(|e| DownloadError::Archive(e.to_string()))?;
 
+        let root = canonical_dest(dest).await?;
         let mut files_extracted = 0;
         let mut total_size = 0u64;
 
@@ -178,12 +184,28 @@ impl Extractor {
 
             #[cfg(unix)]
             let unix_mode = entry.unix_permissions();
+            let is_symlink = entry
+                .unix_permissions()
+                .is_some_and(|mode| u32::from(mode) & S_IFMT == S_IFLNK);
 
-            if is_dir {
+            if is_symlink {
+                let mut entry_reader = zip
+                    .reader_without_entry(i)
+                    .await
+                    .map_err(|e| DownloadError::Archive(e.to_string()))?;
+                let mut target = String::new();
+                futures_lite::io::AsyncReadExt::read_to_string(&mut entry_reader, &mut target)
+                    .await
+                    .map_err(|e| DownloadError::Archive(e.to_string()))?;
+                create_symlink(&root, &out_path, Path::new(&target))?;
+                files_extracted += 1;
+            } else if is_dir {
+                ensure_within(&root, &out_path)?;
                 fs::create_dir_all(&out_path)
                     .await
                     .map_err(|e| DownloadError::io(&out_path, e))?;
             } else {
+                ensure_within(&root, &out_path)?;
                 if let Some(parent) = out_path.parent() {
                     fs::create_dir_all(parent)
                         .await
@@ -297,6 +319,7 @@ impl Extractor {
             .entries()
             .map_err(|e| DownloadError::Archive(e.to_string()))?;
 
+        let root = canonical_dest(dest).await?;
         let mut files_extracted = 0;
         let mut total_size = 0u64;
 
@@ -328,10 +351,24 @@ impl Extractor {
             let entry_type = entry.header().entry_type();
 
             if entry_type.is_dir() {
+                ensure_within(&root, &out_path)?;
                 fs::create_dir_all(&out_path)
                     .await
                     .map_err(|e| DownloadError::io(&out_path, e))?;
+            } else if entry_type.is_symlink() {
+                let target = entry
+                    .link_name()
+                    .map_err(|e| DownloadError::Archive(e.to_string()))?
+                    .ok_or_else(|| {
+                        DownloadError::Archive(format!(
+                            "symlink without target: {}",
+                            path.display()
+                        ))
+                    })?;
+                create_symlink(&root, &out_path, &target)?;
+                files_extracted += 1;
             } else if entry_type.is_file() {
+                ensure_within(&root, &out_path)?;
                 if let Some(parent) = out_path.parent() {
                     fs::create_dir_all(parent)
                         .await
@@ -395,6 +432,97 @@ impl Extractor {
     }
 }
 
+/// Canonical form of the extraction directory, which every extracted path
+/// must resolve into.
+async fn canonical_dest(dest: &Path) -> Result<PathBuf> {
+    fs::canonicalize(dest)
+        .await
+        .map_err(|e| DownloadError::io(dest, e))
+}
+
+/// Check that writing `path` stays inside `root`.
+///
+/// Directories are created and files opened through symlinks extracted
+/// earlier, so the deepest part of `path` that already exists is resolved on
+/// disk rather than judged by its text.
+fn ensure_within(root: &Path, path: &Path) -> Result<()> {
+    let inside = path
+        .ancestors()
+        .find(|ancestor| std::fs::symlink_metadata(ancestor).is_ok())
+        .and_then(|existing| std::fs::canonicalize(existing).ok())
+        .is_some_and(|resolved| resolved.starts_with(root));
+    if inside {
+        Ok(())
+    } else {
+        Err(DownloadError::Archive(format!(
+            "path escape attempt: {}",
+            path.display()
+        )))
+    }
+}
+
+/// Create a symlink extracted from an archive.
+///
+/// Absolute targets and targets that climb out of `root` are rejected, so a
+/// malicious archive cannot point package files at arbitrary paths.
+fn create_symlink(root: &Path, link: &Path, target: &Path) -> Result<()> {
+    if let Some(parent) = link.parent() {
+        ensure_within(root, parent)?;
+        std::fs::create_dir_all(parent).map_err(|e| DownloadError::io(parent, e))?;
+    }
+    if !symlink_stays_within(root, link, target) {
+        return Err(DownloadError::Archive(format!(
+            "symlink escape attempt: {} -> {}",
+            link.display(),
+            target.display()
+        )));
+    }
+
+    if std::fs::symlink_metadata(link).is_ok() {
+        std::fs::remove_file(link).map_err(|e| DownloadError::io(link, e))?;
+    }
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, link).map_err(|e| DownloadError::io(link, e))?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_file(target, link).map_err(|e| DownloadError::io(link, e))?;
+
+    trace!(link = ?link, target = ?target, "extracted symlink");
+    Ok(())
+}
+
+/// Check that a symlink target resolves inside the canonical `root`.
+///
+/// The target is followed from the link's canonical parent, resolving
+/// links extracted earlier along the way, so a chain of links that each
+/// look harmless cannot climb out.
+fn symlink_stays_within(root: &Path, link: &Path, target: &Path) -> bool {
+    use std::path::Component;
+
+    let Some(parent) = link.parent() else {
+        return false;
+    };
+    let mut resolved = std::fs::canonicalize(parent).unwrap_or_else(|_| parent.to_path_buf());
+    for component in target.components() {
+        match component {
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return false;
+                }
+            }
+            Component::Normal(name) => {
+                resolved.push(name);
+                if let Ok(real) = std::fs::canonicalize(&resolved) {
+                    resolved = real;
+                }
+            }
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    resolved.starts_with(root)
+}
+
 /// Extract archive to directory (convenience function).
 ///
 /// # Errors
@@ -446,6 +574,88 @@ mod tests {
         assert_eq!(stripped, path);
     }
 
+    #[test]
+    fn symlink_within_dest_is_allowed() {
+        let dest = Path::new("/pkg");
+        assert!(symlink_stays_within(
+            dest,
+            Path::new("/pkg/bin/tool"),
+            Path::new("../src/tool.php")
+        ));
+        assert!(symlink_stays_within(
+            dest,
+            Path::new("/pkg/link"),
+            Path::new("target")
+        ));
+    }
+
+    #[test]
+    fn symlink_escaping_dest_is_rejected() {
+        let dest = Path::new("/pkg");
+        assert!(!symlink_stays_within(
+            dest,
+            Path::new("/pkg/link"),
+            Path::new("../outside")
+        ));
+        assert!(!symlink_stays_within(
+            dest,
+            Path::new("/pkg/link"),
+            Path::new("/etc/passwd")
+        ));
+        assert!(!symlink_stays_within(
+            dest,
+            Path::new("/pkg/a/link"),
+            Path::new("b/../../../x")
+        ));
+    }
+
+    fn zip_with(archive: &Path, entries: &[(&str, Option<&str>)]) {
+        use std::io::Write;
+
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, link) in entries {
+            match link {
+                Some(target) => zip.add_symlink(*name, *target, options).unwrap(),
+                None => {
+                    zip.start_file(*name, options).unwrap();
+                    zip.write_all(b"<?php").unwrap();
+                }
+            }
+        }
+        zip.finish().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn chained_symlinks_cannot_escape_dest() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let archive = tmp.path().join("pkg.zip");
+        zip_with(
+            &archive,
+            &[("a/q", Some("..")), ("a/r", Some("q/..")), ("a/r/x", None)],
+        );
+
+        let dest = tmp.path().join("vendor/pkg");
+        assert!(extract(&archive, &dest).await.is_err());
+        assert!(!tmp.path().join("vendor/x").exists());
+        assert!(!tmp.path().join("vendor/a").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn entries_are_not_written_through_links_leaving_dest() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let archive = tmp.path().join("pkg.zip");
+        zip_with(&archive, &[("out/x.php", None)]);
+
+        let dest = tmp.path().join("vendor/pkg");
+        std::fs::create_dir_all(&dest).unwrap();
+        std::os::unix::fs::symlink(tmp.path(), dest.join("out")).unwrap();
+        assert!(extract(&archive, &dest).await.is_err());
+        assert!(!tmp.path().join("x.php").exists());
+    }
+
     #[test]
     fn strip_path_prefix_all() {
         let extractor = Extractor::with_options(ExtractOptions::new().with_strip_prefix(3));
@@ -5,11 +5,11 @@
 
 use crate::checksum::MultiHasher;
 use crate::client::HttpClient;
-use crate::config::{AuthConfig, DownloadConfig, ExpectedChecksum};
+use crate::config::{AuthConfig, DownloadConfig};
 use crate::error::{DownloadError, Result};
 use crate::extract::Extractor;
 use crate::progress::{DownloadProgress, ProgressTracker};
-use crate::retry::{CircuitBreaker, RetryConfig, with_retry};
+use crate::retry::{CircuitBreaker, RetryConfig, with_mirrors, with_retry};
 use crate::source::{ArchiveType, DownloadResult, DownloadSource, Source, SourceType};
 use crate::stream::StreamDownloader;
 use crate::throttle::BandwidthThrottler;
@@ -175,6 +175,22 @@ impl ParallelDownloader {
         &self,
         sources: Vec<DownloadSource>,
     ) -> Vec<std::result::Result<DownloadResult, DownloadError>> {
+        self.download_all_with(sources, |_, _| {}).await
+    }
+
+    /// Download multiple packages concurrently, reporting each completion.
+    ///
+    /// `on_complete` is called with the package name and its result as soon
+    /// as each download finishes, so callers can drive their own progress
+    /// display (typically with `show_progress` disabled).
+    pub async fn download_all_with<F>(
+        &self,
+        sources: Vec<DownloadSource>,
+        mut on_complete: F,
+    ) -> Vec<std::result::Result<DownloadResult, DownloadError>>
+    where
+        F: FnMut(&str, &std::result::Result<DownloadResult, DownloadError>),
+    {
         let total = sources.len();
         self.stats.total_packages.store(total, Ordering::Relaxed);
         self.progress.set_total(total);
@@ -196,11 +212,18 @@ impl ParallelDownloader {
             );
         }
 
-        let results: Vec<_> = stream::iter(sources)
-            .map(|source| self.download_source(source))
-            .buffer_unordered(self.config.max_concurrent)
-            .collect()
-            .await;
+        let mut downloads = stream::iter(sources)
+            .map(|source| async move {
+                let name = source.name.clone();
+                (name, self.download_source(source).await)
+            })
+            .buffer_unordered(self.config.max_concurrent);
+
+        let mut results = Vec::with_capacity(total);
+        while let Some((name, result)) = downloads.next().await {
+            on_complete(&name, &result);
+            results.push(result);
+        }
 
         self.progress.finish();
 
@@ -325,16 +348,8 @@ impl ParallelDownloader {
 
         match src {
             Source::Dist { url, archive_type } => {
-                self.download_dist(
-                    url,
-                    *archive_type,
-                    &download_source.dest,
-                    &download_source.checksums,
-                    &download_source.name,
-                    &download_source.version,
-                    progress,
-                )
-                .await
+                self.download_dist(url, *archive_type, download_source, progress)
+                    .await
             }
             Source::Git { url, reference } => {
                 self.download_git(
@@ -393,35 +408,60 @@ impl ParallelDownloader {
     }
 
     /// Download a dist archive.
-    #[allow(clippy::too_many_arguments)]
+    ///
+    /// Configured mirrors are tried in order when the primary URL fails.
+    /// Like Composer, an archive whose content sits in a single top-level
+    /// directory (e.g. GitHub's `vendor-repo-hash/`) is unpacked so that
+    /// directory's content becomes the package directory.
     async fn download_dist(
         &self,
         url: &url::Url,
         archive_type: ArchiveType,
-        dest: &Path,
-        checksums: &[ExpectedChecksum],
-        name: &str,
-        version: &str,
+        download_source: &DownloadSource,
         progress: &DownloadProgress,
     ) -> Result<DownloadResult> {
+        let name = download_source.name.as_str();
+        let dest = download_source.dest.as_path();
+        let checksums = download_source.checksums.as_slice();
+
         info!(package = %name, url = %url, "download_dist starting");
 
         // Get host for circuit breaker tracking
         let host = url.host_str().unwrap_or("unknown").to_string();
         let cb = self.circuit_breakers.entry(host.clone()).or_default();
 
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| DownloadError::io(parent, e))?;
+        }
+
         // Create temp file for archive
         let archive_ext = archive_type.extension();
         let archive_path = dest.with_extension(format!("download{archive_ext}"));
 
         info!(package = %name, archive_path = %archive_path.display(), "starting download with retry");
 
-        // Download with retry
-        let download_result = with_retry(&self.retry_config, || {
-            self.stream_downloader
-                .download(url, &archive_path, checksums, progress)
-        })
-        .await;
+        // Download with retry, then fall back to mirrors
+        let mirrors = self.mirror_urls(download_source, archive_type);
+        let download_result = if mirrors.is_empty() {
+            with_retry(&self.retry_config, || {
+                self.stream_downloader
+                    .download(url, &archive_path, checksums, progress)
+            })
+            .await
+        } else {
+            let archive_path = &archive_path;
+            with_mirrors(url.as_str(), &mirrors, &self.retry_config, |candidate| {
+                let candidate = url::Url::parse(candidate);
+                async move {
+                    self.stream_downloader
+                        .download(&candidate?, archive_path, checksums, progress)
+                        .await
+                }
+            })
+            .await
+        };
 
         let downloaded = match download_result {
             Ok(d) => {
@@ -431,38 +471,40 @@ impl ParallelDownloader {
             }
             Err(e) => {
                 cb.record_failure();
+                let _ = std::fs::remove_file(&archive_path);
                 error!(package = %name, error = %e, "download failed");
                 return Err(e);
             }
         };
+        drop(cb);
 
         // Verify checksums
         if !checksums.is_empty() && self.config.verify_checksum {
             info!(package = %name, "verifying checksums");
-            downloaded.verify(checksums, name)?;
+            if let Err(e) = downloaded.verify(checksums, name) {
+                let _ = std::fs::remove_file(&archive_path);
+                return Err(e);
+            }
         }
 
-        // Extract archive
+        // Extract into a staging directory next to the destination
         info!(package = %name, dest = %dest.display(), "extracting archive");
-        let extract_dest = dest;
-        self.extractor
-            .extract(&archive_path, extract_dest)
+        let staging = dest.with_extension("extracting");
+        let extracted = self
+            .extract_dist(&archive_path, &staging, dest)
             .await
-            .map_err(|e| {
-                // Clean up archive on extraction failure
-                let _ = std::fs::remove_file(&archive_path);
-                error!(package = %name, error = %e, "extraction failed");
-                e
-            })?;
+            .inspect_err(|e| error!(package = %name, error = %e, "extraction failed"));
 
-        // Remove archive after extraction
+        // Remove archive and staging leftovers after extraction
         let _ = std::fs::remove_file(&archive_path);
+        let _ = std::fs::remove_dir_all(&staging);
+        extracted?;
 
         info!(package = %name, "download_dist complete");
 
         Ok(DownloadResult {
             name: name.to_string(),
-            version: version.to_string(),
+            version: download_source.version.clone(),
             path: dest.to_path_buf(),
             size: downloaded.size,
             checksums: downloaded.checksums,
@@ -471,6 +513,63 @@ impl ParallelDownloader {
         })
     }
 
+    /// Extract `archive` via `staging` and move the package content to `dest`.
+    async fn extract_dist(&self, archive: &Path, staging: &Path, dest: &Path) -> Result<()> {
+        if staging.exists() {
+            tokio::fs::remove_dir_all(staging)
+                .await
+                .map_err(|e| DownloadError::io(staging, e))?;
+        }
+
+        let result = self.extractor.extract(archive, staging).await?;
+        let content = result.root_dir.as_deref().unwrap_or(staging);
+
+        match tokio::fs::symlink_metadata(dest).await {
+            Ok(meta) if meta.is_dir() => tokio::fs::remove_dir_all(dest)
+                .await
+                .map_err(|e| DownloadError::io(dest, e))?,
+            Ok(_) => tokio::fs::remove_file(dest)
+                .await
+                .map_err(|e| DownloadError::io(dest, e))?,
+            Err(_) => {}
+        }
+
+        tokio::fs::rename(content, dest)
+            .await
+            .map_err(|e| DownloadError::io(dest, e))
+    }
+
+    /// Expand the configured dist mirrors for a package.
+    ///
+    /// Mirrors are URL templates using Composer's placeholders: `%package%`,
+    /// `%version%`, `%reference%` and `%type%`. A mirror without placeholders
+    /// is treated as a base URL with the `%package%/%version%/%reference%.%type%`
+    /// layout appended.
+    fn mirror_urls(&self, source: &DownloadSource, archive_type: ArchiveType) -> Vec<String> {
+        let reference = source.reference.as_deref().unwrap_or(&source.version);
+        let kind = archive_type.extension().trim_start_matches('.');
+
+        self.config
+            .mirrors
+            .iter()
+            .map(|mirror| {
+                let template = if mirror.contains('%') {
+                    mirror.clone()
+                } else {
+                    format!(
+                        "{}/%package%/%version%/%reference%.%type%",
+                        mirror.trim_end_matches('/')
+                    )
+                };
+                template
+                    .replace("%package%", &source.name)
+                    .replace("%version%", &source.version)
+                    .replace("%reference%", reference)
+                    .replace("%type%", kind)
+            })
+            .collect()
+    }
+
     /// Download from Git repository.
     async fn download_git(
         &self,
@@ -744,6 +843,71 @@ mod tests {
         assert_eq!(snapshot.failed, 0);
     }
 
+    #[test]
+    fn mirror_urls_expand_placeholders() {
+        let dl = ParallelDownloader::builder()
+            .show_progress(false)
+            .mirrors(vec![
+                "https://mirror.example/%package%/%reference%.%type%".to_string(),
+                "https://base.example/dists/".to_string(),
+            ])
+            .build()
+            .unwrap();
+        let source = DownloadSource::new(
+            "psr/log",
+            "3.0.0",
+            Source::Dist {
+                url: url::Url::parse("https://example.com/log.zip").unwrap(),
+                archive_type: ArchiveType::Zip,
+            },
+            "vendor/psr/log".into(),
+        )
+        .with_reference("abc123");
+
+        assert_eq!(
+            dl.mirror_urls(&source, ArchiveType::Zip),
+            vec![
+                "https://mirror.example/psr/log/abc123.zip",
+                "https://base.example/dists/psr/log/3.0.0/abc123.zip",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn extract_dist_flattens_single_root_dir() {
+        use std::io::Write;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let archive = dir.path().join("pkg.zip");
+        {
+            let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+            let options = zip::write::SimpleFileOptions::default();
+            zip.start_file("vendor-repo-abc/composer.json", options)
+                .unwrap();
+            zip.write_all(b"{}").unwrap();
+            zip.start_file("vendor-repo-abc/src/A.php", options)
+                .unwrap();
+            zip.write_all(b"<?php").unwrap();
+            zip.finish().unwrap();
+        }
+
+        let dest = dir.path().join("vendor/pkg");
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(dest.join("stale.php"), "old").unwrap();
+
+        let dl = ParallelDownloader::builder()
+            .show_progress(false)
+            .build()
+            .unwrap();
+        let staging = dest.with_extension("extracting");
+        dl.extract_dist(&archive, &staging, &dest).await.unwrap();
+
+        assert!(dest.join("composer.json").is_file());
+        assert!(dest.join("src/A.php").is_file());
+        assert!(!dest.join("stale.php").exists());
+        assert!(!dest.join("vendor-repo-abc").exists());
+    }
+
     #[tokio::test]
     async fn downloader_creation() {
         let downloader = ParallelDownloader::with_defaults();
@@ -190,6 +190,8 @@ pub struct DownloadSource {
     pub checksums: Vec<ExpectedChecksum>,
     /// Destination path.
     pub dest: PathBuf,
+    /// Dist reference (commit hash), used to expand mirror URLs.
+    pub reference: Option<String>,
 }
 
 impl DownloadSource {
@@ -208,6 +210,7 @@ impl DownloadSource {
             fallbacks: Vec::new(),
             checksums: Vec::new(),
             dest,
+            reference: None,
         }
     }
 
@@ -225,6 +228,13 @@ impl DownloadSource {
         self
     }
 
+    /// Set the dist reference.
+    #[must_use]
+    pub fn with_reference(mut self, reference: impl Into<String>) -> Self {
+        self.reference = Some(reference.into());
+        self
+    }
+
     /// Get all sources (primary + fallbacks) in order.
     pub fn all_sources(&self) -> impl Iterator<Item = &Source> {
         std::iter::once(&self.primary).chain(self.fallbacks.iter())
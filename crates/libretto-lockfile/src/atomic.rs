@@ -60,7 +60,20 @@ impl AtomicWriter {
     /// # Errors
     /// Returns error if lock cannot be acquired.
     pub fn new(path: impl AsRef<Path>) -> Result<Self> {
-        let target = path.as_ref().to_path_buf();
+        Self::create(path.as_ref(), true)
+    }
+
+    /// Create a writer that does not take the `.lck` file lock.
+    ///
+    /// For callers that already serialize writers themselves; nothing is
+    /// left next to the target.
+    #[must_use]
+    pub fn unlocked(path: impl AsRef<Path>) -> Self {
+        Self::create(path.as_ref(), false).expect("no lock to acquire")
+    }
+
+    fn create(path: &Path, lock: bool) -> Result<Self> {
+        let target = path.to_path_buf();
         let lock_file_path = target.with_extension(target.extension().map_or_else(
             || LOCK_SUFFIX[1..].to_string(),
             |e| format!("{}.{}", e.to_string_lossy(), &LOCK_SUFFIX[1..]),
@@ -77,14 +90,18 @@ impl AtomicWriter {
         debug!(target = %target.display(), "Creating atomic writer");
 
         // Acquire exclusive lock
-        let lock_file = acquire_lock(&lock_file_path)?;
+        let lock_file = if lock {
+            Some(acquire_lock(&lock_file_path)?)
+        } else {
+            None
+        };
 
         Ok(Self {
             target,
             lock_file_path,
             temp_path,
             backup_path,
-            _lock_file: Some(lock_file),
+            _lock_file: lock_file,
             content: None,
             expected_hash: None,
             create_backup: true,
@@ -252,6 +269,15 @@ impl AtomicReader {
         })
     }
 
+    /// Create a reader that does not take the `.lck` file lock.
+    #[must_use]
+    pub fn unlocked(path: impl AsRef<Path>) -> Self {
+        Self {
+            target: path.as_ref().to_path_buf(),
+            _lock_file: None,
+        }
+    }
+
     /// Read the file content.
     ///
     /// # Errors
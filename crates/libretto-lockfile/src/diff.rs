@@ -427,8 +427,8 @@ pub fn compute_diff(old: &ComposerLock, new: &ComposerLock) -> LockDiff {
     }
 
     // Check platform requirements
-    diff.platform_changes = compute_btree_diff(&old.platform, &new.platform, "platform");
-    diff.platform_changes.extend(compute_btree_diff(
+    diff.platform_changes = compute_map_diff(&old.platform, &new.platform, "platform");
+    diff.platform_changes.extend(compute_map_diff(
         &old.platform_dev,
         &new.platform_dev,
         "platform-dev",
@@ -572,9 +572,9 @@ fn compute_package_metadata_diff(old: &LockedPackage, new: &LockedPackage) -> Ve
 }
 
 /// Compute diff between two `BTrees`.
-fn compute_btree_diff(
-    old: &std::collections::BTreeMap<String, String>,
-    new: &std::collections::BTreeMap<String, String>,
+fn compute_map_diff(
+    old: &indexmap::IndexMap<String, String>,
+    new: &indexmap::IndexMap<String, String>,
     prefix: &str,
 ) -> Vec<FieldChange> {
     let mut changes = Vec::new();
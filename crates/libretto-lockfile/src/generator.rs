@@ -1,18 +1,18 @@
 //! Deterministic lock file generation.
 //!
-//! Ensures same input always produces identical output for reproducible builds.
+//! Ensures same input always produces identical output for reproducible builds,
+//! and that the output is byte-identical to what Composer 2 writes.
 
 use crate::error::{LockfileError, Result};
 use crate::hash::ContentHasher;
-use crate::types::{
-    AutoloadConfig, AutoloadPath, ComposerLock, LockedPackage, PackageAlias, PackageAuthor,
-    PackageDistInfo, PackageSourceInfo, StabilityFlag,
-};
+use crate::json::{self, PhpJsonStyle};
+use crate::types::{ComposerLock, LockedPackage, PackageAlias, StabilityFlag};
+use indexmap::IndexMap;
 use rayon::prelude::*;
 use serde::ser::SerializeMap;
 use serde::{Serialize, Serializer};
+use sonic_rs::{JsonContainerTrait, JsonValueMutTrait, JsonValueTrait, Value};
 use std::collections::BTreeMap;
-use std::io::Write;
 
 /// Lock file generator with deterministic output.
 #[derive(Debug, Clone)]
@@ -24,9 +24,11 @@ pub struct LockGenerator {
     /// Prefer lowest flag.
     prefer_lowest: bool,
     /// Platform requirements.
-    platform: BTreeMap<String, String>,
+    platform: IndexMap<String, String>,
     /// Dev platform requirements.
-    platform_dev: BTreeMap<String, String>,
+    platform_dev: IndexMap<String, String>,
+    /// `config.platform` overrides.
+    platform_overrides: IndexMap<String, String>,
     /// Plugin API version.
     plugin_api_version: String,
     /// Production packages.
@@ -36,7 +38,9 @@ pub struct LockGenerator {
     /// Package aliases.
     aliases: Vec<PackageAlias>,
     /// Stability flags per package.
-    stability_flags: BTreeMap<String, u8>,
+    stability_flags: IndexMap<String, u8>,
+    /// Content hash, when already known.
+    content_hash: Option<String>,
 }
 
 impl Default for LockGenerator {
@@ -53,16 +57,82 @@ impl LockGenerator {
             minimum_stability: "stable".to_string(),
             prefer_stable: false,
             prefer_lowest: false,
-            platform: BTreeMap::new(),
-            platform_dev: BTreeMap::new(),
+            platform: IndexMap::new(),
+            platform_dev: IndexMap::new(),
+            platform_overrides: IndexMap::new(),
             plugin_api_version: "2.6.0".to_string(),
             packages: Vec::new(),
             packages_dev: Vec::new(),
             aliases: Vec::new(),
-            stability_flags: BTreeMap::new(),
+            stability_flags: IndexMap::new(),
+            content_hash: None,
         }
     }
 
+    /// Create a generator configured from a composer.json document.
+    ///
+    /// Takes the content hash, minimum stability, prefer-stable, stability
+    /// flags, platform requirements and `config.platform` overrides from the
+    /// manifest the way Composer's `Locker` does.
+    #[must_use]
+    pub fn from_manifest(manifest: &Value) -> Self {
+        let mut generator = Self::new();
+
+        let minimum_stability = manifest
+            .get("minimum-stability")
+            .and_then(|v| v.as_str())
+            .and_then(StabilityFlag::parse)
+            .unwrap_or(StabilityFlag::Stable);
+        generator.minimum_stability = minimum_stability.as_str().to_string();
+        generator.prefer_stable = manifest
+            .get("prefer-stable")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let links = |key: &str| -> Vec<(String, String)> {
+            manifest
+                .get(key)
+                .and_then(|v| v.as_object())
+                .map(|obj| {
+                    obj.iter()
+                        .filter_map(|(name, c)| {
+                            c.as_str().map(|c| (name.to_lowercase(), c.to_string()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let require = links("require");
+        let require_dev = links("require-dev");
+
+        generator.platform = require
+            .iter()
+            .filter(|(name, _)| is_platform_package(name))
+            .cloned()
+            .collect();
+        generator.platform_dev = require_dev
+            .iter()
+            .filter(|(name, _)| is_platform_package(name))
+            .cloned()
+            .collect();
+        generator.stability_flags =
+            extract_stability_flags(require.iter().chain(&require_dev), minimum_stability);
+
+        if let Some(platform) = manifest
+            .get("config")
+            .and_then(|c| c.get("platform"))
+            .and_then(|v| v.as_object())
+        {
+            generator.platform_overrides = platform
+                .iter()
+                .filter_map(|(name, v)| v.as_str().map(|v| (name.to_string(), v.to_string())))
+                .collect();
+        }
+
+        generator.content_hash = Some(ContentHasher::composer_content_hash(manifest));
+        generator
+    }
+
     /// Set minimum stability.
     pub fn minimum_stability(&mut self, stability: impl Into<String>) -> &mut Self {
         self.minimum_stability = stability.into();
@@ -87,6 +157,12 @@ impl LockGenerator {
         self
     }
 
+    /// Set the content hash written by [`Self::build`].
+    pub fn content_hash(&mut self, hash: impl Into<String>) -> &mut Self {
+        self.content_hash = Some(hash.into());
+        self
+    }
+
     /// Add platform requirement.
     pub fn add_platform(
         &mut self,
@@ -152,6 +228,9 @@ impl LockGenerator {
 
     /// Generate the lock file with content hash.
     ///
+    /// The hash only covers the given dependency maps; use
+    /// [`Self::from_manifest`] and [`Self::build`] for Composer's hash.
+    ///
     /// # Arguments
     /// * `require` - Production dependencies from composer.json
     /// * `require_dev` - Dev dependencies from composer.json
@@ -161,29 +240,34 @@ impl LockGenerator {
         require: &BTreeMap<String, String>,
         require_dev: &BTreeMap<String, String>,
     ) -> ComposerLock {
-        // Sort packages deterministically
-        self.packages.par_sort();
-        self.packages_dev.par_sort();
-        self.aliases.sort_by(|a, b| a.package.cmp(&b.package));
-
-        // Compute content hash
+        let platform: BTreeMap<String, String> = self
+            .platform
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
         let content_hash = ContentHasher::compute_content_hash(
             require,
             require_dev,
             Some(&self.minimum_stability),
             Some(self.prefer_stable),
             if self.prefer_lowest { Some(true) } else { None },
-            &self.platform,
+            &platform,
             &BTreeMap::new(), // platform overrides
         );
+        self.content_hash = Some(content_hash);
+        self.build()
+    }
+
+    /// Build the lock file using the configured content hash.
+    #[must_use]
+    pub fn build(mut self) -> ComposerLock {
+        // Sort packages deterministically
+        self.packages.par_sort();
+        self.packages_dev.par_sort();
+        self.aliases.sort_by(|a, b| a.package.cmp(&b.package));
 
         ComposerLock {
-            readme: vec![
-                "This file locks the dependencies of your project to a known state".to_string(),
-                "Read more about it at https://getcomposer.org/doc/01-basic-usage.md#installing-dependencies".to_string(),
-                "This file is @generated automatically".to_string(),
-            ],
-            content_hash,
+            content_hash: self.content_hash.unwrap_or_default(),
             packages: self.packages,
             packages_dev: self.packages_dev,
             aliases: self.aliases,
@@ -193,439 +277,215 @@ impl LockGenerator {
             prefer_lowest: self.prefer_lowest,
             platform: self.platform,
             platform_dev: self.platform_dev,
+            platform_overrides: self.platform_overrides,
             plugin_api_version: self.plugin_api_version,
+            ..ComposerLock::default()
         }
     }
 }
 
-/// Deterministic JSON serializer for lock files.
-///
-/// Ensures:
-/// - Alphabetically sorted keys
-/// - 4-space indentation
-/// - Consistent formatting
-/// - No trailing whitespace
-#[derive(Debug, Clone, Copy, Default)]
-pub struct DeterministicSerializer;
-
-impl DeterministicSerializer {
-    /// Serialize lock file to deterministic JSON.
-    ///
-    /// # Errors
-    /// Returns error if serialization fails.
-    pub fn serialize(lock: &ComposerLock) -> Result<String> {
-        // Use a custom serializer for deterministic output
-        let mut output = Vec::with_capacity(64 * 1024); // Pre-allocate 64KB
-        Self::write_lock(&mut output, lock)?;
-        String::from_utf8(output).map_err(|e| LockfileError::Serialization(e.to_string()))
-    }
+/// Whether a requirement names a platform package, per Composer's
+/// `PlatformRepository::isPlatformPackage`.
+fn is_platform_package(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    if matches!(
+        name.as_str(),
+        "php"
+            | "php-64bit"
+            | "php-ipv6"
+            | "php-zts"
+            | "php-debug"
+            | "hhvm"
+            | "composer"
+            | "composer-plugin-api"
+            | "composer-runtime-api"
+    ) {
+        return true;
+    }
+    name.strip_prefix("ext-")
+        .or_else(|| name.strip_prefix("lib-"))
+        .is_some_and(|rest| {
+            rest.starts_with(|c: char| c.is_ascii_alphanumeric())
+                && rest
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        })
+}
 
-    /// Write lock file to a writer.
-    fn write_lock<W: Write>(w: &mut W, lock: &ComposerLock) -> Result<()> {
-        writeln!(w, "{{").map_err(|e| LockfileError::Serialization(e.to_string()))?;
+/// Stability flags for root requirements, as Composer's
+/// `RootPackageLoader::extractStabilityFlags` derives them.
+///
+/// An explicit `@flag` wins; otherwise a bare unstable version such as
+/// `dev-main` or `2.0.0-beta1` flags the package when it is less stable than
+/// `minimum-stability`.
+fn extract_stability_flags<'a>(
+    requires: impl Iterator<Item = &'a (String, String)>,
+    minimum_stability: StabilityFlag,
+) -> IndexMap<String, u8> {
+    let mut flags: IndexMap<String, u8> = IndexMap::new();
+
+    for (name, constraint) in requires {
+        let constraints: Vec<&str> = constraint
+            .split('|')
+            .flat_map(|or| or.split([',', ' ']))
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .collect();
 
-        // _readme
-        write!(w, "    \"_readme\": [").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        for (i, line) in lock.readme.iter().enumerate() {
-            if i > 0 {
-                write!(w, ",").map_err(|e| LockfileError::Serialization(e.to_string()))?;
+        let mut matched = false;
+        for c in &constraints {
+            if let Some((_, flag)) = c.rsplit_once('@')
+                && let Some(stability) = StabilityFlag::parse(flag)
+            {
+                let stability = stability.as_u8();
+                if flags.get(name).is_some_and(|&f| f > stability) {
+                    continue;
+                }
+                flags.insert(name.clone(), stability);
+                matched = true;
             }
-            writeln!(w).map_err(|e| LockfileError::Serialization(e.to_string()))?;
-            write!(w, "        \"{}\"", escape_json(line))
-                .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        }
-        writeln!(w, "\n    ],").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // content-hash
-        writeln!(w, "    \"content-hash\": \"{}\",", lock.content_hash)
-            .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // packages
-        Self::write_packages(w, "packages", &lock.packages)?;
-        writeln!(w, ",").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // packages-dev
-        Self::write_packages(w, "packages-dev", &lock.packages_dev)?;
-        writeln!(w, ",").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // aliases
-        write!(w, "    \"aliases\": ").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        if lock.aliases.is_empty() {
-            write!(w, "[]").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        } else {
-            Self::write_aliases(w, &lock.aliases)?;
         }
-        writeln!(w, ",").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // minimum-stability
-        writeln!(
-            w,
-            "    \"minimum-stability\": \"{}\",",
-            escape_json(&lock.minimum_stability)
-        )
-        .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // stability-flags
-        write!(w, "    \"stability-flags\": ")
-            .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        Self::write_btree_u8(w, &lock.stability_flags)?;
-        writeln!(w, ",").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // prefer-stable
-        writeln!(w, "    \"prefer-stable\": {},", lock.prefer_stable)
-            .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // prefer-lowest
-        writeln!(w, "    \"prefer-lowest\": {},", lock.prefer_lowest)
-            .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // platform
-        write!(w, "    \"platform\": ").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        Self::write_btree_string(w, &lock.platform)?;
-        writeln!(w, ",").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // platform-dev
-        write!(w, "    \"platform-dev\": ")
-            .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        Self::write_btree_string(w, &lock.platform_dev)?;
-        writeln!(w, ",").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // plugin-api-version
-        writeln!(
-            w,
-            "    \"plugin-api-version\": \"{}\"",
-            escape_json(&lock.plugin_api_version)
-        )
-        .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        writeln!(w, "}}").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        Ok(())
-    }
-
-    /// Write packages array.
-    fn write_packages<W: Write>(w: &mut W, name: &str, packages: &[LockedPackage]) -> Result<()> {
-        write!(w, "    \"{name}\": ").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        if packages.is_empty() {
-            write!(w, "[]").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-            return Ok(());
+        if matched {
+            continue;
         }
 
-        writeln!(w, "[").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        for (i, pkg) in packages.iter().enumerate() {
-            Self::write_package(w, pkg, 8)?;
-            if i < packages.len() - 1 {
-                writeln!(w, ",").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-            } else {
-                writeln!(w).map_err(|e| LockfileError::Serialization(e.to_string()))?;
+        for c in &constraints {
+            let stability = version_stability(c).as_u8();
+            if stability == StabilityFlag::Stable.as_u8()
+                || minimum_stability.as_u8() > stability
+                || flags.get(name).is_some_and(|&f| f > stability)
+            {
+                continue;
             }
+            flags.insert(name.clone(), stability);
         }
-
-        write!(w, "    ]").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        Ok(())
     }
 
-    /// Write a single package.
-    fn write_package<W: Write>(w: &mut W, pkg: &LockedPackage, indent: usize) -> Result<()> {
-        let prefix = " ".repeat(indent);
-        let inner = " ".repeat(indent + 4);
-
-        writeln!(w, "{prefix}{{").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-
-        // Collect all fields including required ones in sorted order
-        let mut fields: Vec<(&str, String)> = Vec::new();
-
-        // Required fields
-        fields.push(("name", format!("\"{}\"", escape_json(&pkg.name))));
-        fields.push(("version", format!("\"{}\"", escape_json(&pkg.version))));
-
-        // source
-        if let Some(ref source) = pkg.source {
-            fields.push(("source", Self::format_source(source)));
-        }
-
-        // dist
-        if let Some(ref dist) = pkg.dist {
-            fields.push(("dist", Self::format_dist(dist)));
-        }
-
-        // require
-        if !pkg.require.is_empty() {
-            fields.push(("require", Self::format_btree(&pkg.require)));
-        }
-
-        // require-dev
-        if !pkg.require_dev.is_empty() {
-            fields.push(("require-dev", Self::format_btree(&pkg.require_dev)));
-        }
-
-        // type
-        if let Some(ref t) = pkg.package_type {
-            fields.push(("type", format!("\"{}\"", escape_json(t))));
-        }
-
-        // autoload
-        if let Some(ref autoload) = pkg.autoload
-            && !is_autoload_empty(autoload)
-        {
-            fields.push(("autoload", Self::format_autoload(autoload)));
-        }
-
-        // notification-url
-        if let Some(ref url) = pkg.notification_url {
-            fields.push(("notification-url", format!("\"{}\"", escape_json(url))));
-        }
-
-        // license
-        if !pkg.license.is_empty() {
-            fields.push(("license", Self::format_string_array(&pkg.license)));
-        }
-
-        // authors
-        if !pkg.authors.is_empty() {
-            fields.push(("authors", Self::format_authors(&pkg.authors)));
-        }
-
-        // description
-        if let Some(ref desc) = pkg.description {
-            fields.push(("description", format!("\"{}\"", escape_json(desc))));
-        }
-
-        // homepage
-        if let Some(ref hp) = pkg.homepage {
-            fields.push(("homepage", format!("\"{}\"", escape_json(hp))));
-        }
-
-        // keywords
-        if !pkg.keywords.is_empty() {
-            fields.push(("keywords", Self::format_string_array(&pkg.keywords)));
-        }
-
-        // time
-        if let Some(ref time) = pkg.time {
-            fields.push(("time", format!("\"{}\"", escape_json(time))));
-        }
-
-        // Sort and write fields
-        fields.sort_by(|a, b| a.0.cmp(b.0));
-
-        for (i, (name, value)) in fields.iter().enumerate() {
-            let trailing = if i < fields.len() - 1 { "," } else { "" };
-            writeln!(w, "{inner}\"{name}\": {value}{trailing}")
-                .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        }
+    flags
+}
 
-        write!(w, "{prefix}}}").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        Ok(())
+/// Stability of a single version string, per `VersionParser::parseStability`.
+fn version_stability(version: &str) -> StabilityFlag {
+    let version = version.split('#').next().unwrap_or(version).to_lowercase();
+    if version.starts_with("dev-") || version.ends_with("-dev") {
+        return StabilityFlag::Dev;
+    }
+    let Some((_, suffix)) = version.rsplit_once('-') else {
+        return StabilityFlag::Stable;
+    };
+    let suffix = suffix.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    match suffix {
+        "beta" | "b" => StabilityFlag::Beta,
+        "alpha" | "a" => StabilityFlag::Alpha,
+        "rc" => StabilityFlag::Rc,
+        _ => StabilityFlag::Stable,
     }
+}
 
-    fn format_source(source: &PackageSourceInfo) -> String {
-        format!(
-            "{{ \"type\": \"{}\", \"url\": \"{}\", \"reference\": \"{}\" }}",
-            escape_json(&source.source_type),
-            escape_json(&source.url),
-            escape_json(&source.reference)
-        )
-    }
+/// Serializer producing composer.lock bytes exactly as Composer 2 writes them.
+///
+/// Ensures:
+/// - Composer's key order for the lock and for each package
+/// - PHP `json_encode` pretty-print layout and escaping
+/// - `{}` for the empty top-level maps Composer writes as objects
+/// - A trailing newline
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeterministicSerializer;
 
-    fn format_dist(dist: &PackageDistInfo) -> String {
-        let mut parts = vec![
-            format!("\"type\": \"{}\"", escape_json(&dist.dist_type)),
-            format!("\"url\": \"{}\"", escape_json(&dist.url)),
-        ];
-        if let Some(ref r) = dist.reference {
-            parts.push(format!("\"reference\": \"{}\"", escape_json(r)));
-        }
-        if let Some(ref s) = dist.shasum {
-            parts.push(format!("\"shasum\": \"{}\"", escape_json(s)));
+impl DeterministicSerializer {
+    /// Serialize lock file to deterministic JSON.
+    ///
+    /// # Errors
+    /// Returns error if serialization fails.
+    pub fn serialize(lock: &ComposerLock) -> Result<String> {
+        let mut out = String::with_capacity(64 * 1024); // Pre-allocate 64KB
+        out.push('{');
+
+        let mut fields = Fields::new(&mut out);
+        fields.value("_readme", &to_value(&lock.readme)?);
+        fields.value("content-hash", &Value::from(lock.content_hash.as_str()));
+        fields.packages("packages", &lock.packages)?;
+        fields.packages("packages-dev", &lock.packages_dev)?;
+        fields.value("aliases", &to_value(&lock.aliases)?);
+        fields.value(
+            "minimum-stability",
+            &Value::from(lock.minimum_stability.as_str()),
+        );
+        fields.object("stability-flags", &to_value(&lock.stability_flags)?);
+        fields.value("prefer-stable", &Value::from(lock.prefer_stable));
+        fields.value("prefer-lowest", &Value::from(lock.prefer_lowest));
+        fields.object("platform", &to_value(&lock.platform)?);
+        fields.object("platform-dev", &to_value(&lock.platform_dev)?);
+        if !lock.platform_overrides.is_empty() {
+            fields.value("platform-overrides", &to_value(&lock.platform_overrides)?);
         }
-        format!("{{ {} }}", parts.join(", "))
-    }
+        fields.value(
+            "plugin-api-version",
+            &Value::from(lock.plugin_api_version.as_str()),
+        );
 
-    fn format_btree(map: &BTreeMap<String, String>) -> String {
-        if map.is_empty() {
-            return "{}".to_string();
-        }
-        let pairs: Vec<String> = map
-            .iter()
-            .map(|(k, v)| format!("\"{}\": \"{}\"", escape_json(k), escape_json(v)))
-            .collect();
-        format!("{{ {} }}", pairs.join(", "))
+        out.push_str("\n}\n");
+        Ok(out)
     }
+}
 
-    fn format_string_array(arr: &[String]) -> String {
-        let items: Vec<String> = arr
-            .iter()
-            .map(|s| format!("\"{}\"", escape_json(s)))
-            .collect();
-        format!("[{}]", items.join(", "))
-    }
+/// Writes the top-level members of the lock object.
+struct Fields<'a> {
+    out: &'a mut String,
+    first: bool,
+}
 
-    fn format_authors(authors: &[PackageAuthor]) -> String {
-        let items: Vec<String> = authors
-            .iter()
-            .map(|a| {
-                let mut parts = vec![format!("\"name\": \"{}\"", escape_json(&a.name))];
-                if let Some(ref email) = a.email {
-                    parts.push(format!("\"email\": \"{}\"", escape_json(email)));
-                }
-                if let Some(ref hp) = a.homepage {
-                    parts.push(format!("\"homepage\": \"{}\"", escape_json(hp)));
-                }
-                if let Some(ref role) = a.role {
-                    parts.push(format!("\"role\": \"{}\"", escape_json(role)));
-                }
-                format!("{{ {} }}", parts.join(", "))
-            })
-            .collect();
-        format!("[{}]", items.join(", "))
+impl<'a> Fields<'a> {
+    const fn new(out: &'a mut String) -> Self {
+        Self { out, first: true }
     }
 
-    fn format_autoload(autoload: &AutoloadConfig) -> String {
-        let mut parts = Vec::new();
-
-        if !autoload.psr4.is_empty() {
-            let psr4: Vec<String> = autoload
-                .psr4
-                .iter()
-                .map(|(k, v)| {
-                    let value = match v {
-                        AutoloadPath::Single(s) => format!("\"{}\"", escape_json(s)),
-                        AutoloadPath::Multiple(arr) => {
-                            let items: Vec<String> = arr
-                                .iter()
-                                .map(|s| format!("\"{}\"", escape_json(s)))
-                                .collect();
-                            format!("[{}]", items.join(", "))
-                        }
-                    };
-                    format!("\"{}\": {}", escape_json(k), value)
-                })
-                .collect();
-            parts.push(format!("\"psr-4\": {{ {} }}", psr4.join(", ")));
-        }
-
-        if !autoload.psr0.is_empty() {
-            let psr0: Vec<String> = autoload
-                .psr0
-                .iter()
-                .map(|(k, v)| {
-                    let value = match v {
-                        AutoloadPath::Single(s) => format!("\"{}\"", escape_json(s)),
-                        AutoloadPath::Multiple(arr) => {
-                            let items: Vec<String> = arr
-                                .iter()
-                                .map(|s| format!("\"{}\"", escape_json(s)))
-                                .collect();
-                            format!("[{}]", items.join(", "))
-                        }
-                    };
-                    format!("\"{}\": {}", escape_json(k), value)
-                })
-                .collect();
-            parts.push(format!("\"psr-0\": {{ {} }}", psr0.join(", ")));
-        }
-
-        if !autoload.classmap.is_empty() {
-            let items: Vec<String> = autoload
-                .classmap
-                .iter()
-                .map(|s| format!("\"{}\"", escape_json(s)))
-                .collect();
-            parts.push(format!("\"classmap\": [{}]", items.join(", ")));
-        }
-
-        if !autoload.files.is_empty() {
-            let items: Vec<String> = autoload
-                .files
-                .iter()
-                .map(|s| format!("\"{}\"", escape_json(s)))
-                .collect();
-            parts.push(format!("\"files\": [{}]", items.join(", ")));
+    fn key(&mut self, key: &str) {
+        if !self.first {
+            self.out.push(',');
         }
+        self.first = false;
+        self.out.push_str("\n    ");
+        json::write_string(self.out, key, PhpJsonStyle::Pretty);
+        self.out.push_str(": ");
+    }
 
-        format!("{{ {} }}", parts.join(", "))
-    }
-
-    fn write_aliases<W: Write>(w: &mut W, aliases: &[PackageAlias]) -> Result<()> {
-        writeln!(w, "[").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        for (i, alias) in aliases.iter().enumerate() {
-            let trailing = if i < aliases.len() - 1 { "," } else { "" };
-            writeln!(
-                w,
-                "        {{ \"package\": \"{}\", \"version\": \"{}\", \"alias\": \"{}\", \"alias_normalized\": \"{}\" }}{}",
-                escape_json(&alias.package),
-                escape_json(&alias.version),
-                escape_json(&alias.alias),
-                escape_json(&alias.alias_normalized),
-                trailing
-            )
-            .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        }
-        write!(w, "    ]").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        Ok(())
+    fn value(&mut self, key: &str, value: &Value) {
+        self.key(key);
+        json::write_value(self.out, value, PhpJsonStyle::Pretty, 1);
     }
 
-    fn write_btree_string<W: Write>(w: &mut W, map: &BTreeMap<String, String>) -> Result<()> {
-        if map.is_empty() {
-            write!(w, "{{}}").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-            return Ok(());
+    /// A map Composer writes as `new \stdClass` when empty.
+    fn object(&mut self, key: &str, value: &Value) {
+        if value.as_object().is_some_and(|o| o.is_empty()) {
+            self.key(key);
+            self.out.push_str("{}");
+        } else {
+            self.value(key, value);
         }
-        write!(w, "{{ ").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        let pairs: Vec<String> = map
-            .iter()
-            .map(|(k, v)| format!("\"{}\": \"{}\"", escape_json(k), escape_json(v)))
-            .collect();
-        write!(w, "{}", pairs.join(", "))
-            .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        write!(w, " }}").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        Ok(())
     }
 
-    fn write_btree_u8<W: Write>(w: &mut W, map: &BTreeMap<String, u8>) -> Result<()> {
-        if map.is_empty() {
-            write!(w, "{{}}").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-            return Ok(());
+    fn packages(&mut self, key: &str, packages: &[LockedPackage]) -> Result<()> {
+        let mut items = sonic_rs::Array::with_capacity(packages.len());
+        for pkg in packages {
+            let mut value = to_value(pkg)?;
+            // ArrayDumper sorts keywords
+            if let Some(keywords) = value
+                .as_object_mut()
+                .and_then(|o| o.get_mut(&"keywords"))
+                .and_then(|v| v.as_array_mut())
+            {
+                keywords.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+            }
+            items.push(value);
         }
-        write!(w, "{{ ").map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        let pairs: Vec<String> = map
-            .iter()
-            .map(|(k, v)| format!("\"{}\": {}", escape_json(k), v))
-            .collect();
-        write!(w, "{}", pairs.join(", "))
-            .map_err(|e| LockfileError::Serialization(e.to_string()))?;
-        write!(w, " }}").map_err(|e| LockfileError::Serialization(e.to_string()))?;
+        self.value(key, &items.into_value());
         Ok(())
     }
 }
 
-/// Check if autoload config is empty.
-fn is_autoload_empty(autoload: &AutoloadConfig) -> bool {
-    autoload.psr4.is_empty()
-        && autoload.psr0.is_empty()
-        && autoload.classmap.is_empty()
-        && autoload.files.is_empty()
-}
-
-/// Escape special characters in JSON strings.
-fn escape_json(s: &str) -> String {
-    let mut result = String::with_capacity(s.len());
-    for c in s.chars() {
-        match c {
-            '"' => result.push_str("\\\""),
-            '\\' => result.push_str("\\\\"),
-            '\n' => result.push_str("\\n"),
-            '\r' => result.push_str("\\r"),
-            '\t' => result.push_str("\\t"),
-            c if c.is_control() => {
-                result.push_str(&format!("\\u{:04x}", c as u32));
-            }
-            c => result.push(c),
-        }
-    }
-    result
+fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
+    sonic_rs::to_value(value).map_err(|e| LockfileError::Serialization(e.to_string()))
 }
 
 /// Wrapper for deterministic serialization via serde.
@@ -637,9 +497,9 @@ impl Serialize for DeterministicLock<'_> {
     where
         S: Serializer,
     {
-        // Custom serialization with sorted keys
+        // Composer's key order
         let lock = self.0;
-        let mut map = serializer.serialize_map(Some(12))?;
+        let mut map = serializer.serialize_map(None)?;
 
         map.serialize_entry("_readme", &lock.readme)?;
         map.serialize_entry("content-hash", &lock.content_hash)?;
@@ -652,6 +512,9 @@ impl Serialize for DeterministicLock<'_> {
         map.serialize_entry("prefer-lowest", &lock.prefer_lowest)?;
         map.serialize_entry("platform", &lock.platform)?;
         map.serialize_entry("platform-dev", &lock.platform_dev)?;
+        if !lock.platform_overrides.is_empty() {
+            map.serialize_entry("platform-overrides", &lock.platform_overrides)?;
+        }
         map.serialize_entry("plugin-api-version", &lock.plugin_api_version)?;
 
         map.end()
@@ -661,6 +524,7 @@ impl Serialize for DeterministicLock<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{PackageDistInfo, PackageSourceInfo};
 
     #[test]
     fn test_generator_basic() {
@@ -716,10 +580,142 @@ mod tests {
     }
 
     #[test]
-    fn test_escape_json() {
-        assert_eq!(escape_json("hello"), "hello");
-        assert_eq!(escape_json("hello\"world"), "hello\\\"world");
-        assert_eq!(escape_json("line1\nline2"), "line1\\nline2");
-        assert_eq!(escape_json("path\\to\\file"), "path\\\\to\\\\file");
+    fn test_serializer_matches_composer_layout() {
+        let manifest: Value = sonic_rs::from_str(
+            r#"{"require": {"php": ">=8.1", "acme/lib": "^1.0", "acme/next": "2.0.0-beta1"}}"#,
+        )
+        .unwrap();
+        let mut pkg = LockedPackage::new("acme/lib", "v1.2.0")
+            .with_source(PackageSourceInfo::git(
+                "https://github.com/acme/lib.git",
+                "abc",
+            ))
+            .with_dist(
+                PackageDistInfo::zip("https://api.github.com/repos/acme/lib/zipball/abc")
+                    .with_reference("abc")
+                    .with_shasum(""),
+            );
+        pkg.require.insert("php".to_string(), ">=8.1".to_string());
+        pkg.package_type = Some("library".to_string());
+        pkg.autoload = Some(
+            sonic_rs::from_str(r#"{"files": ["src/fn.php"], "psr-4": {"Acme\\": "src/"}}"#)
+                .unwrap(),
+        );
+        pkg.keywords = vec!["zeta".to_string(), "alpha".to_string()];
+        pkg.time = Some("2024-01-02T03:04:05+00:00".to_string());
+        pkg.description = Some("Ünïcode/description".to_string());
+
+        let mut generator = LockGenerator::from_manifest(&manifest);
+        generator.add_package(pkg);
+        let lock = generator.build();
+
+        let expected = r#"{
+    "_readme": [
+        "This file locks the dependencies of your project to a known state",
+        "Read more about it at https://getcomposer.org/doc/01-basic-usage.md#installing-dependencies",
+        "This file is @generated automatically"
+    ],
+    "content-hash": "HASH",
+    "packages": [
+        {
+            "name": "acme/lib",
+            "version": "v1.2.0",
+            "source": {
+                "type": "git",
+                "url": "https://github.com/acme/lib.git",
+                "reference": "abc"
+            },
+            "dist": {
+                "type": "zip",
+                "url": "https://api.github.com/repos/acme/lib/zipball/abc",
+                "reference": "abc",
+                "shasum": ""
+            },
+            "require": {
+                "php": ">=8.1"
+            },
+            "type": "library",
+            "autoload": {
+                "files": [
+                    "src/fn.php"
+                ],
+                "psr-4": {
+                    "Acme\\": "src/"
+                }
+            },
+            "description": "Ünïcode/description",
+            "keywords": [
+                "alpha",
+                "zeta"
+            ],
+            "time": "2024-01-02T03:04:05+00:00"
+        }
+    ],
+    "packages-dev": [],
+    "aliases": [],
+    "minimum-stability": "stable",
+    "stability-flags": {
+        "acme/next": 10
+    },
+    "prefer-stable": false,
+    "prefer-lowest": false,
+    "platform": {
+        "php": ">=8.1"
+    },
+    "platform-dev": {},
+    "plugin-api-version": "2.6.0"
+}
+"#
+        .replace("HASH", &lock.content_hash);
+
+        let json = DeterministicSerializer::serialize(&lock).unwrap();
+        assert_eq!(json, expected);
+
+        // Reading and writing back is lossless
+        let reread: ComposerLock = sonic_rs::from_str(&json).unwrap();
+        assert_eq!(DeterministicSerializer::serialize(&reread).unwrap(), json);
+    }
+
+    #[test]
+    fn test_reads_php_empty_arrays_as_maps() {
+        let lock: ComposerLock = sonic_rs::from_str(
+            r#"{"_readme": [], "content-hash": "x", "packages": [], "packages-dev": [],
+                "aliases": [], "minimum-stability": "stable", "stability-flags": [],
+                "prefer-stable": false, "prefer-lowest": false, "platform": [],
+                "platform-dev": [], "plugin-api-version": "2.6.0"}"#,
+        )
+        .unwrap();
+        assert!(lock.platform.is_empty());
+        assert!(lock.stability_flags.is_empty());
+    }
+
+    #[test]
+    fn test_stability_flags_from_constraints() {
+        let requires = [
+            ("a/explicit".to_string(), "^1.0@dev".to_string()),
+            ("b/branch".to_string(), "dev-main".to_string()),
+            ("c/stable".to_string(), "^2.0".to_string()),
+            ("d/rc".to_string(), "1.0.0-RC2".to_string()),
+        ];
+        let flags = extract_stability_flags(requires.iter(), StabilityFlag::Stable);
+        assert_eq!(flags.get("a/explicit"), Some(&20));
+        assert_eq!(flags.get("b/branch"), Some(&20));
+        assert_eq!(flags.get("c/stable"), None);
+        assert_eq!(flags.get("d/rc"), Some(&5));
+
+        // Inferred flags only record what minimum-stability does not already allow
+        let flags = extract_stability_flags(requires.iter(), StabilityFlag::Alpha);
+        assert_eq!(flags.get("d/rc"), None);
+        assert_eq!(flags.get("b/branch"), Some(&20));
+    }
+
+    #[test]
+    fn test_platform_package_names() {
+        assert!(is_platform_package("php"));
+        assert!(is_platform_package("ext-mbstring"));
+        assert!(is_platform_package("lib-icu-uc"));
+        assert!(is_platform_package("composer-runtime-api"));
+        assert!(!is_platform_package("php-http/client"));
+        assert!(!is_platform_package("ext-"));
     }
 }
@@ -70,12 +70,67 @@ impl ContentHasher {
         bytes_to_hex(&result)
     }
 
-    /// Compute content-hash from composer.json dependencies.
+    /// Compute Composer's `content-hash` for a composer.json document.
     ///
-    /// Matches Composer's exact algorithm:
-    /// - Sort require/require-dev alphabetically
-    /// - Serialize to JSON without whitespace
-    /// - MD5 hash the result
+    /// Mirrors `Locker::getContentHash`: the dependency-relevant keys and
+    /// `config.platform` are picked out, sorted by key at the top level only,
+    /// encoded with PHP's default `json_encode` flags and MD5-hashed.
+    #[must_use]
+    pub fn composer_content_hash(manifest: &sonic_rs::Value) -> String {
+        use sonic_rs::{JsonValueTrait, Object, Value};
+
+        const RELEVANT_KEYS: [&str; 11] = [
+            "name",
+            "version",
+            "require",
+            "require-dev",
+            "conflict",
+            "replace",
+            "provide",
+            "minimum-stability",
+            "prefer-stable",
+            "repositories",
+            "extra",
+        ];
+
+        let mut relevant: Vec<(&str, Value)> = RELEVANT_KEYS
+            .iter()
+            .filter_map(|&key| {
+                manifest
+                    .get(key)
+                    .filter(|v| !v.is_null())
+                    .map(|v| (key, v.clone()))
+            })
+            .collect();
+
+        if let Some(platform) = manifest
+            .get("config")
+            .and_then(|c| c.get("platform"))
+            .filter(|v| !v.is_null())
+        {
+            let mut config = Object::new();
+            config.insert("platform", platform.clone());
+            relevant.push(("config", config.into_value()));
+        }
+
+        relevant.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut object = Object::with_capacity(relevant.len());
+        for (key, value) in relevant {
+            object.insert(key, value);
+        }
+
+        let json = crate::json::encode(&object.into_value(), crate::json::PhpJsonStyle::Compact);
+        let mut hasher = Self::new();
+        hasher.update(json.as_bytes());
+        hasher.finalize()
+    }
+
+    /// Compute a content hash from composer.json dependency maps.
+    ///
+    /// A fast approximation used for drift checks when only the dependency
+    /// maps are at hand. It does not match Composer's `content-hash`; use
+    /// [`Self::composer_content_hash`] for that.
     #[must_use]
     pub fn compute_content_hash(
         require: &BTreeMap<String, String>,
@@ -498,6 +553,28 @@ pub fn verify_hash(actual: &str, expected: &str) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_composer_content_hash() {
+        let manifest: sonic_rs::Value = sonic_rs::from_str(
+            r#"{
+                "name": "acme/app",
+                "description": "not part of the hash",
+                "require": {"php": ">=8.1", "monolog/monolog": "^3.0"},
+                "require-dev": {},
+                "config": {"platform": {"php": "8.1.0"}, "sort-packages": true}
+            }"#,
+        )
+        .unwrap();
+
+        // md5 of PHP's json_encode of the ksorted relevant keys:
+        // {"config":{"platform":{"php":"8.1.0"}},"name":"acme\/app",
+        //  "require":{"php":">=8.1","monolog\/monolog":"^3.0"},"require-dev":[]}
+        assert_eq!(
+            ContentHasher::composer_content_hash(&manifest),
+            "b0e63c0d28a7ca1080e990369282f729"
+        );
+    }
+
     #[test]
     fn test_content_hash_empty() {
         let hash = ContentHasher::compute_content_hash(
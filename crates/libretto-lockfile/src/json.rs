@@ -0,0 +1,167 @@
+//! PHP `json_encode` compatible output.
+//!
+//! Composer writes composer.lock with `JSON_UNESCAPED_SLASHES |
+//! JSON_PRETTY_PRINT | JSON_UNESCAPED_UNICODE` and hashes composer.json with
+//! no flags at all. Matching its bytes means matching PHP's escaping and
+//! layout rules, not just producing valid JSON.
+//!
+//! Composer decodes JSON into PHP arrays, so an empty object comes back out
+//! as `[]`. Both encoders do the same.
+
+use sonic_rs::{Value, ValueRef};
+
+/// How to encode a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhpJsonStyle {
+    /// `json_encode($value)`: no whitespace, `\/` and `\uXXXX` escapes.
+    Compact,
+    /// `json_encode($value, 448)`: four-space indent, literal UTF-8.
+    Pretty,
+}
+
+/// Encode a value the way PHP's `json_encode` would.
+#[must_use]
+pub fn encode(value: &Value, style: PhpJsonStyle) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, style, 0);
+    out
+}
+
+/// Append an encoded value; `depth` is the current pretty-print nesting.
+pub fn write_value(out: &mut String, value: &Value, style: PhpJsonStyle, depth: usize) {
+    match value.as_ref() {
+        ValueRef::Null => out.push_str("null"),
+        ValueRef::Bool(b) => out.push_str(if b { "true" } else { "false" }),
+        ValueRef::Number(n) => out.push_str(&n.to_string()),
+        ValueRef::String(s) => write_string(out, s, style),
+        ValueRef::Array(items) => {
+            if items.is_empty() {
+                out.push_str("[]");
+                return;
+            }
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                separator(out, i, style, depth + 1);
+                write_value(out, item, style, depth + 1);
+            }
+            close(out, ']', style, depth);
+        }
+        ValueRef::Object(object) => {
+            if object.is_empty() {
+                out.push_str("[]");
+                return;
+            }
+            out.push('{');
+            for (i, (key, item)) in object.iter().enumerate() {
+                separator(out, i, style, depth + 1);
+                write_string(out, key, style);
+                out.push_str(if style == PhpJsonStyle::Pretty {
+                    ": "
+                } else {
+                    ":"
+                });
+                write_value(out, item, style, depth + 1);
+            }
+            close(out, '}', style, depth);
+        }
+    }
+}
+
+/// Append a quoted, escaped string.
+pub fn write_string(out: &mut String, s: &str, style: PhpJsonStyle) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '/' if style == PhpJsonStyle::Compact => out.push_str("\\/"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\u{2028}' || c == '\u{2029}' => {
+                push_unicode_escape(out, c as u32);
+            }
+            c if !c.is_ascii() && style == PhpJsonStyle::Compact => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    push_unicode_escape(out, u32::from(*unit));
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn push_unicode_escape(out: &mut String, unit: u32) {
+    use std::fmt::Write;
+    let _ = write!(out, "\\u{unit:04x}");
+}
+
+fn separator(out: &mut String, index: usize, style: PhpJsonStyle, depth: usize) {
+    if index > 0 {
+        out.push(',');
+    }
+    if style == PhpJsonStyle::Pretty {
+        out.push('\n');
+        indent(out, depth);
+    }
+}
+
+fn close(out: &mut String, bracket: char, style: PhpJsonStyle, depth: usize) {
+    if style == PhpJsonStyle::Pretty {
+        out.push('\n');
+        indent(out, depth);
+    }
+    out.push(bracket);
+}
+
+/// Append PHP's four-space pretty-print indentation.
+pub fn indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("    ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pretty_layout() {
+        let value: Value =
+            sonic_rs::from_str(r#"{"a":[1,{"b":"c/d"}],"e":{},"f":[],"g":true}"#).unwrap();
+        assert_eq!(
+            encode(&value, PhpJsonStyle::Pretty),
+            "{\n    \"a\": [\n        1,\n        {\n            \"b\": \"c/d\"\n        }\n    ],\n    \"e\": [],\n    \"f\": [],\n    \"g\": true\n}"
+        );
+    }
+
+    #[test]
+    fn test_escape_json() {
+        let escape = |s: &str| {
+            let mut out = String::new();
+            write_string(&mut out, s, PhpJsonStyle::Pretty);
+            out
+        };
+        assert_eq!(escape("hello"), "\"hello\"");
+        assert_eq!(escape("hello\"world"), "\"hello\\\"world\"");
+        assert_eq!(escape("line1\nline2"), "\"line1\\nline2\"");
+        assert_eq!(escape("path\\to\\file"), "\"path\\\\to\\\\file\"");
+    }
+
+    #[test]
+    fn test_escaping_matches_php() {
+        let value = Value::from("a/b \"é\" \u{1}\u{7f}\u{2028}😀\\");
+        assert_eq!(
+            encode(&value, PhpJsonStyle::Pretty),
+            "\"a/b \\\"é\\\" \\u0001\u{7f}\\u2028😀\\\\\""
+        );
+        assert_eq!(
+            encode(&value, PhpJsonStyle::Compact),
+            "\"a\\/b \\\"\\u00e9\\\" \\u0001\u{7f}\\u2028\\ud83d\\ude00\\\\\""
+        );
+    }
+}
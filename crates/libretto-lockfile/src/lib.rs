@@ -7,6 +7,7 @@
 //! - **Deterministic output**: Same input always produces identical output
 //! - **Full validation**: Structural checks, drift detection, manual edit detection
 //! - **Migration support**: Handles old lock file versions
+//! - **Policy checks**: License, dist host and checksum rules for CI gates
 //!
 //! # Performance
 //!
@@ -53,7 +54,10 @@ pub mod diff;
 pub mod error;
 pub mod generator;
 pub mod hash;
+pub mod json;
+pub mod merge;
 pub mod migration;
+pub mod policy;
 pub mod types;
 pub mod validation;
 
@@ -62,10 +66,12 @@ pub use diff::{ChangeType, FieldChange, LockDiff, PackageChange, compute_diff};
 pub use error::{LockfileError, Result};
 pub use generator::{DeterministicSerializer, LockGenerator};
 pub use hash::{ContentHasher, IntegrityHasher, ParallelHasher, bytes_to_hex, hex_to_bytes};
+pub use merge::{LockMerge, MergeConflict, merge_locks};
 pub use migration::{MigrationResult, Migrator, SchemaVersion};
+pub use policy::{Policy, PolicyRule, PolicyViolation};
 pub use types::{
-    AutoloadConfig, AutoloadPath, ComposerLock, LockedPackage, PackageAlias, PackageAuthor,
-    PackageDistInfo, PackageSourceInfo, StabilityFlag,
+    AbandonedValue, ArchiveConfig, ComposerLock, LockedPackage, PackageAlias, PackageDistInfo,
+    PackageSourceInfo, StabilityFlag,
 };
 pub use validation::{DriftDetector, DriftResult, ManualEditDetector, ValidationResult, Validator};
 
@@ -90,6 +96,8 @@ pub struct LockfileManager {
     auto_migrate: bool,
     /// Whether to create backups on write.
     create_backup: bool,
+    /// Whether to take `.lck` file locks around reads and writes.
+    file_lock: bool,
 }
 
 impl LockfileManager {
@@ -107,6 +115,7 @@ impl LockfileManager {
             validator: Validator::new(),
             auto_migrate: true,
             create_backup: true,
+            file_lock: true,
         })
     }
 
@@ -131,6 +140,16 @@ impl LockfileManager {
         self
     }
 
+    /// Disable `.lck` file locking.
+    ///
+    /// For callers that already serialize access to the lock file, so no
+    /// lock file is left next to it.
+    #[must_use]
+    pub const fn no_file_lock(mut self) -> Self {
+        self.file_lock = false;
+        self
+    }
+
     /// Get the lock file path.
     #[must_use]
     pub fn path(&self) -> &Path {
@@ -152,27 +171,25 @@ impl LockfileManager {
     pub fn read(&self) -> Result<ComposerLock> {
         debug!(path = %self.path.display(), "Reading lock file");
 
-        let reader = AtomicReader::new(&self.path)?;
+        let reader = if self.file_lock {
+            AtomicReader::new(&self.path)?
+        } else {
+            AtomicReader::unlocked(&self.path)
+        };
         if !reader.exists() {
             return Err(LockfileError::NotFound(self.path.clone()));
         }
 
         let content = reader.read_string()?;
-        let lock: ComposerLock = sonic_rs::from_str(&content)?;
-
-        // Auto-migrate if needed
-        if self.auto_migrate && migration::needs_migration(&content) {
-            info!("Lock file needs migration, auto-migrating");
-            let result = Migrator::new().migrate(lock)?;
-            if result.has_changes() {
-                for change in &result.changes {
-                    debug!("Migration: {}", change);
-                }
-            }
-            return Ok(result.lock);
+        if self.auto_migrate {
+            // A Composer 1.x lock is checked against the composer.json it sits next to
+            let manifest = migration::needs_migration(&content)
+                .then(|| std::fs::read_to_string(self.path.with_extension("json")).ok())
+                .flatten();
+            return migrate_lock(&content, manifest.as_deref());
         }
 
-        Ok(lock)
+        Ok(sonic_rs::from_str(&content)?)
     }
 
     /// Read and validate the lock file.
@@ -199,7 +216,11 @@ impl LockfileManager {
         let content = DeterministicSerializer::serialize(lock)?;
 
         // Write atomically
-        let mut writer = AtomicWriter::new(&self.path)?;
+        let mut writer = if self.file_lock {
+            AtomicWriter::new(&self.path)?
+        } else {
+            AtomicWriter::unlocked(&self.path)
+        };
         writer.content(content.as_bytes());
         if !self.create_backup {
             writer.no_backup();
@@ -422,6 +443,31 @@ impl LockfileManager {
     }
 }
 
+/// Parse lock file content that did not come from disk, such as a git blob.
+///
+/// Old versions are migrated the same way [`LockfileManager::read`] does.
+///
+/// # Errors
+/// Returns error if the content cannot be parsed or migrated.
+pub fn parse_lock(content: &str) -> Result<ComposerLock> {
+    migrate_lock(content, None)
+}
+
+fn migrate_lock(content: &str, manifest: Option<&str>) -> Result<ComposerLock> {
+    if !migration::needs_migration(content) {
+        return Ok(sonic_rs::from_str(content)?);
+    }
+
+    info!("Lock file needs migration, auto-migrating");
+    let result = Migrator::new().migrate_str(content, manifest)?;
+    if result.has_changes() {
+        for change in &result.changes {
+            debug!("Migration: {}", change);
+        }
+    }
+    Ok(result.lock)
+}
+
 /// Lock file statistics.
 #[derive(Debug, Clone)]
 pub struct LockStats {
@@ -540,6 +586,29 @@ mod tests {
         assert_eq!(loaded.packages[0].name, "vendor/pkg");
     }
 
+    #[test]
+    fn test_manager_no_file_lock() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("composer.lock");
+        let manager = LockfileManager::new(&path)
+            .unwrap()
+            .no_backup()
+            .no_file_lock();
+
+        let mut generator = LockGenerator::new();
+        generator.add_package(LockedPackage::new("vendor/pkg", "1.0.0"));
+        manager
+            .write(&generator.generate(&BTreeMap::new(), &BTreeMap::new()))
+            .unwrap();
+        assert_eq!(manager.read().unwrap().packages.len(), 1);
+
+        let files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(files, ["composer.lock"]);
+    }
+
     #[test]
     fn test_manager_update() {
         let dir = TempDir::new().unwrap();
@@ -0,0 +1,290 @@
+//! Three-way merge of lock files.
+//!
+//! The package changes each side made relative to the common ancestor are
+//! computed with [`compute_diff`] and replayed onto our side. Changes only
+//! one side made apply cleanly, as do identical changes on both sides. A
+//! package both sides changed differently is a conflict: the merged lock
+//! keeps our entry for it and the caller decides which version wins.
+
+use crate::diff::compute_diff;
+use crate::types::{ComposerLock, LockedPackage};
+use ahash::AHashSet;
+use indexmap::IndexMap;
+use std::hash::Hash;
+
+/// A package both sides changed in different ways.
+#[derive(Debug, Clone)]
+pub struct MergeConflict {
+    /// Package name.
+    pub name: String,
+    /// Whether the package is in `packages-dev`.
+    pub is_dev: bool,
+    /// Entry in the common ancestor.
+    pub base: Option<LockedPackage>,
+    /// Our entry (`None` if we removed it).
+    pub ours: Option<LockedPackage>,
+    /// Their entry (`None` if they removed it).
+    pub theirs: Option<LockedPackage>,
+}
+
+/// Result of a three-way lock merge.
+#[derive(Debug, Clone)]
+pub struct LockMerge {
+    /// The merged lock; conflicting packages hold our entry.
+    pub lock: ComposerLock,
+    /// Packages that could not be merged automatically.
+    pub conflicts: Vec<MergeConflict>,
+    /// Both sides changed composer.json, so neither `content-hash` is right.
+    pub content_hash_conflict: bool,
+}
+
+impl LockMerge {
+    /// Whether the merge completed without conflicts.
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.conflicts.is_empty() && !self.content_hash_conflict
+    }
+
+    /// Put `entry` in place of the conflicting package `name`.
+    ///
+    /// `None` removes the package.
+    pub fn resolve(&mut self, name: &str, entry: Option<LockedPackage>) {
+        let Some(index) = self.conflicts.iter().position(|c| c.name == name) else {
+            return;
+        };
+        let conflict = self.conflicts.remove(index);
+        let section = section_mut(&mut self.lock, conflict.is_dev);
+        section.retain(|p| !p.name.eq_ignore_ascii_case(name));
+        section.extend(entry);
+        section.sort();
+    }
+}
+
+/// Merge `theirs` into `ours`, both descended from `base`.
+#[must_use]
+pub fn merge_locks(base: &ComposerLock, ours: &ComposerLock, theirs: &ComposerLock) -> LockMerge {
+    let our_changes: AHashSet<(String, bool)> = compute_diff(base, ours)
+        .packages
+        .into_iter()
+        .map(|c| (c.name.to_lowercase(), c.is_dev))
+        .collect();
+
+    let mut lock = ours.clone();
+    let mut conflicts = Vec::new();
+
+    for change in compute_diff(base, theirs).packages {
+        let is_dev = change.is_dev;
+        let theirs_entry = find(theirs, &change.name, is_dev);
+
+        if our_changes.contains(&(change.name.to_lowercase(), is_dev)) {
+            let ours_entry = find(ours, &change.name, is_dev);
+            if ours_entry != theirs_entry {
+                conflicts.push(MergeConflict {
+                    name: change.name.clone(),
+                    is_dev,
+                    base: find(base, &change.name, is_dev).cloned(),
+                    ours: ours_entry.cloned(),
+                    theirs: theirs_entry.cloned(),
+                });
+            }
+            continue;
+        }
+
+        let section = section_mut(&mut lock, is_dev);
+        section.retain(|p| !p.name.eq_ignore_ascii_case(&change.name));
+        section.extend(theirs_entry.cloned());
+    }
+
+    // A package required by both sections only belongs in `packages`
+    let (packages, packages_dev) = (&lock.packages, &mut lock.packages_dev);
+    packages_dev.retain(|dev| {
+        !packages
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(&dev.name))
+    });
+    lock.packages.sort();
+    lock.packages_dev.sort();
+
+    let content_hash_conflict = ours.content_hash != base.content_hash
+        && theirs.content_hash != base.content_hash
+        && ours.content_hash != theirs.content_hash;
+    lock.content_hash = pick(&base.content_hash, &ours.content_hash, &theirs.content_hash);
+    lock.minimum_stability = pick(
+        &base.minimum_stability,
+        &ours.minimum_stability,
+        &theirs.minimum_stability,
+    );
+    lock.prefer_stable = pick(
+        &base.prefer_stable,
+        &ours.prefer_stable,
+        &theirs.prefer_stable,
+    );
+    lock.prefer_lowest = pick(
+        &base.prefer_lowest,
+        &ours.prefer_lowest,
+        &theirs.prefer_lowest,
+    );
+    lock.aliases = pick(&base.aliases, &ours.aliases, &theirs.aliases);
+    lock.stability_flags = merge_maps(
+        &base.stability_flags,
+        &ours.stability_flags,
+        &theirs.stability_flags,
+    );
+    lock.platform = merge_maps(&base.platform, &ours.platform, &theirs.platform);
+    lock.platform_dev = merge_maps(&base.platform_dev, &ours.platform_dev, &theirs.platform_dev);
+    lock.platform_overrides = merge_maps(
+        &base.platform_overrides,
+        &ours.platform_overrides,
+        &theirs.platform_overrides,
+    );
+    lock.plugin_api_version = pick(
+        &base.plugin_api_version,
+        &ours.plugin_api_version,
+        &theirs.plugin_api_version,
+    );
+
+    LockMerge {
+        lock,
+        conflicts,
+        content_hash_conflict,
+    }
+}
+
+fn find<'a>(lock: &'a ComposerLock, name: &str, is_dev: bool) -> Option<&'a LockedPackage> {
+    let section = if is_dev {
+        &lock.packages_dev
+    } else {
+        &lock.packages
+    };
+    section.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+const fn section_mut(lock: &mut ComposerLock, is_dev: bool) -> &mut Vec<LockedPackage> {
+    if is_dev {
+        &mut lock.packages_dev
+    } else {
+        &mut lock.packages
+    }
+}
+
+/// Their value if only they changed it, otherwise ours.
+fn pick<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> T {
+    if ours == base { theirs } else { ours }.clone()
+}
+
+/// Key-wise three-way merge; our value wins where both sides changed a key.
+fn merge_maps<K, V>(
+    base: &IndexMap<K, V>,
+    ours: &IndexMap<K, V>,
+    theirs: &IndexMap<K, V>,
+) -> IndexMap<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: PartialEq + Clone,
+{
+    let mut merged = ours.clone();
+    for (key, value) in theirs {
+        if base.get(key) != Some(value) && ours.get(key) == base.get(key) {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    for key in base.keys() {
+        if !theirs.contains_key(key) && ours.get(key) == base.get(key) {
+            merged.shift_remove(key);
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock(packages: &[(&str, &str)]) -> ComposerLock {
+        ComposerLock {
+            content_hash: "base".to_string(),
+            packages: packages
+                .iter()
+                .map(|(name, version)| LockedPackage::new(*name, *version))
+                .collect(),
+            ..ComposerLock::default()
+        }
+    }
+
+    fn versions(lock: &ComposerLock) -> Vec<(&str, &str)> {
+        lock.packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.version.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_disjoint_changes_merge_cleanly() {
+        let base = lock(&[("a/a", "1.0.0"), ("b/b", "1.0.0"), ("c/c", "1.0.0")]);
+        let ours = lock(&[("a/a", "1.1.0"), ("b/b", "1.0.0"), ("c/c", "1.0.0")]);
+        let mut theirs = lock(&[("a/a", "1.0.0"), ("b/b", "1.0.0"), ("d/d", "2.0.0")]);
+        theirs.content_hash = "theirs".to_string();
+        theirs
+            .platform
+            .insert("php".to_string(), "^8.2".to_string());
+
+        let merged = merge_locks(&base, &ours, &theirs);
+        assert!(merged.is_clean());
+        assert_eq!(
+            versions(&merged.lock),
+            vec![("a/a", "1.1.0"), ("b/b", "1.0.0"), ("d/d", "2.0.0")]
+        );
+        assert_eq!(merged.lock.content_hash, "theirs");
+        assert_eq!(
+            merged.lock.platform.get("php").map(String::as_str),
+            Some("^8.2")
+        );
+    }
+
+    #[test]
+    fn test_same_change_on_both_sides_is_not_a_conflict() {
+        let base = lock(&[("a/a", "1.0.0")]);
+        let ours = lock(&[("a/a", "1.2.0")]);
+        let theirs = lock(&[("a/a", "1.2.0")]);
+
+        let merged = merge_locks(&base, &ours, &theirs);
+        assert!(merged.is_clean());
+        assert_eq!(versions(&merged.lock), vec![("a/a", "1.2.0")]);
+    }
+
+    #[test]
+    fn test_divergent_versions_conflict() {
+        let base = lock(&[("a/a", "1.0.0"), ("b/b", "1.0.0")]);
+        let mut ours = lock(&[("a/a", "1.1.0"), ("b/b", "1.0.0")]);
+        ours.content_hash = "ours".to_string();
+        let mut theirs = lock(&[("a/a", "1.2.0")]);
+        theirs.content_hash = "theirs".to_string();
+
+        let mut merged = merge_locks(&base, &ours, &theirs);
+        assert!(merged.content_hash_conflict);
+        assert_eq!(merged.conflicts.len(), 1);
+        let conflict = &merged.conflicts[0];
+        assert_eq!(conflict.name, "a/a");
+        assert_eq!(conflict.ours.as_ref().unwrap().version, "1.1.0");
+        assert_eq!(conflict.theirs.as_ref().unwrap().version, "1.2.0");
+        assert_eq!(versions(&merged.lock), vec![("a/a", "1.1.0")]);
+
+        let theirs_entry = conflict.theirs.clone();
+        merged.resolve("a/a", theirs_entry);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(versions(&merged.lock), vec![("a/a", "1.2.0")]);
+    }
+
+    #[test]
+    fn test_moved_to_prod_leaves_dev() {
+        let mut base = lock(&[]);
+        base.packages_dev.push(LockedPackage::new("a/a", "1.0.0"));
+        let ours = base.clone();
+        let theirs = lock(&[("a/a", "1.0.0")]);
+
+        let merged = merge_locks(&base, &ours, &theirs);
+        assert!(merged.is_clean());
+        assert_eq!(versions(&merged.lock), vec![("a/a", "1.0.0")]);
+        assert!(merged.lock.packages_dev.is_empty());
+    }
+}
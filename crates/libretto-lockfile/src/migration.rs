@@ -4,12 +4,17 @@
 //! - Schema changes between Composer versions
 //! - Missing field defaults
 //! - Format normalization
+//! - Composer 1.x leftovers (the legacy `hash` field, old dist URLs)
 
 use crate::error::{LockfileError, Result};
+use crate::hash::ContentHasher;
 use crate::types::{ComposerLock, LockedPackage, StabilityFlag};
 use sonic_rs::{JsonValueTrait, Value};
 use tracing::{debug, info, warn};
 
+/// `content-hash` given to migrated locks whose hash cannot be recomputed.
+pub const MIGRATION_REQUIRED: &str = "MIGRATION_REQUIRED";
+
 /// Lock file schema version.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct SchemaVersion {
@@ -175,12 +180,61 @@ impl Migrator {
     pub fn migrate(&self, lock: ComposerLock) -> Result<MigrationResult> {
         let from_version =
             SchemaVersion::parse(&lock.plugin_api_version).unwrap_or(SchemaVersion::V1);
+        self.migrate_from(lock, from_version, Vec::new())
+    }
 
+    /// Migrate lock file JSON, including what only a Composer 1.x lock has.
+    ///
+    /// Composer 1.x locks carry no `plugin-api-version` and may only have the
+    /// legacy `hash` (MD5 of the composer.json bytes) instead of
+    /// `content-hash`. When `manifest`, the composer.json next to the lock,
+    /// still matches that hash, its `content-hash` is computed; otherwise it
+    /// is set to [`MIGRATION_REQUIRED`] so the lock reads as out of date.
+    pub fn migrate_str(&self, json: &str, manifest: Option<&str>) -> Result<MigrationResult> {
+        let value: Value = sonic_rs::from_str(json)?;
+        let mut lock: ComposerLock = sonic_rs::from_str(json)?;
+
+        let api_version = value.get("plugin-api-version").and_then(|v| v.as_str());
+        let from_version = api_version
+            .and_then(SchemaVersion::parse)
+            .unwrap_or(SchemaVersion::V1);
+
+        let mut changes = Vec::new();
+        if let Some(hash) = value.get("hash").and_then(|v| v.as_str()) {
+            changes.push(MigrationChange::RemovedField {
+                field: "hash".to_string(),
+            });
+            if lock.content_hash.is_empty()
+                && let Some(content_hash) = manifest.and_then(|m| legacy_content_hash(hash, m))
+            {
+                changes.push(MigrationChange::AddedField {
+                    field: "content-hash".to_string(),
+                    value: content_hash.clone(),
+                });
+                lock.content_hash = content_hash;
+            }
+        }
+        if api_version.is_none() && from_version < self.target_version {
+            changes.push(MigrationChange::AddedField {
+                field: "plugin-api-version".to_string(),
+                value: self.target_version.to_string(),
+            });
+        }
+
+        self.migrate_from(lock, from_version, changes)
+    }
+
+    fn migrate_from(
+        &self,
+        lock: ComposerLock,
+        from_version: SchemaVersion,
+        mut changes: Vec<MigrationChange>,
+    ) -> Result<MigrationResult> {
         if from_version >= self.target_version {
             return Ok(MigrationResult {
                 from_version,
                 to_version: from_version,
-                changes: Vec::new(),
+                changes,
                 lock,
             });
         }
@@ -191,7 +245,6 @@ impl Migrator {
             "Migrating lock file"
         );
 
-        let mut changes = Vec::new();
         let mut lock = lock;
 
         // Apply migrations in order
@@ -226,7 +279,7 @@ impl Migrator {
         if lock.content_hash.is_empty() {
             // We can't compute the correct hash without composer.json
             // Use a placeholder that will trigger re-resolution
-            lock.content_hash = "MIGRATION_REQUIRED".to_string();
+            lock.content_hash = MIGRATION_REQUIRED.to_string();
             changes.push(MigrationChange::AddedField {
                 field: "content-hash".to_string(),
                 value: "MIGRATION_REQUIRED (run composer update to fix)".to_string(),
@@ -338,6 +391,24 @@ impl Migrator {
                 value: "library".to_string(),
             });
         }
+
+        // Rewrite download URLs that the hosts no longer serve as written
+        let urls = [
+            ("source.url", pkg.source.as_mut().map(|s| &mut s.url)),
+            ("dist.url", pkg.dist.as_mut().map(|d| &mut d.url)),
+            ("notification-url", pkg.notification_url.as_mut()),
+        ];
+        for (field, url) in urls {
+            if let Some(url) = url
+                && let Some(normalized) = normalize_legacy_url(url)
+            {
+                changes.push(MigrationChange::NormalizedValue {
+                    field: format!("packages.{}.{field}", pkg.name),
+                    from: std::mem::replace(url, normalized.clone()),
+                    to: normalized,
+                });
+            }
+        }
     }
 
     /// Migrate from raw JSON value (for very old or malformed lock files).
@@ -350,6 +421,58 @@ impl Migrator {
     }
 }
 
+/// `content-hash` for `manifest` if it is the composer.json a Composer 1.x
+/// lock with legacy `hash` was written from.
+fn legacy_content_hash(hash: &str, manifest: &str) -> Option<String> {
+    let mut hasher = ContentHasher::new();
+    hasher.update(manifest.as_bytes());
+    if !hasher.finalize().eq_ignore_ascii_case(hash) {
+        return None;
+    }
+    let value: Value = sonic_rs::from_str(manifest).ok()?;
+    Some(ContentHasher::composer_content_hash(&value))
+}
+
+/// Hosts whose Composer 1.x era URLs are rewritten by [`normalize_legacy_url`].
+const LEGACY_HOSTS: &[&str] = &[
+    "github.com",
+    "api.github.com",
+    "codeload.github.com",
+    "gitlab.com",
+    "bitbucket.org",
+    "packagist.org",
+    "repo.packagist.org",
+];
+
+/// Modern form of a Composer 1.x era download URL, if it differs.
+///
+/// Upgrades plain HTTP to HTTPS for the well-known hosts, moves
+/// `github.com/{owner}/{repo}/zipball/{ref}` archives to the GitHub API and
+/// GitLab's removed v3 API to v4.
+fn normalize_legacy_url(url: &str) -> Option<String> {
+    let mut normalized = url.to_string();
+
+    if let Some(rest) = url.strip_prefix("http://") {
+        let host = rest.split('/').next().unwrap_or_default();
+        if LEGACY_HOSTS.contains(&host) {
+            normalized = format!("https://{rest}");
+        }
+    }
+
+    if let Some(path) = normalized.strip_prefix("https://github.com/") {
+        let parts: Vec<&str> = path.splitn(4, '/').collect();
+        if let [owner, repo, kind @ ("zipball" | "tarball"), reference] = parts.as_slice() {
+            normalized = format!("https://api.github.com/repos/{owner}/{repo}/{kind}/{reference}");
+        }
+    }
+
+    if normalized.starts_with("https://gitlab.com/api/v3/") {
+        normalized = normalized.replacen("/api/v3/", "/api/v4/", 1);
+    }
+
+    (normalized != url).then_some(normalized)
+}
+
 /// Check if packages are sorted.
 fn is_sorted(packages: &[LockedPackage]) -> bool {
     packages.windows(2).all(|w| w[0] <= w[1])
@@ -385,8 +508,7 @@ pub fn needs_migration(json: &str) -> bool {
 
 /// Auto-migrate a lock file string.
 pub fn auto_migrate(json: &str) -> Result<(ComposerLock, Vec<MigrationChange>)> {
-    let lock: ComposerLock = sonic_rs::from_str(json)?;
-    let result = Migrator::new().migrate(lock)?;
+    let result = Migrator::new().migrate_str(json, None)?;
     Ok((result.lock, result.changes))
 }
 
@@ -434,6 +556,87 @@ mod tests {
         assert_eq!(result.from_version, SchemaVersion::V1);
     }
 
+    const COMPOSER_1_LOCK: &str = r#"{
+        "_readme": ["This file locks the dependencies of your project to a known state"],
+        "hash": "HASH",
+        "packages": [{
+            "name": "monolog/monolog",
+            "version": "1.23.0",
+            "source": {"type": "git", "url": "http://github.com/Seldaek/monolog.git", "reference": "fd8c787"},
+            "dist": {"type": "zip", "url": "https://github.com/Seldaek/monolog/zipball/fd8c787", "reference": "fd8c787", "shasum": ""},
+            "autoload": {"psr-4": {"Monolog\\": "src/Monolog"}},
+            "notification-url": "http://packagist.org/downloads/"
+        }],
+        "packages-dev": [],
+        "aliases": [],
+        "minimum-stability": "stable",
+        "stability-flags": [],
+        "prefer-stable": false,
+        "platform": [],
+        "platform-dev": []
+    }"#;
+
+    #[test]
+    fn test_migrate_composer_1_lock() {
+        let manifest = r#"{"require": {"monolog/monolog": "^1.23"}}"#;
+        let mut hasher = ContentHasher::new();
+        hasher.update(manifest.as_bytes());
+        let json = COMPOSER_1_LOCK.replace("HASH", &hasher.finalize());
+
+        assert!(needs_migration(&json));
+        let result = Migrator::new().migrate_str(&json, Some(manifest)).unwrap();
+        assert_eq!(result.from_version, SchemaVersion::V1);
+
+        let lock = &result.lock;
+        let manifest: Value = sonic_rs::from_str(manifest).unwrap();
+        assert_eq!(
+            lock.content_hash,
+            ContentHasher::composer_content_hash(&manifest)
+        );
+        assert_eq!(lock.plugin_api_version, SchemaVersion::CURRENT.to_string());
+
+        let pkg = &lock.packages[0];
+        assert_eq!(pkg.version, "1.23.0");
+        assert_eq!(
+            pkg.source.as_ref().unwrap().url,
+            "https://github.com/Seldaek/monolog.git"
+        );
+        assert_eq!(
+            pkg.dist.as_ref().unwrap().url,
+            "https://api.github.com/repos/Seldaek/monolog/zipball/fd8c787"
+        );
+        assert_eq!(
+            pkg.notification_url.as_deref(),
+            Some("https://packagist.org/downloads/")
+        );
+    }
+
+    #[test]
+    fn test_migrate_composer_1_lock_with_changed_manifest() {
+        let json = COMPOSER_1_LOCK.replace("HASH", "0123456789abcdef0123456789abcdef");
+        let manifest = r#"{"require": {"monolog/monolog": "^2.0"}}"#;
+
+        let result = Migrator::new().migrate_str(&json, Some(manifest)).unwrap();
+        assert_eq!(result.lock.content_hash, MIGRATION_REQUIRED);
+    }
+
+    #[test]
+    fn test_normalize_legacy_url() {
+        assert_eq!(
+            normalize_legacy_url(
+                "https://gitlab.com/api/v3/projects/a%2Fb/repository/archive.zip?sha=1"
+            ),
+            Some(
+                "https://gitlab.com/api/v4/projects/a%2Fb/repository/archive.zip?sha=1".to_string()
+            )
+        );
+        assert_eq!(normalize_legacy_url("http://example.com/a.zip"), None);
+        assert_eq!(
+            normalize_legacy_url("https://api.github.com/repos/a/b/zipball/1"),
+            None
+        );
+    }
+
     #[test]
     fn test_normalize_stability() {
         assert_eq!(normalize_stability("STABLE"), "stable");
@@ -0,0 +1,325 @@
+//! Lock file policy checks.
+//!
+//! A [`Policy`] is the `policy` section of composer.json (or a standalone
+//! JSON file of the same shape):
+//!
+//! ```json
+//! {
+//!     "licenses": ["MIT", "BSD-3-Clause", "Apache-2.0"],
+//!     "allowed-hosts": ["api.github.com", "*.example.com"],
+//!     "require-https": true,
+//!     "require-shasum": true,
+//!     "forbid-dev-versions": true,
+//!     "abandoned-max-age": 365
+//! }
+//! ```
+//!
+//! Every rule is off unless configured; unknown keys are rejected, so a
+//! misspelled rule fails loudly instead of turning itself off.
+//! [`Policy::check`] reports each package that breaks an enabled rule.
+
+use crate::types::{AbandonedValue, ComposerLock, LockedPackage};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Rules a lock file is checked against.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct Policy {
+    /// Allowed SPDX license identifiers (compared case-insensitively).
+    pub licenses: Vec<String>,
+    /// Hosts dists may come from; `*.example.com` also matches subdomains.
+    pub allowed_hosts: Vec<String>,
+    /// Every dist URL must use HTTPS.
+    pub require_https: bool,
+    /// Every dist must carry a `shasum`.
+    pub require_shasum: bool,
+    /// No `dev-*` or `*-dev` versions in `packages` (`packages-dev` may have them).
+    pub forbid_dev_versions: bool,
+    /// Maximum age in days of the locked release of an abandoned package.
+    pub abandoned_max_age: Option<u32>,
+}
+
+/// A policy rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PolicyRule {
+    /// License allowlist.
+    License,
+    /// Dist host allowlist.
+    AllowedHosts,
+    /// HTTPS dist URLs.
+    Https,
+    /// Dist checksums.
+    Shasum,
+    /// No development versions in production packages.
+    DevVersion,
+    /// Age cap for abandoned packages.
+    AbandonedAge,
+}
+
+impl PolicyRule {
+    /// Rule name as written in reports.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::License => "license",
+            Self::AllowedHosts => "allowed-hosts",
+            Self::Https => "https",
+            Self::Shasum => "shasum",
+            Self::DevVersion => "dev-version",
+            Self::AbandonedAge => "abandoned-age",
+        }
+    }
+}
+
+impl std::fmt::Display for PolicyRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A package breaking a policy rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyViolation {
+    /// Package name.
+    pub package: String,
+    /// Locked version.
+    pub version: String,
+    /// Rule broken.
+    pub rule: PolicyRule,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl Policy {
+    /// Rules this policy enables.
+    #[must_use]
+    pub fn rules(&self) -> Vec<PolicyRule> {
+        let mut rules = Vec::new();
+        if !self.licenses.is_empty() {
+            rules.push(PolicyRule::License);
+        }
+        if !self.allowed_hosts.is_empty() {
+            rules.push(PolicyRule::AllowedHosts);
+        }
+        if self.require_https {
+            rules.push(PolicyRule::Https);
+        }
+        if self.require_shasum {
+            rules.push(PolicyRule::Shasum);
+        }
+        if self.forbid_dev_versions {
+            rules.push(PolicyRule::DevVersion);
+        }
+        if self.abandoned_max_age.is_some() {
+            rules.push(PolicyRule::AbandonedAge);
+        }
+        rules
+    }
+
+    /// Check every locked package, with `now` as the reference for ages.
+    #[must_use]
+    pub fn check(&self, lock: &ComposerLock, now: DateTime<Utc>) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        let packages = lock
+            .packages
+            .iter()
+            .map(|p| (p, false))
+            .chain(lock.packages_dev.iter().map(|p| (p, true)));
+
+        for (pkg, dev) in packages {
+            let mut violate = |rule, message: String| {
+                violations.push(PolicyViolation {
+                    package: pkg.name.clone(),
+                    version: pkg.version.clone(),
+                    rule,
+                    message,
+                });
+            };
+
+            if !self.licenses.is_empty() && !self.license_allowed(pkg) {
+                let message = if pkg.license.is_empty() {
+                    "declares no license".to_string()
+                } else {
+                    format!("license {} is not allowed", pkg.license.join(" or "))
+                };
+                violate(PolicyRule::License, message);
+            }
+
+            if let Some(dist) = &pkg.dist
+                && dist.dist_type != "path"
+                && let Some((scheme, host)) = split_url(&dist.url)
+            {
+                if !self.allowed_hosts.is_empty() && !self.host_allowed(host) {
+                    violate(
+                        PolicyRule::AllowedHosts,
+                        format!("dist host {host} is not allowed"),
+                    );
+                }
+                if self.require_https && !scheme.eq_ignore_ascii_case("https") {
+                    violate(
+                        PolicyRule::Https,
+                        format!("dist URL {} is not HTTPS", dist.url),
+                    );
+                }
+                if self.require_shasum && dist.shasum.as_deref().is_none_or(str::is_empty) {
+                    violate(PolicyRule::Shasum, "dist has no shasum".to_string());
+                }
+            }
+
+            if self.forbid_dev_versions && !dev && is_dev_version(&pkg.version) {
+                violate(
+                    PolicyRule::DevVersion,
+                    format!("development version {} in packages", pkg.version),
+                );
+            }
+
+            if let Some(max_days) = self.abandoned_max_age
+                && let Some(message) = abandoned_too_old(pkg, max_days, now)
+            {
+                violate(PolicyRule::AbandonedAge, message);
+            }
+        }
+
+        violations
+    }
+
+    /// A package is allowed if any of its licenses is; within an SPDX
+    /// expression `or` needs one side allowed and `and` needs both
+    /// (parentheses are not nested).
+    fn license_allowed(&self, pkg: &LockedPackage) -> bool {
+        pkg.license.iter().any(|license| {
+            let expression = license.to_ascii_lowercase();
+            expression.split(" or ").any(|alternative| {
+                alternative.split(" and ").all(|id| {
+                    let id = id.trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace());
+                    self.licenses.iter().any(|l| l.eq_ignore_ascii_case(id))
+                })
+            })
+        })
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        self.allowed_hosts.iter().any(|allowed| {
+            allowed.strip_prefix("*.").map_or_else(
+                || host.eq_ignore_ascii_case(allowed),
+                |domain| {
+                    host.eq_ignore_ascii_case(domain)
+                        || host
+                            .to_ascii_lowercase()
+                            .ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+                },
+            )
+        })
+    }
+}
+
+/// Scheme and host of a URL; `None` for local paths.
+fn split_url(url: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let host = host_port.split(':').next().unwrap_or_default();
+    Some((scheme, host))
+}
+
+fn is_dev_version(version: &str) -> bool {
+    version.starts_with("dev-") || version.ends_with("-dev")
+}
+
+fn abandoned_too_old(pkg: &LockedPackage, max_days: u32, now: DateTime<Utc>) -> Option<String> {
+    let replacement = match pkg.abandoned.as_ref()? {
+        AbandonedValue::Bool(false) => return None,
+        AbandonedValue::Bool(true) => String::new(),
+        AbandonedValue::Replacement(r) => format!(", use {r} instead"),
+    };
+
+    let Some(released) = pkg
+        .time
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+    else {
+        return Some(format!("abandoned with no release date{replacement}"));
+    };
+
+    let age = now.signed_duration_since(released).num_days();
+    (age > i64::from(max_days)).then(|| {
+        format!("abandoned, locked release is {age} days old (max {max_days}){replacement}")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PackageDistInfo;
+
+    fn package(name: &str, version: &str, license: &str, url: &str) -> LockedPackage {
+        let mut pkg = LockedPackage::new(name, version);
+        pkg.license = vec![license.to_string()];
+        let mut dist = PackageDistInfo::zip(url);
+        dist.shasum = Some("abc".to_string());
+        pkg.dist = Some(dist);
+        pkg
+    }
+
+    fn policy() -> Policy {
+        sonic_rs::from_str(
+            r#"{
+                "licenses": ["MIT", "Apache-2.0"],
+                "allowed-hosts": ["*.github.com"],
+                "require-https": true,
+                "require-shasum": true,
+                "forbid-dev-versions": true,
+                "abandoned-max-age": 365
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_clean_lock_passes() {
+        let mut lock = ComposerLock::default();
+        lock.packages.push(package(
+            "a/a",
+            "1.0.0",
+            "(MIT or GPL-3.0)",
+            "https://api.github.com/a",
+        ));
+        lock.packages_dev.push(package(
+            "b/b",
+            "dev-main",
+            "Apache-2.0",
+            "https://github.com/b",
+        ));
+
+        assert!(policy().check(&lock, Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn test_violations() {
+        let mut lock = ComposerLock::default();
+        let mut pkg = package("a/a", "dev-main", "GPL-3.0", "http://example.com/a.zip");
+        pkg.dist.as_mut().unwrap().shasum = Some(String::new());
+        pkg.abandoned = Some(AbandonedValue::Replacement("c/c".to_string()));
+        pkg.time = Some("2020-01-01T00:00:00+00:00".to_string());
+        lock.packages.push(pkg);
+
+        let rules: Vec<PolicyRule> = policy()
+            .check(&lock, Utc::now())
+            .into_iter()
+            .map(|v| v.rule)
+            .collect();
+        assert_eq!(rules, policy().rules());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert!(sonic_rs::from_str::<Policy>(r#"{"allowed_hosts": ["github.com"]}"#).is_err());
+        assert!(sonic_rs::from_str::<Policy>(r#"{"require-http": true}"#).is_err());
+        assert_eq!(
+            sonic_rs::from_str::<Policy>("{}").unwrap(),
+            Policy::default()
+        );
+    }
+}
@@ -2,14 +2,22 @@
 //!
 //! This module defines all data structures needed for composer.lock files,
 //! with full compatibility with Composer's JSON schema.
+//!
+//! Field declaration order is the order Composer writes keys in, so a lock
+//! read and written back comes out byte-identical. Free-form package metadata
+//! (autoload, extra, authors, ...) is kept as raw JSON to preserve its key
+//! order.
 
-use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::BTreeMap;
 
 /// Complete composer.lock file structure.
 ///
-/// Fields are ordered for deterministic JSON output.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Fields are declared in Composer's output order. Like Composer, reading
+/// is lenient: missing top-level keys take their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct ComposerLock {
     /// Usage warning.
     #[serde(rename = "_readme")]
@@ -23,20 +31,24 @@ pub struct ComposerLock {
     pub packages: Vec<LockedPackage>,
 
     /// Installed development packages.
-    #[serde(rename = "packages-dev")]
+    #[serde(rename = "packages-dev", default)]
     pub packages_dev: Vec<LockedPackage>,
 
     /// Package aliases.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
     pub aliases: Vec<PackageAlias>,
 
     /// Minimum stability setting.
     #[serde(rename = "minimum-stability")]
     pub minimum_stability: String,
 
-    /// Per-package stability flags.
-    #[serde(rename = "stability-flags")]
-    pub stability_flags: BTreeMap<String, u8>,
+    /// Per-package stability flags, in composer.json order.
+    #[serde(
+        rename = "stability-flags",
+        default,
+        deserialize_with = "map_or_empty_list"
+    )]
+    pub stability_flags: IndexMap<String, u8>,
 
     /// Prefer stable versions.
     #[serde(rename = "prefer-stable")]
@@ -46,12 +58,26 @@ pub struct ComposerLock {
     #[serde(rename = "prefer-lowest")]
     pub prefer_lowest: bool,
 
-    /// PHP and extension versions.
-    pub platform: BTreeMap<String, String>,
+    /// Root platform requirements, in composer.json order.
+    #[serde(default, deserialize_with = "map_or_empty_list")]
+    pub platform: IndexMap<String, String>,
 
     /// Dev platform requirements.
-    #[serde(rename = "platform-dev")]
-    pub platform_dev: BTreeMap<String, String>,
+    #[serde(
+        rename = "platform-dev",
+        default,
+        deserialize_with = "map_or_empty_list"
+    )]
+    pub platform_dev: IndexMap<String, String>,
+
+    /// `config.platform` overrides the lock was resolved against.
+    #[serde(
+        rename = "platform-overrides",
+        default,
+        skip_serializing_if = "IndexMap::is_empty",
+        deserialize_with = "map_or_empty_list"
+    )]
+    pub platform_overrides: IndexMap<String, String>,
 
     /// Plugin API version.
     #[serde(rename = "plugin-api-version")]
@@ -71,17 +97,35 @@ impl Default for ComposerLock {
             packages_dev: Vec::new(),
             aliases: Vec::new(),
             minimum_stability: "stable".to_string(),
-            stability_flags: BTreeMap::new(),
+            stability_flags: IndexMap::new(),
             prefer_stable: false,
             prefer_lowest: false,
-            platform: BTreeMap::new(),
-            platform_dev: BTreeMap::new(),
+            platform: IndexMap::new(),
+            platform_dev: IndexMap::new(),
+            platform_overrides: IndexMap::new(),
             plugin_api_version: "2.6.0".to_string(),
         }
     }
 }
 
+impl ComposerLock {
+    /// Iterate over production and dev packages.
+    pub fn all_packages(&self) -> impl Iterator<Item = &LockedPackage> {
+        self.packages.iter().chain(self.packages_dev.iter())
+    }
+
+    /// Find a locked package by name (case-insensitive).
+    #[must_use]
+    pub fn find_package(&self, name: &str) -> Option<&LockedPackage> {
+        self.all_packages()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+}
+
 /// A locked package with full metadata.
+///
+/// Fields follow the key order of Composer's `ArrayDumper`, with `time`
+/// moved to the end as `Locker` does.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct LockedPackage {
     /// Package name (vendor/name).
@@ -90,6 +134,14 @@ pub struct LockedPackage {
     /// Exact locked version.
     pub version: String,
 
+    /// Legacy PSR-0 install target directory.
+    #[serde(
+        rename = "target-dir",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target_dir: Option<String>,
+
     /// Source repository information.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub source: Option<PackageSourceInfo>,
@@ -99,28 +151,89 @@ pub struct LockedPackage {
     pub dist: Option<PackageDistInfo>,
 
     /// Production dependencies.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "map_or_empty_list"
+    )]
     pub require: BTreeMap<String, String>,
 
+    /// Conflicting packages.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "map_or_empty_list"
+    )]
+    pub conflict: BTreeMap<String, String>,
+
+    /// Provided packages.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "map_or_empty_list"
+    )]
+    pub provide: BTreeMap<String, String>,
+
+    /// Replaced packages.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "map_or_empty_list"
+    )]
+    pub replace: BTreeMap<String, String>,
+
     /// Development dependencies.
     #[serde(
         rename = "require-dev",
         default,
-        skip_serializing_if = "BTreeMap::is_empty"
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "map_or_empty_list"
     )]
     pub require_dev: BTreeMap<String, String>,
 
+    /// Suggested packages.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "map_or_empty_list"
+    )]
+    pub suggest: BTreeMap<String, String>,
+
+    /// Default branch flag.
+    #[serde(
+        rename = "default-branch",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default_branch: Option<bool>,
+
+    /// Binary files.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bin: Vec<String>,
+
     /// Package type.
     #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
     pub package_type: Option<String>,
 
     /// Extra metadata.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub extra: BTreeMap<String, sonic_rs::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<sonic_rs::Value>,
+
+    /// Installation source preference (never written, Composer drops it).
+    #[serde(rename = "installation-source", default, skip_serializing)]
+    pub installation_source: Option<String>,
 
     /// Autoload configuration.
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub autoload: Option<AutoloadConfig>,
+    pub autoload: Option<sonic_rs::Value>,
+
+    /// Dev autoload configuration.
+    #[serde(
+        rename = "autoload-dev",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub autoload_dev: Option<sonic_rs::Value>,
 
     /// Notification URL for downloads.
     #[serde(
@@ -130,13 +243,33 @@ pub struct LockedPackage {
     )]
     pub notification_url: Option<String>,
 
+    /// Legacy include paths.
+    #[serde(
+        rename = "include-path",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub include_path: Vec<String>,
+
+    /// PHP extension build metadata.
+    #[serde(rename = "php-ext", default, skip_serializing_if = "Option::is_none")]
+    pub php_ext: Option<sonic_rs::Value>,
+
+    /// Archive configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<ArchiveConfig>,
+
+    /// Package scripts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<sonic_rs::Value>,
+
     /// License(s).
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub license: Vec<String>,
 
     /// Authors.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub authors: Vec<PackageAuthor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authors: Option<sonic_rs::Value>,
 
     /// Package description.
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -152,67 +285,19 @@ pub struct LockedPackage {
 
     /// Support information.
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub support: Option<SupportInfo>,
+    pub support: Option<sonic_rs::Value>,
 
     /// Funding information.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub funding: Vec<FundingInfo>,
-
-    /// Installation timestamp (ISO 8601).
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub time: Option<String>,
-
-    /// Replaced packages.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub replace: BTreeMap<String, String>,
-
-    /// Provided packages.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub provide: BTreeMap<String, String>,
-
-    /// Conflicting packages.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub conflict: BTreeMap<String, String>,
-
-    /// Suggested packages.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub suggest: BTreeMap<String, String>,
-
-    /// Binary files.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub bin: Vec<String>,
-
-    /// Dev autoload configuration.
-    #[serde(
-        rename = "autoload-dev",
-        default,
-        skip_serializing_if = "Option::is_none"
-    )]
-    pub autoload_dev: Option<AutoloadConfig>,
-
-    /// Default branch flag.
-    #[serde(
-        rename = "default-branch",
-        default,
-        skip_serializing_if = "Option::is_none"
-    )]
-    pub default_branch: Option<bool>,
-
-    /// Archive configuration.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub archive: Option<ArchiveConfig>,
+    pub funding: Option<sonic_rs::Value>,
 
     /// Abandoned notice.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub abandoned: Option<AbandonedValue>,
 
-    /// Installation source preference.
-    #[serde(
-        rename = "installation-source",
-        default,
-        skip_serializing_if = "Option::is_none"
-    )]
-    pub installation_source: Option<String>,
+    /// Release timestamp (RFC 3339).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
 }
 
 impl LockedPackage {
@@ -222,32 +307,36 @@ impl LockedPackage {
         Self {
             name: name.into(),
             version: version.into(),
+            target_dir: None,
             source: None,
             dist: None,
             require: BTreeMap::new(),
+            conflict: BTreeMap::new(),
+            provide: BTreeMap::new(),
+            replace: BTreeMap::new(),
             require_dev: BTreeMap::new(),
+            suggest: BTreeMap::new(),
+            default_branch: None,
+            bin: Vec::new(),
             package_type: None,
-            extra: BTreeMap::new(),
+            extra: None,
+            installation_source: None,
             autoload: None,
+            autoload_dev: None,
             notification_url: None,
+            include_path: Vec::new(),
+            php_ext: None,
+            archive: None,
+            scripts: None,
             license: Vec::new(),
-            authors: Vec::new(),
+            authors: None,
             description: None,
             homepage: None,
             keywords: Vec::new(),
             support: None,
-            funding: Vec::new(),
-            time: None,
-            replace: BTreeMap::new(),
-            provide: BTreeMap::new(),
-            conflict: BTreeMap::new(),
-            suggest: BTreeMap::new(),
-            bin: Vec::new(),
-            autoload_dev: None,
-            default_branch: None,
-            archive: None,
+            funding: None,
             abandoned: None,
-            installation_source: None,
+            time: None,
         }
     }
 
@@ -277,9 +366,12 @@ impl LockedPackage {
     }
 }
 
+/// Composer sorts locked packages by name, then version, comparing bytes.
 impl Ord for LockedPackage {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.name.to_lowercase().cmp(&other.name.to_lowercase())
+        self.name
+            .cmp(&other.name)
+            .then_with(|| self.version.cmp(&other.version))
     }
 }
 
@@ -289,6 +381,25 @@ impl PartialOrd for LockedPackage {
     }
 }
 
+/// Deserialize a map that PHP may have encoded as `[]` when empty.
+fn map_or_empty_list<'de, D, M>(deserializer: D) -> std::result::Result<M, D::Error>
+where
+    D: Deserializer<'de>,
+    M: Deserialize<'de> + Default,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum MapOrList<M> {
+        Map(M),
+        Empty([(); 0]),
+    }
+
+    Ok(match MapOrList::deserialize(deserializer)? {
+        MapOrList::Map(map) => map,
+        MapOrList::Empty([]) => M::default(),
+    })
+}
+
 /// Package source (VCS) information.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PackageSourceInfo {
@@ -339,8 +450,8 @@ pub struct PackageDistInfo {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub reference: Option<String>,
 
-    /// SHA-1 checksum.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// SHA-1 checksum (always written; `null` when unknown, as Composer does).
+    #[serde(default)]
     pub shasum: Option<String>,
 }
 
@@ -371,149 +482,6 @@ impl PackageDistInfo {
     }
 }
 
-/// Autoload configuration.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
-pub struct AutoloadConfig {
-    /// PSR-4 autoloading.
-    #[serde(rename = "psr-4", default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub psr4: BTreeMap<String, AutoloadPath>,
-
-    /// PSR-0 autoloading (legacy).
-    #[serde(rename = "psr-0", default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub psr0: BTreeMap<String, AutoloadPath>,
-
-    /// Classmap paths.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub classmap: Vec<String>,
-
-    /// Files to include.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub files: Vec<String>,
-
-    /// Excluded paths from classmap.
-    #[serde(
-        rename = "exclude-from-classmap",
-        default,
-        skip_serializing_if = "Vec::is_empty"
-    )]
-    pub exclude_from_classmap: Vec<String>,
-}
-
-/// Autoload path (can be string or array of strings).
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(untagged)]
-pub enum AutoloadPath {
-    /// Single path.
-    Single(String),
-    /// Multiple paths.
-    Multiple(Vec<String>),
-}
-
-impl AutoloadPath {
-    /// Get paths as slice.
-    #[must_use]
-    pub fn as_vec(&self) -> Vec<&str> {
-        match self {
-            Self::Single(s) => vec![s.as_str()],
-            Self::Multiple(v) => v.iter().map(String::as_str).collect(),
-        }
-    }
-}
-
-/// Package author.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct PackageAuthor {
-    /// Author name.
-    pub name: String,
-
-    /// Email address.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub email: Option<String>,
-
-    /// Homepage URL.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub homepage: Option<String>,
-
-    /// Role.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub role: Option<String>,
-}
-
-impl PackageAuthor {
-    /// Create author with name only.
-    #[must_use]
-    pub fn new(name: impl Into<String>) -> Self {
-        Self {
-            name: name.into(),
-            email: None,
-            homepage: None,
-            role: None,
-        }
-    }
-
-    /// Set email.
-    #[must_use]
-    pub fn with_email(mut self, email: impl Into<String>) -> Self {
-        self.email = Some(email.into());
-        self
-    }
-}
-
-/// Support information.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
-pub struct SupportInfo {
-    /// Email.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub email: Option<String>,
-
-    /// Issues URL.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub issues: Option<String>,
-
-    /// Forum URL.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub forum: Option<String>,
-
-    /// Wiki URL.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub wiki: Option<String>,
-
-    /// IRC channel.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub irc: Option<String>,
-
-    /// Source URL.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub source: Option<String>,
-
-    /// Documentation URL.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub docs: Option<String>,
-
-    /// RSS feed URL.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub rss: Option<String>,
-
-    /// Chat URL.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub chat: Option<String>,
-
-    /// Security contact URL.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub security: Option<String>,
-}
-
-/// Funding information.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct FundingInfo {
-    /// Funding type (github, patreon, etc.).
-    #[serde(rename = "type")]
-    pub funding_type: String,
-
-    /// Funding URL.
-    pub url: String,
-}
-
 /// Package alias.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PackageAlias {
@@ -6,6 +6,7 @@ use libretto_resolver::{
     PackageFetcher, PackageIndex, PackageName, ResolutionMode, Resolver, ResolverConfig, Stability,
 };
 use rand::prelude::*;
+use std::collections::BTreeMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
@@ -58,6 +59,11 @@ impl PackageFetcher for MockFetcher {
                         .iter()
                         .map(|d| (d.name.as_str().to_string(), d.constraint.to_string()))
                         .collect(),
+                    conflict: v
+                        .conflicts
+                        .iter()
+                        .map(|d| (d.name.as_str().to_string(), d.constraint.to_string()))
+                        .collect(),
                     suggest: v
                         .suggests
                         .iter()
@@ -233,6 +239,8 @@ fn bench_resolution(c: &mut Criterion) {
             mode: ResolutionMode::PreferStable,
             min_stability: Stability::Stable,
             include_dev: false,
+            platform: BTreeMap::new(),
+            locked: BTreeMap::new(),
         };
         let resolver = Resolver::new(Arc::clone(&fetcher), config);
 
@@ -267,6 +275,8 @@ fn bench_warm_resolution(c: &mut Criterion) {
         mode: ResolutionMode::PreferStable,
         min_stability: Stability::Stable,
         include_dev: false,
+        platform: BTreeMap::new(),
+        locked: BTreeMap::new(),
     };
     let resolver = Resolver::new(Arc::clone(&fetcher), config);
 
@@ -299,6 +309,8 @@ fn bench_prefer_lowest(c: &mut Criterion) {
         mode: ResolutionMode::PreferLowest,
         min_stability: Stability::Stable,
         include_dev: false,
+        platform: BTreeMap::new(),
+        locked: BTreeMap::new(),
     };
     let resolver = Resolver::new(fetcher, config);
 
@@ -52,6 +52,8 @@ pub struct FetchedVersion {
     pub replace: Vec<(String, String)>,
     /// Virtual packages this provides.
     pub provide: Vec<(String, String)>,
+    /// Packages this conflicts with.
+    pub conflict: Vec<(String, String)>,
     /// Suggested packages.
     pub suggest: Vec<(String, String)>,
     /// Distribution URL.
@@ -13,6 +13,9 @@ use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 use std::sync::Arc;
 
+/// Vendor of the names given to platform requirements.
+pub const PLATFORM_VENDOR: &str = "__platform__";
+
 /// A validated Composer package name in vendor/name format.
 ///
 /// Package names must:
@@ -49,6 +52,13 @@ impl PackageName {
         }
     }
 
+    /// Name of the requirement on a platform package (`php`, `ext-*`,
+    /// `lib-*`), `__platform__/<name>` in the solver's namespace.
+    #[must_use]
+    pub fn platform(name: &str) -> Self {
+        Self::new(PLATFORM_VENDOR, name)
+    }
+
     /// Parse a package name from a string.
     ///
     /// Returns `None` if the string is not a valid package name.
@@ -215,6 +225,9 @@ pub struct PackageVersion {
     pub conflicts: SmallVec<[Dependency; 2]>,
     /// Suggested packages.
     pub suggests: SmallVec<[Dependency; 2]>,
+    /// Platform requirements, named with [`PackageName::platform`].
+    #[serde(default)]
+    pub platform_requirements: SmallVec<[Dependency; 2]>,
     /// Stability of this version.
     pub stability: Stability,
     /// Distribution URL.
@@ -274,6 +287,7 @@ impl PackageVersion {
             provides: SmallVec::new(),
             conflicts: SmallVec::new(),
             suggests: SmallVec::new(),
+            platform_requirements: SmallVec::new(),
             stability,
             dist_url: None,
             dist_type: None,
@@ -322,6 +336,11 @@ impl PackageVersion {
     pub fn add_conflict(&mut self, dep: Dependency) {
         self.conflicts.push(dep);
     }
+
+    /// Add a platform requirement.
+    pub fn add_platform_requirement(&mut self, dep: Dependency) {
+        self.platform_requirements.push(dep);
+    }
 }
 
 /// Package entry for the package index.
@@ -25,7 +25,7 @@
 //! ```
 
 use crate::fetcher::{FetchedPackage, PackageFetcher};
-use crate::package::{Dependency, PackageEntry, PackageName, PackageVersion};
+use crate::package::{Dependency, PLATFORM_VENDOR, PackageEntry, PackageName, PackageVersion};
 use crate::provider::ResolutionMode;
 use crate::types::{Resolution, ResolveError, ResolvedPackage};
 use crate::version::{ComposerConstraint, ComposerVersion, Stability};
@@ -38,6 +38,7 @@ use pubgrub::{
     DefaultStringReporter, Dependencies, DependencyConstraints, DependencyProvider,
     PackageResolutionStatistics, PubGrubError, Reporter, resolve,
 };
+use std::collections::BTreeMap;
 use std::convert::Infallible;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -75,6 +76,15 @@ pub struct ResolverConfig {
     pub min_stability: Stability,
     /// Whether to include dev dependencies.
     pub include_dev: bool,
+    /// Platform packages (`php`, `ext-*`) to resolve for, by name. A
+    /// requirement on a listed package must hold for every version given,
+    /// so several versions resolve for all of them at once. Requirements on
+    /// unlisted platform packages are ignored.
+    pub platform: BTreeMap<String, Vec<ComposerVersion>>,
+    /// Versions to keep, by package name. A listed package that is still
+    /// part of the graph resolves to exactly this version; listing it does
+    /// not require it.
+    pub locked: BTreeMap<String, ComposerVersion>,
 }
 
 impl Default for ResolverConfig {
@@ -85,6 +95,8 @@ impl Default for ResolverConfig {
             mode: ResolutionMode::PreferStable,
             min_stability: Stability::Stable,
             include_dev: true,
+            platform: BTreeMap::new(),
+            locked: BTreeMap::new(),
         }
     }
 }
@@ -281,7 +293,13 @@ impl<F: PackageFetcher> Resolver<F> {
         dev_deps: &[Dependency],
         packages: AHashMap<String, PackageEntry>,
     ) -> Result<Resolution, ResolveError> {
-        let provider = PubGrubProvider::new(packages, self.config.mode, self.config.min_stability);
+        let provider = PubGrubProvider::new(
+            packages,
+            self.config.mode,
+            self.config.min_stability,
+            &self.config.platform,
+            &self.config.locked,
+        );
 
         let all_deps: Vec<_> = if self.config.include_dev {
             root_deps.iter().chain(dev_deps.iter()).cloned().collect()
@@ -289,11 +307,10 @@ impl<F: PackageFetcher> Resolver<F> {
             root_deps.to_vec()
         };
 
-        let root_dep_ranges: Vec<_> = all_deps
-            .iter()
-            .filter(|d| !is_platform_package(d.name.as_str()))
-            .map(|d| (d.name.clone(), d.constraint.ranges().clone()))
-            .collect();
+        let mut root_dep_ranges = DependencyConstraints::default();
+        for dep in &all_deps {
+            provider.add_dependency(&mut root_dep_ranges, dep);
+        }
 
         provider.set_root_deps(root_dep_ranges);
 
@@ -334,7 +351,7 @@ impl<F: PackageFetcher> Resolver<F> {
         let mut pkg_map: AHashMap<String, (PackageName, ComposerVersion)> = AHashMap::new();
 
         for (name, version) in solution {
-            if name.as_str() == "__root__/__root__" {
+            if name.as_str() == "__root__/__root__" || is_platform_package(name.as_str()) {
                 continue;
             }
             let key = name.as_str().to_string();
@@ -446,6 +463,13 @@ struct PubGrubProvider {
     mode: ResolutionMode,
     min_stability: Stability,
     root_deps: parking_lot::Mutex<DependencyConstraints<PackageName, Ranges<ComposerVersion>>>,
+    /// Pseudo-packages standing for each configured platform version, by
+    /// platform requirement name.
+    platform: AHashMap<String, Vec<(PackageName, ComposerVersion)>>,
+    /// The single version of each platform pseudo-package.
+    platform_versions: AHashMap<String, ComposerVersion>,
+    /// Only version each locked package may resolve to.
+    locked: AHashMap<String, ComposerVersion>,
 }
 
 impl PubGrubProvider {
@@ -453,26 +477,58 @@ impl PubGrubProvider {
         packages: AHashMap<String, PackageEntry>,
         mode: ResolutionMode,
         min_stability: Stability,
+        platform: &BTreeMap<String, Vec<ComposerVersion>>,
+        locked: &BTreeMap<String, ComposerVersion>,
     ) -> Self {
+        let mut pseudo = AHashMap::new();
+        let mut platform_versions = AHashMap::new();
+        for (name, versions) in platform {
+            let targets: Vec<_> = versions
+                .iter()
+                .map(|version| {
+                    let package = PackageName::platform(&format!("{name}@{version}"));
+                    platform_versions.insert(package.as_str().to_string(), version.clone());
+                    (package, version.clone())
+                })
+                .collect();
+            pseudo.insert(PackageName::platform(name).as_str().to_string(), targets);
+        }
+
         Self {
             packages,
             mode,
             min_stability,
             root_deps: parking_lot::Mutex::new(DependencyConstraints::default()),
+            platform: pseudo,
+            platform_versions,
+            locked: locked
+                .iter()
+                .map(|(name, version)| (name.to_lowercase(), version.clone()))
+                .collect(),
         }
     }
 
-    fn set_root_deps(
+    /// Add `dep` to `deps`. A requirement on a configured platform package
+    /// becomes one on the pseudo-package of every configured version;
+    /// other platform requirements are dropped.
+    fn add_dependency(
         &self,
-        deps: impl IntoIterator<Item = (PackageName, Ranges<ComposerVersion>)>,
+        deps: &mut DependencyConstraints<PackageName, Ranges<ComposerVersion>>,
+        dep: &Dependency,
     ) {
-        let mut root = self.root_deps.lock();
-        root.clear();
-        for (n, r) in deps {
-            root.insert(n, r);
+        if !is_platform_package(dep.name.as_str()) {
+            deps.insert(dep.name.clone(), dep.constraint.ranges().clone());
+        } else if let Some(targets) = self.platform.get(dep.name.as_str()) {
+            for (package, _) in targets {
+                deps.insert(package.clone(), dep.constraint.ranges().clone());
+            }
         }
     }
 
+    fn set_root_deps(&self, deps: DependencyConstraints<PackageName, Ranges<ComposerVersion>>) {
+        *self.root_deps.lock() = deps;
+    }
+
     fn deps_for(&self, name: &PackageName, version: &ComposerVersion) -> Option<Vec<Dependency>> {
         self.packages
             .get(name.as_str())?
@@ -529,7 +585,11 @@ impl DependencyProvider for PubGrubProvider {
         }
 
         if is_platform_package(pkg.as_str()) {
-            return Ok(None);
+            return Ok(self
+                .platform_versions
+                .get(pkg.as_str())
+                .filter(|version| range.contains(version))
+                .cloned());
         }
 
         let entry = match self.packages.get(pkg.as_str()) {
@@ -537,6 +597,14 @@ impl DependencyProvider for PubGrubProvider {
             None => return Ok(None),
         };
 
+        if let Some(locked) = self.locked.get(pkg.as_str()) {
+            return Ok(entry
+                .versions
+                .iter()
+                .find(|v| v.version == *locked && range.contains(&v.version))
+                .map(|v| v.version.clone()));
+        }
+
         // Filter by range and stability
         let matching: Vec<_> = entry
             .versions
@@ -585,10 +653,12 @@ impl DependencyProvider for PubGrubProvider {
         };
 
         let mut deps = DependencyConstraints::default();
-        for dep in &version.dependencies {
-            if !is_platform_package(dep.name.as_str()) {
-                deps.insert(dep.name.clone(), dep.constraint.ranges().clone());
-            }
+        for dep in version
+            .dependencies
+            .iter()
+            .chain(&version.platform_requirements)
+        {
+            self.add_dependency(&mut deps, dep);
         }
 
         Ok(Dependencies::Available(deps))
@@ -599,10 +669,13 @@ impl DependencyProvider for PubGrubProvider {
 // Helper Functions
 // ============================================================================
 
-/// Check if a package name is a platform package (php, ext-*, lib-*).
+/// Check if a package name is a platform package (php, ext-*, lib-*), or
+/// the solver's name for one.
 #[inline]
 fn is_platform_package(name: &str) -> bool {
-    name == "php"
+    name.strip_prefix(PLATFORM_VENDOR)
+        .is_some_and(|rest| rest.starts_with('/'))
+        || name == "php"
         || name.starts_with("php-")
         || name.starts_with("ext-")
         || name.starts_with("lib-")
@@ -625,7 +698,14 @@ fn convert_fetched_package(
 
         // Dependencies
         for (dep_name, constraint) in &v.require {
-            if let (Some(n), Some(c)) = (
+            if !dep_name.contains('/') && is_platform_package(dep_name) {
+                if let Some(c) = ComposerConstraint::parse(constraint) {
+                    pv.add_platform_requirement(Dependency::new(
+                        PackageName::platform(dep_name),
+                        c,
+                    ));
+                }
+            } else if let (Some(n), Some(c)) = (
                 PackageName::parse(dep_name),
                 ComposerConstraint::parse(constraint),
             ) {
@@ -653,6 +733,16 @@ fn convert_fetched_package(
             }
         }
 
+        // Conflicts
+        for (dep_name, constraint) in &v.conflict {
+            if let (Some(n), Some(c)) = (
+                PackageName::parse(dep_name),
+                ComposerConstraint::parse(constraint),
+            ) {
+                pv.add_conflict(Dependency::new(n, c));
+            }
+        }
+
         // Distribution info
         pv.dist_url = v.dist_url.as_ref().map(|s| Arc::from(s.as_str()));
         pv.dist_type = v.dist_type.as_ref().map(|s| Arc::from(s.as_str()));
@@ -689,6 +779,16 @@ fn convert_fetched_package(
     }
 }
 
+/// `replace`/`provide`/`conflict` links as name-constraint pairs, `None`
+/// when empty.
+fn link_list(deps: &[Dependency]) -> Option<Vec<(String, String)>> {
+    let links: Vec<(String, String)> = deps
+        .iter()
+        .map(|d| (d.name.as_str().to_string(), d.constraint.to_string()))
+        .collect();
+    (!links.is_empty()).then_some(links)
+}
+
 /// Build a resolved package from provider data.
 fn build_resolved_package(
     pkg_name: PackageName,
@@ -744,6 +844,10 @@ fn build_resolved_package(
         )
     });
 
+    let replace = pkg_info.and_then(|v| link_list(&v.replaces));
+    let provide = pkg_info.and_then(|v| link_list(&v.provides));
+    let conflict = pkg_info.and_then(|v| link_list(&v.conflicts));
+
     let (package_type, description, homepage, license, authors, keywords, time) =
         pkg_info.map_or((None, None, None, None, None, None, None), |v| {
             (
@@ -784,6 +888,9 @@ fn build_resolved_package(
         require,
         require_dev,
         suggest,
+        replace,
+        provide,
+        conflict,
         package_type,
         description,
         homepage,
@@ -813,3 +920,176 @@ pub type TurboResolver<F> = Resolver<F>;
 
 /// Backward-compatible alias for `ResolverStats`.
 pub type TurboStats = ResolverStats;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::FetchedVersion;
+    use std::pin::Pin;
+
+    /// Serves `acme/lib` 2.0.0 (PHP 8.2+) and 1.0.0 (PHP 8.0+).
+    struct PlatformFetcher;
+
+    fn version(version: &str, php: &str) -> FetchedVersion {
+        FetchedVersion {
+            version: version.to_string(),
+            require: vec![("php".to_string(), php.to_string())],
+            require_dev: vec![],
+            replace: vec![],
+            provide: vec![],
+            conflict: vec![],
+            suggest: vec![],
+            dist_url: None,
+            dist_type: None,
+            dist_shasum: None,
+            source_url: None,
+            source_type: None,
+            source_reference: None,
+            package_type: None,
+            description: None,
+            homepage: None,
+            license: None,
+            authors: None,
+            keywords: None,
+            time: None,
+            autoload: None,
+            autoload_dev: None,
+            extra: None,
+            support: None,
+            funding: None,
+            notification_url: None,
+            bin: None,
+        }
+    }
+
+    impl PackageFetcher for PlatformFetcher {
+        fn fetch(
+            &self,
+            name: String,
+        ) -> Pin<Box<dyn std::future::Future<Output = Option<FetchedPackage>> + Send + '_>>
+        {
+            Box::pin(async move {
+                (name == "acme/lib").then(|| FetchedPackage {
+                    name,
+                    versions: vec![version("2.0.0", ">=8.2"), version("1.0.0", ">=8.0")],
+                })
+            })
+        }
+    }
+
+    async fn resolve_for(php: &[&str], constraint: &str) -> Result<String, ResolveError> {
+        let platform = if php.is_empty() {
+            BTreeMap::new()
+        } else {
+            BTreeMap::from([(
+                "php".to_string(),
+                php.iter()
+                    .map(|v| ComposerVersion::parse(v).unwrap())
+                    .collect(),
+            )])
+        };
+        let config = ResolverConfig {
+            platform,
+            ..ResolverConfig::default()
+        };
+        let root = [Dependency::new(
+            PackageName::parse("acme/lib").unwrap(),
+            ComposerConstraint::parse(constraint).unwrap(),
+        )];
+        let resolution = Resolver::new(Arc::new(PlatformFetcher), config)
+            .resolve(&root, &[])
+            .await?;
+        assert_eq!(resolution.packages.len(), 1);
+        Ok(resolution.packages[0].version.to_string())
+    }
+
+    #[tokio::test]
+    async fn package_links_are_carried_into_the_resolution() {
+        struct LinkFetcher;
+        impl PackageFetcher for LinkFetcher {
+            fn fetch(
+                &self,
+                name: String,
+            ) -> Pin<Box<dyn std::future::Future<Output = Option<FetchedPackage>> + Send + '_>>
+            {
+                Box::pin(async move {
+                    let mut linked = version("1.0.0", ">=8.0");
+                    linked.provide =
+                        vec![("psr/log-implementation".to_string(), "1.0".to_string())];
+                    linked.conflict = vec![("acme/old".to_string(), "<2.0".to_string())];
+                    (name == "acme/lib").then(|| FetchedPackage {
+                        name,
+                        versions: vec![linked],
+                    })
+                })
+            }
+        }
+
+        let root = [Dependency::new(
+            PackageName::parse("acme/lib").unwrap(),
+            ComposerConstraint::parse("*").unwrap(),
+        )];
+        let resolution = Resolver::new(Arc::new(LinkFetcher), ResolverConfig::default())
+            .resolve(&root, &[])
+            .await
+            .unwrap();
+        let pkg = &resolution.packages[0];
+        let names = |links: &Option<Vec<(String, String)>>| -> Vec<String> {
+            links
+                .iter()
+                .flatten()
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+        assert_eq!(names(&pkg.provide), ["psr/log-implementation"]);
+        assert_eq!(names(&pkg.conflict), ["acme/old"]);
+    }
+
+    #[tokio::test]
+    async fn locked_versions_are_kept_only_when_required() {
+        let resolve = |locked: &[(&str, &str)]| {
+            let config = ResolverConfig {
+                locked: locked
+                    .iter()
+                    .map(|(name, v)| ((*name).to_string(), ComposerVersion::parse(v).unwrap()))
+                    .collect(),
+                ..ResolverConfig::default()
+            };
+            let root = [Dependency::new(
+                PackageName::parse("acme/lib").unwrap(),
+                ComposerConstraint::parse("*").unwrap(),
+            )];
+            async move {
+                Resolver::new(Arc::new(PlatformFetcher), config)
+                    .resolve(&root, &[])
+                    .await
+            }
+        };
+
+        let kept = resolve(&[("acme/lib", "1.0.0"), ("acme/gone", "1.0.0")])
+            .await
+            .unwrap();
+        assert_eq!(kept.packages.len(), 1);
+        assert_eq!(kept.packages[0].version.to_string(), "1.0.0");
+
+        assert!(matches!(
+            resolve(&[("acme/lib", "3.0.0")]).await,
+            Err(ResolveError::Conflict { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn platform_versions_limit_candidates() {
+        assert_eq!(resolve_for(&[], "*").await.unwrap(), "2.0.0");
+        assert_eq!(resolve_for(&["8.1.0"], "*").await.unwrap(), "1.0.0");
+        assert_eq!(resolve_for(&["8.4.0"], "*").await.unwrap(), "2.0.0");
+        assert_eq!(
+            resolve_for(&["8.1.0", "8.4.0"], "*").await.unwrap(),
+            "1.0.0"
+        );
+        assert!(matches!(
+            resolve_for(&["8.1.0"], "^2.0").await,
+            Err(ResolveError::Conflict { .. })
+        ));
+    }
+}
@@ -42,6 +42,12 @@ pub struct ResolvedPackage {
     pub require_dev: Option<Vec<(String, String)>>,
     /// Package suggest (for lock file).
     pub suggest: Option<Vec<(String, String)>>,
+    /// Packages this package replaces (for lock file).
+    pub replace: Option<Vec<(String, String)>>,
+    /// Virtual packages this package provides (for lock file).
+    pub provide: Option<Vec<(String, String)>>,
+    /// Packages this package conflicts with (for lock file).
+    pub conflict: Option<Vec<(String, String)>>,
     /// Package type (library, project, etc.).
     pub package_type: Option<String>,
     /// Package description.
@@ -72,6 +78,50 @@ pub struct ResolvedPackage {
     pub bin: Option<Vec<String>>,
 }
 
+impl ResolvedPackage {
+    /// Create a package with its dependencies and no other metadata.
+    #[must_use]
+    pub const fn new(
+        name: PackageName,
+        version: ComposerVersion,
+        dependencies: Vec<PackageName>,
+        is_dev: bool,
+    ) -> Self {
+        Self {
+            name,
+            version,
+            dependencies,
+            is_dev,
+            dist_url: None,
+            dist_type: None,
+            dist_shasum: None,
+            source_url: None,
+            source_type: None,
+            source_reference: None,
+            require: None,
+            require_dev: None,
+            suggest: None,
+            replace: None,
+            provide: None,
+            conflict: None,
+            package_type: None,
+            description: None,
+            homepage: None,
+            license: None,
+            authors: None,
+            keywords: None,
+            time: None,
+            autoload: None,
+            autoload_dev: None,
+            extra: None,
+            support: None,
+            funding: None,
+            notification_url: None,
+            bin: None,
+        }
+    }
+}
+
 /// Result of dependency resolution.
 #[derive(Debug)]
 pub struct Resolution {
@@ -88,6 +138,37 @@ pub struct Resolution {
 }
 
 impl Resolution {
+    /// Build a resolution from packages whose dependencies are already
+    /// known, such as those of a lock file.
+    ///
+    /// Dependencies on packages that are not in `packages` get no edge.
+    #[must_use]
+    pub fn from_packages(packages: Vec<ResolvedPackage>) -> Self {
+        let mut graph = DiGraph::new();
+        let mut indices = AHashMap::new();
+        for pkg in &packages {
+            let idx = graph.add_node(pkg.name.clone());
+            indices.insert(pkg.name.as_str().to_string(), idx);
+        }
+
+        for pkg in &packages {
+            let from = indices[pkg.name.as_str()];
+            for dep in &pkg.dependencies {
+                if let Some(&to) = indices.get(dep.as_str()) {
+                    graph.update_edge(to, from, ());
+                }
+            }
+        }
+
+        Self {
+            packages,
+            graph,
+            indices,
+            platform_packages: vec![],
+            duration: Duration::ZERO,
+        }
+    }
+
     /// Get the number of resolved packages.
     #[must_use]
     pub const fn len(&self) -> usize {